@@ -0,0 +1,759 @@
+use std::{convert::TryInto, str::FromStr};
+
+use bonfida_bot::{
+    error::BonfidaBotError,
+    instruction::{
+        add_trader, claim_fees, collect_fees, create, deposit, init, post_bond,
+        process_deposit_queue, process_redeem_queue, redeem, remove_trader,
+        request_bond_withdrawal, request_deposit, request_redeem, set_multisig, settle_funds,
+        slash_bond, with_unsettled_open_orders, withdraw_bond,
+    },
+    math::mul_div_ceil,
+    state::{
+        DepositRequest, RedeemRequest, BONFIDA_BNB, BONFIDA_FEE, FIDA_MINT, MAX_DEPOSIT_BATCH_SIZE,
+        MAX_REDEEM_BATCH_SIZE,
+    },
+    utils::{find_bond_key, find_deposit_request_key, find_redeem_request_key, find_trader_key},
+};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_client::RpcClient,
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_program::{
+    instruction::{Instruction, InstructionError}, program_pack::Pack, pubkey::Pubkey, system_program, sysvar,
+};
+use solana_sdk::{
+    signature::{Keypair, Signature, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::state::{Account, Mint};
+
+use crate::{
+    market::{find_open_orders, MarketInfo},
+    pool::{find_pool_seed, get_pool_key, get_pool_mint_key, PoolInfo},
+    spec::PoolSpec,
+    CliResult,
+};
+
+/// Names of the fee recipients, in the order of the pool fee ledger.
+pub const FEE_RECIPIENTS: [&str; 3] = ["signal-provider", "bonfida-fee", "bonfida-bnb"];
+
+/// Maximum number of account creation instructions sent in a single transaction.
+const CREATE_ACCOUNTS_CHUNK: usize = 4;
+
+/// Decodes the bonfida-bot error which made a transaction fail, if the failing instruction
+/// belongs to the program. Preflight simulation failures are decoded as well.
+pub fn decode_error(
+    program_id: &Pubkey,
+    instructions: &[Instruction],
+    error: &ClientError,
+) -> Option<(u8, BonfidaBotError)> {
+    let transaction_error = match error.kind() {
+        ClientErrorKind::TransactionError(e) => Some(e.clone()),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.err.clone(),
+        _ => None,
+    };
+    match transaction_error {
+        Some(TransactionError::InstructionError(index, InstructionError::Custom(code)))
+            if instructions
+                .get(index as usize)
+                .map_or(false, |i| &i.program_id == program_id) =>
+        {
+            BonfidaBotError::decode(code).map(|e| (index, e))
+        }
+        _ => None,
+    }
+}
+
+pub struct Client {
+    pub rpc: RpcClient,
+    pub payer: Keypair,
+    pub program_id: Pubkey,
+}
+
+impl Client {
+    pub fn new(rpc_url: String, payer: Keypair, program_id: Pubkey) -> Self {
+        Self {
+            rpc: RpcClient::new(rpc_url),
+            payer,
+            program_id,
+        }
+    }
+
+    pub fn send(&self, instructions: &[Instruction], signers: &[&Keypair]) -> CliResult<Signature> {
+        let (recent_blockhash, _) = self.rpc.get_recent_blockhash()?;
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend(signers.iter().filter(|s| s.pubkey() != self.payer.pubkey()));
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            recent_blockhash,
+        );
+        self.rpc
+            .send_and_confirm_transaction_with_spinner(&transaction)
+            .map_err(
+                |e| match decode_error(&self.program_id, instructions, &e) {
+                    Some((index, program_error)) => {
+                        format!("Instruction {} failed: {}", index, program_error).into()
+                    }
+                    None => e.into(),
+                },
+            )
+    }
+
+    /// Returns the associated token address for the owner and mint, along with the instruction
+    /// creating it when it does not exist yet.
+    pub fn get_or_create_associated_account(
+        &self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+    ) -> (Pubkey, Option<Instruction>) {
+        let key = get_associated_token_address(owner, mint);
+        match self.rpc.get_account(&key) {
+            Ok(_) => (key, None),
+            Err(_) => (
+                key,
+                Some(create_associated_token_account(
+                    &self.payer.pubkey(),
+                    owner,
+                    mint,
+                )),
+            ),
+        }
+    }
+
+    /// Returns the unix timestamp of the cluster clock, which the program checks the request
+    /// expiries against.
+    pub fn cluster_time(&self) -> CliResult<u64> {
+        let data = self.rpc.get_account_data(&sysvar::clock::id())?;
+        // The timestamp follows the slot, epoch start timestamp, epoch and leader schedule epoch
+        let unix_timestamp = data
+            .get(32..40)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i64::from_le_bytes)
+            .ok_or("Invalid clock sysvar account")?;
+        Ok(unix_timestamp.try_into()?)
+    }
+
+    pub fn fetch_pool(&self, pool_seed: [u8; 32]) -> CliResult<PoolInfo> {
+        PoolInfo::fetch(&self.rpc, &self.program_id, pool_seed)
+    }
+
+    /// Initializes and creates a new pool from a spec. The first deposit is made by the source owner.
+    pub fn create_pool(&self, spec: &PoolSpec, source_owner: &Keypair) -> CliResult<[u8; 32]> {
+        let pool_seed = find_pool_seed(&self.program_id);
+        let pool_key = get_pool_key(&self.program_id, &pool_seed)?;
+        let mint_key = get_pool_mint_key(&self.program_id, &pool_seed)?;
+        let signal_provider = spec.signal_provider()?;
+        let markets = spec.markets()?;
+        let source_asset_keys = spec.sources()?;
+
+        let mut asset_mints = Vec::with_capacity(source_asset_keys.len());
+        for source in source_asset_keys.iter() {
+            asset_mints.push(Account::unpack(&self.rpc.get_account_data(source)?)?.mint);
+        }
+
+        let init_instruction = init(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &mint_key,
+            &self.payer.pubkey(),
+            &pool_key,
+            pool_seed,
+            spec.max_number_of_assets,
+            markets.len() as u16,
+        )?;
+        let mut instructions = vec![init_instruction];
+        // The pooltoken accounts receiving the first pooltokens and the claimed fees
+        for owner in [
+            source_owner.pubkey(),
+            signal_provider,
+            Pubkey::from_str(BONFIDA_FEE)?,
+            Pubkey::from_str(BONFIDA_BNB)?,
+        ]
+        .iter()
+        {
+            if let (_, Some(instruction)) = self.get_or_create_associated_account(owner, &mint_key) {
+                instructions.push(instruction);
+            }
+        }
+        self.send(&instructions, &[])?;
+
+        let pool_asset_keys: Vec<Pubkey> = asset_mints
+            .iter()
+            .map(|mint| get_associated_token_address(&pool_key, mint))
+            .collect();
+        let create_asset_instructions: Vec<Instruction> = asset_mints
+            .iter()
+            .map(|mint| create_associated_token_account(&self.payer.pubkey(), &pool_key, mint))
+            .collect();
+        for chunk in create_asset_instructions.chunks(CREATE_ACCOUNTS_CHUNK) {
+            self.send(chunk, &[])?;
+        }
+
+        let create_instruction = create(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &mint_key,
+            &pool_key,
+            pool_seed,
+            &pool_asset_keys,
+            &get_associated_token_address(&source_owner.pubkey(), &mint_key),
+            &source_owner.pubkey(),
+            &source_asset_keys,
+            &spec.serum_program_id()?,
+            &signal_provider,
+            spec.annual_fee_ratio,
+            spec.fee_ratio,
+            spec.min_signal_provider_ratio,
+            spec.trade_limits()?,
+            spec.deposit_amounts(),
+            markets,
+        )?;
+        self.send(&[create_instruction], &[source_owner])?;
+
+        Ok(pool_seed)
+    }
+
+    pub fn deposit(
+        &self,
+        pool_seed: [u8; 32],
+        source_owner: &Keypair,
+        pool_token_amount: u64,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let mut instructions = vec![];
+        let (target_pool_token_key, create_target) =
+            self.get_or_create_associated_account(&source_owner.pubkey(), &pool.mint_key);
+        instructions.extend(create_target);
+        let source_asset_keys = pool
+            .asset_mints()
+            .iter()
+            .map(|mint| get_associated_token_address(&source_owner.pubkey(), mint))
+            .collect();
+        instructions.push(deposit(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.mint_key,
+            &pool.key,
+            &pool.pool_asset_keys(),
+            &target_pool_token_key,
+            &source_owner.pubkey(),
+            &source_asset_keys,
+            pool_seed,
+            pool_token_amount,
+        )?);
+        self.send(&instructions, &[source_owner])
+    }
+
+    pub fn redeem(
+        &self,
+        pool_seed: [u8; 32],
+        source_owner: &Keypair,
+        pool_token_amount: u64,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let mut instructions = vec![];
+        let mut target_asset_keys = Vec::with_capacity(pool.assets.len());
+        for mint in pool.asset_mints().iter() {
+            let (key, create_target) =
+                self.get_or_create_associated_account(&source_owner.pubkey(), mint);
+            instructions.extend(create_target);
+            target_asset_keys.push(key);
+        }
+        // The unsettled funds of the pending orders are part of the payout
+        let mut unsettled_open_orders = vec![];
+        for market_key in pool.markets.iter() {
+            let open_orders = find_open_orders(
+                &self.rpc,
+                &pool.header.serum_program_id,
+                market_key,
+                &pool.key,
+            )?;
+            unsettled_open_orders.extend(
+                open_orders
+                    .iter()
+                    .filter(|o| o.is_pending())
+                    .map(|o| (*market_key, o.key)),
+            );
+        }
+        let instruction = redeem(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.mint_key,
+            &pool.key,
+            &pool.pool_asset_keys(),
+            &source_owner.pubkey(),
+            &get_associated_token_address(&source_owner.pubkey(), &pool.mint_key),
+            &target_asset_keys,
+            pool_seed,
+            pool_token_amount,
+        )?;
+        instructions.push(with_unsettled_open_orders(
+            instruction,
+            &unsettled_open_orders,
+        ));
+        self.send(&instructions, &[source_owner])
+    }
+
+    /// Queues a deposit of exactly `pool_token_amount` pool tokens, processed by
+    /// `process_deposit_queue` once the pool is unlocked and settled. The escrowed basket is the
+    /// current price of the pool tokens increased by `slippage_bps` basis points, any remainder is
+    /// refunded.
+    pub fn request_deposit(
+        &self,
+        pool_seed: [u8; 32],
+        source_owner: &Keypair,
+        pool_token_amount: u64,
+        slippage_bps: u16,
+        expiry_seconds: u64,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let pooltoken_supply = Mint::unpack(&self.rpc.get_account_data(&pool.mint_key)?)?.supply;
+        let mut asset_amounts = vec![];
+        for pool_asset_key in pool.pool_asset_keys() {
+            let balance = Account::unpack(&self.rpc.get_account_data(&pool_asset_key)?)?.amount;
+            let amount = mul_div_ceil(pool_token_amount, balance, pooltoken_supply)?;
+            asset_amounts.push(mul_div_ceil(amount, 10_000 + slippage_bps as u64, 10_000)?);
+        }
+        let source_asset_keys = pool
+            .asset_mints()
+            .iter()
+            .map(|mint| get_associated_token_address(&source_owner.pubkey(), mint))
+            .collect();
+        let (deposit_request_key, _) =
+            find_deposit_request_key(&self.program_id, &pool_seed, pool.header.deposit_queue_tail);
+        let expiry_timestamp = self.cluster_time()? + expiry_seconds;
+        let instruction = request_deposit(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.mint_key,
+            &pool.key,
+            &deposit_request_key,
+            &source_owner.pubkey(),
+            &pool.asset_mints(),
+            &source_asset_keys,
+            pool_seed,
+            pool_token_amount,
+            expiry_timestamp,
+            asset_amounts,
+        )?;
+        self.send(&[instruction], &[source_owner])
+    }
+
+    /// Processes up to `max_requests` of the oldest queued deposit requests, creating the pool
+    /// token and asset accounts of their owners if needed.
+    pub fn process_deposit_queue(
+        &self,
+        pool_seed: [u8; 32],
+        max_requests: u8,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let head = pool.header.deposit_queue_head;
+        let queued = pool.header.deposit_queue_tail - head;
+        if queued == 0 {
+            return Err("No deposit requests are queued".into());
+        }
+        let number_of_requests = queued.min(max_requests.min(MAX_DEPOSIT_BATCH_SIZE) as u64);
+
+        let mut deposit_requests = Vec::with_capacity(number_of_requests as usize);
+        let mut created_keys = vec![];
+        let mut create_instructions = vec![];
+        for sequence in head..head + number_of_requests {
+            let (deposit_request_key, _) =
+                find_deposit_request_key(&self.program_id, &pool_seed, sequence);
+            let request =
+                DepositRequest::unpack(&self.rpc.get_account_data(&deposit_request_key)?)?;
+            // The escrows of the assets dropped by the pool are refunded as well
+            let mut mints = vec![pool.mint_key];
+            mints.extend(request.escrowed_mints.iter().copied());
+            for mint in mints.iter() {
+                // The same owner can have several requests in the batch
+                if let (key, Some(instruction)) =
+                    self.get_or_create_associated_account(&request.owner, mint)
+                {
+                    if !created_keys.contains(&key) {
+                        created_keys.push(key);
+                        create_instructions.push(instruction);
+                    }
+                }
+            }
+            deposit_requests.push((deposit_request_key, request.owner, request.escrowed_mints));
+        }
+        for chunk in create_instructions.chunks(CREATE_ACCOUNTS_CHUNK) {
+            self.send(chunk, &[])?;
+        }
+
+        let instruction = process_deposit_queue(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.mint_key,
+            &pool.key,
+            &pool.asset_mints(),
+            &deposit_requests,
+            pool_seed,
+        )?;
+        self.send(&[instruction], &[])
+    }
+
+    /// Queues a redeem which is paid out by `process_redeem_queue` once the pool has no pending
+    /// orders.
+    pub fn request_redeem(
+        &self,
+        pool_seed: [u8; 32],
+        source_owner: &Keypair,
+        pool_token_amount: u64,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let (redeem_request_key, _) = find_redeem_request_key(
+            &self.program_id,
+            &pool_seed,
+            pool.header.redeem_queue_tail,
+        );
+        let instruction = request_redeem(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &pool.mint_key,
+            &pool.key,
+            &redeem_request_key,
+            &source_owner.pubkey(),
+            &get_associated_token_address(&source_owner.pubkey(), &pool.mint_key),
+            pool_seed,
+            pool_token_amount,
+        )?;
+        self.send(&[instruction], &[source_owner])
+    }
+
+    /// Pays out up to `max_requests` of the oldest queued redeem requests, creating the asset
+    /// accounts of their owners if needed.
+    pub fn process_redeem_queue(
+        &self,
+        pool_seed: [u8; 32],
+        max_requests: u8,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let head = pool.header.redeem_queue_head;
+        let queued = pool.header.redeem_queue_tail - head;
+        if queued == 0 {
+            return Err("No redeem requests are queued".into());
+        }
+        let number_of_requests = queued.min(max_requests.min(MAX_REDEEM_BATCH_SIZE) as u64);
+
+        let mut redeem_requests = Vec::with_capacity(number_of_requests as usize);
+        let mut created_keys = vec![];
+        let mut create_instructions = vec![];
+        for sequence in head..head + number_of_requests {
+            let (redeem_request_key, _) =
+                find_redeem_request_key(&self.program_id, &pool_seed, sequence);
+            let request = RedeemRequest::unpack(&self.rpc.get_account_data(&redeem_request_key)?)?;
+            for mint in pool.asset_mints().iter() {
+                // The same owner can have several requests in the batch
+                if let (key, Some(instruction)) =
+                    self.get_or_create_associated_account(&request.owner, mint)
+                {
+                    if !created_keys.contains(&key) {
+                        created_keys.push(key);
+                        create_instructions.push(instruction);
+                    }
+                }
+            }
+            redeem_requests.push((redeem_request_key, request.owner));
+        }
+        for chunk in create_instructions.chunks(CREATE_ACCOUNTS_CHUNK) {
+            self.send(chunk, &[])?;
+        }
+
+        let instruction = process_redeem_queue(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.mint_key,
+            &pool.key,
+            &pool.asset_mints(),
+            &redeem_requests,
+            pool_seed,
+        )?;
+        self.send(&[instruction], &[])
+    }
+
+    pub fn collect_fees(&self, pool_seed: [u8; 32]) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let instruction = collect_fees(
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.key,
+            &pool.mint_key,
+            pool_seed,
+        )?;
+        self.send(&[instruction], &[])
+    }
+
+    /// Mints the fees owed to one of the `FEE_RECIPIENTS`, creating its pool token account if
+    /// needed.
+    pub fn claim_fees(&self, pool_seed: [u8; 32], recipient: usize) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let owner = match recipient {
+            0 => pool.header.signal_provider,
+            1 => Pubkey::from_str(BONFIDA_FEE)?,
+            _ => Pubkey::from_str(BONFIDA_BNB)?,
+        };
+        let (recipient_pool_token_key, create_recipient) =
+            self.get_or_create_associated_account(&owner, &pool.mint_key);
+        let mut instructions: Vec<Instruction> = create_recipient.into_iter().collect();
+        instructions.push(claim_fees(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.key,
+            &pool.mint_key,
+            &recipient_pool_token_key,
+            pool_seed,
+        )?);
+        self.send(&instructions, &[])
+    }
+
+    /// Adds FIDA from the associated FIDA account of the signal provider to the pool bond.
+    pub fn post_bond(
+        &self,
+        pool_seed: [u8; 32],
+        signal_provider: &Keypair,
+        amount: u64,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let fida_mint_key = Pubkey::from_str(FIDA_MINT)?;
+        let (bond_key, _) = find_bond_key(&self.program_id, &pool_seed);
+        let instruction = post_bond(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &pool.key,
+            &fida_mint_key,
+            &bond_key,
+            &signal_provider.pubkey(),
+            &get_associated_token_address(&signal_provider.pubkey(), &fida_mint_key),
+            pool_seed,
+            amount,
+        )?;
+        self.send(&[instruction], &[signal_provider])
+    }
+
+    pub fn request_bond_withdrawal(
+        &self,
+        pool_seed: [u8; 32],
+        signal_provider: &Keypair,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let instruction = request_bond_withdrawal(
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.key,
+            &signal_provider.pubkey(),
+            pool_seed,
+        )?;
+        self.send(&[instruction], &[signal_provider])
+    }
+
+    /// Returns the bond to the associated FIDA account of the signal provider, creating it if
+    /// needed.
+    pub fn withdraw_bond(
+        &self,
+        pool_seed: [u8; 32],
+        signal_provider: &Keypair,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let fida_mint_key = Pubkey::from_str(FIDA_MINT)?;
+        let (bond_key, _) = find_bond_key(&self.program_id, &pool_seed);
+        let (target_fida_key, create_target) =
+            self.get_or_create_associated_account(&signal_provider.pubkey(), &fida_mint_key);
+        let mut instructions: Vec<Instruction> = create_target.into_iter().collect();
+        instructions.push(withdraw_bond(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.key,
+            &fida_mint_key,
+            &bond_key,
+            &signal_provider.pubkey(),
+            &target_fida_key,
+            pool_seed,
+        )?);
+        self.send(&instructions, &[signal_provider])
+    }
+
+    /// Starts the slashing timer of a pool with pending orders, or slashes its bond once the
+    /// orders have been pending for too long. The payer funds the FIDA account of the pool if the
+    /// program has to create it.
+    pub fn slash_bond(&self, pool_seed: [u8; 32]) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let fida_mint_key = Pubkey::from_str(FIDA_MINT)?;
+        let (bond_key, _) = find_bond_key(&self.program_id, &pool_seed);
+        let instruction = slash_bond(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &pool.key,
+            &fida_mint_key,
+            &bond_key,
+            &pool.header.signal_provider,
+            &self.payer.pubkey(),
+            pool_seed,
+        )?;
+        self.send(&[instruction], &[])
+    }
+
+    /// Allows `trader` to place orders for the pool, or updates its limit if it already can.
+    pub fn add_trader(
+        &self,
+        pool_seed: [u8; 32],
+        signal_provider: &Keypair,
+        trader: &Pubkey,
+        max_ratio_of_pool_assets_to_trade: u16,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let (trader_account_key, _) = find_trader_key(
+            &self.program_id,
+            &pool_seed,
+            &pool.header.signal_provider,
+            trader,
+        );
+        let instruction = add_trader(
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &pool.key,
+            &trader_account_key,
+            &signal_provider.pubkey(),
+            pool_seed,
+            trader,
+            max_ratio_of_pool_assets_to_trade,
+        )?;
+        self.send(&[instruction], &[signal_provider])
+    }
+
+    pub fn remove_trader(
+        &self,
+        pool_seed: [u8; 32],
+        signal_provider: &Keypair,
+        trader: &Pubkey,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let (trader_account_key, _) = find_trader_key(
+            &self.program_id,
+            &pool_seed,
+            &pool.header.signal_provider,
+            trader,
+        );
+        let instruction = remove_trader(
+            &self.program_id,
+            &pool.key,
+            &trader_account_key,
+            &signal_provider.pubkey(),
+            pool_seed,
+            trader,
+        )?;
+        self.send(&[instruction], &[signal_provider])
+    }
+
+    /// Replaces the multisig configuration of the signal provider. `signers` are the signal
+    /// provider, or the approving multisig signers when the pool already has a multisig.
+    pub fn set_multisig(
+        &self,
+        pool_seed: [u8; 32],
+        signers: &[&Keypair],
+        threshold: u8,
+        multisig_signers: Vec<Pubkey>,
+    ) -> CliResult<Signature> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let signer_pubkeys: Vec<Pubkey> = if pool.header.multisig_threshold == 0 {
+            vec![]
+        } else {
+            signers.iter().map(|s| s.pubkey()).collect()
+        };
+        let instruction = set_multisig(
+            &self.program_id,
+            &pool.key,
+            &pool.header.signal_provider,
+            &signer_pubkeys.iter().collect::<Vec<_>>(),
+            pool_seed,
+            threshold,
+            multisig_signers,
+        )?;
+        self.send(&[instruction], signers)
+    }
+
+    /// Builds the SettleFunds instruction for one of the pool's OpenOrders accounts.
+    pub fn settle_instruction(
+        &self,
+        pool: &PoolInfo,
+        market: &MarketInfo,
+        open_orders: &Pubkey,
+    ) -> CliResult<Instruction> {
+        let serum_program_id = pool.header.serum_program_id;
+        let (coin_index, pc_index) = pool
+            .settle_indices(&market.coin_mint, &market.pc_mint)
+            .ok_or("The pool has no free asset slot for the market tokens")?;
+        Ok(settle_funds(
+            &self.program_id,
+            &market.key,
+            open_orders,
+            &pool.key,
+            &pool.mint_key,
+            &market.coin_vault,
+            &market.pc_vault,
+            &get_associated_token_address(&pool.key, &market.coin_mint),
+            &get_associated_token_address(&pool.key, &market.pc_mint),
+            &market.vault_signer(&serum_program_id)?,
+            &spl_token::id(),
+            &serum_program_id,
+            None,
+            pool.seed,
+            pc_index as u64,
+            coin_index as u64,
+        )?)
+    }
+
+    /// Settles every OpenOrders account of the pool which holds free funds.
+    pub fn settle_all(&self, pool_seed: [u8; 32]) -> CliResult<Vec<Signature>> {
+        let pool = self.fetch_pool(pool_seed)?;
+        let mut signatures = vec![];
+        for market_key in pool.markets.iter() {
+            let market = MarketInfo::fetch(&self.rpc, market_key)?;
+            let open_orders = find_open_orders(
+                &self.rpc,
+                &pool.header.serum_program_id,
+                market_key,
+                &pool.key,
+            )?;
+            for open_order in open_orders.iter().filter(|o| o.has_free_funds()) {
+                let mut instructions = vec![];
+                for mint in [market.coin_mint, market.pc_mint].iter() {
+                    let (_, create_asset) = self.get_or_create_associated_account(&pool.key, mint);
+                    instructions.extend(create_asset);
+                }
+                instructions.push(self.settle_instruction(&pool, &market, &open_order.key)?);
+                signatures.push(self.send(&instructions, &[])?);
+            }
+        }
+        Ok(signatures)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod client;
+pub mod market;
+pub mod pool;
+pub mod spec;
+
+pub type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
@@ -0,0 +1,498 @@
+use std::{process::exit, str::FromStr};
+
+use bonfida_bot::state::{MAX_DEPOSIT_BATCH_SIZE, MAX_REDEEM_BATCH_SIZE};
+use bonfida_bot_cli::{
+    client::{Client, FEE_RECIPIENTS},
+    pool::{parse_pool_seed, PoolInfo},
+    spec::PoolSpec,
+    CliResult,
+};
+use clap::{value_t_or_exit, App, AppSettings, Arg, ArgMatches, SubCommand};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+
+const BONFIDA_BOT_PROGRAM_ID: &str = "63xyXHpA6EVF69kRmEbXAr8aBEkhgpaNUSRoTQyi5Rwr";
+const DEFAULT_RPC_URL: &str = "http://localhost:8899";
+
+fn read_keypair(path: &str) -> CliResult<Keypair> {
+    read_keypair_file(path).map_err(|e| format!("Failed to read keypair {}: {}", path, e).into())
+}
+
+fn default_keypair_path() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_owned());
+    format!("{}/.config/solana/id.json", home)
+}
+
+fn pool_seed_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("pool_seed")
+        .long("pool-seed")
+        .value_name("BASE58")
+        .takes_value(true)
+        .required(true)
+        .help("The base58 encoded seed of the pool")
+}
+
+fn owner_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("owner")
+        .long("owner")
+        .value_name("KEYPAIR")
+        .takes_value(true)
+        .help("The keypair owning the token accounts, defaults to the fee payer")
+}
+
+fn amount_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("amount")
+        .long("amount")
+        .value_name("POOLTOKENS")
+        .takes_value(true)
+        .required(true)
+        .help("The amount of pooltokens")
+}
+
+fn signal_provider_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("signal_provider")
+        .long("signal-provider")
+        .value_name("KEYPAIR")
+        .takes_value(true)
+        .help("The signal provider keypair, defaults to the fee payer")
+}
+
+fn trader_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("trader")
+        .long("trader")
+        .value_name("PUBKEY")
+        .takes_value(true)
+        .required(true)
+        .help("The public key of the trader")
+}
+
+fn owner_keypair(matches: &ArgMatches, payer_path: &str) -> CliResult<Keypair> {
+    read_keypair(matches.value_of("owner").unwrap_or(payer_path))
+}
+
+fn process(matches: &ArgMatches) -> CliResult<()> {
+    let payer_path = matches
+        .value_of("keypair")
+        .map(String::from)
+        .unwrap_or_else(default_keypair_path);
+    let client = Client::new(
+        matches.value_of("url").unwrap_or(DEFAULT_RPC_URL).to_owned(),
+        read_keypair(&payer_path)?,
+        Pubkey::from_str(
+            matches
+                .value_of("program_id")
+                .unwrap_or(BONFIDA_BOT_PROGRAM_ID),
+        )?,
+    );
+
+    match matches.subcommand() {
+        ("create", Some(m)) => {
+            let spec = PoolSpec::load(m.value_of("spec").unwrap())?;
+            let source_owner = owner_keypair(m, &payer_path)?;
+            let pool_seed = client.create_pool(&spec, &source_owner)?;
+            println!("Pool seed: {}", bs58::encode(&pool_seed).into_string());
+        }
+        ("deposit", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let amount = value_t_or_exit!(m, "amount", u64);
+            let source_owner = owner_keypair(m, &payer_path)?;
+            println!("{}", client.deposit(pool_seed, &source_owner, amount)?);
+        }
+        ("redeem", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let amount = value_t_or_exit!(m, "amount", u64);
+            let source_owner = owner_keypair(m, &payer_path)?;
+            println!("{}", client.redeem(pool_seed, &source_owner, amount)?);
+        }
+        ("request-deposit", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let amount = value_t_or_exit!(m, "amount", u64);
+            let slippage_bps = value_t_or_exit!(m, "slippage_bps", u16);
+            let expiry_seconds = value_t_or_exit!(m, "expiry_seconds", u64);
+            let source_owner = owner_keypair(m, &payer_path)?;
+            println!(
+                "{}",
+                client.request_deposit(
+                    pool_seed,
+                    &source_owner,
+                    amount,
+                    slippage_bps,
+                    expiry_seconds
+                )?
+            );
+        }
+        ("process-deposit-queue", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let max_requests = if m.is_present("max_requests") {
+                value_t_or_exit!(m, "max_requests", u8)
+            } else {
+                MAX_DEPOSIT_BATCH_SIZE
+            };
+            println!("{}", client.process_deposit_queue(pool_seed, max_requests)?);
+        }
+        ("request-redeem", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let amount = value_t_or_exit!(m, "amount", u64);
+            let source_owner = owner_keypair(m, &payer_path)?;
+            println!(
+                "{}",
+                client.request_redeem(pool_seed, &source_owner, amount)?
+            );
+        }
+        ("process-redeem-queue", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let max_requests = if m.is_present("max_requests") {
+                value_t_or_exit!(m, "max_requests", u8)
+            } else {
+                MAX_REDEEM_BATCH_SIZE
+            };
+            println!("{}", client.process_redeem_queue(pool_seed, max_requests)?);
+        }
+        ("collect-fees", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            println!("{}", client.collect_fees(pool_seed)?);
+        }
+        ("claim-fees", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let recipient = FEE_RECIPIENTS
+                .iter()
+                .position(|r| *r == m.value_of("recipient").unwrap())
+                .unwrap();
+            println!("{}", client.claim_fees(pool_seed, recipient)?);
+        }
+        ("post-bond", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let amount = value_t_or_exit!(m, "amount", u64);
+            let signal_provider =
+                read_keypair(m.value_of("signal_provider").unwrap_or(&payer_path))?;
+            println!("{}", client.post_bond(pool_seed, &signal_provider, amount)?);
+        }
+        ("request-bond-withdrawal", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let signal_provider =
+                read_keypair(m.value_of("signal_provider").unwrap_or(&payer_path))?;
+            println!(
+                "{}",
+                client.request_bond_withdrawal(pool_seed, &signal_provider)?
+            );
+        }
+        ("withdraw-bond", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let signal_provider =
+                read_keypair(m.value_of("signal_provider").unwrap_or(&payer_path))?;
+            println!("{}", client.withdraw_bond(pool_seed, &signal_provider)?);
+        }
+        ("slash-bond", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            println!("{}", client.slash_bond(pool_seed)?);
+        }
+        ("add-trader", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let trader = Pubkey::from_str(m.value_of("trader").unwrap())?;
+            let max_ratio = value_t_or_exit!(m, "max_ratio", u16);
+            let signal_provider =
+                read_keypair(m.value_of("signal_provider").unwrap_or(&payer_path))?;
+            println!(
+                "{}",
+                client.add_trader(pool_seed, &signal_provider, &trader, max_ratio)?
+            );
+        }
+        ("remove-trader", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let trader = Pubkey::from_str(m.value_of("trader").unwrap())?;
+            let signal_provider =
+                read_keypair(m.value_of("signal_provider").unwrap_or(&payer_path))?;
+            println!(
+                "{}",
+                client.remove_trader(pool_seed, &signal_provider, &trader)?
+            );
+        }
+        ("set-multisig", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let threshold = value_t_or_exit!(m, "threshold", u8);
+            let multisig_signers = m
+                .values_of("multisig_signer")
+                .into_iter()
+                .flatten()
+                .map(Pubkey::from_str)
+                .collect::<Result<Vec<_>, _>>()?;
+            let signers = match m.values_of("approver") {
+                Some(paths) => paths.map(read_keypair).collect::<CliResult<Vec<_>>>()?,
+                None => vec![read_keypair(
+                    m.value_of("signal_provider").unwrap_or(&payer_path),
+                )?],
+            };
+            println!(
+                "{}",
+                client.set_multisig(
+                    pool_seed,
+                    &signers.iter().collect::<Vec<_>>(),
+                    threshold,
+                    multisig_signers
+                )?
+            );
+        }
+        ("settle-all", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let signatures = client.settle_all(pool_seed)?;
+            if signatures.is_empty() {
+                println!("No OpenOrders account holds free funds.");
+            }
+            for signature in signatures {
+                println!("{}", signature);
+            }
+        }
+        ("inspect", Some(m)) => {
+            let pool_seed = parse_pool_seed(m.value_of("pool_seed").unwrap())?;
+            let view = PoolInfo::fetch(&client.rpc, &client.program_id, pool_seed)?
+                .view(&client.rpc)?;
+            match m.value_of("output").unwrap() {
+                "json" => println!("{}", serde_json::to_string_pretty(&view)?),
+                _ => view.print_table(),
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("bonfida-bot-cli")
+        .about("Operate bonfida-bot trading pools")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("url")
+                .short("u")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .help("The RPC endpoint to use, defaults to http://localhost:8899"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .value_name("KEYPAIR")
+                .takes_value(true)
+                .global(true)
+                .help("The fee payer keypair file, defaults to ~/.config/solana/id.json"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .long("program-id")
+                .value_name("PUBKEY")
+                .takes_value(true)
+                .global(true)
+                .help("The bonfida-bot program id"),
+        )
+        .subcommand(
+            SubCommand::with_name("create")
+                .about("Initialize and create a new pool from a TOML pool spec")
+                .arg(
+                    Arg::with_name("spec")
+                        .long("spec")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The TOML pool spec"),
+                )
+                .arg(owner_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("deposit")
+                .about("Buy into a pool")
+                .arg(pool_seed_arg())
+                .arg(amount_arg())
+                .arg(owner_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("redeem")
+                .about("Buy out of a pool")
+                .arg(pool_seed_arg())
+                .arg(amount_arg())
+                .arg(owner_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("request-deposit")
+                .about("Queue a deposit processed once the pool is unlocked and settled")
+                .arg(pool_seed_arg())
+                .arg(amount_arg())
+                .arg(owner_arg())
+                .arg(
+                    Arg::with_name("slippage_bps")
+                        .long("slippage-bps")
+                        .value_name("BPS")
+                        .takes_value(true)
+                        .default_value("100")
+                        .help("The escrowed margin over the current pooltoken price"),
+                )
+                .arg(
+                    Arg::with_name("expiry_seconds")
+                        .long("expiry-seconds")
+                        .value_name("SECONDS")
+                        .takes_value(true)
+                        .default_value("3600")
+                        .help("The time after which the request is refunded"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("process-deposit-queue")
+                .about("Process the oldest queued deposit requests")
+                .arg(pool_seed_arg())
+                .arg(
+                    Arg::with_name("max_requests")
+                        .long("max-requests")
+                        .value_name("N")
+                        .takes_value(true)
+                        .help("The maximum number of requests to process, defaults to 2"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("request-redeem")
+                .about("Queue a redeem paid out once the pool has no pending orders")
+                .arg(pool_seed_arg())
+                .arg(amount_arg())
+                .arg(owner_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("process-redeem-queue")
+                .about("Pay out the oldest queued redeem requests")
+                .arg(pool_seed_arg())
+                .arg(
+                    Arg::with_name("max_requests")
+                        .long("max-requests")
+                        .value_name("N")
+                        .takes_value(true)
+                        .help("The maximum number of requests to process, defaults to 4"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("collect-fees")
+                .about("Account for the management fees accrued since the last collection")
+                .arg(pool_seed_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("claim-fees")
+                .about("Mint the fees owed to the signal provider or Bonfida")
+                .arg(pool_seed_arg())
+                .arg(
+                    Arg::with_name("recipient")
+                        .long("recipient")
+                        .takes_value(true)
+                        .possible_values(&FEE_RECIPIENTS)
+                        .default_value(FEE_RECIPIENTS[0])
+                        .help("The fee recipient"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("post-bond")
+                .about("Lock FIDA of the signal provider into the pool bond")
+                .arg(pool_seed_arg())
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("FIDA")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The amount of FIDA in native units, the bond is at least 100 FIDA"),
+                )
+                .arg(signal_provider_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("request-bond-withdrawal")
+                .about("Start the one week notice period before the bond can be withdrawn")
+                .arg(pool_seed_arg())
+                .arg(signal_provider_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw-bond")
+                .about("Return the bond to the signal provider once the notice period is over")
+                .arg(pool_seed_arg())
+                .arg(signal_provider_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("slash-bond")
+                .about("Start the slashing timer of a pool with pending orders, or slash its bond")
+                .arg(pool_seed_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("add-trader")
+                .about("Allow a key to place orders for the pool, or update its limit")
+                .arg(pool_seed_arg())
+                .arg(trader_arg())
+                .arg(
+                    Arg::with_name("max_ratio")
+                        .long("max-ratio")
+                        .value_name("RATIO")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The largest share of the pool assets per order, in 2^-16"),
+                )
+                .arg(signal_provider_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("remove-trader")
+                .about("Revoke a trader and return the rent of its account to the signal provider")
+                .arg(pool_seed_arg())
+                .arg(trader_arg())
+                .arg(signal_provider_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("set-multisig")
+                .about("Require multisig signatures in place of the signal provider")
+                .arg(pool_seed_arg())
+                .arg(
+                    Arg::with_name("threshold")
+                        .long("threshold")
+                        .value_name("COUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of signatures required, 0 disables the multisig"),
+                )
+                .arg(
+                    Arg::with_name("multisig_signer")
+                        .long("multisig-signer")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("A key of the new multisig, can be repeated"),
+                )
+                .arg(
+                    Arg::with_name("approver")
+                        .long("approver")
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("A current multisig signer approving the change, can be repeated"),
+                )
+                .arg(signal_provider_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("settle-all")
+                .about("Settle every OpenOrders account of the pool holding free funds")
+                .arg(pool_seed_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about("Print the pool state")
+                .arg(pool_seed_arg())
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .possible_values(&["table", "json"])
+                        .default_value("table")
+                        .help("The output format"),
+                ),
+        )
+        .get_matches();
+
+    if let Err(e) = process(&matches) {
+        eprintln!("Error: {}", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,146 @@
+use std::convert::TryInto;
+
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+};
+use solana_program::pubkey::Pubkey;
+
+use crate::CliResult;
+
+pub const OPEN_ORDERS_LEN: u64 = 3228;
+
+/// The fields of a Serum market account that are needed to operate a pool.
+#[derive(Debug)]
+pub struct MarketInfo {
+    pub key: Pubkey,
+    pub vault_signer_nonce: u64,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub request_queue: Pubkey,
+    pub event_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub coin_lot_size: u64,
+    pub pc_lot_size: u64,
+}
+
+fn read_u64(data: &[u8], offset: usize) -> CliResult<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|slice| slice.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| "Invalid Serum account data".into())
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> CliResult<Pubkey> {
+    data.get(offset..offset + 32)
+        .map(Pubkey::new)
+        .ok_or_else(|| "Invalid Serum account data".into())
+}
+
+impl MarketInfo {
+    pub fn fetch(rpc: &RpcClient, key: &Pubkey) -> CliResult<Self> {
+        Self::parse(*key, &rpc.get_account_data(key)?)
+    }
+
+    pub fn parse(key: Pubkey, data: &[u8]) -> CliResult<Self> {
+        Ok(Self {
+            key,
+            vault_signer_nonce: read_u64(data, 45)?,
+            coin_mint: read_pubkey(data, 53)?,
+            pc_mint: read_pubkey(data, 85)?,
+            coin_vault: read_pubkey(data, 117)?,
+            pc_vault: read_pubkey(data, 165)?,
+            request_queue: read_pubkey(data, 221)?,
+            event_queue: read_pubkey(data, 253)?,
+            bids: read_pubkey(data, 285)?,
+            asks: read_pubkey(data, 317)?,
+            coin_lot_size: read_u64(data, 349)?,
+            pc_lot_size: read_u64(data, 357)?,
+        })
+    }
+
+    pub fn vault_signer(&self, serum_program_id: &Pubkey) -> CliResult<Pubkey> {
+        Ok(serum_dex::state::gen_vault_signer_key(
+            self.vault_signer_nonce,
+            &self.key,
+            serum_program_id,
+        )?)
+    }
+}
+
+/// The balances of a Serum OpenOrders account.
+#[derive(Debug)]
+pub struct OpenOrdersInfo {
+    pub key: Pubkey,
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub native_coin_free: u64,
+    pub native_coin_total: u64,
+    pub native_pc_free: u64,
+    pub native_pc_total: u64,
+}
+
+impl OpenOrdersInfo {
+    pub fn parse(key: Pubkey, data: &[u8]) -> CliResult<Self> {
+        Ok(Self {
+            key,
+            market: read_pubkey(data, 13)?,
+            owner: read_pubkey(data, 45)?,
+            native_coin_free: read_u64(data, 77)?,
+            native_coin_total: read_u64(data, 85)?,
+            native_pc_free: read_u64(data, 93)?,
+            native_pc_total: read_u64(data, 101)?,
+        })
+    }
+
+    pub fn has_free_funds(&self) -> bool {
+        self.native_coin_free != 0 || self.native_pc_free != 0
+    }
+
+    /// Whether the account counts as a pending order of the pool which owns it.
+    pub fn is_pending(&self) -> bool {
+        self.native_coin_total != 0 || self.native_pc_total != 0
+    }
+
+    pub fn has_locked_funds(&self) -> bool {
+        self.native_coin_total != self.native_coin_free
+            || self.native_pc_total != self.native_pc_free
+    }
+}
+
+/// Lists all the OpenOrders accounts owned by the given pool on a market.
+pub fn find_open_orders(
+    rpc: &RpcClient,
+    serum_program_id: &Pubkey,
+    market: &Pubkey,
+    owner: &Pubkey,
+) -> CliResult<Vec<OpenOrdersInfo>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::DataSize(OPEN_ORDERS_LEN),
+            RpcFilterType::Memcmp(Memcmp {
+                offset: 13,
+                bytes: MemcmpEncodedBytes::Binary(market.to_string()),
+                encoding: None,
+            }),
+            RpcFilterType::Memcmp(Memcmp {
+                offset: 45,
+                bytes: MemcmpEncodedBytes::Binary(owner.to_string()),
+                encoding: None,
+            }),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+    };
+    rpc.get_program_accounts_with_config(serum_program_id, config)?
+        .into_iter()
+        .map(|(key, account)| OpenOrdersInfo::parse(key, &account.data))
+        .collect()
+}
@@ -0,0 +1,340 @@
+use std::convert::TryInto;
+
+use bonfida_bot::{
+    accounting::owed_fees,
+    state::{PoolHeader, PoolState, PoolStatus},
+};
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account, Mint};
+
+use crate::{
+    spec::{set_names, ORDER_TYPES, SELF_TRADE_BEHAVIORS},
+    CliResult,
+};
+
+pub fn get_pool_key(program_id: &Pubkey, pool_seed: &[u8; 32]) -> CliResult<Pubkey> {
+    Ok(Pubkey::create_program_address(&[pool_seed], program_id)?)
+}
+
+pub fn get_pool_mint_key(program_id: &Pubkey, pool_seed: &[u8; 32]) -> CliResult<Pubkey> {
+    Ok(Pubkey::create_program_address(&[pool_seed, &[1]], program_id)?)
+}
+
+/// Finds a random pool seed for which both the pool and the pooltoken mint addresses are valid.
+pub fn find_pool_seed(program_id: &Pubkey) -> [u8; 32] {
+    loop {
+        let mut pool_seed = rand::random::<[u8; 32]>();
+        let (_, bump) = Pubkey::find_program_address(&[&pool_seed[..31]], program_id);
+        pool_seed[31] = bump;
+        if Pubkey::create_program_address(&[&pool_seed, &[1]], program_id).is_ok() {
+            return pool_seed;
+        }
+    }
+}
+
+pub fn parse_pool_seed(encoded: &str) -> CliResult<[u8; 32]> {
+    let bytes = bs58::decode(encoded).into_vec()?;
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| "The pool seed should be 32 bytes long".into())
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssetView {
+    pub index: usize,
+    pub mint: String,
+    pub pool_asset_account: String,
+    pub balance: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolView {
+    pub pool_seed: String,
+    pub pool_key: String,
+    pub pool_mint: String,
+    pub pooltoken_supply: u64,
+    pub serum_program_id: String,
+    pub signal_provider: String,
+    pub status: String,
+    pub fee_ratio: u16,
+    pub last_fee_collection_timestamp: u64,
+    pub annual_fee_ratio: u16,
+    pub accrued_fees: u64,
+    /// Fees owed to the signal provider, Bonfida and the Bonfida buy and burn account
+    pub unclaimed_fees: [u64; 3],
+    /// Redeem requests waiting for the pool to have no pending orders
+    pub queued_redeem_requests: u64,
+    pub queued_deposit_requests: u64,
+    pub bond_amount: u64,
+    pub bond_unlock_timestamp: u64,
+    pub pending_order_timestamp: u64,
+    pub min_signal_provider_ratio: u16,
+    /// Signatures of the multisig signers required in place of the signal provider, 0 when the
+    /// pool has a single signal provider
+    pub multisig_threshold: u8,
+    pub multisig_signers: Vec<String>,
+    /// Trader accounts of the signal provider, which cannot create orders while there are any
+    pub number_of_traders: u16,
+    pub max_order_ratio: u16,
+    pub max_daily_turnover: u32,
+    pub max_daily_orders: u16,
+    pub max_price_deviation: u16,
+    pub allowed_order_types: Vec<String>,
+    pub allowed_self_trade_behaviors: Vec<String>,
+    /// Start of the current trading day, over which the daily limits apply
+    pub trading_day_start: u64,
+    pub daily_turnover: u32,
+    pub daily_orders: u16,
+    pub markets: Vec<String>,
+    pub assets: Vec<AssetView>,
+}
+
+/// The decoded on-chain state of a pool.
+pub struct PoolInfo {
+    pub seed: [u8; 32],
+    pub key: Pubkey,
+    pub mint_key: Pubkey,
+    pub header: PoolHeader,
+    pub markets: Vec<Pubkey>,
+    /// The pool assets along with their index in the pool account data. Empty slots are skipped.
+    pub assets: Vec<(usize, Pubkey)>,
+    /// The indices of the unused asset slots.
+    pub free_asset_indices: Vec<usize>,
+}
+
+impl PoolInfo {
+    pub fn fetch(rpc: &RpcClient, program_id: &Pubkey, pool_seed: [u8; 32]) -> CliResult<Self> {
+        let key = get_pool_key(program_id, &pool_seed)?;
+        let mint_key = get_pool_mint_key(program_id, &pool_seed)?;
+        let data = rpc.get_account_data(&key)?;
+        Self::parse(pool_seed, key, mint_key, &data)
+    }
+
+    pub fn parse(seed: [u8; 32], key: Pubkey, mint_key: Pubkey, data: &[u8]) -> CliResult<Self> {
+        let mut data = data.to_vec();
+        let state = PoolState::new(&mut data)?;
+        let header = state.header()?;
+        let markets = state.markets().collect();
+        let assets: Vec<(usize, Pubkey)> = state
+            .assets()
+            .map(|(i, asset)| (i, asset.mint_address))
+            .collect();
+        let free_asset_indices = (0..state.number_of_asset_slots())
+            .filter(|i| assets.iter().all(|(j, _)| i != j))
+            .collect();
+        Ok(Self {
+            seed,
+            key,
+            mint_key,
+            header,
+            markets,
+            assets,
+            free_asset_indices,
+        })
+    }
+
+    pub fn asset_mints(&self) -> Vec<Pubkey> {
+        self.assets.iter().map(|(_, mint)| *mint).collect()
+    }
+
+    pub fn pool_asset_keys(&self) -> Vec<Pubkey> {
+        self.assets
+            .iter()
+            .map(|(_, mint)| get_associated_token_address(&self.key, mint))
+            .collect()
+    }
+
+    pub fn asset_index(&self, mint: &Pubkey) -> Option<usize> {
+        self.assets
+            .iter()
+            .find(|(_, m)| m == mint)
+            .map(|(i, _)| *i)
+    }
+
+    /// Returns the (coin, pc) asset indices to use when settling on a market. Mints which are
+    /// not yet held by the pool are assigned distinct free slots.
+    pub fn settle_indices(&self, coin_mint: &Pubkey, pc_mint: &Pubkey) -> Option<(usize, usize)> {
+        let mut free_slots = self.free_asset_indices.iter();
+        let coin_index = match self.asset_index(coin_mint) {
+            Some(i) => i,
+            None => *free_slots.next()?,
+        };
+        let pc_index = match self.asset_index(pc_mint) {
+            Some(i) => i,
+            None => *free_slots.next()?,
+        };
+        Some((coin_index, pc_index))
+    }
+
+    pub fn view(&self, rpc: &RpcClient) -> CliResult<PoolView> {
+        let pooltoken_supply = Mint::unpack(&rpc.get_account_data(&self.mint_key)?)?.supply;
+        let mut assets = Vec::with_capacity(self.assets.len());
+        for (index, mint) in self.assets.iter() {
+            let pool_asset_key = get_associated_token_address(&self.key, mint);
+            let balance = Account::unpack(&rpc.get_account_data(&pool_asset_key)?)?.amount;
+            assets.push(AssetView {
+                index: *index,
+                mint: mint.to_string(),
+                pool_asset_account: pool_asset_key.to_string(),
+                balance,
+            });
+        }
+        Ok(PoolView {
+            pool_seed: bs58::encode(&self.seed).into_string(),
+            pool_key: self.key.to_string(),
+            pool_mint: self.mint_key.to_string(),
+            pooltoken_supply,
+            serum_program_id: self.header.serum_program_id.to_string(),
+            signal_provider: self.header.signal_provider.to_string(),
+            status: format_status(&self.header.status),
+            fee_ratio: self.header.fee_ratio,
+            last_fee_collection_timestamp: self.header.last_fee_collection_timestamp,
+            annual_fee_ratio: self.header.annual_fee_ratio,
+            accrued_fees: self.header.accrued_fees,
+            unclaimed_fees: owed_fees(self.header.accrued_fees, &self.header.claimed_fees)?,
+            queued_redeem_requests: self.header.redeem_queue_tail - self.header.redeem_queue_head,
+            queued_deposit_requests: self.header.deposit_queue_tail
+                - self.header.deposit_queue_head,
+            bond_amount: self.header.bond_amount,
+            bond_unlock_timestamp: self.header.bond_unlock_timestamp,
+            pending_order_timestamp: self.header.pending_order_timestamp,
+            min_signal_provider_ratio: self.header.min_signal_provider_ratio,
+            multisig_threshold: self.header.multisig_threshold,
+            multisig_signers: self
+                .header
+                .multisig_signers
+                .iter()
+                .filter(|k| **k != Pubkey::default())
+                .map(|k| k.to_string())
+                .collect(),
+            number_of_traders: self.header.number_of_traders,
+            max_order_ratio: self.header.trade_limits.max_order_ratio,
+            max_daily_turnover: self.header.trade_limits.max_daily_turnover,
+            max_daily_orders: self.header.trade_limits.max_daily_orders,
+            max_price_deviation: self.header.trade_limits.max_price_deviation,
+            allowed_order_types: set_names(
+                self.header.trade_limits.allowed_order_types,
+                &ORDER_TYPES,
+            ),
+            allowed_self_trade_behaviors: set_names(
+                self.header.trade_limits.allowed_self_trade_behaviors,
+                &SELF_TRADE_BEHAVIORS,
+            ),
+            trading_day_start: self.header.trading_day_start,
+            daily_turnover: self.header.daily_turnover,
+            daily_orders: self.header.daily_orders,
+            markets: self.markets.iter().map(|m| m.to_string()).collect(),
+            assets,
+        })
+    }
+}
+
+pub fn format_status(status: &PoolStatus) -> String {
+    match status {
+        PoolStatus::Uninitialized => "Uninitialized".to_owned(),
+        PoolStatus::Unlocked => "Unlocked".to_owned(),
+        PoolStatus::Locked => "Locked".to_owned(),
+        PoolStatus::PendingOrder(n) => format!("PendingOrder({})", n),
+        PoolStatus::LockedPendingOrder(n) => format!("LockedPendingOrder({})", n),
+    }
+}
+
+impl PoolView {
+    pub fn print_table(&self) {
+        println!("{:<32} {}", "Pool seed", self.pool_seed);
+        println!("{:<32} {}", "Pool account", self.pool_key);
+        println!("{:<32} {}", "Pooltoken mint", self.pool_mint);
+        println!("{:<32} {}", "Pooltoken supply", self.pooltoken_supply);
+        println!("{:<32} {}", "Serum program", self.serum_program_id);
+        println!("{:<32} {}", "Signal provider", self.signal_provider);
+        println!("{:<32} {}", "Status", self.status);
+        println!("{:<32} {}", "Fee ratio (2^-16)", self.fee_ratio);
+        println!(
+            "{:<32} {}",
+            "Last fee collection", self.last_fee_collection_timestamp
+        );
+        println!("{:<32} {}", "Annual fee ratio (2^-16)", self.annual_fee_ratio);
+        println!("{:<32} {}", "Accrued fees", self.accrued_fees);
+        println!(
+            "{:<32} {} / {} / {}",
+            "Unclaimed fees (SP/Bonfida/BNB)",
+            self.unclaimed_fees[0],
+            self.unclaimed_fees[1],
+            self.unclaimed_fees[2]
+        );
+        println!(
+            "{:<32} {}",
+            "Queued redeem requests", self.queued_redeem_requests
+        );
+        println!(
+            "{:<32} {}",
+            "Queued deposit requests", self.queued_deposit_requests
+        );
+        println!("{:<32} {}", "Bond (FIDA)", self.bond_amount);
+        println!("{:<32} {}", "Bond unlock", self.bond_unlock_timestamp);
+        println!(
+            "{:<32} {}",
+            "Pending orders since", self.pending_order_timestamp
+        );
+        println!(
+            "{:<32} {}",
+            "Min SP share (2^-16)", self.min_signal_provider_ratio
+        );
+        if self.multisig_threshold > 0 {
+            println!(
+                "{:<32} {} of {}",
+                "Multisig threshold",
+                self.multisig_threshold,
+                self.multisig_signers.len()
+            );
+            for signer in &self.multisig_signers {
+                println!("{:<32} {}", "Multisig signer", signer);
+            }
+        }
+        println!("{:<32} {}", "Traders", self.number_of_traders);
+        println!("{:<32} {}", "Max order ratio (2^-16)", self.max_order_ratio);
+        println!(
+            "{:<32} {} / {}",
+            "Daily turnover (2^-16)", self.daily_turnover, self.max_daily_turnover
+        );
+        println!(
+            "{:<32} {} / {}",
+            "Daily orders", self.daily_orders, self.max_daily_orders
+        );
+        println!("{:<32} {}", "Trading day start", self.trading_day_start);
+        println!(
+            "{:<32} {}",
+            "Max price deviation (2^-16)", self.max_price_deviation
+        );
+        println!(
+            "{:<32} {}",
+            "Allowed order types",
+            self.allowed_order_types.join(", ")
+        );
+        println!(
+            "{:<32} {}",
+            "Allowed self trade behaviors",
+            self.allowed_self_trade_behaviors.join(", ")
+        );
+        println!();
+        println!("Markets");
+        for (i, market) in self.markets.iter().enumerate() {
+            println!("  {:>3}  {}", i, market);
+        }
+        println!();
+        println!(
+            "{:>5}  {:<44}  {:<44}  {:>20}",
+            "Index", "Mint", "Pool asset account", "Balance"
+        );
+        for asset in self.assets.iter() {
+            println!(
+                "{:>5}  {:<44}  {:<44}  {:>20}",
+                asset.index, asset.mint, asset.pool_asset_account, asset.balance
+            );
+        }
+    }
+}
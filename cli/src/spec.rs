@@ -0,0 +1,165 @@
+use std::{fmt::Debug, fs, path::Path, str::FromStr};
+
+use bonfida_bot::{
+    instruction::{OrderType, SelfTradeBehavior},
+    state::TradeLimits,
+};
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+
+use crate::CliResult;
+
+/// A pool specification, read from a TOML file.
+///
+/// ```toml
+/// signal_provider = "<pubkey>"
+/// max_number_of_assets = 10
+/// annual_fee_ratio = 1311
+/// fee_ratio = 655
+/// min_signal_provider_ratio = 3277
+/// max_order_ratio = 6554
+/// max_daily_turnover = 65536
+/// max_daily_orders = 24
+/// max_price_deviation = 3277
+/// allowed_order_types = ["ImmediateOrCancel"]
+/// allowed_self_trade_behaviors = ["DecrementTake", "CancelProvide"]
+/// markets = ["<market pubkey>"]
+///
+/// [[deposits]]
+/// source = "<source token account>"
+/// amount = 1000000
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct PoolSpec {
+    pub signal_provider: String,
+    /// Defaults to the mainnet Serum dex program.
+    pub serum_program_id: Option<String>,
+    pub max_number_of_assets: u32,
+    /// The share of the pool taken as management fees over a year, in units of 2^-16.
+    pub annual_fee_ratio: u16,
+    /// The share of the deposits taken as fees, in units of 2^-16.
+    pub fee_ratio: u16,
+    /// The share of the pool tokens the signal provider has to hold to send orders, in units of
+    /// 2^-16. Defaults to no requirement.
+    #[serde(default)]
+    pub min_signal_provider_ratio: u16,
+    /// The largest share of a pool asset traded by a single order, in units of 2^-16. Defaults
+    /// to no limit.
+    #[serde(default)]
+    pub max_order_ratio: u16,
+    /// The largest sum of the order ratios over a trading day, in units of 2^-16. Defaults to no
+    /// limit.
+    #[serde(default)]
+    pub max_daily_turnover: u32,
+    /// The largest number of orders over a trading day. Defaults to no limit.
+    #[serde(default)]
+    pub max_daily_orders: u16,
+    /// The largest deviation of the limit prices from the best price of the market book, in units
+    /// of 2^-16. Defaults to no limit.
+    #[serde(default)]
+    pub max_price_deviation: u16,
+    /// The names of the order types the pool allows. Defaults to all of them.
+    #[serde(default)]
+    pub allowed_order_types: Vec<String>,
+    /// The names of the self trade behaviors the pool allows. Defaults to all of them.
+    #[serde(default)]
+    pub allowed_self_trade_behaviors: Vec<String>,
+    pub markets: Vec<String>,
+    pub deposits: Vec<DepositSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DepositSpec {
+    pub source: String,
+    pub amount: u64,
+}
+
+pub const SERUM_PROGRAM_ID: &str = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+
+/// The order types and self trade behaviors in the order of their bit in the pool trade limits.
+pub const ORDER_TYPES: [OrderType; 3] = [
+    OrderType::Limit,
+    OrderType::ImmediateOrCancel,
+    OrderType::PostOnly,
+];
+pub const SELF_TRADE_BEHAVIORS: [SelfTradeBehavior; 3] = [
+    SelfTradeBehavior::DecrementTake,
+    SelfTradeBehavior::CancelProvide,
+    SelfTradeBehavior::AbortTransaction,
+];
+
+/// Returns the set of the given names among `values`, where bit `i` stands for `values[i]`.
+fn name_set<T: Debug>(names: &[String], values: &[T]) -> CliResult<u8> {
+    names.iter().try_fold(0, |set, name| {
+        let index = values
+            .iter()
+            .position(|v| &format!("{:?}", v) == name)
+            .ok_or_else(|| format!("Unknown order policy value {}", name))?;
+        Ok(set | 1 << index)
+    })
+}
+
+/// Returns the names of the `values` in a set, an empty set standing for all of them.
+pub fn set_names<T: Debug>(set: u8, values: &[T]) -> Vec<String> {
+    values
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| set == 0 || set & 1 << i != 0)
+        .map(|(_, v)| format!("{:?}", v))
+        .collect()
+}
+
+impl PoolSpec {
+    pub fn load<P: AsRef<Path>>(path: P) -> CliResult<Self> {
+        let spec: Self = toml::from_str(&fs::read_to_string(path)?)?;
+        if spec.deposits.is_empty() {
+            return Err("The pool spec should contain at least one deposit".into());
+        }
+        if spec.deposits.len() > spec.max_number_of_assets as usize {
+            return Err("The pool spec contains more deposits than max_number_of_assets".into());
+        }
+        Ok(spec)
+    }
+
+    pub fn signal_provider(&self) -> CliResult<Pubkey> {
+        Ok(Pubkey::from_str(&self.signal_provider)?)
+    }
+
+    pub fn serum_program_id(&self) -> CliResult<Pubkey> {
+        Ok(Pubkey::from_str(
+            self.serum_program_id.as_deref().unwrap_or(SERUM_PROGRAM_ID),
+        )?)
+    }
+
+    pub fn markets(&self) -> CliResult<Vec<Pubkey>> {
+        self.markets
+            .iter()
+            .map(|m| Ok(Pubkey::from_str(m)?))
+            .collect()
+    }
+
+    pub fn sources(&self) -> CliResult<Vec<Pubkey>> {
+        self.deposits
+            .iter()
+            .map(|d| Ok(Pubkey::from_str(&d.source)?))
+            .collect()
+    }
+
+    pub fn trade_limits(&self) -> CliResult<TradeLimits> {
+        Ok(TradeLimits {
+            max_order_ratio: self.max_order_ratio,
+            max_daily_turnover: self.max_daily_turnover,
+            max_daily_orders: self.max_daily_orders,
+            max_price_deviation: self.max_price_deviation,
+            allowed_order_types: name_set(&self.allowed_order_types, &ORDER_TYPES)?,
+            allowed_self_trade_behaviors: name_set(
+                &self.allowed_self_trade_behaviors,
+                &SELF_TRADE_BEHAVIORS,
+            )?,
+        })
+    }
+
+    pub fn deposit_amounts(&self) -> Vec<u64> {
+        self.deposits.iter().map(|d| d.amount).collect()
+    }
+}
@@ -0,0 +1,230 @@
+#![cfg(feature = "test-validator")]
+//! End to end tests of the CLI against a local `solana-test-validator`.
+//!
+//! The bonfida-bot program needs to be built beforehand with `cargo build-bpf` in the `program`
+//! folder. The `solana-test-validator` binary should be available in the `PATH`.
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Child, Command, Output, Stdio},
+    thread::sleep,
+    time::Duration,
+};
+
+use solana_client::rpc_client::RpcClient;
+use solana_program::{program_pack::Pack, pubkey::Pubkey, system_instruction};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::{write_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::{
+    instruction::{initialize_mint, mint_to},
+    state::Mint,
+};
+
+const RPC_URL: &str = "http://localhost:8899";
+
+struct TestValidator {
+    process: Child,
+    ledger: PathBuf,
+}
+
+impl TestValidator {
+    fn start(program_id: &Pubkey) -> Self {
+        let ledger = std::env::temp_dir().join(format!("bonfida-bot-cli-{}", program_id));
+        let program_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../program/target/deploy/bonfida_bot.so");
+        let process = Command::new("solana-test-validator")
+            .arg("--reset")
+            .arg("--quiet")
+            .arg("--ledger")
+            .arg(&ledger)
+            .arg("--bpf-program")
+            .arg(program_id.to_string())
+            .arg(program_path)
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("Failed to start solana-test-validator");
+        let rpc = RpcClient::new(RPC_URL.to_owned());
+        for _ in 0..60 {
+            if rpc.get_recent_blockhash().is_ok() {
+                break;
+            }
+            sleep(Duration::from_secs(1));
+        }
+        Self { process, ledger }
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        self.process.kill().ok();
+        fs::remove_dir_all(&self.ledger).ok();
+    }
+}
+
+fn send(rpc: &RpcClient, instructions: &[solana_program::instruction::Instruction], signers: &[&Keypair]) {
+    let (recent_blockhash, _) = rpc.get_recent_blockhash().unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signers[0].pubkey()),
+        signers,
+        recent_blockhash,
+    );
+    rpc.send_and_confirm_transaction(&transaction).unwrap();
+}
+
+fn run_cli(keypair_path: &PathBuf, program_id: &Pubkey, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bonfida-bot-cli"))
+        .arg("--url")
+        .arg(RPC_URL)
+        .arg("--keypair")
+        .arg(keypair_path)
+        .arg("--program-id")
+        .arg(program_id.to_string())
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cli_end_to_end() {
+    let program_id = Pubkey::new_unique();
+    let _validator = TestValidator::start(&program_id);
+    let rpc = RpcClient::new_with_commitment(RPC_URL.to_owned(), CommitmentConfig::confirmed());
+
+    let payer = Keypair::new();
+    let keypair_path = std::env::temp_dir().join(format!("{}.json", payer.pubkey()));
+    write_keypair_file(&payer, &keypair_path).unwrap();
+    let airdrop = rpc.request_airdrop(&payer.pubkey(), 100_000_000_000).unwrap();
+    while !rpc.confirm_transaction(&airdrop).unwrap() {
+        sleep(Duration::from_millis(500));
+    }
+
+    // Create two asset mints and fund the payer
+    let mut sources = vec![];
+    for _ in 0..2 {
+        let mint = Keypair::new();
+        let source = get_associated_token_address(&payer.pubkey(), &mint.pubkey());
+        send(
+            &rpc,
+            &[
+                system_instruction::create_account(
+                    &payer.pubkey(),
+                    &mint.pubkey(),
+                    rpc.get_minimum_balance_for_rent_exemption(Mint::LEN).unwrap(),
+                    Mint::LEN as u64,
+                    &spl_token::id(),
+                ),
+                initialize_mint(&spl_token::id(), &mint.pubkey(), &payer.pubkey(), None, 6)
+                    .unwrap(),
+                create_associated_token_account(&payer.pubkey(), &payer.pubkey(), &mint.pubkey()),
+                mint_to(
+                    &spl_token::id(),
+                    &mint.pubkey(),
+                    &source,
+                    &payer.pubkey(),
+                    &[],
+                    1 << 30,
+                )
+                .unwrap(),
+            ],
+            &[&payer, &mint],
+        );
+        sources.push(source);
+    }
+
+    let spec_path = std::env::temp_dir().join(format!("{}.toml", payer.pubkey()));
+    fs::write(
+        &spec_path,
+        format!(
+            r#"
+signal_provider = "{}"
+max_number_of_assets = 10
+annual_fee_ratio = 1311
+fee_ratio = 655
+markets = ["{}"]
+
+[[deposits]]
+source = "{}"
+amount = 1000000
+
+[[deposits]]
+source = "{}"
+amount = 2000000
+"#,
+            payer.pubkey(),
+            Pubkey::new_unique(),
+            sources[0],
+            sources[1]
+        ),
+    )
+    .unwrap();
+
+    let output = run_cli(&keypair_path, &program_id, &["create", "--spec", spec_path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let pool_seed = stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("Pool seed: "))
+        .unwrap()
+        .to_owned();
+
+    let output = run_cli(&keypair_path, &program_id, &["deposit", "--pool-seed", &pool_seed, "--amount", "500000"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = run_cli(&keypair_path, &program_id, &["inspect", "--pool-seed", &pool_seed, "--output", "json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let view: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // The deposit fees are accounted for in the pool until they are claimed
+    assert_eq!(view["pooltoken_supply"], 1_495_003);
+    assert_eq!(view["accrued_fees"], 4_997);
+    assert_eq!(view["assets"][0]["balance"], 1_500_000);
+    assert_eq!(view["assets"][1]["balance"], 3_000_000);
+
+    let output = run_cli(&keypair_path, &program_id, &["claim-fees", "--pool-seed", &pool_seed]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run_cli(&keypair_path, &program_id, &["inspect", "--pool-seed", &pool_seed, "--output", "json"]);
+    let view: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(view["pooltoken_supply"], 1_497_502);
+    assert_eq!(view["unclaimed_fees"], serde_json::json!([0, 1_249, 1_249]));
+
+    let output = run_cli(&keypair_path, &program_id, &["redeem", "--pool-seed", &pool_seed, "--amount", "750000"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = run_cli(&keypair_path, &program_id, &["request-redeem", "--pool-seed", &pool_seed, "--amount", "100000"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run_cli(&keypair_path, &program_id, &["inspect", "--pool-seed", &pool_seed, "--output", "json"]);
+    let view: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(view["queued_redeem_requests"], 1);
+
+    let output = run_cli(&keypair_path, &program_id, &["process-redeem-queue", "--pool-seed", &pool_seed]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run_cli(&keypair_path, &program_id, &["inspect", "--pool-seed", &pool_seed, "--output", "json"]);
+    let view: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(view["queued_redeem_requests"], 0);
+    let output = run_cli(&keypair_path, &program_id, &["process-redeem-queue", "--pool-seed", &pool_seed]);
+    assert!(!output.status.success());
+
+    let output = run_cli(&keypair_path, &program_id, &["request-deposit", "--pool-seed", &pool_seed, "--amount", "100000"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run_cli(&keypair_path, &program_id, &["inspect", "--pool-seed", &pool_seed, "--output", "json"]);
+    let view: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(view["queued_deposit_requests"], 1);
+
+    let output = run_cli(&keypair_path, &program_id, &["process-deposit-queue", "--pool-seed", &pool_seed]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run_cli(&keypair_path, &program_id, &["inspect", "--pool-seed", &pool_seed, "--output", "json"]);
+    let view: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(view["queued_deposit_requests"], 0);
+
+    // Not enough time has elapsed for the management fees to amount to a pool token
+    let output = run_cli(&keypair_path, &program_id, &["collect-fees", "--pool-seed", &pool_seed]);
+    assert!(!output.status.success());
+
+    // The registered market is a random key without any account behind it
+    let output = run_cli(&keypair_path, &program_id, &["settle-all", "--pool-seed", &pool_seed]);
+    assert!(!output.status.success());
+}
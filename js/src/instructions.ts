@@ -6,13 +6,27 @@ import bs58 from 'bs58';
 
 export enum Instruction {
   Init,
-  Create,
+  // The original Create layout, still accepted by the program
+  LegacyCreate,
   Deposit,
   CreateOrder,
   CancelOrder,
   SettleFunds,
   Redeem,
   CollectFees,
+  ClaimFees,
+  RequestRedeem,
+  ProcessRedeemQueue,
+  RequestDeposit,
+  ProcessDepositQueue,
+  PostBond,
+  RequestBondWithdrawal,
+  WithdrawBond,
+  SlashBond,
+  AddTrader,
+  RemoveTrader,
+  SetMultisig,
+  Create,
 }
 
 export interface InitInstructionData {
@@ -22,8 +36,15 @@ export interface InitInstructionData {
 }
 export interface CreateInstructionData {
   poolSeed: string;
-  feeCollectionPeriod: number;
+  annualFeeRatio: number;
   feeRatio: number;
+  minSignalProviderRatio: number;
+  maxOrderRatio: number;
+  maxDailyTurnover: number;
+  maxDailyOrders: number;
+  maxPriceDeviation: number;
+  allowedOrderTypes: number;
+  allowedSelfTradeBehaviors: number;
   depositAmounts: number[];
   markets: PublicKey[];
 }
@@ -68,6 +89,48 @@ export interface RedeemInstructionData {
 export interface CollectFeesInstructionData {
   poolSeed: string;
 }
+export interface ClaimFeesInstructionData {
+  poolSeed: string;
+}
+export interface RequestRedeemInstructionData {
+  poolSeed: string;
+  poolTokenAmount: number;
+}
+export interface ProcessRedeemQueueInstructionData {
+  poolSeed: string;
+  numberOfRequests: number;
+}
+export interface RequestDepositInstructionData {
+  poolSeed: string;
+  poolTokenAmount: number;
+  expiryTimestamp: number;
+  assetAmounts: number[];
+}
+export interface ProcessDepositQueueInstructionData {
+  poolSeed: string;
+  numberOfRequests: number;
+}
+export interface PostBondInstructionData {
+  poolSeed: string;
+  amount: number;
+}
+export interface BondInstructionData {
+  poolSeed: string;
+}
+export interface AddTraderInstructionData {
+  poolSeed: string;
+  trader: string;
+  maxRatioOfPoolAssetsToTrade: number;
+}
+export interface RemoveTraderInstructionData {
+  poolSeed: string;
+  trader: string;
+}
+export interface SetMultisigInstructionData {
+  poolSeed: string;
+  threshold: number;
+  signers: string[];
+}
 
 export type ParsedInstruction =
   | InitInstructionData
@@ -78,7 +141,17 @@ export type ParsedInstruction =
   | CancelOrderInstructionData
   | SettleFundsInstructionData
   | RedeemInstructionData
-  | CollectFeesInstructionData;
+  | CollectFeesInstructionData
+  | ClaimFeesInstructionData
+  | RequestRedeemInstructionData
+  | ProcessRedeemQueueInstructionData
+  | RequestDepositInstructionData
+  | ProcessDepositQueueInstructionData
+  | PostBondInstructionData
+  | BondInstructionData
+  | AddTraderInstructionData
+  | RemoveTraderInstructionData
+  | SetMultisigInstructionData;
 
 export function decodeInstruction(
   buffer: Buffer,
@@ -109,26 +182,50 @@ export function decodeInstruction(
       };
     }
     case Instruction.Create: {
-      let numberOfMarkets = new BN(
+      let annualFeeRatio =
+        new BN(buffer.slice(offset, offset + 2), 'le').toNumber() / 2 ** 16;
+      offset += 2;
+      let feeRatio =
+        new BN(buffer.slice(offset, offset + 2), 'le').toNumber() / 2 ** 16;
+      offset += 2;
+      let minSignalProviderRatio =
+        new BN(buffer.slice(offset, offset + 2), 'le').toNumber() / 2 ** 16;
+      offset += 2;
+      let maxOrderRatio =
+        new BN(buffer.slice(offset, offset + 2), 'le').toNumber() / 2 ** 16;
+      offset += 2;
+      let maxDailyTurnover =
+        new BN(buffer.slice(offset, offset + 4), 'le').toNumber() / 2 ** 16;
+      offset += 4;
+      let maxDailyOrders = new BN(
         buffer.slice(offset, offset + 2),
         'le',
       ).toNumber();
       offset += 2;
-      let feeCollectionPeriod = new BN(
-        buffer.slice(offset, offset + 8),
-        'le',
-      ).toNumber();
-      offset += 8;
-      let feeRatio =
+      let maxPriceDeviation =
         new BN(buffer.slice(offset, offset + 2), 'le').toNumber() / 2 ** 16;
       offset += 2;
+      let allowedOrderTypes = buffer[offset];
+      offset++;
+      let allowedSelfTradeBehaviors = buffer[offset];
+      offset++;
+      let numberOfMarkets = new BN(
+        buffer.slice(offset, offset + 4),
+        'le',
+      ).toNumber();
+      offset += 4;
       let markets: PublicKey[] = [];
       for (let i = 0; i < numberOfMarkets; i++) {
         markets.push(new PublicKey(buffer.slice(offset, offset + 32)));
         offset += 32;
       }
+      let numberOfDeposits = new BN(
+        buffer.slice(offset, offset + 4),
+        'le',
+      ).toNumber();
+      offset += 4;
       let depositAmounts: number[] = [];
-      while (offset < buffer.length) {
+      for (let i = 0; i < numberOfDeposits; i++) {
         depositAmounts.push(
           new BN(buffer.slice(offset, offset + 8), 'le').toNumber(),
         );
@@ -137,8 +234,15 @@ export function decodeInstruction(
 
       return {
         poolSeed: bs58.encode(poolSeed),
-        feeCollectionPeriod,
+        annualFeeRatio,
         feeRatio,
+        minSignalProviderRatio,
+        maxOrderRatio,
+        maxDailyTurnover,
+        maxDailyOrders,
+        maxPriceDeviation,
+        allowedOrderTypes,
+        allowedSelfTradeBehaviors,
         depositAmounts,
         markets,
       };
@@ -254,9 +358,123 @@ export function decodeInstruction(
         poolTokenAmount,
       };
     }
-    case Instruction.CollectFees: {
+    case Instruction.CollectFees:
+    case Instruction.ClaimFees: {
+      return {
+        poolSeed: bs58.encode(poolSeed),
+      };
+    }
+    case Instruction.RequestRedeem: {
+      let poolTokenAmount = new BN(
+        buffer.slice(offset, offset + 8),
+        'le',
+      ).toNumber();
+      offset += 8;
+      return {
+        poolSeed: bs58.encode(poolSeed),
+        poolTokenAmount,
+      };
+    }
+    case Instruction.ProcessRedeemQueue: {
+      let numberOfRequests = buffer[offset];
+      offset++;
+      return {
+        poolSeed: bs58.encode(poolSeed),
+        numberOfRequests,
+      };
+    }
+    case Instruction.RequestDeposit: {
+      let poolTokenAmount = new BN(
+        buffer.slice(offset, offset + 8),
+        'le',
+      ).toNumber();
+      offset += 8;
+      let expiryTimestamp = new BN(
+        buffer.slice(offset, offset + 8),
+        'le',
+      ).toNumber();
+      offset += 8;
+      let numberOfAssets = new BN(
+        buffer.slice(offset, offset + 4),
+        'le',
+      ).toNumber();
+      offset += 4;
+      let assetAmounts: number[] = [];
+      for (let i = 0; i < numberOfAssets; i++) {
+        assetAmounts.push(
+          new BN(buffer.slice(offset, offset + 8), 'le').toNumber(),
+        );
+        offset += 8;
+      }
+      return {
+        poolSeed: bs58.encode(poolSeed),
+        poolTokenAmount,
+        expiryTimestamp,
+        assetAmounts,
+      };
+    }
+    case Instruction.ProcessDepositQueue: {
+      let numberOfRequests = buffer[offset];
+      offset++;
+      return {
+        poolSeed: bs58.encode(poolSeed),
+        numberOfRequests,
+      };
+    }
+    case Instruction.PostBond: {
+      let amount = new BN(buffer.slice(offset, offset + 8), 'le').toNumber();
+      offset += 8;
+      return {
+        poolSeed: bs58.encode(poolSeed),
+        amount,
+      };
+    }
+    case Instruction.RequestBondWithdrawal:
+    case Instruction.WithdrawBond:
+    case Instruction.SlashBond: {
+      return {
+        poolSeed: bs58.encode(poolSeed),
+      };
+    }
+    case Instruction.AddTrader: {
+      let trader = bs58.encode(buffer.slice(offset, offset + 32));
+      offset += 32;
+      let maxRatioOfPoolAssetsToTrade = new BN(
+        buffer.slice(offset, offset + 2),
+        'le',
+      ).toNumber();
+      offset += 2;
+      return {
+        poolSeed: bs58.encode(poolSeed),
+        trader,
+        maxRatioOfPoolAssetsToTrade,
+      };
+    }
+    case Instruction.RemoveTrader: {
+      let trader = bs58.encode(buffer.slice(offset, offset + 32));
+      offset += 32;
+      return {
+        poolSeed: bs58.encode(poolSeed),
+        trader,
+      };
+    }
+    case Instruction.SetMultisig: {
+      let threshold = buffer[offset];
+      offset++;
+      let numberOfSigners = new BN(
+        buffer.slice(offset, offset + 4),
+        'le',
+      ).toNumber();
+      offset += 4;
+      let signers: string[] = [];
+      for (let i = 0; i < numberOfSigners; i++) {
+        signers.push(bs58.encode(buffer.slice(offset, offset + 32)));
+        offset += 32;
+      }
       return {
         poolSeed: bs58.encode(poolSeed),
+        threshold,
+        signers,
       };
     }
   }
@@ -339,21 +557,36 @@ export function createInstruction(
   signalProviderKey: PublicKey,
   depositAmounts: Array<number>,
   markets: Array<PublicKey>,
-  feeCollectionPeriod: Numberu64,
+  annualFeeRatio: Numberu16,
   feeRatio: Numberu16,
+  minSignalProviderRatio: Numberu16,
+  maxOrderRatio: Numberu16,
+  maxDailyTurnover: Numberu32,
+  maxDailyOrders: Numberu16,
+  maxPriceDeviation: Numberu16,
+  allowedOrderTypes: number,
+  allowedSelfTradeBehaviors: number,
 ): TransactionInstruction {
   let buffers = [
-    Buffer.from(Int8Array.from([1])),
+    Buffer.from(Int8Array.from([20])),
     Buffer.concat(poolSeed),
-    // @ts-ignore
-    new Numberu16(markets.length).toBuffer(),
-    feeCollectionPeriod.toBuffer(),
+    annualFeeRatio.toBuffer(),
     feeRatio.toBuffer(),
+    minSignalProviderRatio.toBuffer(),
+    maxOrderRatio.toBuffer(),
+    maxDailyTurnover.toBuffer(),
+    maxDailyOrders.toBuffer(),
+    maxPriceDeviation.toBuffer(),
+    Buffer.from(Uint8Array.from([allowedOrderTypes, allowedSelfTradeBehaviors])),
+    // @ts-ignore
+    new Numberu32(markets.length).toBuffer(),
   ];
   for (var market of markets) {
     // @ts-ignore
     buffers.push(market.toBuffer());
   }
+  // @ts-ignore
+  buffers.push(new Numberu32(depositAmounts.length).toBuffer());
   for (var amount of depositAmounts) {
     // @ts-ignore
     buffers.push(new Numberu64(amount).toBuffer());
@@ -427,9 +660,7 @@ export function createInstruction(
 export function depositInstruction(
   splTokenProgramId: PublicKey,
   bonfidaBotProgramId: PublicKey,
-  sigProviderFeeReceiverKey: PublicKey,
-  bonfidaFeeReceiverKey: PublicKey,
-  bonfidaBuyAndBurnKey: PublicKey,
+  clockSysvarKey: PublicKey,
   mintKey: PublicKey,
   poolKey: PublicKey,
   poolAssetKeys: Array<PublicKey>,
@@ -454,34 +685,24 @@ export function depositInstruction(
       isWritable: false,
     },
     {
-      pubkey: mintKey,
-      isSigner: false,
-      isWritable: true,
-    },
-    {
-      pubkey: targetPoolTokenKey,
-      isSigner: false,
-      isWritable: true,
-    },
-    {
-      pubkey: sigProviderFeeReceiverKey,
+      pubkey: clockSysvarKey,
       isSigner: false,
-      isWritable: true,
+      isWritable: false,
     },
     {
-      pubkey: bonfidaFeeReceiverKey,
+      pubkey: mintKey,
       isSigner: false,
       isWritable: true,
     },
     {
-      pubkey: bonfidaBuyAndBurnKey,
+      pubkey: targetPoolTokenKey,
       isSigner: false,
       isWritable: true,
     },
     {
       pubkey: poolKey,
       isSigner: false,
-      isWritable: false,
+      isWritable: true,
     },
   ];
   for (var poolAsset of poolAssetKeys) {
@@ -529,6 +750,10 @@ export function createOrderInstruction(
   splTokenProgramId: PublicKey,
   dexProgramKey: PublicKey,
   rentProgramId: PublicKey,
+  poolMintKey: PublicKey,
+  signalProviderPoolTokenKey: PublicKey,
+  traderKey: PublicKey,
+  clockSysvarKey: PublicKey,
   srmReferrerKey: PublicKey | null,
   poolSeed: Array<Buffer | Uint8Array>,
   side: OrderSide,
@@ -633,6 +858,26 @@ export function createOrderInstruction(
       isSigner: false,
       isWritable: false,
     },
+    {
+      pubkey: poolMintKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: signalProviderPoolTokenKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: traderKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
   ];
   if (!!srmReferrerKey) {
     keys.push({
@@ -659,6 +904,7 @@ export function cancelOrderInstruction(
   serumMarketAsks: PublicKey,
   poolKey: PublicKey,
   dexProgramKey: PublicKey,
+  traderKey: PublicKey | null,
   poolSeed: Array<Buffer | Uint8Array>,
   side: OrderSide,
   orderId: Numberu128,
@@ -713,6 +959,13 @@ export function cancelOrderInstruction(
       isWritable: false,
     },
   ];
+  if (!!traderKey) {
+    keys.push({
+      pubkey: traderKey,
+      isSigner: false,
+      isWritable: false,
+    });
+  }
 
   return new TransactionInstruction({
     keys,
@@ -895,25 +1148,16 @@ export function redeemInstruction(
 }
 
 export function collectFeesInstruction(
-  splTokenProgramId: PublicKey,
   clockSysvarKey: PublicKey,
   bonfidaBotProgramId: PublicKey,
   poolKey: PublicKey,
   mintKey: PublicKey,
-  signalProviderPoolTokenKey: PublicKey,
-  bonfidaFeePoolTokenKey: PublicKey,
-  bonfidaBnBPTKey: PublicKey,
   poolSeed: Array<Buffer | Uint8Array>,
 ): TransactionInstruction {
   let buffers = [Buffer.from(Int8Array.from([7])), Buffer.concat(poolSeed)];
 
   const data = Buffer.concat(buffers);
   const keys = [
-    {
-      pubkey: splTokenProgramId,
-      isSigner: false,
-      isWritable: false,
-    },
     {
       pubkey: clockSysvarKey,
       isSigner: false,
@@ -927,20 +1171,52 @@ export function collectFeesInstruction(
     {
       pubkey: mintKey,
       isSigner: false,
-      isWritable: true,
+      isWritable: false,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function claimFeesInstruction(
+  splTokenProgramId: PublicKey,
+  clockSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  mintKey: PublicKey,
+  recipientPoolTokenKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+): TransactionInstruction {
+  let buffers = [Buffer.from(Int8Array.from([8])), Buffer.concat(poolSeed)];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
     },
     {
-      pubkey: signalProviderPoolTokenKey,
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
       isSigner: false,
       isWritable: true,
     },
     {
-      pubkey: bonfidaFeePoolTokenKey,
+      pubkey: mintKey,
       isSigner: false,
       isWritable: true,
     },
     {
-      pubkey: bonfidaBnBPTKey,
+      pubkey: recipientPoolTokenKey,
       isSigner: false,
       isWritable: true,
     },
@@ -952,3 +1228,788 @@ export function collectFeesInstruction(
     data,
   });
 }
+
+export function requestRedeemInstruction(
+  splTokenProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  rentSysvarKey: PublicKey,
+  associatedTokenProgramId: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  mintKey: PublicKey,
+  poolKey: PublicKey,
+  redeemRequestKey: PublicKey,
+  escrowKey: PublicKey,
+  sourcePoolTokenOwnerKey: PublicKey,
+  sourcePoolTokenKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  poolTokenAmount: Numberu64,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([9])),
+    Buffer.concat(poolSeed),
+    // @ts-ignore
+    new Numberu64(poolTokenAmount).toBuffer(),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: rentSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: associatedTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: mintKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: redeemRequestKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: escrowKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: sourcePoolTokenOwnerKey,
+      isSigner: true,
+      isWritable: true,
+    },
+    {
+      pubkey: sourcePoolTokenKey,
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export interface RedeemRequestAccounts {
+  redeemRequestKey: PublicKey;
+  escrowKey: PublicKey;
+  ownerKey: PublicKey;
+  ownerAssetKeys: Array<PublicKey>;
+}
+
+export function processRedeemQueueInstruction(
+  splTokenProgramId: PublicKey,
+  clockSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  mintKey: PublicKey,
+  poolKey: PublicKey,
+  poolAssetKeys: Array<PublicKey>,
+  redeemRequests: Array<RedeemRequestAccounts>,
+  poolSeed: Array<Buffer | Uint8Array>,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([10])),
+    Buffer.concat(poolSeed),
+    Buffer.from(Uint8Array.from([redeemRequests.length])),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: mintKey,
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+  for (var poolAsset of poolAssetKeys) {
+    keys.push({
+      pubkey: poolAsset,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+  for (var request of redeemRequests) {
+    for (var key of [request.redeemRequestKey, request.escrowKey, request.ownerKey].concat(
+      request.ownerAssetKeys,
+    )) {
+      keys.push({
+        pubkey: key,
+        isSigner: false,
+        isWritable: true,
+      });
+    }
+  }
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function requestDepositInstruction(
+  splTokenProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  rentSysvarKey: PublicKey,
+  associatedTokenProgramId: PublicKey,
+  clockSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  mintKey: PublicKey,
+  poolKey: PublicKey,
+  depositRequestKey: PublicKey,
+  sourceOwnerKey: PublicKey,
+  poolAssetMints: Array<PublicKey>,
+  sourceAssetKeys: Array<PublicKey>,
+  escrowKeys: Array<PublicKey>,
+  poolSeed: Array<Buffer | Uint8Array>,
+  poolTokenAmount: Numberu64,
+  expiryTimestamp: Numberu64,
+  assetAmounts: Array<Numberu64>,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([11])),
+    Buffer.concat(poolSeed),
+    // @ts-ignore
+    new Numberu64(poolTokenAmount).toBuffer(),
+    // @ts-ignore
+    new Numberu64(expiryTimestamp).toBuffer(),
+  ];
+  // @ts-ignore
+  buffers.push(new Numberu32(assetAmounts.length).toBuffer());
+  for (var amount of assetAmounts) {
+    // @ts-ignore
+    buffers.push(new Numberu64(amount).toBuffer());
+  }
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: rentSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: associatedTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: mintKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: depositRequestKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: sourceOwnerKey,
+      isSigner: true,
+      isWritable: true,
+    },
+  ];
+  for (var assetMint of poolAssetMints) {
+    keys.push({
+      pubkey: assetMint,
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+  for (var key of sourceAssetKeys.concat(escrowKeys)) {
+    keys.push({
+      pubkey: key,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export interface DepositRequestAccounts {
+  depositRequestKey: PublicKey;
+  ownerKey: PublicKey;
+  ownerPoolTokenKey: PublicKey;
+  escrowKeys: Array<PublicKey>;
+  ownerAssetKeys: Array<PublicKey>;
+}
+
+export function processDepositQueueInstruction(
+  splTokenProgramId: PublicKey,
+  clockSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  mintKey: PublicKey,
+  poolKey: PublicKey,
+  poolAssetKeys: Array<PublicKey>,
+  depositRequests: Array<DepositRequestAccounts>,
+  poolSeed: Array<Buffer | Uint8Array>,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([12])),
+    Buffer.concat(poolSeed),
+    Buffer.from(Uint8Array.from([depositRequests.length])),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: mintKey,
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+  for (var poolAsset of poolAssetKeys) {
+    keys.push({
+      pubkey: poolAsset,
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+  for (var request of depositRequests) {
+    for (var key of [
+      request.depositRequestKey,
+      request.ownerKey,
+      request.ownerPoolTokenKey,
+    ]
+      .concat(request.escrowKeys)
+      .concat(request.ownerAssetKeys)) {
+      keys.push({
+        pubkey: key,
+        isSigner: false,
+        isWritable: true,
+      });
+    }
+  }
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function postBondInstruction(
+  splTokenProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  rentSysvarKey: PublicKey,
+  associatedTokenProgramId: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  fidaMintKey: PublicKey,
+  bondKey: PublicKey,
+  bondEscrowKey: PublicKey,
+  signalProviderKey: PublicKey,
+  sourceFidaKey: PublicKey,
+  poolFidaKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  amount: Numberu64,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([13])),
+    Buffer.concat(poolSeed),
+    // @ts-ignore
+    new Numberu64(amount).toBuffer(),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: rentSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: associatedTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: fidaMintKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: bondKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: bondEscrowKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: signalProviderKey,
+      isSigner: true,
+      isWritable: true,
+    },
+    {
+      pubkey: sourceFidaKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: poolFidaKey,
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function requestBondWithdrawalInstruction(
+  clockSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  signalProviderKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+): TransactionInstruction {
+  let buffers = [Buffer.from(Int8Array.from([14])), Buffer.concat(poolSeed)];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: signalProviderKey,
+      isSigner: true,
+      isWritable: false,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function withdrawBondInstruction(
+  splTokenProgramId: PublicKey,
+  clockSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  bondKey: PublicKey,
+  bondEscrowKey: PublicKey,
+  signalProviderKey: PublicKey,
+  targetFidaKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+): TransactionInstruction {
+  let buffers = [Buffer.from(Int8Array.from([15])), Buffer.concat(poolSeed)];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: bondKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: bondEscrowKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: signalProviderKey,
+      isSigner: true,
+      isWritable: true,
+    },
+    {
+      pubkey: targetFidaKey,
+      isSigner: false,
+      isWritable: true,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function slashBondInstruction(
+  splTokenProgramId: PublicKey,
+  systemProgramId: PublicKey,
+  rentSysvarKey: PublicKey,
+  associatedTokenProgramId: PublicKey,
+  clockSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  fidaMintKey: PublicKey,
+  bondKey: PublicKey,
+  bondEscrowKey: PublicKey,
+  poolFidaKey: PublicKey,
+  signalProviderKey: PublicKey,
+  payerKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+): TransactionInstruction {
+  let buffers = [Buffer.from(Int8Array.from([16])), Buffer.concat(poolSeed)];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: rentSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: splTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: associatedTokenProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: clockSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: fidaMintKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: bondKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: bondEscrowKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: poolFidaKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: signalProviderKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: payerKey,
+      isSigner: true,
+      isWritable: true,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function addTraderInstruction(
+  systemProgramId: PublicKey,
+  rentSysvarKey: PublicKey,
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  traderAccountKey: PublicKey,
+  signalProviderKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  trader: PublicKey,
+  maxRatioOfPoolAssetsToTrade: Numberu16,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([17])),
+    Buffer.concat(poolSeed),
+    trader.toBuffer(),
+    maxRatioOfPoolAssetsToTrade.toBuffer(),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: systemProgramId,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: rentSysvarKey,
+      isSigner: false,
+      isWritable: false,
+    },
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: traderAccountKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: signalProviderKey,
+      isSigner: true,
+      isWritable: true,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+export function removeTraderInstruction(
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  traderAccountKey: PublicKey,
+  signalProviderKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  trader: PublicKey,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([18])),
+    Buffer.concat(poolSeed),
+    trader.toBuffer(),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: traderAccountKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: signalProviderKey,
+      isSigner: true,
+      isWritable: true,
+    },
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+// Signed by the signal provider when no signerKeys of the current multisig are given
+export function setMultisigInstruction(
+  bonfidaBotProgramId: PublicKey,
+  poolKey: PublicKey,
+  signalProviderKey: PublicKey,
+  signerKeys: Array<PublicKey>,
+  poolSeed: Array<Buffer | Uint8Array>,
+  threshold: number,
+  multisigSigners: Array<PublicKey>,
+): TransactionInstruction {
+  let buffers = [
+    Buffer.from(Int8Array.from([19])),
+    Buffer.concat(poolSeed),
+    Buffer.from(Uint8Array.from([threshold])),
+    // @ts-ignore
+    new Numberu32(multisigSigners.length).toBuffer(),
+    ...multisigSigners.map(k => k.toBuffer()),
+  ];
+
+  const data = Buffer.concat(buffers);
+  const keys = [
+    {
+      pubkey: poolKey,
+      isSigner: false,
+      isWritable: true,
+    },
+    {
+      pubkey: signalProviderKey,
+      isSigner: signerKeys.length == 0,
+      isWritable: false,
+    },
+    ...signerKeys.map(pubkey => ({
+      pubkey,
+      isSigner: true,
+      isWritable: false,
+    })),
+  ];
+
+  return new TransactionInstruction({
+    keys,
+    programId: bonfidaBotProgramId,
+    data,
+  });
+}
+
+/**
+ * Adapts an instruction reserved to the signal provider to a multisig signal provider: the signer keys are
+ * appended to its accounts and the signal provider no longer signs. postBond and addTrader still need the
+ * signal provider among the signers.
+ */
+/**
+ * Appends the [market, openOrders] pairs of the pending orders of the pool to a redeem
+ * instruction, so that their unsettled funds are included in the payout.
+ */
+export function withUnsettledOpenOrders(
+  instruction: TransactionInstruction,
+  openOrders: Array<[PublicKey, PublicKey]>,
+): TransactionInstruction {
+  openOrders.forEach(([market, openOrdersKey]) =>
+    instruction.keys.push(
+      { pubkey: market, isSigner: false, isWritable: false },
+      { pubkey: openOrdersKey, isSigner: false, isWritable: false },
+    ),
+  );
+  return instruction;
+}
+
+export function withMultisigSigners(
+  instruction: TransactionInstruction,
+  signalProviderKey: PublicKey,
+  signerKeys: Array<PublicKey>,
+): TransactionInstruction {
+  instruction.keys.forEach(k => {
+    if (k.pubkey.equals(signalProviderKey)) {
+      k.isSigner = false;
+    }
+  });
+  signerKeys.forEach(pubkey =>
+    instruction.keys.push({ pubkey, isSigner: true, isWritable: false }),
+  );
+  return instruction;
+}
@@ -12,22 +12,39 @@ import {
 import { TOKEN_PROGRAM_ID, AccountLayout } from '@solana/spl-token';
 import {
   cancelOrderInstruction,
+  claimFeesInstruction,
   collectFeesInstruction,
   createInstruction,
   createOrderInstruction,
   depositInstruction,
+  DepositRequestAccounts,
   initInstruction,
+  processDepositQueueInstruction,
+  postBondInstruction,
+  processRedeemQueueInstruction,
   redeemInstruction,
+  RedeemRequestAccounts,
+  requestDepositInstruction,
+  requestBondWithdrawalInstruction,
+  requestRedeemInstruction,
   settleFundsInstruction,
+  slashBondInstruction,
+  withdrawBondInstruction,
+  addTraderInstruction,
+  removeTraderInstruction,
+  setMultisigInstruction,
+  withUnsettledOpenOrders,
 } from './instructions';
 import {
   findAssociatedTokenAddress,
   createAssociatedTokenAccount,
   Numberu64,
   Numberu16,
+  Numberu32,
   getMarketData,
   Numberu128,
   findAndCreateAssociatedAccount,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
 } from './utils';
 import {
   OrderSide,
@@ -38,6 +55,12 @@ import {
   unpack_assets,
   PUBKEY_LENGTH,
   unpack_markets,
+  RedeemRequest,
+  REDEEM_REQUEST_SEED,
+  DepositRequest,
+  DEPOSIT_REQUEST_SEED,
+  BOND_SEED,
+  TRADER_SEED,
 } from './state';
 import bs58 from 'bs58';
 import * as crypto from 'crypto';
@@ -93,8 +116,21 @@ export const PUBLIC_POOLS_SEEDS = [
  * @param maxNumberOfAssets The maximum number of different tokens the pool will ever be able to hold (solana memory allocation is fixed)
  * @param markets An array of the addresses of the serum markets that the signalProvider will be able to trade on
  * @param payer The address of the account that should pay for the allocation fees
- * @param feeCollectionPeriod The smallest period in seconds after which the trading fees can be payed out again (minimum is 604800 s or 1 week)
- * @param feePercentage The percentage (a number from 0 to 100) of the pool assets that should be collected as fees
+ * @param annualFeePercentage The percentage (a number from 0 to 25) of the pool assets that should be collected as management fees over a year. These fees accrue continuously.
+ * @param feePercentage The percentage (a number from 0 to 100) of the deposits that should be collected as fees
+ * @param minSignalProviderPercentage The percentage (a number from 0 to 100) of the pooltokens that the associated pooltoken
+ *  account of the signal provider must hold for orders to be created. Redeems out of that account cannot take it below
+ *  this share. Defaults to 0, which disables the requirement.
+ * @param maxOrderPercentage The largest percentage (a number from 0 to 100) of a pool asset that a single order can trade.
+ *  Defaults to 0, which disables the limit.
+ * @param maxDailyTurnoverPercentage The largest sum of the percentages traded by the orders of a trading day, which can
+ *  exceed 100. A trading day starts with the first order after the previous one ended and lasts 24 hours. Defaults to 0,
+ *  which disables the limit.
+ * @param maxDailyOrders The largest number of orders of a trading day. Defaults to 0, which disables the limit.
+ * @param maxPriceDeviationPercentage The largest percentage by which orders can buy above or sell below the best price
+ *  of the other side of the market book. Defaults to 0, which disables the limit.
+ * @param allowedOrderTypes The order types that the orders of the pool can have. Defaults to all of them.
+ * @param allowedSelfTradeBehaviors The self trade behaviors that the orders of the pool can have. Defaults to all of them.
  */
 export async function createPool(
   connection: Connection,
@@ -105,8 +141,15 @@ export async function createPool(
   maxNumberOfAssets: number,
   markets: Array<PublicKey>,
   payer: PublicKey,
-  feeCollectionPeriod: Numberu64,
+  annualFeePercentage: number,
   feePercentage: number,
+  minSignalProviderPercentage: number = 0,
+  maxOrderPercentage: number = 0,
+  maxDailyTurnoverPercentage: number = 0,
+  maxDailyOrders: number = 0,
+  maxPriceDeviationPercentage: number = 0,
+  allowedOrderTypes: Array<OrderType> = [],
+  allowedSelfTradeBehaviors: Array<SelfTradeBehavior> = [],
 ): Promise<[Uint8Array, TransactionInstruction[]]> {
 
   // Find a valid pool seed
@@ -211,6 +254,20 @@ export async function createPool(
   // Create the pool
   // @ts-ignore
   let feeRatioU16 = new Numberu16(2**16 * feePercentage / 100);
+  let annualFeeRatioU16 = new Numberu16(2**16 * annualFeePercentage / 100);
+  // @ts-ignore
+  let minSignalProviderRatioU16 = new Numberu16(Math.min(2**16 * minSignalProviderPercentage / 100, 2**16 - 1));
+  // @ts-ignore
+  let maxOrderRatioU16 = new Numberu16(Math.min(2**16 * maxOrderPercentage / 100, 2**16 - 1));
+  // @ts-ignore
+  let maxDailyTurnoverU32 = new Numberu32(Math.min(2**16 * maxDailyTurnoverPercentage / 100, 2**32 - 1));
+  // @ts-ignore
+  let maxDailyOrdersU16 = new Numberu16(maxDailyOrders);
+  // @ts-ignore
+  let maxPriceDeviationU16 = new Numberu16(Math.min(2**16 * maxPriceDeviationPercentage / 100, 2**16 - 1));
+  // An empty set allows all the values
+  let allowedOrderTypesSet = allowedOrderTypes.reduce((set, t) => set | (1 << t), 0);
+  let allowedSelfTradeBehaviorsSet = allowedSelfTradeBehaviors.reduce((set, b) => set | (1 << b), 0);
   let createTxInstruction = createInstruction(
     TOKEN_PROGRAM_ID,
     BONFIDABOT_PROGRAM_ID,
@@ -226,8 +283,15 @@ export async function createPool(
     signalProviderKey,
     depositAmounts,
     markets,
-    feeCollectionPeriod,
+    annualFeeRatioU16,
     feeRatioU16,
+    minSignalProviderRatioU16,
+    maxOrderRatioU16,
+    maxDailyTurnoverU32,
+    maxDailyOrdersU16,
+    maxPriceDeviationU16,
+    allowedOrderTypesSet,
+    allowedSelfTradeBehaviorsSet,
   );
   txInstructions = txInstructions.concat(assetTxInstructions);
   txInstructions.push(createTxInstruction);
@@ -290,7 +354,7 @@ export async function deposit(
     poolAssetKeys.push(assetKey);
   }
 
-  // If nonexistent, create the source owner and signal provider associated addresses to receive the pooltokens
+  // If nonexistent, create the source owner associated address to receive the pooltokens
   let createTargetsTxInstructions: Array<TransactionInstruction> = [];
   let [targetPoolTokenKey, targetPTInstruction] = await findAndCreateAssociatedAccount(
     SystemProgram.programId,
@@ -301,39 +365,10 @@ export async function deposit(
   );
   targetPTInstruction? createTargetsTxInstructions.push(targetPTInstruction) : null;
 
-  let [sigProviderFeeReceiverKey, sigProvInstruction] = await findAndCreateAssociatedAccount(
-    SystemProgram.programId,
-    connection,
-    poolHeader.signalProvider,
-    poolMintKey,
-    payer
-  );
-  sigProvInstruction? createTargetsTxInstructions.push(sigProvInstruction) : null;
-
-  let [bonfidaFeeReceiverKey, bonfidaFeeInstruction] = await findAndCreateAssociatedAccount(
-    SystemProgram.programId,
-    connection,
-    BONFIDA_FEE_KEY,
-    poolMintKey,
-    payer
-  );
-  bonfidaFeeInstruction? createTargetsTxInstructions.push(bonfidaFeeInstruction) : null;
-
-  let [bonfidaBuyAndBurnKey, bonfidaBNBInstruction] = await findAndCreateAssociatedAccount(
-    SystemProgram.programId,
-    connection,
-    BONFIDA_BNB_KEY,
-    poolMintKey,
-    payer
-  );
-  bonfidaBNBInstruction? createTargetsTxInstructions.push(bonfidaBNBInstruction) : null;
-
   let depositTxInstruction = depositInstruction(
     TOKEN_PROGRAM_ID,
     BONFIDABOT_PROGRAM_ID,
-    sigProviderFeeReceiverKey,
-    bonfidaFeeReceiverKey,
-    bonfidaBuyAndBurnKey,
+    SYSVAR_CLOCK_PUBKEY,
     poolMintKey,
     poolKey,
     poolAssetKeys,
@@ -348,7 +383,7 @@ export async function deposit(
 
 /**
  * Returns the solana instructions to create a new serum order for the pool.
- * (Signed by the SignalProvider account of the pool and the OpenOrder Account
+ * (Signed by the SignalProvider account of the pool, or one of its traders, and the OpenOrder Account
  * returned by this function)
  *
  * @param connection The connection object to the rpc node
@@ -365,6 +400,7 @@ export async function deposit(
  * @param amountToTrade If this optional argument is given, it will overwrite the maxQuantityPercentage and fix
  *  the size of the order in base quantity. If you want to trade one and a half FIDA on FIDA/USDC for example,
  *  give 1.5 as an input here.
+ * @param traderKey The trader placing the order instead of the signal provider (optional)
  */
 export async function createOrder(
   connection: Connection,
@@ -379,6 +415,7 @@ export async function createOrder(
   srmDiscountKey: PublicKey | null,
   payerKey: PublicKey,
   amountToTrade?: number,
+  traderKey?: PublicKey,
 ): Promise<[Account, TransactionInstruction[]]> {
 
   // Find the pool key
@@ -395,6 +432,18 @@ export async function createOrder(
     poolInfo.data.slice(0, PoolHeader.LEN),
   );
 
+  // The signal provider's pooltokens are checked against the supply
+  let array_one = new Uint8Array(1);
+  array_one[0] = 1;
+  let poolMintKey = await PublicKey.createProgramAddress(
+    [poolSeed, array_one],
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let signalProviderPoolTokenKey = await findAssociatedTokenAddress(
+    poolHeader.signalProvider,
+    poolMintKey,
+  );
+
   let marketData = await getMarketData(connection, market);
   let sourceMintKey: PublicKey;
   let targetMintKey: PublicKey;
@@ -482,9 +531,14 @@ export async function createOrder(
   // @ts-ignore
   : new Numberu16((2**16 * amountToTrade / (sourcePoolAssetBalance['uiAmount'])));
 
+  // The trader account is ignored when the signal provider signs
+  let traderAccountKey = !!traderKey
+    ? await findTraderKey([poolSeed], poolHeader.signalProvider, traderKey)
+    : poolHeader.signalProvider;
+
   let createOrderTxInstruction = createOrderInstruction(
     BONFIDABOT_PROGRAM_ID,
-    poolHeader.signalProvider,
+    traderKey || poolHeader.signalProvider,
     market,
     sourcePoolAssetKey,
     sourcePoolAssetIndex,
@@ -501,6 +555,10 @@ export async function createOrder(
     TOKEN_PROGRAM_ID,
     SERUM_PROGRAM_ID,
     SYSVAR_RENT_PUBKEY,
+    poolMintKey,
+    signalProviderPoolTokenKey,
+    traderAccountKey,
+    SYSVAR_CLOCK_PUBKEY,
     srmDiscountKey,
     [poolSeed],
     side,
@@ -631,12 +689,14 @@ export async function settleFunds(
  * @param poolSeed
  * @param market
  * @param openOrdersKey
+ * @param traderKey The trader cancelling the order instead of the signal provider (optional)
  */
 export async function cancelOrder(
   connection: Connection,
   poolSeed: Buffer | Uint8Array,
   market: PublicKey,
   openOrdersKey: PublicKey,
+  traderKey?: PublicKey,
 ): Promise<TransactionInstruction[]> {
   // Find the pool key
   let poolKey = await PublicKey.createProgramAddress(
@@ -672,7 +732,7 @@ export async function cancelOrder(
 
   let cancelOrderTxInstruction = await cancelOrderInstruction(
     BONFIDABOT_PROGRAM_ID,
-    signalProviderKey,
+    traderKey || signalProviderKey,
     market,
     openOrdersKey,
     marketData.eventQueueKey,
@@ -680,6 +740,9 @@ export async function cancelOrder(
     marketData.asksKey,
     poolKey,
     SERUM_PROGRAM_ID,
+    !!traderKey
+      ? await findTraderKey([poolSeed], signalProviderKey, traderKey)
+      : null,
     [poolSeed],
     side,
     orderId,
@@ -691,9 +754,11 @@ export async function cancelOrder(
 
 /**
  * Returns the solana instructions to buy out of the pool by redeeming (burning) pooltokens.
- * This instruction needs to be executed after (and within the same transaction)
- * having settled on all possible open orders for the pool.
- * This is because as long as an order is open for the pool, redeeming is impossible.
+ * Redeeming is impossible as long as an order of the pool is on the book. The openOrders accounts
+ * of the filled orders which are not settled yet have to be given, their funds are then part of
+ * the payout. The payout fails if the pool asset accounts do not hold enough settled funds, in
+ * which case the orders need to be settled first (see settleFunds). Use requestRedeem to be paid
+ * out once the orders are settled.
  * (Signed by the owner of the pooltokens)
  *
  * @param connection The connection object to the rpc node
@@ -702,6 +767,7 @@ export async function cancelOrder(
  * @param targetAssetKeys An array of addresses to which the pool asset tokens are payed out to
  * @param poolSeed The seed of the pool that should be redeemed from
  * @param poolTokenAmount The amount of pooltokens that should be used (ie the amount of tokens that should be bought back)
+ * @param unsettledOpenOrders The [market, openOrders] pairs of the pending orders of the pool (optional)
  */
 export async function redeem(
   connection: Connection,
@@ -710,6 +776,7 @@ export async function redeem(
   targetAssetKeys: Array<PublicKey>,
   poolSeed: Array<Buffer | Uint8Array>,
   poolTokenAmount: Numberu64,
+  unsettledOpenOrders: Array<[PublicKey, PublicKey]> = [],
 ): Promise<TransactionInstruction[]> {
 
   // Find the pool key and mint key
@@ -754,13 +821,13 @@ export async function redeem(
     poolSeed,
     poolTokenAmount,
   );
-  return [redeemTxInstruction];
+  return [withUnsettledOpenOrders(redeemTxInstruction, unsettledOpenOrders)];
 }
 
 
  /**
-  *  Returns the solana instructions to collect the fees from the pool.
-  *  See the readme for the payout destinations.
+  *  Returns the solana instructions to accrue the management fees in the pool state.
+  *  The fees are paid out separately with claimFees.
   * (Permissionless)
   *
   * @param connection The connection object to the rpc node
@@ -782,36 +849,759 @@ export async function collectFees(
     BONFIDABOT_PROGRAM_ID,
   );
 
+  let collectFeesTxInstruction = collectFeesInstruction(
+    SYSVAR_CLOCK_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    poolMintKey,
+    poolSeed,
+  );
+  return [collectFeesTxInstruction];
+}
+
+ /**
+  *  Returns the solana instructions to pay out the fees owed to one of the fee recipients
+  *  of the pool. See the readme for the payout destinations.
+  * (Permissionless)
+  *
+  * @param connection The connection object to the rpc node
+  * @param poolSeed The seed of the pool
+  * @param recipientOwnerKey The fee recipient: the signal provider, BONFIDA_FEE_KEY or BONFIDA_BNB_KEY
+  * @param payer The address of the account that should pay for the allocation fees
+  */
+export async function claimFees(
+  connection: Connection,
+  poolSeed: Array<Buffer | Uint8Array>,
+  recipientOwnerKey: PublicKey,
+  payer: PublicKey,
+): Promise<TransactionInstruction[]> {
+  // Find the pool key and mint key
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let array_one = new Uint8Array(1);
+  array_one[0] = 1;
+  let poolMintKey = await PublicKey.createProgramAddress(
+    poolSeed.concat(array_one),
+    BONFIDABOT_PROGRAM_ID,
+  );
+
+  // If nonexistent, create the recipient associated address to receive the pooltokens
+  let txInstructions: Array<TransactionInstruction> = [];
+  let [recipientPoolTokenKey, recipientInstruction] = await findAndCreateAssociatedAccount(
+    SystemProgram.programId,
+    connection,
+    recipientOwnerKey,
+    poolMintKey,
+    payer
+  );
+  recipientInstruction? txInstructions.push(recipientInstruction) : null;
+
+  let claimFeesTxInstruction = claimFeesInstruction(
+    TOKEN_PROGRAM_ID,
+    SYSVAR_CLOCK_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    poolMintKey,
+    recipientPoolTokenKey,
+    poolSeed,
+  );
+  return txInstructions.concat(claimFeesTxInstruction);
+}
+
+// Maximum number of redeem requests paid out by a single processRedeemQueue instruction
+export const MAX_REDEEM_BATCH_SIZE = 4;
+
+export async function findRedeemRequestKey(
+  poolSeed: Array<Buffer | Uint8Array>,
+  sequence: Numberu64,
+): Promise<PublicKey> {
+  let [redeemRequestKey] = await PublicKey.findProgramAddress(
+    poolSeed.concat([
+      Buffer.from(REDEEM_REQUEST_SEED),
+      new Numberu64(sequence).toBuffer(),
+    ]),
+    BONFIDABOT_PROGRAM_ID,
+  );
+  return redeemRequestKey;
+}
+
+ /**
+  *  Returns the solana instructions to queue a redeem. The pooltokens are escrowed and the
+  *  redeem is paid out by processRedeemQueue once the pool has no pending orders.
+  *
+  * @param connection The connection object to the rpc node
+  * @param sourcePoolTokenOwnerKey The owner of the pooltokens, which pays for the request accounts
+  * @param sourcePoolTokenKey The pooltoken account to escrow from
+  * @param poolSeed The seed of the pool
+  * @param poolTokenAmount The amount of pooltokens to redeem
+  */
+export async function requestRedeem(
+  connection: Connection,
+  sourcePoolTokenOwnerKey: PublicKey,
+  sourcePoolTokenKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  poolTokenAmount: Numberu64,
+): Promise<TransactionInstruction[]> {
+  // Find the pool key and mint key
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let array_one = new Uint8Array(1);
+  array_one[0] = 1;
+  let poolMintKey = await PublicKey.createProgramAddress(
+    poolSeed.concat(array_one),
+    BONFIDABOT_PROGRAM_ID,
+  );
+
   let poolInfo = await connection.getAccountInfo(poolKey);
   if (!poolInfo) {
     throw 'Pool account is unavailable';
   }
-  let poolData = poolInfo.data;
-  let poolHeader = PoolHeader.fromBuffer(poolData.slice(0, PoolHeader.LEN));
+  let poolHeader = PoolHeader.fromBuffer(poolInfo.data.slice(0, PoolHeader.LEN));
+  let redeemRequestKey = await findRedeemRequestKey(
+    poolSeed,
+    poolHeader.redeemQueueTail,
+  );
+  let escrowKey = await findAssociatedTokenAddress(redeemRequestKey, poolMintKey);
 
-  let sigProviderFeeReceiverKey = await findAssociatedTokenAddress(
-    poolHeader.signalProvider,
+  let requestRedeemTxInstruction = requestRedeemInstruction(
+    TOKEN_PROGRAM_ID,
+    SystemProgram.programId,
+    SYSVAR_RENT_PUBKEY,
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    BONFIDABOT_PROGRAM_ID,
     poolMintKey,
+    poolKey,
+    redeemRequestKey,
+    escrowKey,
+    sourcePoolTokenOwnerKey,
+    sourcePoolTokenKey,
+    poolSeed,
+    poolTokenAmount,
   );
-  let bonfidaFeeReceiverKey = await findAssociatedTokenAddress(
-    BONFIDA_FEE_KEY,
-    poolMintKey,
+  return [requestRedeemTxInstruction];
+}
+
+ /**
+  *  Returns the solana instructions to pay out the oldest queued redeem requests, creating the
+  *  missing asset accounts of their owners.
+  * (Permissionless)
+  *
+  * @param connection The connection object to the rpc node
+  * @param poolSeed The seed of the pool
+  * @param payer The payer of the asset account creations
+  * @param maxRequests The maximum number of requests to pay out, at most MAX_REDEEM_BATCH_SIZE
+  */
+export async function processRedeemQueue(
+  connection: Connection,
+  poolSeed: Array<Buffer | Uint8Array>,
+  payer: PublicKey,
+  maxRequests: number = MAX_REDEEM_BATCH_SIZE,
+): Promise<TransactionInstruction[]> {
+  // Find the pool key and mint key
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
   );
-  let bonfidaBuyAndBurnKey = await findAssociatedTokenAddress(
-    BONFIDA_BNB_KEY,
+  let array_one = new Uint8Array(1);
+  array_one[0] = 1;
+  let poolMintKey = await PublicKey.createProgramAddress(
+    poolSeed.concat(array_one),
+    BONFIDABOT_PROGRAM_ID,
+  );
+
+  let poolInfo = await connection.getAccountInfo(poolKey);
+  if (!poolInfo) {
+    throw 'Pool account is unavailable';
+  }
+  let poolHeader = PoolHeader.fromBuffer(poolInfo.data.slice(0, PoolHeader.LEN));
+  let poolAssets = unpack_assets(
+    poolInfo.data.slice(
+      PoolHeader.LEN + Number(poolHeader.numberOfMarkets) * PUBKEY_LENGTH,
+    ),
+  );
+  let poolAssetKeys: Array<PublicKey> = [];
+  for (var asset of poolAssets) {
+    let assetKey = await findAssociatedTokenAddress(poolKey, asset.mintAddress);
+    poolAssetKeys.push(assetKey);
+  }
+
+  let head = Number(poolHeader.redeemQueueHead);
+  let numberOfRequests = Math.min(
+    Number(poolHeader.redeemQueueTail) - head,
+    maxRequests,
+    MAX_REDEEM_BATCH_SIZE,
+  );
+  if (numberOfRequests <= 0) {
+    throw 'No redeem requests are queued';
+  }
+
+  let txInstructions: Array<TransactionInstruction> = [];
+  let createdKeys: Array<string> = [];
+  let redeemRequests: Array<RedeemRequestAccounts> = [];
+  for (let sequence = head; sequence < head + numberOfRequests; sequence++) {
+    let redeemRequestKey = await findRedeemRequestKey(
+      poolSeed,
+      new Numberu64(sequence),
+    );
+    let requestInfo = await connection.getAccountInfo(redeemRequestKey);
+    if (!requestInfo) {
+      throw 'Redeem request account is unavailable';
+    }
+    let request = RedeemRequest.fromBuffer(requestInfo.data);
+    let ownerAssetKeys: Array<PublicKey> = [];
+    for (var asset of poolAssets) {
+      let [ownerAssetKey, createInstruction] = await findAndCreateAssociatedAccount(
+        SystemProgram.programId,
+        connection,
+        request.owner,
+        asset.mintAddress,
+        payer
+      );
+      // The same owner can have several requests in the batch
+      if (createInstruction && !createdKeys.includes(ownerAssetKey.toBase58())) {
+        createdKeys.push(ownerAssetKey.toBase58());
+        txInstructions.push(createInstruction);
+      }
+      ownerAssetKeys.push(ownerAssetKey);
+    }
+    redeemRequests.push({
+      redeemRequestKey,
+      escrowKey: await findAssociatedTokenAddress(redeemRequestKey, poolMintKey),
+      ownerKey: request.owner,
+      ownerAssetKeys,
+    });
+  }
+
+  let processRedeemQueueTxInstruction = processRedeemQueueInstruction(
+    TOKEN_PROGRAM_ID,
+    SYSVAR_CLOCK_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
     poolMintKey,
+    poolKey,
+    poolAssetKeys,
+    redeemRequests,
+    poolSeed,
   );
+  return txInstructions.concat(processRedeemQueueTxInstruction);
+}
 
-  let collectFeesTxInstruction = collectFeesInstruction(
+// Maximum number of deposit requests processed by a single processDepositQueue instruction
+export const MAX_DEPOSIT_BATCH_SIZE = 2;
+
+export async function findDepositRequestKey(
+  poolSeed: Array<Buffer | Uint8Array>,
+  sequence: Numberu64,
+): Promise<PublicKey> {
+  let [depositRequestKey] = await PublicKey.findProgramAddress(
+    poolSeed.concat([
+      Buffer.from(DEPOSIT_REQUEST_SEED),
+      new Numberu64(sequence).toBuffer(),
+    ]),
+    BONFIDABOT_PROGRAM_ID,
+  );
+  return depositRequestKey;
+}
+
+ /**
+  *  Returns the solana instructions to queue a deposit of exactly poolTokenAmount pooltokens.
+  *  The asset amounts are escrowed and bound the price paid, processDepositQueue mints the
+  *  pooltokens once the pool is unlocked and settled and refunds the unused part of the escrow.
+  *
+  * @param connection The connection object to the rpc node
+  * @param sourceOwnerKey The owner of the asset accounts, which pays for the request accounts
+  * @param sourceAssetKeys The asset accounts to escrow from, in the order of the pool assets
+  * @param poolSeed The seed of the pool
+  * @param poolTokenAmount The amount of pooltokens to buy, fees included
+  * @param expiryTimestamp The time after which the request is refunded instead of processed
+  * @param assetAmounts The maximum amount of each pool asset to pay
+  */
+export async function requestDeposit(
+  connection: Connection,
+  sourceOwnerKey: PublicKey,
+  sourceAssetKeys: Array<PublicKey>,
+  poolSeed: Array<Buffer | Uint8Array>,
+  poolTokenAmount: Numberu64,
+  expiryTimestamp: Numberu64,
+  assetAmounts: Array<Numberu64>,
+): Promise<TransactionInstruction[]> {
+  // Find the pool key and mint key
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let array_one = new Uint8Array(1);
+  array_one[0] = 1;
+  let poolMintKey = await PublicKey.createProgramAddress(
+    poolSeed.concat(array_one),
+    BONFIDABOT_PROGRAM_ID,
+  );
+
+  let poolInfo = await connection.getAccountInfo(poolKey);
+  if (!poolInfo) {
+    throw 'Pool account is unavailable';
+  }
+  let poolHeader = PoolHeader.fromBuffer(poolInfo.data.slice(0, PoolHeader.LEN));
+  let poolAssets = unpack_assets(
+    poolInfo.data.slice(
+      PoolHeader.LEN + Number(poolHeader.numberOfMarkets) * PUBKEY_LENGTH,
+    ),
+  );
+  let depositRequestKey = await findDepositRequestKey(
+    poolSeed,
+    poolHeader.depositQueueTail,
+  );
+  let poolAssetMints = poolAssets.map(a => a.mintAddress);
+  let escrowKeys: Array<PublicKey> = [];
+  for (var mint of poolAssetMints) {
+    escrowKeys.push(await findAssociatedTokenAddress(depositRequestKey, mint));
+  }
+
+  let requestDepositTxInstruction = requestDepositInstruction(
     TOKEN_PROGRAM_ID,
+    SystemProgram.programId,
+    SYSVAR_RENT_PUBKEY,
+    ASSOCIATED_TOKEN_PROGRAM_ID,
     SYSVAR_CLOCK_PUBKEY,
     BONFIDABOT_PROGRAM_ID,
+    poolMintKey,
     poolKey,
+    depositRequestKey,
+    sourceOwnerKey,
+    poolAssetMints,
+    sourceAssetKeys,
+    escrowKeys,
+    poolSeed,
+    poolTokenAmount,
+    expiryTimestamp,
+    assetAmounts,
+  );
+  return [requestDepositTxInstruction];
+}
+
+ /**
+  *  Returns the solana instructions to process the oldest queued deposit requests, creating the
+  *  missing pooltoken and asset accounts of their owners.
+  * (Permissionless)
+  *
+  * @param connection The connection object to the rpc node
+  * @param poolSeed The seed of the pool
+  * @param payer The payer of the account creations
+  * @param maxRequests The maximum number of requests to process, at most MAX_DEPOSIT_BATCH_SIZE
+  */
+export async function processDepositQueue(
+  connection: Connection,
+  poolSeed: Array<Buffer | Uint8Array>,
+  payer: PublicKey,
+  maxRequests: number = MAX_DEPOSIT_BATCH_SIZE,
+): Promise<TransactionInstruction[]> {
+  // Find the pool key and mint key
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let array_one = new Uint8Array(1);
+  array_one[0] = 1;
+  let poolMintKey = await PublicKey.createProgramAddress(
+    poolSeed.concat(array_one),
+    BONFIDABOT_PROGRAM_ID,
+  );
+
+  let poolInfo = await connection.getAccountInfo(poolKey);
+  if (!poolInfo) {
+    throw 'Pool account is unavailable';
+  }
+  let poolHeader = PoolHeader.fromBuffer(poolInfo.data.slice(0, PoolHeader.LEN));
+  let poolAssets = unpack_assets(
+    poolInfo.data.slice(
+      PoolHeader.LEN + Number(poolHeader.numberOfMarkets) * PUBKEY_LENGTH,
+    ),
+  );
+  let poolAssetKeys: Array<PublicKey> = [];
+  for (var asset of poolAssets) {
+    let assetKey = await findAssociatedTokenAddress(poolKey, asset.mintAddress);
+    poolAssetKeys.push(assetKey);
+  }
+
+  let head = Number(poolHeader.depositQueueHead);
+  let numberOfRequests = Math.min(
+    Number(poolHeader.depositQueueTail) - head,
+    maxRequests,
+    MAX_DEPOSIT_BATCH_SIZE,
+  );
+  if (numberOfRequests <= 0) {
+    throw 'No deposit requests are queued';
+  }
+
+  let txInstructions: Array<TransactionInstruction> = [];
+  let createdKeys: Array<string> = [];
+  let depositRequests: Array<DepositRequestAccounts> = [];
+  for (let sequence = head; sequence < head + numberOfRequests; sequence++) {
+    let depositRequestKey = await findDepositRequestKey(
+      poolSeed,
+      new Numberu64(sequence),
+    );
+    let requestInfo = await connection.getAccountInfo(depositRequestKey);
+    if (!requestInfo) {
+      throw 'Deposit request account is unavailable';
+    }
+    let request = DepositRequest.fromBuffer(requestInfo.data);
+    let ownerKeys: Array<PublicKey> = [];
+    let escrowKeys: Array<PublicKey> = [];
+    // The escrows of the assets dropped by the pool since the request are refunded as well
+    for (var mint of [poolMintKey].concat(request.escrowedMints)) {
+      let [ownerKey, createInstruction] = await findAndCreateAssociatedAccount(
+        SystemProgram.programId,
+        connection,
+        request.owner,
+        mint,
+        payer
+      );
+      // The same owner can have several requests in the batch
+      if (createInstruction && !createdKeys.includes(ownerKey.toBase58())) {
+        createdKeys.push(ownerKey.toBase58());
+        txInstructions.push(createInstruction);
+      }
+      ownerKeys.push(ownerKey);
+    }
+    for (var escrowedMint of request.escrowedMints) {
+      escrowKeys.push(
+        await findAssociatedTokenAddress(depositRequestKey, escrowedMint),
+      );
+    }
+    depositRequests.push({
+      depositRequestKey,
+      ownerKey: request.owner,
+      ownerPoolTokenKey: ownerKeys[0],
+      escrowKeys,
+      ownerAssetKeys: ownerKeys.slice(1),
+    });
+  }
+
+  let processDepositQueueTxInstruction = processDepositQueueInstruction(
+    TOKEN_PROGRAM_ID,
+    SYSVAR_CLOCK_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
     poolMintKey,
-    sigProviderFeeReceiverKey,
-    bonfidaFeeReceiverKey,
-    bonfidaBuyAndBurnKey,
+    poolKey,
+    poolAssetKeys,
+    depositRequests,
     poolSeed,
   );
-  return [collectFeesTxInstruction];
+  return txInstructions.concat(processDepositQueueTxInstruction);
+}
+
+export async function findBondKey(
+  poolSeed: Array<Buffer | Uint8Array>,
+): Promise<PublicKey> {
+  let [bondKey] = await PublicKey.findProgramAddress(
+    poolSeed.concat([Buffer.from(BOND_SEED)]),
+    BONFIDABOT_PROGRAM_ID,
+  );
+  return bondKey;
+}
+
+/**
+ * Returns the solana instructions to add FIDA to the bond of the pool, which has to reach
+ * MIN_BOND_AMOUNT. The bond is slashed into the pool when an immediate or cancel order is left
+ * unsettled for three days, and posting cancels any withdrawal request. FIDA is added to the
+ * pool assets if needed, so that the bond can always be slashed into the pool.
+ * (Signed by the signal provider)
+ *
+ * @param connection The connection object to the rpc node
+ * @param signalProviderKey The signal provider, which pays for the escrow account and the pool
+ * FIDA account
+ * @param sourceFidaKey The FIDA account of the signal provider to take the bond from
+ * @param poolSeed The seed of the pool
+ * @param amount The amount of FIDA to add, in native units
+ */
+export async function postBond(
+  connection: Connection,
+  signalProviderKey: PublicKey,
+  sourceFidaKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  amount: Numberu64,
+): Promise<TransactionInstruction[]> {
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let bondKey = await findBondKey(poolSeed);
+  let bondEscrowKey = await findAssociatedTokenAddress(bondKey, FIDA_KEY);
+  let poolFidaKey = await findAssociatedTokenAddress(poolKey, FIDA_KEY);
+
+  let postBondTxInstruction = postBondInstruction(
+    TOKEN_PROGRAM_ID,
+    SystemProgram.programId,
+    SYSVAR_RENT_PUBKEY,
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    FIDA_KEY,
+    bondKey,
+    bondEscrowKey,
+    signalProviderKey,
+    sourceFidaKey,
+    poolFidaKey,
+    poolSeed,
+    amount,
+  );
+  return [postBondTxInstruction];
+}
+
+/**
+ * Returns the solana instructions to start the one week notice period after which the
+ * signal provider can withdraw the bond.
+ * (Signed by the signal provider)
+ *
+ * @param connection The connection object to the rpc node
+ * @param signalProviderKey The signal provider
+ * @param poolSeed The seed of the pool
+ */
+export async function requestBondWithdrawal(
+  connection: Connection,
+  signalProviderKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+): Promise<TransactionInstruction[]> {
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let requestBondWithdrawalTxInstruction = requestBondWithdrawalInstruction(
+    SYSVAR_CLOCK_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    signalProviderKey,
+    poolSeed,
+  );
+  return [requestBondWithdrawalTxInstruction];
+}
+
+/**
+ * Returns the solana instructions to return the bond to the signal provider once the notice
+ * period is over, or at any time once the pool has been emptied.
+ * (Signed by the signal provider)
+ *
+ * @param connection The connection object to the rpc node
+ * @param signalProviderKey The signal provider, which receives the rent of the escrow account
+ * @param targetFidaKey The FIDA account receiving the bond
+ * @param poolSeed The seed of the pool
+ */
+export async function withdrawBond(
+  connection: Connection,
+  signalProviderKey: PublicKey,
+  targetFidaKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+): Promise<TransactionInstruction[]> {
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let bondKey = await findBondKey(poolSeed);
+  let bondEscrowKey = await findAssociatedTokenAddress(bondKey, FIDA_KEY);
+
+  let withdrawBondTxInstruction = withdrawBondInstruction(
+    TOKEN_PROGRAM_ID,
+    SYSVAR_CLOCK_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    bondKey,
+    bondEscrowKey,
+    signalProviderKey,
+    targetFidaKey,
+    poolSeed,
+  );
+  return [withdrawBondTxInstruction];
+}
+
+/**
+ * Returns the solana instructions to crank the bond of a pool with pending orders. The first
+ * immediate or cancel order of the pool starts a three day timer, which settling the orders
+ * resets, and a call after it ends moves the bond into the pool FIDA account, which the program
+ * creates if needed. Resting limit and post only orders do not start the timer.
+ * (Permissionless)
+ *
+ * @param connection The connection object to the rpc node
+ * @param poolSeed The seed of the pool
+ * @param payer The payer of the pool FIDA account creation
+ */
+export async function slashBond(
+  connection: Connection,
+  poolSeed: Array<Buffer | Uint8Array>,
+  payer: PublicKey,
+): Promise<TransactionInstruction[]> {
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let poolInfo = await connection.getAccountInfo(poolKey);
+  if (!poolInfo) {
+    throw 'Pool account is unavailable';
+  }
+  let poolHeader = PoolHeader.fromBuffer(poolInfo.data.slice(0, PoolHeader.LEN));
+  let bondKey = await findBondKey(poolSeed);
+  let bondEscrowKey = await findAssociatedTokenAddress(bondKey, FIDA_KEY);
+
+  let poolFidaKey = await findAssociatedTokenAddress(poolKey, FIDA_KEY);
+
+  let slashBondTxInstruction = slashBondInstruction(
+    TOKEN_PROGRAM_ID,
+    SystemProgram.programId,
+    SYSVAR_RENT_PUBKEY,
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+    SYSVAR_CLOCK_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    FIDA_KEY,
+    bondKey,
+    bondEscrowKey,
+    poolFidaKey,
+    poolHeader.signalProvider,
+    payer,
+    poolSeed,
+  );
+  return [slashBondTxInstruction];
+}
+
+export async function findTraderKey(
+  poolSeed: Array<Buffer | Uint8Array>,
+  signalProviderKey: PublicKey,
+  traderKey: PublicKey,
+): Promise<PublicKey> {
+  let [traderAccountKey] = await PublicKey.findProgramAddress(
+    poolSeed.concat([
+      Buffer.from(TRADER_SEED),
+      signalProviderKey.toBuffer(),
+      traderKey.toBuffer(),
+    ]),
+    BONFIDABOT_PROGRAM_ID,
+  );
+  return traderAccountKey;
+}
+
+/**
+ * Returns the solana instructions to let a trader create and cancel orders for the pool, or to
+ * update the limit of an existing trader. Traders cannot claim fees, manage the bond or other traders.
+ * (Signed by the signal provider)
+ *
+ * @param signalProviderKey The signal provider, which pays for the trader account
+ * @param poolSeed The seed of the pool
+ * @param traderKey The key allowed to trade
+ * @param maxPercentageOfPoolAssetsToTrade The largest percentage (a number from 0 to 100) of the pool
+ *  assets a single order of the trader can use
+ */
+export async function addTrader(
+  signalProviderKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  traderKey: PublicKey,
+  maxPercentageOfPoolAssetsToTrade: number,
+): Promise<TransactionInstruction[]> {
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let traderAccountKey = await findTraderKey(
+    poolSeed,
+    signalProviderKey,
+    traderKey,
+  );
+
+  let addTraderTxInstruction = addTraderInstruction(
+    SystemProgram.programId,
+    SYSVAR_RENT_PUBKEY,
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    traderAccountKey,
+    signalProviderKey,
+    poolSeed,
+    traderKey,
+    // @ts-ignore
+    new Numberu16(
+      Math.min(
+        Math.floor((2 ** 16 * maxPercentageOfPoolAssetsToTrade) / 100),
+        2 ** 16 - 1,
+      ),
+    ),
+  );
+  return [addTraderTxInstruction];
+}
+
+/**
+ * Returns the solana instructions to revoke a trader, the rent of its account goes back to
+ * the signal provider.
+ * (Signed by the signal provider)
+ *
+ * @param signalProviderKey The signal provider
+ * @param poolSeed The seed of the pool
+ * @param traderKey The trader to revoke
+ */
+export async function removeTrader(
+  signalProviderKey: PublicKey,
+  poolSeed: Array<Buffer | Uint8Array>,
+  traderKey: PublicKey,
+): Promise<TransactionInstruction[]> {
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let traderAccountKey = await findTraderKey(
+    poolSeed,
+    signalProviderKey,
+    traderKey,
+  );
+
+  let removeTraderTxInstruction = removeTraderInstruction(
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    traderAccountKey,
+    signalProviderKey,
+    poolSeed,
+    traderKey,
+  );
+  return [removeTraderTxInstruction];
+}
+
+/**
+ * Returns the solana instructions to replace the multisig configuration of the signal provider. Once a
+ * threshold is set, the instructions reserved to the signal provider need that many signatures of the
+ * multisig signers (see withMultisigSigners). A threshold of 0 without signers restores the single
+ * signal provider.
+ * (Signed by the signal provider, or by the signers of the current multisig)
+ *
+ * @param connection The connection object to the rpc node
+ * @param poolSeed The seed of the pool
+ * @param signerKeys The signers of the current multisig approving the change, ignored when the pool has none
+ * @param threshold The number of signatures required, at most the number of multisig signers
+ * @param multisigSigners At most MAX_MULTISIG_SIGNERS distinct keys
+ */
+export async function setMultisig(
+  connection: Connection,
+  poolSeed: Array<Buffer | Uint8Array>,
+  signerKeys: Array<PublicKey>,
+  threshold: number,
+  multisigSigners: Array<PublicKey>,
+): Promise<TransactionInstruction[]> {
+  let poolKey = await PublicKey.createProgramAddress(
+    poolSeed,
+    BONFIDABOT_PROGRAM_ID,
+  );
+  let poolInfo = await connection.getAccountInfo(poolKey);
+  if (!poolInfo) {
+    throw 'Pool account is unavailable';
+  }
+  let poolHeader = PoolHeader.fromBuffer(poolInfo.data.slice(0, PoolHeader.LEN));
+
+  let setMultisigTxInstruction = setMultisigInstruction(
+    BONFIDABOT_PROGRAM_ID,
+    poolKey,
+    poolHeader.signalProvider,
+    poolHeader.multisigThreshold == 0 ? [] : signerKeys,
+    poolSeed,
+    threshold,
+    multisigSigners,
+  );
+  return [setMultisigTxInstruction];
 }
@@ -9,6 +9,7 @@ import {
   CompiledInnerInstruction,
   CompiledInstruction,
   ConfirmedTransaction,
+  SYSVAR_CLOCK_PUBKEY,
 } from '@solana/web3.js';
 import { TOKEN_PROGRAM_ID, AccountLayout, u64 } from '@solana/spl-token';
 import { Market, TOKEN_MINTS, MARKETS, OpenOrders } from '@project-serum/serum';
@@ -43,8 +44,6 @@ import {
 } from './types';
 import {
   BONFIDABOT_PROGRAM_ID,
-  BONFIDA_BNB_KEY,
-  BONFIDA_FEE_KEY,
   createPool,
   SERUM_PROGRAM_ID,
   settleFunds,
@@ -59,7 +58,7 @@ export type PoolInfo = {
   signalProvider: PublicKey;
   status: PoolStatus;
   feeRatio: Numberu16;
-  feePeriod: Numberu64;
+  annualFeeRatio: Numberu16;
   mintKey: PublicKey;
   assetMintkeys: Array<PublicKey>;
   authorizedMarkets: Array<PublicKey>;
@@ -192,7 +191,7 @@ export async function fetchPoolInfo(
     signalProvider: poolHeader.signalProvider,
     status: poolHeader.status,
     feeRatio: poolHeader.feeRatio,
-    feePeriod: poolHeader.feeCollectionPeriod,
+    annualFeeRatio: poolHeader.annualFeeRatio,
     mintKey: poolMintKey,
     assetMintkeys: poolAssets.map(asset => asset.mintAddress),
     authorizedMarkets,
@@ -540,7 +539,7 @@ export async function singleTokenDeposit(
     }
   }
 
-  // If nonexistent, create the source owner and signal provider associated addresses to receive the pooltokens
+  // If nonexistent, create the source owner associated address to receive the pooltokens
   let instructions: Array<TransactionInstruction> = [];
   let [
     targetPoolTokenKey,
@@ -554,42 +553,6 @@ export async function singleTokenDeposit(
   );
   targetPTInstruction ? instructions.push(targetPTInstruction) : null;
 
-  let [
-    sigProviderFeeReceiverKey,
-    sigProvInstruction,
-  ] = await findAndCreateAssociatedAccount(
-    SystemProgram.programId,
-    connection,
-    poolHeader.signalProvider,
-    poolMintKey,
-    payer.publicKey,
-  );
-  sigProvInstruction ? instructions.push(sigProvInstruction) : null;
-
-  let [
-    bonfidaFeeReceiverKey,
-    bonfidaFeeInstruction,
-  ] = await findAndCreateAssociatedAccount(
-    SystemProgram.programId,
-    connection,
-    BONFIDA_FEE_KEY,
-    poolMintKey,
-    payer.publicKey,
-  );
-  bonfidaFeeInstruction ? instructions.push(bonfidaFeeInstruction) : null;
-
-  let [
-    bonfidaBuyAndBurnKey,
-    bonfidaBNBInstruction,
-  ] = await findAndCreateAssociatedAccount(
-    SystemProgram.programId,
-    connection,
-    BONFIDA_BNB_KEY,
-    poolMintKey,
-    payer.publicKey,
-  );
-  bonfidaBNBInstruction ? instructions.push(bonfidaBNBInstruction) : null;
-
   // @ts-ignore
   console.log(poolTokenAmount, new Numberu64(1000000 * poolTokenAmount));
 
@@ -598,9 +561,7 @@ export async function singleTokenDeposit(
   let depositTxInstruction = depositInstruction(
     TOKEN_PROGRAM_ID,
     BONFIDABOT_PROGRAM_ID,
-    sigProviderFeeReceiverKey,
-    bonfidaFeeReceiverKey,
-    bonfidaBuyAndBurnKey,
+    SYSVAR_CLOCK_PUBKEY,
     poolMintKey,
     poolKey,
     poolAssetKeys,
@@ -1,5 +1,5 @@
 import { PublicKey } from '@solana/web3.js';
-import { Numberu16, Numberu64 } from './utils';
+import { Numberu16, Numberu32, Numberu64 } from './utils';
 
 // Serum analog types
 export enum OrderSide {
@@ -19,6 +19,17 @@ export enum SelfTradeBehavior {
 
 export const PUBKEY_LENGTH: number = 32;
 
+// Seed of the account owning the bond escrow, along with the pool seed
+export const BOND_SEED = 'bond';
+// The smallest bond, in native FIDA units (100 FIDA)
+export const MIN_BOND_AMOUNT = 100_000_000;
+// Seed of the trader accounts, along with the pool seed and the trader key
+export const TRADER_SEED = 'trader';
+// The largest number of keys of a multisig signal provider
+export const MAX_MULTISIG_SIGNERS = 5;
+// Duration in seconds of the trading days over which the daily trade limits apply
+export const TRADING_DAY_DURATION = 86_400;
+
 const STATUS_PENDING_ORDER_FLAG: number = 1 << 6;
 const STATUS_PENDING_ORDER_MASK: number = 0x3f;
 const STATUS_LOCKED_FLAG: number = 2 << 6;
@@ -35,7 +46,7 @@ export enum PoolStatusID {
 export type PoolStatus = [PoolStatusID, number];
 
 export class PoolHeader {
-  static LEN = 117;
+  static LEN = 390;
   serumProgramId!: PublicKey;
   seed!: Uint8Array;
   signalProvider!: PublicKey;
@@ -43,7 +54,51 @@ export class PoolHeader {
   numberOfMarkets!: Numberu16;
   feeRatio!: Numberu16;
   lastFeeCollectionTimestamp!: Numberu64;
-  feeCollectionPeriod!: Numberu64
+  annualFeeRatio!: Numberu16;
+  // Pool tokens accrued as fees since the pool creation
+  accruedFees!: Numberu64;
+  // Pool tokens claimed by the signal provider, Bonfida and the Bonfida buy and burn account
+  claimedFees!: Array<Numberu64>;
+  // Sequence number of the next redeem request to be paid out
+  redeemQueueHead!: Numberu64;
+  // Sequence number given to the next redeem request
+  redeemQueueTail!: Numberu64;
+  // Sequence number of the next deposit request to be processed
+  depositQueueHead!: Numberu64;
+  // Sequence number given to the next deposit request
+  depositQueueTail!: Numberu64;
+  // FIDA locked by the signal provider, slashed when orders are left pending for too long
+  bondAmount!: Numberu64;
+  // Time from which the bond can be withdrawn, 0 when no withdrawal was requested
+  bondUnlockTimestamp!: Numberu64;
+  // Time at which the pool created its first unsettled immediate or cancel order, 0 otherwise
+  pendingOrderTimestamp!: Numberu64;
+  // Share of the pooltoken supply the signal provider's associated pooltoken account must hold, 0 when not required
+  minSignalProviderRatio!: Numberu16;
+  // Signatures of the multisig signers required in place of the signal provider, 0 for a single signal provider
+  multisigThreshold!: number;
+  // The keys of a multisig signal provider
+  multisigSigners!: Array<PublicKey>;
+  // Largest share of a pool asset traded by a single order, 0 for no limit
+  maxOrderRatio!: Numberu16;
+  // Largest sum of the order ratios over a trading day, 0 for no limit
+  maxDailyTurnover!: Numberu32;
+  // Largest number of orders over a trading day, 0 for no limit
+  maxDailyOrders!: Numberu16;
+  // Largest deviation of the limit prices from the best price of the market book, 0 for no limit
+  maxPriceDeviation!: Numberu16;
+  // Allowed order types, bit i standing for the OrderType of value i, 0 when all are allowed
+  allowedOrderTypes!: number;
+  // Allowed self trade behaviors, bit i standing for the SelfTradeBehavior of value i, 0 when all are allowed
+  allowedSelfTradeBehaviors!: number;
+  // Start of the current trading day, which begins with the first order after the previous one ended
+  tradingDayStart!: Numberu64;
+  // Sum of the ratios of the orders created since the start of the trading day
+  dailyTurnover!: Numberu32;
+  // Number of orders created since the start of the trading day
+  dailyOrders!: Numberu16;
+  // Number of trader accounts, the signal provider cannot create orders while it has any
+  numberOfTraders!: Numberu16;
 
   constructor(
     serumProgramId: PublicKey,
@@ -53,7 +108,29 @@ export class PoolHeader {
     numberOfMarkets: Numberu16,
     feeRatio: Numberu16,
     lastFeeCollectionTimestamp: Numberu64,
-    feeCollectionPeriod: Numberu64
+    annualFeeRatio: Numberu16,
+    accruedFees: Numberu64,
+    claimedFees: Array<Numberu64>,
+    redeemQueueHead: Numberu64,
+    redeemQueueTail: Numberu64,
+    depositQueueHead: Numberu64,
+    depositQueueTail: Numberu64,
+    bondAmount: Numberu64,
+    bondUnlockTimestamp: Numberu64,
+    pendingOrderTimestamp: Numberu64,
+    minSignalProviderRatio: Numberu16,
+    multisigThreshold: number,
+    multisigSigners: Array<PublicKey>,
+    maxOrderRatio: Numberu16,
+    maxDailyTurnover: Numberu32,
+    maxDailyOrders: Numberu16,
+    maxPriceDeviation: Numberu16,
+    allowedOrderTypes: number,
+    allowedSelfTradeBehaviors: number,
+    tradingDayStart: Numberu64,
+    dailyTurnover: Numberu32,
+    dailyOrders: Numberu16,
+    numberOfTraders: Numberu16,
   ) {
     this.serumProgramId = serumProgramId;
     this.seed = seed;
@@ -62,7 +139,29 @@ export class PoolHeader {
     this.numberOfMarkets = numberOfMarkets;
     this.feeRatio = feeRatio;
     this.lastFeeCollectionTimestamp = lastFeeCollectionTimestamp;
-    this.feeCollectionPeriod = feeCollectionPeriod;
+    this.annualFeeRatio = annualFeeRatio;
+    this.accruedFees = accruedFees;
+    this.claimedFees = claimedFees;
+    this.redeemQueueHead = redeemQueueHead;
+    this.redeemQueueTail = redeemQueueTail;
+    this.depositQueueHead = depositQueueHead;
+    this.depositQueueTail = depositQueueTail;
+    this.bondAmount = bondAmount;
+    this.bondUnlockTimestamp = bondUnlockTimestamp;
+    this.pendingOrderTimestamp = pendingOrderTimestamp;
+    this.minSignalProviderRatio = minSignalProviderRatio;
+    this.multisigThreshold = multisigThreshold;
+    this.multisigSigners = multisigSigners;
+    this.maxOrderRatio = maxOrderRatio;
+    this.maxDailyTurnover = maxDailyTurnover;
+    this.maxDailyOrders = maxDailyOrders;
+    this.maxPriceDeviation = maxPriceDeviation;
+    this.allowedOrderTypes = allowedOrderTypes;
+    this.allowedSelfTradeBehaviors = allowedSelfTradeBehaviors;
+    this.tradingDayStart = tradingDayStart;
+    this.dailyTurnover = dailyTurnover;
+    this.dailyOrders = dailyOrders;
+    this.numberOfTraders = numberOfTraders;
   }
 
   static match_status(status_byte: Buffer): PoolStatus {
@@ -99,7 +198,34 @@ export class PoolHeader {
     const numberOfMarkets = Numberu16.fromBuffer(buf.slice(97, 99));
     const feeRatio = Numberu16.fromBuffer(buf.slice(99, 101));
     const lastFeeCollectionTimestamp = Numberu64.fromBuffer(buf.slice(101, 109));
-    const feeCollectionPeriod = Numberu64.fromBuffer(buf.slice(109, 117));
+    const annualFeeRatio = Numberu16.fromBuffer(buf.slice(109, 111));
+    const accruedFees = Numberu64.fromBuffer(buf.slice(111, 119));
+    const claimedFees = [0, 1, 2].map(i =>
+      Numberu64.fromBuffer(buf.slice(119 + 8 * i, 127 + 8 * i)),
+    );
+    const redeemQueueHead = Numberu64.fromBuffer(buf.slice(143, 151));
+    const redeemQueueTail = Numberu64.fromBuffer(buf.slice(151, 159));
+    const depositQueueHead = Numberu64.fromBuffer(buf.slice(159, 167));
+    const depositQueueTail = Numberu64.fromBuffer(buf.slice(167, 175));
+    const bondAmount = Numberu64.fromBuffer(buf.slice(175, 183));
+    const bondUnlockTimestamp = Numberu64.fromBuffer(buf.slice(183, 191));
+    const pendingOrderTimestamp = Numberu64.fromBuffer(buf.slice(191, 199));
+    const minSignalProviderRatio = Numberu16.fromBuffer(buf.slice(199, 201));
+    const multisigThreshold = buf[201];
+    // Unused slots hold the default key
+    const multisigSigners = [0, 1, 2, 3, 4]
+      .map(i => new PublicKey(buf.slice(202 + 32 * i, 234 + 32 * i)))
+      .filter(k => !k.equals(new PublicKey(0)));
+    const maxOrderRatio = Numberu16.fromBuffer(buf.slice(362, 364));
+    const maxDailyTurnover = Numberu32.fromBuffer(buf.slice(364, 368));
+    const maxDailyOrders = Numberu16.fromBuffer(buf.slice(368, 370));
+    const maxPriceDeviation = Numberu16.fromBuffer(buf.slice(370, 372));
+    const allowedOrderTypes = buf[372];
+    const allowedSelfTradeBehaviors = buf[373];
+    const tradingDayStart = Numberu64.fromBuffer(buf.slice(374, 382));
+    const dailyTurnover = Numberu32.fromBuffer(buf.slice(382, 386));
+    const dailyOrders = Numberu16.fromBuffer(buf.slice(386, 388));
+    const numberOfTraders = Numberu16.fromBuffer(buf.slice(388, 390));
     return new PoolHeader(
       serumProgramId,
       seed,
@@ -108,7 +234,101 @@ export class PoolHeader {
       numberOfMarkets,
       feeRatio,
       lastFeeCollectionTimestamp,
-      feeCollectionPeriod
+      annualFeeRatio,
+      accruedFees,
+      claimedFees,
+      redeemQueueHead,
+      redeemQueueTail,
+      depositQueueHead,
+      depositQueueTail,
+      bondAmount,
+      bondUnlockTimestamp,
+      pendingOrderTimestamp,
+      minSignalProviderRatio,
+      multisigThreshold,
+      multisigSigners,
+      maxOrderRatio,
+      maxDailyTurnover,
+      maxDailyOrders,
+      maxPriceDeviation,
+      allowedOrderTypes,
+      allowedSelfTradeBehaviors,
+      tradingDayStart,
+      dailyTurnover,
+      dailyOrders,
+      numberOfTraders,
+    );
+  }
+}
+
+export const REDEEM_REQUEST_SEED = 'redeem_request';
+
+// A redeem queued until the pool has no pending orders
+export class RedeemRequest {
+  static LEN = 41;
+  owner!: PublicKey;
+  poolTokenAmount!: Numberu64;
+  nonce!: number;
+
+  constructor(owner: PublicKey, poolTokenAmount: Numberu64, nonce: number) {
+    this.owner = owner;
+    this.poolTokenAmount = poolTokenAmount;
+    this.nonce = nonce;
+  }
+
+  static fromBuffer(buf: Buffer): RedeemRequest {
+    const owner = new PublicKey(buf.slice(0, 32));
+    const poolTokenAmount = Numberu64.fromBuffer(buf.slice(32, 40));
+    const nonce = buf[40];
+    return new RedeemRequest(owner, poolTokenAmount, nonce);
+  }
+}
+
+export const DEPOSIT_REQUEST_SEED = 'deposit_request';
+
+// A deposit queued until the pool is unlocked and settled, paid from an escrowed basket
+export class DepositRequest {
+  // Size without the escrowed mints, each of which takes PUBKEY_LENGTH more bytes
+  static LEN = 53;
+  owner!: PublicKey;
+  // The exact amount of pool tokens bought, fees included
+  poolTokenAmount!: Numberu64;
+  // The request is refunded when it is processed after this time
+  expiryTimestamp!: Numberu64;
+  nonce!: number;
+  // The mints of the escrow accounts, the pool assets when the request was made
+  escrowedMints!: Array<PublicKey>;
+
+  constructor(
+    owner: PublicKey,
+    poolTokenAmount: Numberu64,
+    expiryTimestamp: Numberu64,
+    nonce: number,
+    escrowedMints: Array<PublicKey>,
+  ) {
+    this.owner = owner;
+    this.poolTokenAmount = poolTokenAmount;
+    this.expiryTimestamp = expiryTimestamp;
+    this.nonce = nonce;
+    this.escrowedMints = escrowedMints;
+  }
+
+  static fromBuffer(buf: Buffer): DepositRequest {
+    const owner = new PublicKey(buf.slice(0, 32));
+    const poolTokenAmount = Numberu64.fromBuffer(buf.slice(32, 40));
+    const expiryTimestamp = Numberu64.fromBuffer(buf.slice(40, 48));
+    const nonce = buf[48];
+    const escrowedMints: Array<PublicKey> = [];
+    for (let i = 0; i < buf.readUInt32LE(49); i++) {
+      const offset = DepositRequest.LEN + i * PUBKEY_LENGTH;
+      escrowedMints.push(new PublicKey(buf.slice(offset, offset + PUBKEY_LENGTH)));
+    }
+    return new DepositRequest(
+      owner,
+      poolTokenAmount,
+      expiryTimestamp,
+      nonce,
+      escrowedMints,
     );
   }
 }
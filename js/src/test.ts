@@ -282,7 +282,7 @@ const test = async (): Promise<void> => {
   //     signalProvider: poolInfo.signalProvider.toString(),
   //     status: [PoolStatusID[poolInfo.status[0]], poolInfo.status[1]],
   //     feeRatio: Number(poolInfo.feeRatio),
-  //     feePeriod: Number(poolInfo.feePeriod),
+  //     annualFeeRatio: Number(poolInfo.annualFeeRatio),
   //     mintKey: poolInfo.mintKey.toString(),
   //     assetMintkeys: poolInfo.assetMintkeys.map(asset => asset.toString()),
   //     authorizedMarkets: poolInfo.authorizedMarkets.map(market => market.toString())
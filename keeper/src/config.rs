@@ -0,0 +1,77 @@
+use std::{fs, path::Path, str::FromStr};
+
+use bonfida_bot_cli::{pool::parse_pool_seed, CliResult};
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+
+pub const BONFIDA_BOT_PROGRAM_ID: &str = "63xyXHpA6EVF69kRmEbXAr8aBEkhgpaNUSRoTQyi5Rwr";
+
+/// The keeper configuration, read from a TOML file.
+#[derive(Debug, Deserialize)]
+pub struct KeeperConfig {
+    pub rpc_url: String,
+    pub keypair: String,
+    /// Defaults to the mainnet bonfida-bot program.
+    pub program_id: Option<String>,
+    /// The number of seconds between two iterations.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// The number of attempts for each transaction.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// The number of seconds after which a pending order state is reported as stuck.
+    #[serde(default = "default_stuck_pending_order_threshold")]
+    pub stuck_pending_order_threshold: u64,
+    /// The number of seconds after the last fee collection at which the fees are collected.
+    #[serde(default = "default_fee_collection_interval")]
+    pub fee_collection_interval: u64,
+    #[serde(default = "default_metrics_address")]
+    pub metrics_address: String,
+    /// The base58 encoded seeds of the pools to watch.
+    pub pools: Vec<String>,
+}
+
+fn default_interval() -> u64 {
+    30
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_stuck_pending_order_threshold() -> u64 {
+    3600
+}
+
+fn default_fee_collection_interval() -> u64 {
+    604800
+}
+
+fn default_metrics_address() -> String {
+    "127.0.0.1:9184".to_owned()
+}
+
+impl KeeperConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> CliResult<Self> {
+        let config: Self = toml::from_str(&fs::read_to_string(path)?)?;
+        if config.pools.is_empty() {
+            return Err("The keeper configuration should contain at least one pool".into());
+        }
+        if config.retries == 0 {
+            return Err("The number of retries should be at least 1".into());
+        }
+        Ok(config)
+    }
+
+    pub fn program_id(&self) -> CliResult<Pubkey> {
+        Ok(Pubkey::from_str(
+            self.program_id
+                .as_deref()
+                .unwrap_or(BONFIDA_BOT_PROGRAM_ID),
+        )?)
+    }
+
+    pub fn pool_seeds(&self) -> CliResult<Vec<[u8; 32]>> {
+        self.pools.iter().map(|p| parse_pool_seed(p)).collect()
+    }
+}
@@ -0,0 +1,499 @@
+use std::{collections::HashMap, thread::sleep, time::Duration};
+
+use bonfida_bot::{
+    state::{
+        DepositRequest, PoolHeader, PoolStatus, MAX_DEPOSIT_BATCH_SIZE, MAX_PENDING_ORDER_DURATION,
+        MAX_REDEEM_BATCH_SIZE,
+    },
+    utils::find_deposit_request_key,
+};
+use bonfida_bot_cli::{
+    client::Client,
+    market::{find_open_orders, MarketInfo},
+    pool::PoolInfo,
+    CliResult,
+};
+use log::{info, warn};
+use solana_program::{clock::Clock, program_pack::Pack, sysvar};
+
+use crate::metrics::SharedMetrics;
+
+/// Runs `f` up to `attempts` times, waiting a bit longer after each failure.
+pub fn with_retries<T, F: FnMut() -> CliResult<T>>(
+    attempts: u32,
+    description: &str,
+    mut f: F,
+) -> CliResult<T> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Ok(r) => return Ok(r),
+            Err(e) if attempt < attempts => {
+                warn!(
+                    "{} failed (attempt {}/{}): {}",
+                    description, attempt, attempts, e
+                );
+                sleep(Duration::from_secs(attempt as u64));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Returns true when the fees have not been collected for at least `fee_collection_interval`
+/// seconds.
+pub fn fees_are_due(
+    current_timestamp: u64,
+    last_fee_collection_timestamp: u64,
+    fee_collection_interval: u64,
+) -> bool {
+    current_timestamp.saturating_sub(last_fee_collection_timestamp) >= fee_collection_interval
+}
+
+/// Returns true when the pool has queued redeem requests and no pending orders, which is when
+/// `ProcessRedeemQueue` can pay them out.
+pub fn redeem_queue_is_processable(header: &PoolHeader) -> bool {
+    match header.status {
+        PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) => false,
+        _ => header.redeem_queue_tail > header.redeem_queue_head,
+    }
+}
+
+/// Returns true when the pool has queued deposit requests and `ProcessDepositQueue` can process
+/// the oldest one, which requires an unlocked pool unless the request has expired and is refunded.
+pub fn deposit_queue_is_processable(
+    header: &PoolHeader,
+    head_expiry_timestamp: u64,
+    current_timestamp: u64,
+) -> bool {
+    header.deposit_queue_tail > header.deposit_queue_head
+        && (header.status == PoolStatus::Unlocked || head_expiry_timestamp < current_timestamp)
+}
+
+/// Returns true when `SlashBond` would succeed on the pool: it has a bond and pending orders, and
+/// the slashing timer started by its first immediate or cancel order has run out.
+pub fn bond_is_slashable(header: &PoolHeader, current_timestamp: u64) -> bool {
+    match header.status {
+        PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) => {
+            header.bond_amount != 0
+                && header.pending_order_timestamp != 0
+                && current_timestamp
+                    >= header
+                        .pending_order_timestamp
+                        .saturating_add(MAX_PENDING_ORDER_DURATION)
+        }
+        _ => false,
+    }
+}
+
+/// Keeps track of how long each pool has been in a pending order state.
+#[derive(Default)]
+pub struct PendingOrderTracker {
+    /// pool seed -> timestamp at which the pending order state was first observed
+    since: HashMap<[u8; 32], u64>,
+}
+
+impl PendingOrderTracker {
+    /// Records the current status of a pool and returns the number of seconds it has been
+    /// pending for, if it is pending.
+    pub fn update(
+        &mut self,
+        pool_seed: [u8; 32],
+        status: &PoolStatus,
+        current_timestamp: u64,
+    ) -> Option<u64> {
+        match status {
+            PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) => {
+                let since = self.since.entry(pool_seed).or_insert(current_timestamp);
+                Some(current_timestamp.saturating_sub(*since))
+            }
+            _ => {
+                self.since.remove(&pool_seed);
+                None
+            }
+        }
+    }
+}
+
+pub struct Keeper {
+    pub client: Client,
+    pub pool_seeds: Vec<[u8; 32]>,
+    pub retries: u32,
+    pub stuck_pending_order_threshold: u64,
+    pub fee_collection_interval: u64,
+    pub metrics: SharedMetrics,
+    pending_orders: PendingOrderTracker,
+}
+
+impl Keeper {
+    pub fn new(
+        client: Client,
+        pool_seeds: Vec<[u8; 32]>,
+        retries: u32,
+        stuck_pending_order_threshold: u64,
+        fee_collection_interval: u64,
+        metrics: SharedMetrics,
+    ) -> Self {
+        Self {
+            client,
+            pool_seeds,
+            retries,
+            stuck_pending_order_threshold,
+            fee_collection_interval,
+            metrics,
+            pending_orders: PendingOrderTracker::default(),
+        }
+    }
+
+    /// Reads the cluster time from the clock sysvar, which is what the program compares against.
+    fn current_timestamp(&self) -> CliResult<u64> {
+        let data = self.client.rpc.get_account_data(&sysvar::clock::id())?;
+        let clock: Clock = bincode::deserialize(&data)?;
+        Ok(clock.unix_timestamp as u64)
+    }
+
+    /// Processes every configured pool once. Errors on a pool are reported and do not prevent
+    /// the other pools from being processed.
+    pub fn run_once(&mut self) -> CliResult<()> {
+        let current_timestamp = with_retries(self.retries, "Fetching the clock", || {
+            self.current_timestamp()
+        })?;
+        for pool_seed in self.pool_seeds.clone() {
+            let pool_name = bs58::encode(&pool_seed).into_string();
+            if let Err(e) = self.process_pool(pool_seed, &pool_name, current_timestamp) {
+                warn!("Failed to process pool {}: {}", pool_name, e);
+                self.metrics.lock().unwrap().record_error(&pool_name);
+            }
+        }
+        self.metrics
+            .lock()
+            .unwrap()
+            .set_last_iteration_timestamp(current_timestamp);
+        Ok(())
+    }
+
+    fn process_pool(
+        &mut self,
+        pool_seed: [u8; 32],
+        pool_name: &str,
+        current_timestamp: u64,
+    ) -> CliResult<()> {
+        self.settle_pool(pool_seed, pool_name)?;
+
+        let pool = with_retries(self.retries, "Fetching the pool", || {
+            self.client.fetch_pool(pool_seed)
+        })?;
+
+        if fees_are_due(
+            current_timestamp,
+            pool.header.last_fee_collection_timestamp,
+            self.fee_collection_interval,
+        ) && pool.header.annual_fee_ratio != 0
+        {
+            let result = with_retries(self.retries, "Collecting fees", || {
+                self.client.collect_fees(pool_seed)
+            });
+            self.metrics
+                .lock()
+                .unwrap()
+                .record_collect_fees(pool_name, result.is_ok());
+            match result {
+                Ok(signature) => info!("Collected fees for pool {}: {}", pool_name, signature),
+                Err(e) => warn!("Failed to collect fees for pool {}: {}", pool_name, e),
+            }
+        }
+
+        if redeem_queue_is_processable(&pool.header) {
+            self.process_redeem_queue(pool_seed, pool_name)?;
+        }
+        if pool.header.deposit_queue_tail > pool.header.deposit_queue_head {
+            self.process_deposit_queue(pool_seed, pool_name, current_timestamp)?;
+        }
+
+        if bond_is_slashable(&pool.header, current_timestamp) {
+            let result = with_retries(self.retries, "Slashing the bond", || {
+                self.client.slash_bond(pool_seed)
+            });
+            self.metrics
+                .lock()
+                .unwrap()
+                .record_slash_bond(pool_name, result.is_ok());
+            match result {
+                Ok(signature) => info!("Cranked the bond of pool {}: {}", pool_name, signature),
+                Err(e) => warn!("Failed to crank the bond of pool {}: {}", pool_name, e),
+            }
+        }
+
+        let pending_seconds =
+            self.pending_orders
+                .update(pool_seed, &pool.header.status, current_timestamp);
+        let stuck = pending_seconds.map_or(false, |s| s > self.stuck_pending_order_threshold);
+        if stuck {
+            warn!(
+                "Pool {} has been in a pending order state for {} seconds",
+                pool_name,
+                pending_seconds.unwrap()
+            );
+        }
+        self.metrics.lock().unwrap().set_pending_order(
+            pool_name,
+            pending_seconds.unwrap_or(0),
+            stuck,
+        );
+        Ok(())
+    }
+
+    /// Pays out the queued redeem requests in batches until the queue is empty or a batch fails.
+    fn process_redeem_queue(&self, pool_seed: [u8; 32], pool_name: &str) -> CliResult<()> {
+        loop {
+            let pool = with_retries(self.retries, "Fetching the pool", || {
+                self.client.fetch_pool(pool_seed)
+            })?;
+            if !redeem_queue_is_processable(&pool.header) {
+                return Ok(());
+            }
+            let result = with_retries(self.retries, "Processing the redeem queue", || {
+                self.client
+                    .process_redeem_queue(pool_seed, MAX_REDEEM_BATCH_SIZE)
+            });
+            self.metrics
+                .lock()
+                .unwrap()
+                .record_process_redeem_queue(pool_name, result.is_ok());
+            match result {
+                Ok(signature) => info!(
+                    "Processed redeem requests for pool {}: {}",
+                    pool_name, signature
+                ),
+                Err(e) => {
+                    warn!(
+                        "Failed to process the redeem queue of pool {}: {}",
+                        pool_name, e
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Processes the queued deposit requests in batches until the queue is empty, the oldest request
+    /// cannot be processed yet or a batch fails. Only one request is processed at a time while the
+    /// pool is not unlocked, as only expired requests can be refunded then.
+    fn process_deposit_queue(
+        &self,
+        pool_seed: [u8; 32],
+        pool_name: &str,
+        current_timestamp: u64,
+    ) -> CliResult<()> {
+        loop {
+            let pool = with_retries(self.retries, "Fetching the pool", || {
+                self.client.fetch_pool(pool_seed)
+            })?;
+            if pool.header.deposit_queue_tail == pool.header.deposit_queue_head {
+                return Ok(());
+            }
+            let (head_request_key, _) = find_deposit_request_key(
+                &self.client.program_id,
+                &pool_seed,
+                pool.header.deposit_queue_head,
+            );
+            let head_request = with_retries(self.retries, "Fetching the deposit request", || {
+                Ok(DepositRequest::unpack(
+                    &self.client.rpc.get_account_data(&head_request_key)?,
+                )?)
+            })?;
+            if !deposit_queue_is_processable(
+                &pool.header,
+                head_request.expiry_timestamp,
+                current_timestamp,
+            ) {
+                return Ok(());
+            }
+            let max_requests = if pool.header.status == PoolStatus::Unlocked {
+                MAX_DEPOSIT_BATCH_SIZE
+            } else {
+                1
+            };
+            let result = with_retries(self.retries, "Processing the deposit queue", || {
+                self.client.process_deposit_queue(pool_seed, max_requests)
+            });
+            self.metrics
+                .lock()
+                .unwrap()
+                .record_process_deposit_queue(pool_name, result.is_ok());
+            match result {
+                Ok(signature) => info!(
+                    "Processed deposit requests for pool {}: {}",
+                    pool_name, signature
+                ),
+                Err(e) => {
+                    warn!(
+                        "Failed to process the deposit queue of pool {}: {}",
+                        pool_name, e
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Settles every OpenOrders account of the pool which holds free funds.
+    fn settle_pool(&self, pool_seed: [u8; 32], pool_name: &str) -> CliResult<()> {
+        let pool = with_retries(self.retries, "Fetching the pool", || {
+            self.client.fetch_pool(pool_seed)
+        })?;
+        for market_key in pool.markets.iter() {
+            let market = with_retries(self.retries, "Fetching the market", || {
+                MarketInfo::fetch(&self.client.rpc, market_key)
+            })?;
+            let open_orders = with_retries(self.retries, "Fetching the OpenOrders accounts", || {
+                find_open_orders(
+                    &self.client.rpc,
+                    &pool.header.serum_program_id,
+                    market_key,
+                    &pool.key,
+                )
+            })?;
+            for open_order in open_orders.iter().filter(|o| o.has_free_funds()) {
+                let result = with_retries(self.retries, "Settling funds", || {
+                    // Settling can add new assets to the pool, the state is fetched each time
+                    let pool = self.client.fetch_pool(pool_seed)?;
+                    let mut instructions = vec![];
+                    for mint in [market.coin_mint, market.pc_mint].iter() {
+                        let (_, create_asset) =
+                            self.client.get_or_create_associated_account(&pool.key, mint);
+                        instructions.extend(create_asset);
+                    }
+                    instructions.push(self.client.settle_instruction(
+                        &pool,
+                        &market,
+                        &open_order.key,
+                    )?);
+                    self.client.send(&instructions, &[])
+                });
+                self.metrics
+                    .lock()
+                    .unwrap()
+                    .record_settle(pool_name, result.is_ok());
+                match result {
+                    Ok(signature) => info!(
+                        "Settled {} for pool {}: {}",
+                        open_order.key, pool_name, signature
+                    ),
+                    Err(e) => warn!(
+                        "Failed to settle {} for pool {}: {}",
+                        open_order.key, pool_name, e
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use solana_program::pubkey::Pubkey;
+
+    use super::*;
+
+    #[test]
+    fn test_fees_are_due() {
+        assert!(!fees_are_due(1_000, 1_000, 604800));
+        assert!(!fees_are_due(1_000 + 604799, 1_000, 604800));
+        assert!(fees_are_due(1_000 + 604800, 1_000, 604800));
+        // The cluster clock can lag behind the last collection
+        assert!(!fees_are_due(999, 1_000, 604800));
+    }
+
+    #[test]
+    fn test_redeem_queue_is_processable() {
+        let mut header = PoolHeader {
+            serum_program_id: Pubkey::new_unique(),
+            signal_provider: Pubkey::new_unique(),
+            status: PoolStatus::Unlocked,
+            number_of_markets: 1,
+            redeem_queue_head: 3,
+            redeem_queue_tail: 3,
+            ..PoolHeader::default()
+        };
+        assert!(!redeem_queue_is_processable(&header));
+        header.redeem_queue_tail = 4;
+        assert!(redeem_queue_is_processable(&header));
+        header.status = PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap());
+        assert!(!redeem_queue_is_processable(&header));
+        header.status = PoolStatus::Locked;
+        assert!(redeem_queue_is_processable(&header));
+    }
+
+    #[test]
+    fn test_deposit_queue_is_processable() {
+        let mut header = PoolHeader {
+            serum_program_id: Pubkey::new_unique(),
+            signal_provider: Pubkey::new_unique(),
+            status: PoolStatus::Unlocked,
+            number_of_markets: 1,
+            deposit_queue_head: 2,
+            deposit_queue_tail: 2,
+            ..PoolHeader::default()
+        };
+        assert!(!deposit_queue_is_processable(&header, 2_000, 1_000));
+        header.deposit_queue_tail = 3;
+        assert!(deposit_queue_is_processable(&header, 2_000, 1_000));
+        header.status = PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap());
+        assert!(!deposit_queue_is_processable(&header, 2_000, 1_000));
+        // Expired requests are refunded whatever the pool status
+        assert!(deposit_queue_is_processable(&header, 999, 1_000));
+    }
+
+    #[test]
+    fn test_bond_is_slashable() {
+        let mut header = PoolHeader {
+            serum_program_id: Pubkey::new_unique(),
+            signal_provider: Pubkey::new_unique(),
+            status: PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap()),
+            number_of_markets: 1,
+            ..PoolHeader::default()
+        };
+        assert!(!bond_is_slashable(&header, 1_000));
+        // Resting orders do not start the timer
+        header.bond_amount = 100_000_000;
+        assert!(!bond_is_slashable(&header, 1_000));
+        header.pending_order_timestamp = 1_000;
+        let deadline = 1_000 + MAX_PENDING_ORDER_DURATION;
+        assert!(!bond_is_slashable(&header, deadline - 1));
+        assert!(bond_is_slashable(&header, deadline));
+        header.status = PoolStatus::Unlocked;
+        assert!(!bond_is_slashable(&header, deadline));
+    }
+
+    #[test]
+    fn test_pending_order_tracker() {
+        let mut tracker = PendingOrderTracker::default();
+        let pending = PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap());
+        assert_eq!(tracker.update([0; 32], &PoolStatus::Unlocked, 100), None);
+        assert_eq!(tracker.update([0; 32], &pending, 200), Some(0));
+        assert_eq!(tracker.update([1; 32], &pending, 250), Some(0));
+        assert_eq!(tracker.update([0; 32], &pending, 300), Some(100));
+        assert_eq!(tracker.update([0; 32], &PoolStatus::Unlocked, 400), None);
+        assert_eq!(tracker.update([0; 32], &pending, 500), Some(0));
+        assert_eq!(tracker.update([1; 32], &pending, 500), Some(250));
+    }
+
+    #[test]
+    fn test_with_retries() {
+        let mut calls = 0;
+        let result: CliResult<u32> = with_retries(3, "test", || {
+            calls += 1;
+            if calls < 2 {
+                Err("failure".into())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    process::exit,
+    sync::{Arc, Mutex},
+    thread::sleep,
+    time::Duration,
+};
+
+use bonfida_bot_cli::{client::Client, CliResult};
+use clap::{App, Arg};
+use log::{error, info};
+use solana_sdk::signature::{read_keypair_file, Signer};
+
+use crate::{config::KeeperConfig, keeper::Keeper, metrics::Metrics};
+
+pub mod config;
+pub mod keeper;
+pub mod metrics;
+
+fn run(config_path: &str) -> CliResult<()> {
+    let config = KeeperConfig::load(config_path)?;
+    let payer = read_keypair_file(&config.keypair)
+        .map_err(|e| format!("Failed to read keypair {}: {}", config.keypair, e))?;
+    info!("Running the keeper with fee payer {}", payer.pubkey());
+    let client = Client::new(config.rpc_url.clone(), payer, config.program_id()?);
+
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+    metrics::serve(&config.metrics_address, Arc::clone(&metrics))?;
+
+    let mut keeper = Keeper::new(
+        client,
+        config.pool_seeds()?,
+        config.retries,
+        config.stuck_pending_order_threshold,
+        config.fee_collection_interval,
+        metrics,
+    );
+    loop {
+        if let Err(e) = keeper.run_once() {
+            error!("Keeper iteration failed: {}", e);
+        }
+        sleep(Duration::from_secs(config.interval));
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let matches = App::new("bonfida-bot-keeper")
+        .about("Settle funds and collect fees on bonfida-bot pools")
+        .arg(
+            Arg::with_name("config")
+                .value_name("PATH")
+                .required(true)
+                .help("The TOML keeper configuration"),
+        )
+        .get_matches();
+
+    if let Err(e) = run(matches.value_of("config").unwrap()) {
+        error!("{}", e);
+        exit(1);
+    }
+}
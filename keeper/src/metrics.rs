@@ -0,0 +1,281 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use log::{error, info};
+use tiny_http::{Header, Response, Server};
+
+/// The keeper metrics, rendered in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    /// (pool, result) -> number of settle transactions
+    settle: BTreeMap<(String, &'static str), u64>,
+    /// (pool, result) -> number of fee collection transactions
+    collect_fees: BTreeMap<(String, &'static str), u64>,
+    /// (pool, result) -> number of redeem queue processing transactions
+    process_redeem_queue: BTreeMap<(String, &'static str), u64>,
+    /// (pool, result) -> number of deposit queue processing transactions
+    process_deposit_queue: BTreeMap<(String, &'static str), u64>,
+    /// (pool, result) -> number of bond slashing transactions
+    slash_bond: BTreeMap<(String, &'static str), u64>,
+    /// pool -> number of failed iterations
+    errors: BTreeMap<String, u64>,
+    /// pool -> seconds spent in a pending order state
+    pending_order_seconds: BTreeMap<String, u64>,
+    /// pool -> whether the pending order state exceeds the threshold
+    pending_order_stuck: BTreeMap<String, bool>,
+    last_iteration_timestamp: u64,
+}
+
+pub type SharedMetrics = Arc<Mutex<Metrics>>;
+
+pub fn result_label(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+impl Metrics {
+    pub fn record_settle(&mut self, pool: &str, success: bool) {
+        *self
+            .settle
+            .entry((pool.to_owned(), result_label(success)))
+            .or_default() += 1;
+    }
+
+    pub fn record_collect_fees(&mut self, pool: &str, success: bool) {
+        *self
+            .collect_fees
+            .entry((pool.to_owned(), result_label(success)))
+            .or_default() += 1;
+    }
+
+    pub fn record_process_redeem_queue(&mut self, pool: &str, success: bool) {
+        *self
+            .process_redeem_queue
+            .entry((pool.to_owned(), result_label(success)))
+            .or_default() += 1;
+    }
+
+    pub fn record_process_deposit_queue(&mut self, pool: &str, success: bool) {
+        *self
+            .process_deposit_queue
+            .entry((pool.to_owned(), result_label(success)))
+            .or_default() += 1;
+    }
+
+    pub fn record_slash_bond(&mut self, pool: &str, success: bool) {
+        *self
+            .slash_bond
+            .entry((pool.to_owned(), result_label(success)))
+            .or_default() += 1;
+    }
+
+    pub fn record_error(&mut self, pool: &str) {
+        *self.errors.entry(pool.to_owned()).or_default() += 1;
+    }
+
+    pub fn set_pending_order(&mut self, pool: &str, seconds: u64, stuck: bool) {
+        self.pending_order_seconds.insert(pool.to_owned(), seconds);
+        self.pending_order_stuck.insert(pool.to_owned(), stuck);
+    }
+
+    pub fn set_last_iteration_timestamp(&mut self, timestamp: u64) {
+        self.last_iteration_timestamp = timestamp;
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_settle_total",
+            "counter",
+            "Settle transactions sent by the keeper",
+        );
+        for ((pool, result), count) in self.settle.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_settle_total{{pool=\"{}\",result=\"{}\"}} {}",
+                pool, result, count
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_collect_fees_total",
+            "counter",
+            "Fee collection transactions sent by the keeper",
+        );
+        for ((pool, result), count) in self.collect_fees.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_collect_fees_total{{pool=\"{}\",result=\"{}\"}} {}",
+                pool, result, count
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_process_redeem_queue_total",
+            "counter",
+            "Redeem queue processing transactions sent by the keeper",
+        );
+        for ((pool, result), count) in self.process_redeem_queue.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_process_redeem_queue_total{{pool=\"{}\",result=\"{}\"}} {}",
+                pool, result, count
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_process_deposit_queue_total",
+            "counter",
+            "Deposit queue processing transactions sent by the keeper",
+        );
+        for ((pool, result), count) in self.process_deposit_queue.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_process_deposit_queue_total{{pool=\"{}\",result=\"{}\"}} {}",
+                pool, result, count
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_slash_bond_total",
+            "counter",
+            "Bond slashing transactions sent by the keeper",
+        );
+        for ((pool, result), count) in self.slash_bond.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_slash_bond_total{{pool=\"{}\",result=\"{}\"}} {}",
+                pool, result, count
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_errors_total",
+            "counter",
+            "Pools which could not be processed during an iteration",
+        );
+        for (pool, count) in self.errors.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_errors_total{{pool=\"{}\"}} {}",
+                pool, count
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_pending_order_seconds",
+            "gauge",
+            "Time spent by the pool in a pending order state",
+        );
+        for (pool, seconds) in self.pending_order_seconds.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_pending_order_seconds{{pool=\"{}\"}} {}",
+                pool, seconds
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_pending_order_stuck",
+            "gauge",
+            "Whether the pending order state exceeds the configured threshold",
+        );
+        for (pool, stuck) in self.pending_order_stuck.iter() {
+            writeln!(
+                out,
+                "bonfida_bot_keeper_pending_order_stuck{{pool=\"{}\"}} {}",
+                pool, *stuck as u8
+            )
+            .unwrap();
+        }
+        render_header(
+            &mut out,
+            "bonfida_bot_keeper_last_iteration_timestamp",
+            "gauge",
+            "Unix timestamp of the last complete iteration",
+        );
+        writeln!(
+            out,
+            "bonfida_bot_keeper_last_iteration_timestamp {}",
+            self.last_iteration_timestamp
+        )
+        .unwrap();
+        out
+    }
+}
+
+fn render_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+/// Serves the metrics on `http://<address>/metrics` from a background thread.
+pub fn serve(address: &str, metrics: SharedMetrics) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Server::http(address).map_err(|e| e.to_string())?;
+    info!("Serving metrics on http://{}/metrics", address);
+    thread::spawn(move || {
+        let content_type =
+            Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..]).unwrap();
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                Response::from_string(metrics.lock().unwrap().render())
+                    .with_header(content_type.clone())
+            } else {
+                Response::from_string("Not found").with_status_code(404)
+            };
+            if let Err(e) = request.respond(response) {
+                error!("Failed to respond to a metrics request: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut metrics = Metrics::default();
+        metrics.record_settle("pool", true);
+        metrics.record_settle("pool", true);
+        metrics.record_collect_fees("pool", false);
+        metrics.record_process_redeem_queue("pool", true);
+        metrics.record_process_deposit_queue("pool", false);
+        metrics.record_slash_bond("pool", true);
+        metrics.set_pending_order("pool", 42, false);
+        metrics.set_last_iteration_timestamp(1_600_000_000);
+        let rendered = metrics.render();
+        assert!(rendered
+            .contains("bonfida_bot_keeper_settle_total{pool=\"pool\",result=\"success\"} 2\n"));
+        assert!(rendered
+            .contains("bonfida_bot_keeper_collect_fees_total{pool=\"pool\",result=\"failure\"} 1\n"));
+        assert!(rendered.contains(
+            "bonfida_bot_keeper_process_redeem_queue_total{pool=\"pool\",result=\"success\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "bonfida_bot_keeper_process_deposit_queue_total{pool=\"pool\",result=\"failure\"} 1\n"
+        ));
+        assert!(rendered
+            .contains("bonfida_bot_keeper_slash_bond_total{pool=\"pool\",result=\"success\"} 1\n"));
+        assert!(rendered.contains("bonfida_bot_keeper_pending_order_seconds{pool=\"pool\"} 42\n"));
+        assert!(rendered.contains("bonfida_bot_keeper_pending_order_stuck{pool=\"pool\"} 0\n"));
+        assert!(rendered.contains("bonfida_bot_keeper_last_iteration_timestamp 1600000000\n"));
+    }
+}
@@ -1,11 +1,13 @@
-use bonfida_bot::{common::utils::{Context, mint_bootstrap}};
+use std::{fs, path::Path, time::{SystemTime, UNIX_EPOCH}};
+
+use bonfida_bot_test_utils::utils::{Context, mint_bootstrap};
 use futures::executor::block_on;
 use honggfuzz::fuzz;
 use solana_program::{pubkey::Pubkey};
 use solana_program_test::{ProgramTest, find_file, read_file};
 use solana_sdk::{account::Account, signature::{Keypair, Signer}};
 
-use bonfida_bot::common::simulation::Execution;
+use bonfida_bot_test_utils::simulation::Execution;
 use arbitrary::Arbitrary;
 
 const SRM_MINT_KEY: &str = "SRMuApVNdxXokk5GT7XD5cUUgXMBCoAz2LHeuAoKWRt";
@@ -19,7 +21,19 @@ fn main() {
         let mints = ctx.get_mints();
 
         fuzz!(|e: Execution| {
-            rt.block_on(e.run(&mut ctx, &mints));
+            if let Err(divergence) = rt.block_on(e.run(&mut ctx, &mints)) {
+                println!("{}", divergence);
+                let (reproducer, divergence) = rt.block_on(e.minimize(divergence));
+                // Recorded scenarios are replayed by the `scenarios` test once moved to
+                // `tests/scenarios`
+                fs::create_dir_all("scenarios").unwrap();
+                let path = Path::new("scenarios").join(format!(
+                    "{}.json",
+                    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+                ));
+                reproducer.save(&path).unwrap();
+                panic!("{}\nMinimized reproducer recorded in {}", divergence, path.display());
+            }
         });
     }
 
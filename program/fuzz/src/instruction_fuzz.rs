@@ -0,0 +1,17 @@
+use bonfida_bot::instruction::PoolInstruction;
+use honggfuzz::fuzz;
+
+fn main() {
+    loop {
+        fuzz!(|data: &[u8]| {
+            let instruction = match PoolInstruction::unpack(data) {
+                Ok(i) => i,
+                Err(_) => return,
+            };
+            // Borsh encodings are canonical and the whole input has to be consumed
+            let packed = instruction.pack();
+            assert_eq!(packed, data);
+            assert_eq!(PoolInstruction::unpack(&packed).unwrap(), instruction);
+        });
+    }
+}
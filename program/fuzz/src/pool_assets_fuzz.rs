@@ -0,0 +1,31 @@
+use bonfida_bot::state::{PoolAsset, PoolState};
+use honggfuzz::fuzz;
+use solana_program::program_pack::{IsInitialized, Pack};
+
+fn main() {
+    loop {
+        fuzz!(|data: &[u8]| {
+            let mut data = data.to_vec();
+            let mut state = match PoolState::new(&mut data) {
+                Ok(s) => s,
+                Err(_) => return,
+            };
+            let slots = state.number_of_asset_slots();
+            assert!(state.asset(slots).is_err());
+            for i in 0..slots {
+                let asset = state.asset(i).unwrap();
+                let mut packed = [0u8; PoolAsset::LEN];
+                asset.pack_into_slice(&mut packed);
+                assert_eq!(&packed[..], state.asset_mut(i).unwrap());
+                assert_eq!(PoolAsset::unpack_unchecked(&packed).unwrap(), asset);
+            }
+            for (i, asset) in state.assets() {
+                assert!(asset.is_initialized());
+                assert_eq!(state.asset(i).unwrap(), asset);
+            }
+            if let Some(i) = state.free_asset_slot() {
+                assert!(!state.asset(i).unwrap().is_initialized());
+            }
+        });
+    }
+}
@@ -0,0 +1,25 @@
+use bonfida_bot::state::PoolHeader;
+use honggfuzz::fuzz;
+use solana_program::program_pack::{IsInitialized, Pack};
+
+/// Offset of the status byte in the packed PoolHeader.
+const STATUS_OFFSET: usize = 96;
+
+fn main() {
+    loop {
+        fuzz!(|data: &[u8]| {
+            let header = match PoolHeader::unpack_unchecked(data) {
+                Ok(h) => h,
+                Err(_) => return,
+            };
+            assert_eq!(PoolHeader::unpack(data).is_ok(), header.is_initialized());
+
+            let mut packed = [0u8; PoolHeader::LEN];
+            header.pack_into_slice(&mut packed);
+            assert_eq!(PoolHeader::unpack_unchecked(&packed).unwrap(), header);
+            // The status byte is the only field with several encodings of the same value
+            assert_eq!(packed[..STATUS_OFFSET], data[..STATUS_OFFSET]);
+            assert_eq!(packed[STATUS_OFFSET + 1..], data[STATUS_OFFSET + 1..]);
+        });
+    }
+}
@@ -0,0 +1,47 @@
+use bonfida_bot::state::{PoolHeader, PoolState};
+use honggfuzz::fuzz;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+
+fn main() {
+    loop {
+        fuzz!(|data: &[u8]| {
+            let mut data = data.to_vec();
+            let len = data.len();
+            let (header, markets) = {
+                let state = match PoolState::new(&mut data) {
+                    Ok(s) => s,
+                    Err(_) => return,
+                };
+                let number_of_markets = state.number_of_markets();
+                let markets: Vec<Pubkey> = (0..number_of_markets)
+                    .map(|i| state.market(i).unwrap())
+                    .collect();
+                assert_eq!(markets, state.markets().collect::<Vec<_>>());
+                assert!(state.market(number_of_markets).is_err());
+                match state.header_unchecked() {
+                    Ok(h) => (h, markets),
+                    Err(_) => return,
+                }
+            };
+
+            // Writing the markets back into an empty account gives the same bytes
+            let mut packed = vec![0u8; len];
+            PoolState::new(&mut packed)
+                .unwrap()
+                .init(&header, &markets, &[])
+                .unwrap();
+            let markets_end = PoolHeader::LEN + 32 * markets.len();
+            assert_eq!(
+                packed[PoolHeader::LEN..markets_end],
+                data[PoolHeader::LEN..markets_end]
+            );
+            assert_eq!(
+                PoolState::new(&mut packed)
+                    .unwrap()
+                    .markets()
+                    .collect::<Vec<_>>(),
+                markets
+            );
+        });
+    }
+}
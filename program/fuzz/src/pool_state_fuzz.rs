@@ -0,0 +1,40 @@
+use bonfida_bot::state::{PoolAsset, PoolState};
+use honggfuzz::fuzz;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+
+fn main() {
+    loop {
+        fuzz!(|data: &[u8]| {
+            let mut data = data.to_vec();
+            let mut state = match PoolState::new(&mut data) {
+                Ok(s) => s,
+                Err(_) => return,
+            };
+            let _ = state.header();
+            let _ = state.header_unchecked();
+            let number_of_markets = state.number_of_markets();
+            for i in 0..number_of_markets.saturating_add(1) {
+                let _ = state.market(i);
+            }
+            let _ = state.markets().count();
+            let slots = state.number_of_asset_slots();
+            for i in 0..slots + 1 {
+                let _ = state.asset(i);
+            }
+            let _ = state.assets().count();
+            let _ = state.find_asset(&Pubkey::default());
+            let _ = state.free_asset_slot();
+            let _ = state.asset_mut(slots);
+            for i in (usize::MAX - PoolAsset::LEN)..=usize::MAX {
+                let _ = state.asset(i);
+                let _ = state.asset_mut(i);
+            }
+            let _ = state.asset(usize::MAX / PoolAsset::LEN);
+            let _ = state.asset_mut(usize::MAX / PoolAsset::LEN + 1);
+            if let Ok(header) = state.header_unchecked() {
+                let _ = state.set_header(&header);
+            }
+            let _ = state.reset();
+        });
+    }
+}
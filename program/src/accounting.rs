@@ -0,0 +1,395 @@
+//! Share accounting of the pools, independent of the accounts it is applied to. The processor
+//! reads the token balances, calls into this module and executes the resulting transfers.
+use solana_program::msg;
+
+use crate::{
+    error::BonfidaBotError,
+    math::{mul_div_ceil, mul_div_floor, Q16},
+};
+
+/// Outcome of a deposit into a pool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepositAmounts {
+    /// Pool tokens minted to the depositor.
+    pub pool_tokens: u64,
+    /// Pool tokens accrued as fees.
+    pub fee: u64,
+    /// Asset amounts transferred from the depositor to the pool, in pool asset order.
+    pub asset_amounts: Vec<u64>,
+}
+
+/// Computes a deposit of at most `pool_token_amount` pool tokens. The effective amount can be
+/// less than requested as the source balances need to satisfy the pool asset ratios. Asset
+/// amounts are rounded up so that the value of a pool token never decreases.
+pub fn compute_deposit(
+    pool_token_amount: u64,
+    total_pooltokens: u64,
+    pool_asset_amounts: &[u64],
+    source_asset_amounts: &[u64],
+    fee_ratio: u16,
+) -> Result<DepositAmounts, BonfidaBotError> {
+    let mut pool_token_effective_amount = pool_token_amount;
+    for (pool_asset_amount, source_asset_amount) in
+        pool_asset_amounts.iter().zip(source_asset_amounts)
+    {
+        if *pool_asset_amount == 0 {
+            // Empty pool assets do not constrain the deposit
+            continue;
+        }
+        // Neither do source amounts worth more pool tokens than can exist
+        pool_token_effective_amount = std::cmp::min(
+            mul_div_floor(*source_asset_amount, total_pooltokens, *pool_asset_amount)
+                .unwrap_or(std::u64::MAX),
+            pool_token_effective_amount,
+        );
+    }
+
+    let asset_amounts = pool_asset_amounts
+        .iter()
+        .map(|a| mul_div_ceil(pool_token_effective_amount, *a, total_pooltokens))
+        .collect::<Result<Vec<_>, _>>()?;
+    if asset_amounts.iter().all(|a| *a == 0) {
+        msg!("The provided amounts cannot be all zero.");
+        return Err(BonfidaBotError::OperationTooSmall);
+    }
+
+    let fee = Q16::from_raw(fee_ratio as u64).mul_floor(pool_token_effective_amount)?;
+    Ok(DepositAmounts {
+        pool_tokens: pool_token_effective_amount
+            .checked_sub(fee)
+            .ok_or(BonfidaBotError::Overflow)?,
+        fee,
+        asset_amounts,
+    })
+}
+
+/// Computes a queued deposit of exactly `pool_token_amount` pool tokens, paid from an escrowed
+/// basket. Returns `None` when the basket does not cover the pool tokens at the current value of
+/// the pool, in which case the request is refunded.
+pub fn compute_queued_deposit(
+    pool_token_amount: u64,
+    total_pooltokens: u64,
+    pool_asset_amounts: &[u64],
+    escrowed_asset_amounts: &[u64],
+    fee_ratio: u16,
+) -> Result<Option<DepositAmounts>, BonfidaBotError> {
+    match compute_deposit(
+        pool_token_amount,
+        total_pooltokens,
+        pool_asset_amounts,
+        escrowed_asset_amounts,
+        fee_ratio,
+    ) {
+        Ok(deposit) if deposit.pool_tokens + deposit.fee == pool_token_amount => Ok(Some(deposit)),
+        Ok(_) | Err(BonfidaBotError::OperationTooSmall) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Computes the asset amounts paid out for `pool_token_amount` redeemed pool tokens, rounded
+/// down.
+pub fn compute_redeem(
+    pool_token_amount: u64,
+    total_pooltokens: u64,
+    pool_asset_amounts: &[u64],
+) -> Result<Vec<u64>, BonfidaBotError> {
+    if pool_token_amount > total_pooltokens {
+        return Err(BonfidaBotError::Overflow);
+    }
+    pool_asset_amounts
+        .iter()
+        .map(|a| mul_div_floor(pool_token_amount, *a, total_pooltokens))
+        .collect()
+}
+
+/// Splits pool token fees between the signal provider (one half), Bonfida (one quarter) and the
+/// Bonfida buy and burn account (one quarter). The shares add up to the amount and never decrease
+/// when it grows, so the cumulated fees can be split again on each claim without losing or taking
+/// back any rounding dust.
+pub fn split_fees(amount: u64) -> [u64; 3] {
+    [amount - amount / 2, amount / 2 - amount / 4, amount / 4]
+}
+
+/// Computes the pool tokens owed to each fee recipient, in the order of `split_fees`, from the
+/// fees accrued since the pool creation and the amounts already claimed.
+pub fn owed_fees(accrued_fees: u64, claimed_fees: &[u64; 3]) -> Result<[u64; 3], BonfidaBotError> {
+    let shares = split_fees(accrued_fees);
+    let mut owed = [0; 3];
+    for i in 0..3 {
+        owed[i] = shares[i]
+            .checked_sub(claimed_fees[i])
+            .ok_or(BonfidaBotError::Overflow)?;
+    }
+    Ok(owed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_deposit, compute_queued_deposit, compute_redeem, owed_fees, split_fees};
+    use crate::{state::SECONDS_PER_YEAR, utils::accrued_fee_tokens};
+    use proptest::prelude::*;
+
+    /// A pool and the balances of its holders, mirroring what the program does on chain.
+    #[derive(Clone, Debug)]
+    struct Model {
+        pool_assets: Vec<u64>,
+        supply: u64,
+        fee_ratio: u16,
+        annual_fee_ratio: u16,
+        /// Asset balances of the depositors
+        wallets: Vec<Vec<u64>>,
+        /// Pool token balances of the depositors
+        pool_tokens: Vec<u64>,
+        /// Pool tokens of the pool creator and of the fee recipients, claimed or not
+        other_tokens: u64,
+    }
+
+    #[derive(Clone, Debug)]
+    enum Operation {
+        Deposit { user: usize, amount: u64 },
+        Redeem { user: usize, amount: u64 },
+        CollectFees { elapsed: u64 },
+    }
+
+    impl Model {
+        fn total_assets(&self) -> Vec<u128> {
+            (0..self.pool_assets.len())
+                .map(|i| {
+                    self.pool_assets[i] as u128
+                        + self.wallets.iter().map(|w| w[i] as u128).sum::<u128>()
+                })
+                .collect()
+        }
+
+        fn apply(&mut self, op: &Operation) {
+            match *op {
+                Operation::Deposit { user, amount } => {
+                    let deposit = match compute_deposit(
+                        amount,
+                        self.supply,
+                        &self.pool_assets,
+                        &self.wallets[user],
+                        self.fee_ratio,
+                    ) {
+                        Ok(d) => d,
+                        Err(_) => return,
+                    };
+                    // The token program fails the mint when the supply overflows
+                    let supply = match self.supply.checked_add(deposit.pool_tokens + deposit.fee) {
+                        Some(s) => s,
+                        None => return,
+                    };
+                    for (i, a) in deposit.asset_amounts.iter().enumerate() {
+                        self.wallets[user][i] -= a;
+                        self.pool_assets[i] += a;
+                    }
+                    self.pool_tokens[user] += deposit.pool_tokens;
+                    self.other_tokens += deposit.fee;
+                    self.supply = supply;
+                }
+                Operation::Redeem { user, amount } => {
+                    let amount = amount % (self.pool_tokens[user] + 1);
+                    let amounts = compute_redeem(amount, self.supply, &self.pool_assets).unwrap();
+                    for (i, a) in amounts.iter().enumerate() {
+                        self.wallets[user][i] += a;
+                        self.pool_assets[i] -= a;
+                    }
+                    self.pool_tokens[user] -= amount;
+                    self.supply -= amount;
+                }
+                Operation::CollectFees { elapsed } => {
+                    let fees =
+                        accrued_fee_tokens(self.supply, self.annual_fee_ratio, elapsed).unwrap();
+                    if let Some(supply) = self.supply.checked_add(fees) {
+                        self.other_tokens += fees;
+                        self.supply = supply;
+                    }
+                }
+            }
+        }
+    }
+
+    fn model(nb_users: usize) -> impl Strategy<Value = Model> {
+        (1usize..5)
+            .prop_flat_map(move |nb_assets| {
+                (
+                    prop::collection::vec(1u64..1 << 40, nb_assets),
+                    prop::collection::vec(
+                        prop::collection::vec(0u64..1 << 40, nb_assets),
+                        nb_users,
+                    ),
+                    0u16..1 << 12,
+                    0u16..1 << 14,
+                )
+            })
+            .prop_map(
+                move |(pool_assets, wallets, fee_ratio, annual_fee_ratio)| Model {
+                    pool_assets,
+                    supply: 1_000_000,
+                    fee_ratio,
+                    annual_fee_ratio,
+                    wallets,
+                    pool_tokens: vec![0; nb_users],
+                    other_tokens: 1_000_000,
+                },
+            )
+    }
+
+    fn operation(nb_users: usize) -> impl Strategy<Value = Operation> {
+        prop_oneof![
+            (0..nb_users, any::<u64>())
+                .prop_map(|(user, amount)| Operation::Deposit { user, amount }),
+            (0..nb_users, any::<u64>())
+                .prop_map(|(user, amount)| Operation::Redeem { user, amount }),
+            (0..SECONDS_PER_YEAR).prop_map(|elapsed| Operation::CollectFees { elapsed }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn assets_are_conserved(
+            mut pool in model(3),
+            ops in prop::collection::vec(operation(3), 1..50),
+        ) {
+            let total_assets = pool.total_assets();
+            for op in &ops {
+                pool.apply(op);
+                prop_assert_eq!(&pool.total_assets(), &total_assets);
+                prop_assert_eq!(
+                    pool.supply,
+                    pool.other_tokens + pool.pool_tokens.iter().sum::<u64>()
+                );
+            }
+        }
+
+        #[test]
+        fn rounding_favors_the_pool(
+            mut pool in model(3),
+            ops in prop::collection::vec(operation(3), 1..50),
+        ) {
+            for op in &ops {
+                let before = pool.clone();
+                pool.apply(op);
+                if let Operation::CollectFees { .. } = op {
+                    continue;
+                }
+                // The assets backing a pool token never decrease
+                for i in 0..pool.pool_assets.len() {
+                    prop_assert!(
+                        pool.pool_assets[i] as u128 * before.supply as u128
+                            >= before.pool_assets[i] as u128 * pool.supply as u128
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn depositors_cannot_redeem_more_than_they_contributed(
+            mut pool in model(1),
+            amount in any::<u64>(),
+        ) {
+            let wallet = pool.wallets[0].clone();
+            pool.apply(&Operation::Deposit { user: 0, amount });
+            let pool_tokens = pool.pool_tokens[0];
+            pool.apply(&Operation::Redeem { user: 0, amount: pool_tokens });
+            prop_assert_eq!(pool.pool_tokens[0], 0);
+            for i in 0..wallet.len() {
+                prop_assert!(pool.wallets[0][i] <= wallet[i]);
+            }
+        }
+
+        #[test]
+        fn deposit_fees_are_bounded_by_the_fee_ratio(
+            pool in model(1),
+            amount in any::<u64>(),
+        ) {
+            if let Ok(deposit) = compute_deposit(
+                amount,
+                pool.supply,
+                &pool.pool_assets,
+                &pool.wallets[0],
+                pool.fee_ratio,
+            ) {
+                let effective = (deposit.pool_tokens + deposit.fee) as u128;
+                prop_assert_eq!(deposit.fee as u128, (effective * pool.fee_ratio as u128) >> 16);
+            }
+        }
+
+        #[test]
+        fn queued_deposits_are_all_or_nothing(
+            pool in model(1),
+            amount in 1u64..1 << 40,
+        ) {
+            let escrow = &pool.wallets[0];
+            match compute_queued_deposit(
+                amount,
+                pool.supply,
+                &pool.pool_assets,
+                escrow,
+                pool.fee_ratio,
+            ).unwrap() {
+                Some(deposit) => {
+                    prop_assert_eq!(deposit.pool_tokens + deposit.fee, amount);
+                    for i in 0..escrow.len() {
+                        prop_assert!(deposit.asset_amounts[i] <= escrow[i]);
+                    }
+                }
+                // The basket is short of the pool token value in at least one asset
+                None => prop_assert!((0..escrow.len()).any(|i| {
+                    escrow[i] as u128 * pool.supply as u128
+                        < amount as u128 * pool.pool_assets[i] as u128
+                })),
+            }
+        }
+
+        #[test]
+        fn management_fees_are_bounded_by_the_annual_fee_ratio(
+            supply in 1u64..1 << 48,
+            annual_fee_ratio in any::<u16>(),
+            elapsed in 0..4 * SECONDS_PER_YEAR,
+        ) {
+            let fees = accrued_fee_tokens(supply, annual_fee_ratio, elapsed).unwrap() as u128;
+            // The fee accounts hold at most the accrued share of the new supply, and never more
+            // than half of it
+            let share = annual_fee_ratio as u128 * elapsed as u128;
+            let year = (SECONDS_PER_YEAR as u128) << 16;
+            prop_assert!(fees * year <= share * (supply as u128 + fees));
+            prop_assert!(fees <= supply as u128);
+        }
+
+        #[test]
+        fn fee_split_is_exhaustive(amount in 0..u64::MAX) {
+            let [signal_provider, bonfida, bnb] = split_fees(amount);
+            prop_assert_eq!(signal_provider as u128 + bonfida as u128 + bnb as u128, amount as u128);
+            prop_assert!(signal_provider >= bonfida && bonfida >= bnb && bonfida - bnb <= 1);
+            prop_assert!(signal_provider - bonfida - bnb <= 1);
+            // Each share grows with the amount
+            let next = split_fees(amount + 1);
+            prop_assert!((0..3).all(|i| next[i] >= split_fees(amount)[i]));
+        }
+
+        #[test]
+        fn claimed_fees_add_up_to_the_accrued_fees(
+            accruals in prop::collection::vec((0u64..1 << 40, 0usize..3), 1..50),
+        ) {
+            let mut accrued_fees = 0;
+            let mut claimed_fees = [0; 3];
+            for (amount, recipient) in accruals {
+                accrued_fees += amount;
+                let owed = owed_fees(accrued_fees, &claimed_fees).unwrap();
+                claimed_fees[recipient] += owed[recipient];
+                let owed = owed_fees(accrued_fees, &claimed_fees).unwrap();
+                prop_assert_eq!(owed[recipient], 0);
+                prop_assert_eq!(
+                    claimed_fees.iter().sum::<u64>() + owed.iter().sum::<u64>(),
+                    accrued_fees
+                );
+            }
+            // Once everyone has claimed, the recipients hold their exact share of the total
+            for recipient in 0..3 {
+                claimed_fees[recipient] += owed_fees(accrued_fees, &claimed_fees).unwrap()[recipient];
+            }
+            prop_assert_eq!(claimed_fees, split_fees(accrued_fees));
+        }
+    }
+}
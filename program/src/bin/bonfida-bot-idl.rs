@@ -0,0 +1,7 @@
+//! Prints the interface description of the bonfida-bot program as JSON.
+fn main() {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&bonfida_bot::idl::generate()).unwrap()
+    );
+}
@@ -32,7 +32,47 @@ impl PrintProgramError for BonfidaBotError {
             BonfidaBotError::Overflow => msg!("Error: Arithmetic operation overflow!"),
             BonfidaBotError::LockedOperation => msg!("Error: Operation is locked in the current pool state!"),
             BonfidaBotError::NotEnoughFIDA => msg!("Error: Pool must contain a minimum amount of FIDA tokens"),
-            BonfidaBotError::OperationTooSmall => msg!("Error: Operation was too small")
+            BonfidaBotError::OperationTooSmall => msg!("Error: Operation was too small"),
+            BonfidaBotError::InvalidPoolKey => msg!("Error: Provided pool account does not match the pool seed!"),
+            BonfidaBotError::InvalidMint => msg!("Error: Provided pool token mint does not match the pool seed!"),
+            BonfidaBotError::InvalidPoolOwner => msg!("Error: Program should own the pool account!"),
+            BonfidaBotError::PoolAlreadyExists => msg!("Error: A pool already exists for this seed!"),
+            BonfidaBotError::InvalidPoolAssetAccount => msg!("Error: Provided pool asset account is invalid!"),
+            BonfidaBotError::InvalidFeeAccount => msg!("Error: Provided fee pool token account is invalid!"),
+            BonfidaBotError::MarketAssetMismatch => msg!("Error: Pool asset does not match the market token!"),
+            BonfidaBotError::UnauthorizedMarket => msg!("Error: Market is not authorized for this pool!"),
+            BonfidaBotError::InvalidAssetIndex => msg!("Error: No pool asset slot at this index!"),
+            BonfidaBotError::InvalidSignalProvider => msg!("Error: Provided signal provider account is invalid!"),
+            BonfidaBotError::TooManyMarkets => msg!("Error: Number of markets is too high!"),
+            BonfidaBotError::FeeCollectionPeriodTooShort => msg!("Error: Fee collection period is too short!"),
+            BonfidaBotError::FeesOverdue => msg!("Error: Fees should be collected first!"),
+            BonfidaBotError::NoFeesToCollect => msg!("Error: No fees to collect!"),
+            BonfidaBotError::PendingOrders => msg!("Error: Operation is not possible while the pool has pending orders!"),
+            BonfidaBotError::NoPendingOrders => msg!("Error: Pool has no pending orders!"),
+            BonfidaBotError::NothingToSettle => msg!("Error: No funds to settle!"),
+            BonfidaBotError::FeeRatioTooHigh => msg!("Error: Annual fee ratio is too high!"),
+            BonfidaBotError::InvalidRedeemRequest => msg!("Error: Provided redeem request account is invalid!"),
+            BonfidaBotError::NotEnoughRedeemRequests => msg!("Error: Not enough redeem requests are queued!"),
+            BonfidaBotError::InvalidDepositRequest => msg!("Error: Provided deposit request account is invalid!"),
+            BonfidaBotError::NotEnoughDepositRequests => msg!("Error: Not enough deposit requests are queued!"),
+            BonfidaBotError::InvalidBondAccount => msg!("Error: Provided bond account is invalid!"),
+            BonfidaBotError::BondLocked => msg!("Error: Bond cannot be withdrawn yet!"),
+            BonfidaBotError::NoBond => msg!("Error: Pool has no bond!"),
+            BonfidaBotError::NotSlashable => msg!("Error: Bond cannot be slashed yet!"),
+            BonfidaBotError::SignalProviderStakeTooLow => msg!("Error: Signal provider holds too few pool tokens!"),
+            BonfidaBotError::InvalidTrader => msg!("Error: Provided trader account is invalid!"),
+            BonfidaBotError::TradeLimitExceeded => msg!("Error: Order exceeds the trading limits!"),
+            BonfidaBotError::NotEnoughSigners => msg!("Error: Not enough signers of the multisig signal provider!"),
+            BonfidaBotError::InvalidMultisig => msg!("Error: Multisig configuration is invalid!"),
+            BonfidaBotError::OrderTooLarge => msg!("Error: Order exceeds the maximum order ratio of the pool!"),
+            BonfidaBotError::DailyTurnoverExceeded => msg!("Error: Order exceeds the daily turnover limit of the pool!"),
+            BonfidaBotError::TooManyDailyOrders => msg!("Error: Order exceeds the daily order count limit of the pool!"),
+            BonfidaBotError::PriceOutOfBand => msg!("Error: Limit price is outside of the price band of the pool!"),
+            BonfidaBotError::OrderTypeNotAllowed => msg!("Error: Order type is not allowed by the pool!"),
+            BonfidaBotError::SelfTradeBehaviorNotAllowed => msg!("Error: Self trade behavior is not allowed by the pool!"),
+            BonfidaBotError::OpenOrdersInUse => msg!("Error: OpenOrders account already holds an order of the pool!"),
+            BonfidaBotError::LegacyPoolLayout => msg!("Error: The pool layout of the first program version cannot store this change!"),
+            BonfidaBotError::TradingDelegated => msg!("Error: The signal provider cannot create orders while it has traders!"),
         }
     }
 }
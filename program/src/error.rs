@@ -1,8 +1,10 @@
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
 use solana_program::{decode_error::DecodeError, program_error::ProgramError};
 use thiserror::Error;
 
-/// Errors that may be returned by the Token vesting program.
+/// Errors that may be returned by the bonfida-bot program. The discriminants are the custom
+/// error codes seen by clients, new variants should only be appended.
 #[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
 pub enum BonfidaBotError {
     // Invalid instruction
@@ -16,6 +18,96 @@ pub enum BonfidaBotError {
     NotEnoughFIDA,
     #[error("Operation too small.")]
     OperationTooSmall,
+    #[error("Provided pool account does not match the pool seed")]
+    InvalidPoolKey,
+    #[error("Provided pool token mint does not match the pool seed")]
+    InvalidMint,
+    #[error("Program should own the pool account")]
+    InvalidPoolOwner,
+    #[error("A pool already exists for this seed")]
+    PoolAlreadyExists,
+    #[error("Provided pool asset account is invalid")]
+    InvalidPoolAssetAccount,
+    #[error("Provided fee pool token account is invalid")]
+    InvalidFeeAccount,
+    #[error("Pool asset does not match the market token")]
+    MarketAssetMismatch,
+    #[error("Market is not authorized for this pool")]
+    UnauthorizedMarket,
+    #[error("No pool asset slot at this index")]
+    InvalidAssetIndex,
+    #[error("Provided signal provider account is invalid")]
+    InvalidSignalProvider,
+    #[error("Number of markets is too high")]
+    TooManyMarkets,
+    /// No longer returned since the fees accrue continuously.
+    #[error("Fee collection period is too short")]
+    FeeCollectionPeriodTooShort,
+    /// No longer returned since the fees are minted during redeems.
+    #[error("Fees should be collected first")]
+    FeesOverdue,
+    #[error("No fees to collect")]
+    NoFeesToCollect,
+    #[error("Operation is not possible while the pool has pending orders")]
+    PendingOrders,
+    #[error("Pool has no pending orders")]
+    NoPendingOrders,
+    #[error("No funds to settle")]
+    NothingToSettle,
+    #[error("Annual fee ratio is too high")]
+    FeeRatioTooHigh,
+    #[error("Provided redeem request account is invalid")]
+    InvalidRedeemRequest,
+    #[error("Not enough redeem requests are queued")]
+    NotEnoughRedeemRequests,
+    #[error("Provided deposit request account is invalid")]
+    InvalidDepositRequest,
+    #[error("Not enough deposit requests are queued")]
+    NotEnoughDepositRequests,
+    #[error("Provided bond account is invalid")]
+    InvalidBondAccount,
+    #[error("Bond cannot be withdrawn yet")]
+    BondLocked,
+    #[error("Pool has no bond")]
+    NoBond,
+    #[error("Bond cannot be slashed yet")]
+    NotSlashable,
+    #[error("Signal provider holds too few pool tokens")]
+    SignalProviderStakeTooLow,
+    #[error("Provided trader account is invalid")]
+    InvalidTrader,
+    #[error("Order exceeds the trading limits")]
+    TradeLimitExceeded,
+    #[error("Not enough signers of the multisig signal provider")]
+    NotEnoughSigners,
+    #[error("Multisig configuration is invalid")]
+    InvalidMultisig,
+    #[error("Order exceeds the maximum order ratio of the pool")]
+    OrderTooLarge,
+    #[error("Order exceeds the daily turnover limit of the pool")]
+    DailyTurnoverExceeded,
+    #[error("Order exceeds the daily order count limit of the pool")]
+    TooManyDailyOrders,
+    #[error("Limit price is outside of the price band of the pool")]
+    PriceOutOfBand,
+    #[error("Order type is not allowed by the pool")]
+    OrderTypeNotAllowed,
+    #[error("Self trade behavior is not allowed by the pool")]
+    SelfTradeBehaviorNotAllowed,
+    #[error("OpenOrders account already holds an order of the pool")]
+    OpenOrdersInUse,
+    #[error("The pool layout of the first program version cannot store this change")]
+    LegacyPoolLayout,
+    #[error("The signal provider cannot create orders while it has traders")]
+    TradingDelegated,
+}
+
+impl BonfidaBotError {
+    /// Decodes the code of a `ProgramError::Custom` or `InstructionError::Custom` returned by
+    /// the program.
+    pub fn decode(code: u32) -> Option<Self> {
+        Self::from_u32(code)
+    }
 }
 
 impl From<BonfidaBotError> for ProgramError {
@@ -29,3 +121,78 @@ impl<T> DecodeError<T> for BonfidaBotError {
         "BonfidaBotError"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        // Clients depend on these codes, they should never change
+        assert_eq!(
+            ProgramError::from(BonfidaBotError::LockedOperation),
+            ProgramError::Custom(2)
+        );
+        assert_eq!(
+            ProgramError::from(BonfidaBotError::InvalidPoolKey),
+            ProgramError::Custom(5)
+        );
+        assert_eq!(
+            ProgramError::from(BonfidaBotError::NothingToSettle),
+            ProgramError::Custom(21)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(10),
+            Some(BonfidaBotError::InvalidFeeAccount)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(24),
+            Some(BonfidaBotError::NotEnoughRedeemRequests)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(26),
+            Some(BonfidaBotError::NotEnoughDepositRequests)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(30),
+            Some(BonfidaBotError::NotSlashable)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(31),
+            Some(BonfidaBotError::SignalProviderStakeTooLow)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(33),
+            Some(BonfidaBotError::TradeLimitExceeded)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(34),
+            Some(BonfidaBotError::NotEnoughSigners)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(37),
+            Some(BonfidaBotError::DailyTurnoverExceeded)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(39),
+            Some(BonfidaBotError::PriceOutOfBand)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(41),
+            Some(BonfidaBotError::SelfTradeBehaviorNotAllowed)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(42),
+            Some(BonfidaBotError::OpenOrdersInUse)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(43),
+            Some(BonfidaBotError::LegacyPoolLayout)
+        );
+        assert_eq!(
+            BonfidaBotError::decode(44),
+            Some(BonfidaBotError::TradingDelegated)
+        );
+        assert_eq!(BonfidaBotError::decode(45), None);
+    }
+}
@@ -0,0 +1,876 @@
+//! Machine readable description of the program interface, generated from the Borsh schemas of
+//! the instruction and state types. Other language clients can be generated from its JSON output.
+use borsh::{
+    schema::{Definition, Fields},
+    BorshSchema,
+};
+use serde_json::{json, Value};
+use solana_program::program_pack::Pack;
+
+use crate::{
+    error::BonfidaBotError,
+    instruction::PoolInstruction,
+    state::{DepositRequest, PoolAsset, PoolHeader, RedeemRequest, Trader},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Multiplicity {
+    One,
+    /// The account can be omitted when it is the last one.
+    Optional,
+    /// One account for each of the M assets of the pool, in the order of the pool account data.
+    PerAsset,
+    /// One account for each of the N redeem requests processed. The accounts of a request are
+    /// grouped together and the groups are in the order of the queue.
+    PerRedeemRequest,
+    /// One account for each of the M assets of the pool, within the group of a redeem request.
+    PerRedeemRequestAsset,
+    /// One account for each of the N deposit requests processed, grouped like the redeem
+    /// requests.
+    PerDepositRequest,
+    /// One account for each of the E escrowed mints of a deposit request, within its group.
+    PerDepositRequestAsset,
+    /// One signing account for each of the M multisig signers approving the instruction, when
+    /// the pool has a multisig signal provider.
+    PerMultisigSigner,
+    /// One account for each of the P pending orders of the pool. The accounts of a pending order
+    /// are grouped together.
+    PerPendingOrder,
+}
+
+pub struct AccountSpec {
+    pub name: &'static str,
+    pub writable: bool,
+    pub signer: bool,
+    pub multiplicity: Multiplicity,
+}
+
+const fn account(name: &'static str, writable: bool, signer: bool) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable,
+        signer,
+        multiplicity: Multiplicity::One,
+    }
+}
+
+const fn per_asset(name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable,
+        signer: false,
+        multiplicity: Multiplicity::PerAsset,
+    }
+}
+
+const fn optional(name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable,
+        signer: false,
+        multiplicity: Multiplicity::Optional,
+    }
+}
+
+const fn per_redeem_request(name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable,
+        signer: false,
+        multiplicity: Multiplicity::PerRedeemRequest,
+    }
+}
+
+const fn per_redeem_request_asset(name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable,
+        signer: false,
+        multiplicity: Multiplicity::PerRedeemRequestAsset,
+    }
+}
+
+const fn per_deposit_request(name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable,
+        signer: false,
+        multiplicity: Multiplicity::PerDepositRequest,
+    }
+}
+
+const fn per_deposit_request_asset(name: &'static str, writable: bool) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable,
+        signer: false,
+        multiplicity: Multiplicity::PerDepositRequestAsset,
+    }
+}
+
+const fn per_pending_order(name: &'static str) -> AccountSpec {
+    AccountSpec {
+        name,
+        writable: false,
+        signer: false,
+        multiplicity: Multiplicity::PerPendingOrder,
+    }
+}
+
+const fn per_multisig_signer() -> AccountSpec {
+    AccountSpec {
+        name: "multisig_signer",
+        writable: false,
+        signer: true,
+        multiplicity: Multiplicity::PerMultisigSigner,
+    }
+}
+
+const CREATE_ACCOUNTS: &[AccountSpec] = &[
+    account("spl_token_program", false, false),
+    account("clock_sysvar", false, false),
+    account("serum_program", false, false),
+    account("signal_provider", false, false),
+    account("pool_mint", true, false),
+    account("target_pool_token", true, false),
+    account("pool", true, false),
+    per_asset("pool_assets", true),
+    account("source_owner", false, true),
+    per_asset("source_assets", true),
+];
+
+/// The accounts expected by each instruction, indexed by instruction tag.
+pub const INSTRUCTION_ACCOUNTS: &[&[AccountSpec]] = &[
+    // Init
+    &[
+        account("system_program", false, false),
+        account("rent_sysvar", false, false),
+        account("spl_token_program", false, false),
+        account("pool", true, false),
+        account("pool_mint", true, false),
+        account("payer", true, true),
+    ],
+    // LegacyCreate
+    CREATE_ACCOUNTS,
+    // Deposit
+    &[
+        account("spl_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool_mint", true, false),
+        account("target_pool_token", true, false),
+        account("pool", true, false),
+        per_asset("pool_assets", true),
+        account("source_owner", false, true),
+        per_asset("source_assets", true),
+    ],
+    // CreateOrder
+    &[
+        account("signal_provider", false, true),
+        account("market", true, false),
+        account("payer_pool_asset", true, false),
+        account("open_orders", true, false),
+        account("event_queue", true, false),
+        account("request_queue", true, false),
+        account("bids", true, false),
+        account("asks", true, false),
+        account("pool", true, false),
+        account("coin_vault", true, false),
+        account("pc_vault", true, false),
+        account("spl_token_program", false, false),
+        account("rent_sysvar", false, false),
+        account("dex_program", false, false),
+        account("pool_mint", false, false),
+        account("signal_provider_pool_token", false, false),
+        account("trader", false, false),
+        account("clock_sysvar", false, false),
+        optional("srm_discount", true),
+        per_multisig_signer(),
+    ],
+    // CancelOrder
+    &[
+        account("signal_provider", false, true),
+        account("market", false, false),
+        account("open_orders", true, false),
+        account("bids", true, false),
+        account("asks", true, false),
+        account("event_queue", true, false),
+        account("pool", false, false),
+        account("dex_program", false, false),
+        optional("trader", false),
+        per_multisig_signer(),
+    ],
+    // SettleFunds
+    &[
+        account("market", true, false),
+        account("open_orders", true, false),
+        account("pool", true, false),
+        account("pool_mint", false, false),
+        account("coin_vault", true, false),
+        account("pc_vault", true, false),
+        account("pool_coin_wallet", true, false),
+        account("pool_pc_wallet", true, false),
+        account("vault_signer", false, false),
+        account("spl_token_program", false, false),
+        account("dex_program", false, false),
+        optional("referrer_pc_wallet", true),
+    ],
+    // Redeem
+    &[
+        account("spl_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool_mint", true, false),
+        account("source_pool_token_owner", false, true),
+        account("source_pool_token", true, false),
+        account("pool", true, false),
+        per_asset("pool_assets", true),
+        per_asset("target_assets", true),
+        per_pending_order("market"),
+        per_pending_order("open_orders"),
+    ],
+    // CollectFees
+    &[
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("pool_mint", false, false),
+    ],
+    // ClaimFees
+    &[
+        account("spl_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("pool_mint", true, false),
+        account("recipient_pool_token", true, false),
+    ],
+    // RequestRedeem
+    &[
+        account("system_program", false, false),
+        account("rent_sysvar", false, false),
+        account("spl_token_program", false, false),
+        account("associated_token_program", false, false),
+        account("pool", true, false),
+        account("pool_mint", false, false),
+        account("redeem_request", true, false),
+        account("redeem_request_pool_token", true, false),
+        account("source_pool_token_owner", true, true),
+        account("source_pool_token", true, false),
+    ],
+    // ProcessRedeemQueue
+    &[
+        account("spl_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("pool_mint", true, false),
+        per_asset("pool_assets", true),
+        per_redeem_request("redeem_request", true),
+        per_redeem_request("redeem_request_pool_token", true),
+        per_redeem_request("redeem_request_owner", true),
+        per_redeem_request_asset("redeem_request_owner_assets", true),
+    ],
+    // RequestDeposit
+    &[
+        account("system_program", false, false),
+        account("rent_sysvar", false, false),
+        account("spl_token_program", false, false),
+        account("associated_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("pool_mint", false, false),
+        account("deposit_request", true, false),
+        account("source_owner", true, true),
+        per_asset("asset_mints", false),
+        per_asset("source_assets", true),
+        per_asset("deposit_request_assets", true),
+    ],
+    // ProcessDepositQueue
+    &[
+        account("spl_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("pool_mint", true, false),
+        per_asset("pool_assets", true),
+        per_deposit_request("deposit_request", true),
+        per_deposit_request("deposit_request_owner", true),
+        per_deposit_request("deposit_request_owner_pool_token", true),
+        per_deposit_request_asset("deposit_request_assets", true),
+        per_deposit_request_asset("deposit_request_owner_assets", true),
+    ],
+    // PostBond
+    &[
+        account("system_program", false, false),
+        account("rent_sysvar", false, false),
+        account("spl_token_program", false, false),
+        account("associated_token_program", false, false),
+        account("pool", true, false),
+        account("fida_mint", false, false),
+        account("bond", false, false),
+        account("bond_escrow", true, false),
+        account("signal_provider", true, true),
+        account("source_fida", true, false),
+        account("pool_fida", true, false),
+        per_multisig_signer(),
+    ],
+    // RequestBondWithdrawal
+    &[
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("signal_provider", false, true),
+        per_multisig_signer(),
+    ],
+    // WithdrawBond
+    &[
+        account("spl_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("bond", false, false),
+        account("bond_escrow", true, false),
+        account("signal_provider", true, true),
+        account("target_fida", true, false),
+        per_multisig_signer(),
+    ],
+    // SlashBond
+    &[
+        account("system_program", false, false),
+        account("rent_sysvar", false, false),
+        account("spl_token_program", false, false),
+        account("associated_token_program", false, false),
+        account("clock_sysvar", false, false),
+        account("pool", true, false),
+        account("fida_mint", false, false),
+        account("bond", false, false),
+        account("bond_escrow", true, false),
+        account("pool_fida", true, false),
+        account("signal_provider", true, false),
+        account("payer", true, true),
+    ],
+    // AddTrader
+    &[
+        account("system_program", false, false),
+        account("rent_sysvar", false, false),
+        account("pool", true, false),
+        account("trader", true, false),
+        account("signal_provider", true, true),
+        per_multisig_signer(),
+    ],
+    // RemoveTrader
+    &[
+        account("pool", true, false),
+        account("trader", true, false),
+        account("signal_provider", true, true),
+        per_multisig_signer(),
+    ],
+    // SetMultisig
+    &[
+        account("pool", true, false),
+        account("signal_provider", false, true),
+        per_multisig_signer(),
+    ],
+    // Create
+    CREATE_ACCOUNTS,
+];
+
+fn fields_to_json(fields: &Fields) -> Value {
+    match fields {
+        Fields::NamedFields(fields) => fields
+            .iter()
+            .map(|(name, declaration)| json!({ "name": name, "type": declaration }))
+            .collect(),
+        Fields::UnnamedFields(fields) => fields
+            .iter()
+            .map(|declaration| json!({ "type": declaration }))
+            .collect(),
+        Fields::Empty => json!([]),
+    }
+}
+
+fn definition_to_json(definition: &Definition) -> Value {
+    match definition {
+        Definition::Array { length, elements } => {
+            json!({ "kind": "array", "length": length, "elements": elements })
+        }
+        // Sequences are prefixed with their length as a u32
+        Definition::Sequence { elements } => json!({ "kind": "vec", "elements": elements }),
+        Definition::Tuple { elements } => json!({ "kind": "tuple", "elements": elements }),
+        Definition::Enum { variants } => json!({
+            "kind": "enum",
+            "variants": variants
+                .iter()
+                .enumerate()
+                .map(|(index, (name, declaration))| {
+                    json!({ "name": name, "index": index, "type": declaration })
+                })
+                .collect::<Vec<_>>(),
+        }),
+        Definition::Struct { fields } => json!({ "kind": "struct", "fields": fields_to_json(fields) }),
+    }
+}
+
+fn accounts_to_json(accounts: &[AccountSpec]) -> Value {
+    accounts
+        .iter()
+        .map(|a| {
+            json!({
+                "name": a.name,
+                "writable": a.writable,
+                "signer": a.signer,
+                "multiplicity": match a.multiplicity {
+                    Multiplicity::One => "one",
+                    Multiplicity::Optional => "optional",
+                    Multiplicity::PerAsset => "per_asset",
+                    Multiplicity::PerRedeemRequest => "per_redeem_request",
+                    Multiplicity::PerRedeemRequestAsset => "per_redeem_request_asset",
+                    Multiplicity::PerDepositRequest => "per_deposit_request",
+                    Multiplicity::PerDepositRequestAsset => "per_deposit_request_asset",
+                    Multiplicity::PerMultisigSigner => "per_multisig_signer",
+                    Multiplicity::PerPendingOrder => "per_pending_order",
+                },
+            })
+        })
+        .collect()
+}
+
+/// Generates the interface description of the program.
+pub fn generate() -> Value {
+    let mut definitions = std::collections::HashMap::new();
+    PoolInstruction::add_definitions_recursively(&mut definitions);
+    PoolHeader::add_definitions_recursively(&mut definitions);
+    PoolAsset::add_definitions_recursively(&mut definitions);
+    RedeemRequest::add_definitions_recursively(&mut definitions);
+    DepositRequest::add_definitions_recursively(&mut definitions);
+    Trader::add_definitions_recursively(&mut definitions);
+
+    let variants = match definitions.get(&PoolInstruction::declaration()) {
+        Some(Definition::Enum { variants }) => variants.clone(),
+        _ => unreachable!(),
+    };
+    let instructions: Vec<Value> = variants
+        .iter()
+        .zip(INSTRUCTION_ACCOUNTS.iter())
+        .enumerate()
+        .map(|(tag, ((name, declaration), accounts))| {
+            let args = match definitions.get(declaration) {
+                Some(Definition::Struct { fields }) => fields_to_json(fields),
+                _ => json!([]),
+            };
+            json!({
+                "name": name,
+                "tag": tag,
+                "args": args,
+                "accounts": accounts_to_json(accounts),
+            })
+        })
+        .collect();
+
+    let mut types: Vec<(&String, &Definition)> = definitions.iter().collect();
+    types.sort_by_key(|(declaration, _)| *declaration);
+    let types: serde_json::Map<String, Value> = types
+        .into_iter()
+        .map(|(declaration, definition)| (declaration.clone(), definition_to_json(definition)))
+        .collect();
+
+    let errors: Vec<Value> = (0u32..)
+        .map(|code| (code, BonfidaBotError::decode(code)))
+        .take_while(|(_, e)| e.is_some())
+        .map(|(code, e)| {
+            let e = e.unwrap();
+            json!({ "code": code, "name": format!("{:?}", e), "msg": e.to_string() })
+        })
+        .collect();
+
+    json!({
+        "name": "bonfida-bot",
+        "version": env!("CARGO_PKG_VERSION"),
+        "encoding": "borsh",
+        "instructions": instructions,
+        "types": types,
+        "errors": errors,
+        "accounts": [
+            {
+                "name": "Pool",
+                "layout": [
+                    { "name": "header", "type": PoolHeader::declaration(), "size": PoolHeader::LEN },
+                    {
+                        "name": "markets",
+                        "type": "Pubkey",
+                        "count": "header.number_of_markets",
+                    },
+                    {
+                        "name": "assets",
+                        "type": PoolAsset::declaration(),
+                        "size": PoolAsset::LEN,
+                        "count": "remaining",
+                        "note": "Slots with a zero mint address are unused",
+                    },
+                ],
+            },
+            {
+                "name": "RedeemRequest",
+                "layout": [
+                    {
+                        "name": "request",
+                        "type": RedeemRequest::declaration(),
+                        "size": RedeemRequest::LEN,
+                    },
+                ],
+                "seeds": ["pool_seed", "redeem_request", "sequence number (u64 le)", "nonce"],
+            },
+            {
+                "name": "DepositRequest",
+                "layout": [
+                    {
+                        "name": "request",
+                        "type": DepositRequest::declaration(),
+                        "size": DepositRequest::LEN,
+                        "note": "Followed by 32 bytes for each escrowed mint",
+                    },
+                ],
+                "seeds": ["pool_seed", "deposit_request", "sequence number (u64 le)", "nonce"],
+            },
+            {
+                "name": "Trader",
+                "layout": [
+                    {
+                        "name": "trader",
+                        "type": Trader::declaration(),
+                        "size": Trader::LEN,
+                    },
+                ],
+                "seeds": ["pool_seed", "trader", "signal provider key", "trader key", "nonce"],
+            },
+        ],
+        "bond": {
+            "seeds": ["pool_seed", "bond", "nonce"],
+            "escrow": "Associated FIDA account of the bond account",
+        },
+        "notes": {
+            "PoolHeader.status": "Single byte. 0 is uninitialized, otherwise the two high bits \
+                are the locked and pending order flags and the 6 low bits hold the number of \
+                pending orders minus one. Unlocked is 0x3f.",
+            "TradeLimits.allowed_order_types": "Bit i stands for the OrderType of index i, 0 \
+                allows all of them. The same goes for allowed_self_trade_behaviors.",
+            "LegacyCreate": "Not Borsh encoded. The pool seed is followed by the number of \
+                markets (u16), fee_collection_period (u64), fee_ratio (u16), the markets and \
+                the deposit amounts (u64) up to the end of the data.",
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{instruction::Instruction, pubkey::Pubkey, system_program, sysvar};
+
+    use super::*;
+    use crate::instruction::{
+        add_trader, claim_fees, collect_fees, create, deposit, init, post_bond,
+        process_deposit_queue, process_redeem_queue, redeem, remove_trader,
+        request_bond_withdrawal, request_deposit, request_redeem, set_multisig, slash_bond,
+        withdraw_bond,
+    };
+    use crate::state::TradeLimits;
+
+    fn check_accounts(instruction: Instruction, tag: usize) {
+        assert_eq!(instruction.data[0] as usize, tag);
+        // The builders are called with a single asset and a single redeem request, and without
+        // multisig signers nor pending orders
+        let expected: Vec<&AccountSpec> = INSTRUCTION_ACCOUNTS[tag]
+            .iter()
+            .filter(|a| {
+                a.multiplicity != Multiplicity::Optional
+                    && a.multiplicity != Multiplicity::PerMultisigSigner
+                    && a.multiplicity != Multiplicity::PerPendingOrder
+            })
+            .collect();
+        assert_eq!(instruction.accounts.len(), expected.len());
+        for (meta, spec) in instruction.accounts.iter().zip(expected) {
+            assert_eq!(meta.is_writable, spec.writable, "{}", spec.name);
+            assert_eq!(meta.is_signer, spec.signer, "{}", spec.name);
+        }
+    }
+
+    #[test]
+    fn test_instruction_accounts() {
+        let k = Pubkey::new_unique();
+        check_accounts(
+            init(
+                &spl_token::id(),
+                &system_program::id(),
+                &sysvar::rent::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+                1,
+                1,
+            )
+            .unwrap(),
+            0,
+        );
+        check_accounts(
+            create(
+                &spl_token::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+                &vec![Pubkey::new_unique()],
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                1311,
+                15,
+                0,
+                TradeLimits::default(),
+                vec![1],
+                vec![Pubkey::new_unique()],
+            )
+            .unwrap(),
+            20,
+        );
+        check_accounts(
+            deposit(
+                &spl_token::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                [0; 32],
+                1,
+            )
+            .unwrap(),
+            2,
+        );
+        check_accounts(
+            redeem(
+                &spl_token::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                [0; 32],
+                1,
+            )
+            .unwrap(),
+            6,
+        );
+        check_accounts(
+            collect_fees(
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+            )
+            .unwrap(),
+            7,
+        );
+        check_accounts(
+            claim_fees(
+                &spl_token::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+            )
+            .unwrap(),
+            8,
+        );
+        check_accounts(
+            request_redeem(
+                &spl_token::id(),
+                &system_program::id(),
+                &sysvar::rent::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+                1,
+            )
+            .unwrap(),
+            9,
+        );
+        check_accounts(
+            process_redeem_queue(
+                &spl_token::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                &vec![(Pubkey::new_unique(), Pubkey::new_unique())],
+                [0; 32],
+            )
+            .unwrap(),
+            10,
+        );
+        check_accounts(
+            request_deposit(
+                &spl_token::id(),
+                &system_program::id(),
+                &sysvar::rent::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                &vec![Pubkey::new_unique()],
+                [0; 32],
+                1,
+                1,
+                vec![1],
+            )
+            .unwrap(),
+            11,
+        );
+        check_accounts(
+            process_deposit_queue(
+                &spl_token::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &vec![Pubkey::new_unique()],
+                &vec![(
+                    Pubkey::new_unique(),
+                    Pubkey::new_unique(),
+                    vec![Pubkey::new_unique()],
+                )],
+                [0; 32],
+            )
+            .unwrap(),
+            12,
+        );
+        check_accounts(
+            post_bond(
+                &spl_token::id(),
+                &system_program::id(),
+                &sysvar::rent::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+                1,
+            )
+            .unwrap(),
+            13,
+        );
+        check_accounts(
+            request_bond_withdrawal(
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+            )
+            .unwrap(),
+            14,
+        );
+        check_accounts(
+            withdraw_bond(
+                &spl_token::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+            )
+            .unwrap(),
+            15,
+        );
+        check_accounts(
+            slash_bond(
+                &spl_token::id(),
+                &system_program::id(),
+                &sysvar::rent::id(),
+                &sysvar::clock::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+            )
+            .unwrap(),
+            16,
+        );
+        check_accounts(
+            add_trader(
+                &system_program::id(),
+                &sysvar::rent::id(),
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+                &Pubkey::new_unique(),
+                0,
+            )
+            .unwrap(),
+            17,
+        );
+        check_accounts(
+            remove_trader(
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                [0; 32],
+                &Pubkey::new_unique(),
+            )
+            .unwrap(),
+            18,
+        );
+        check_accounts(
+            set_multisig(
+                &k,
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &[],
+                [0; 32],
+                0,
+                vec![],
+            )
+            .unwrap(),
+            19,
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let idl = generate();
+        let instructions = idl["instructions"].as_array().unwrap();
+        assert_eq!(instructions.len(), INSTRUCTION_ACCOUNTS.len());
+        assert_eq!(instructions[1]["name"], "LegacyCreate");
+        assert_eq!(instructions[20]["name"], "Create");
+        assert_eq!(instructions[20]["args"][5]["name"], "markets");
+        assert_eq!(idl["accounts"][0]["layout"][0]["size"], 390);
+        assert_eq!(idl["accounts"][1]["layout"][0]["size"], 41);
+        assert_eq!(idl["accounts"][2]["layout"][0]["size"], 53);
+        assert_eq!(idl["accounts"][3]["layout"][0]["size"], 35);
+        assert_eq!(idl["errors"][2]["name"], "LockedOperation");
+    }
+}
@@ -1,9 +1,6 @@
-use crate::error::BonfidaBotError;
-use crate::state::{BONFIDA_BNB, BONFIDA_FEE};
-use serum_dex::{
-    instruction::SelfTradeBehavior,
-    matching::{OrderType, Side},
-};
+use crate::{error::BonfidaBotError, state::TradeLimits, utils::find_trader_key};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use serum_dex::{instruction::SelfTradeBehavior as SerumSelfTradeBehavior, matching};
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     msg,
@@ -13,13 +10,97 @@ use solana_program::{
 use spl_associated_token_account::get_associated_token_address;
 use std::{
     convert::TryInto,
-    mem::size_of,
     num::{NonZeroU16, NonZeroU64},
-    str::FromStr,
 };
 
+/// The tag of `LegacyCreate`, which keeps the manual layout of the original program.
+pub const LEGACY_CREATE_TAG: u8 = 1;
+
+/// Serum order side, as encoded in the instruction data.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+/// Serum order type, as encoded in the instruction data.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Clone, Copy, Debug, PartialEq)]
+pub enum OrderType {
+    Limit,
+    ImmediateOrCancel,
+    PostOnly,
+}
+
+/// Serum self trade behavior, as encoded in the instruction data.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Clone, Copy, Debug, PartialEq)]
+pub enum SelfTradeBehavior {
+    DecrementTake,
+    CancelProvide,
+    AbortTransaction,
+}
+
+impl From<matching::Side> for Side {
+    fn from(side: matching::Side) -> Self {
+        match side {
+            matching::Side::Bid => Self::Bid,
+            matching::Side::Ask => Self::Ask,
+        }
+    }
+}
+
+impl From<Side> for matching::Side {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Bid => Self::Bid,
+            Side::Ask => Self::Ask,
+        }
+    }
+}
+
+impl From<matching::OrderType> for OrderType {
+    fn from(order_type: matching::OrderType) -> Self {
+        match order_type {
+            matching::OrderType::Limit => Self::Limit,
+            matching::OrderType::ImmediateOrCancel => Self::ImmediateOrCancel,
+            matching::OrderType::PostOnly => Self::PostOnly,
+        }
+    }
+}
+
+impl From<OrderType> for matching::OrderType {
+    fn from(order_type: OrderType) -> Self {
+        match order_type {
+            OrderType::Limit => Self::Limit,
+            OrderType::ImmediateOrCancel => Self::ImmediateOrCancel,
+            OrderType::PostOnly => Self::PostOnly,
+        }
+    }
+}
+
+impl From<SerumSelfTradeBehavior> for SelfTradeBehavior {
+    fn from(self_trade_behavior: SerumSelfTradeBehavior) -> Self {
+        match self_trade_behavior {
+            SerumSelfTradeBehavior::DecrementTake => Self::DecrementTake,
+            SerumSelfTradeBehavior::CancelProvide => Self::CancelProvide,
+            SerumSelfTradeBehavior::AbortTransaction => Self::AbortTransaction,
+        }
+    }
+}
+
+impl From<SelfTradeBehavior> for SerumSelfTradeBehavior {
+    fn from(self_trade_behavior: SelfTradeBehavior) -> Self {
+        match self_trade_behavior {
+            SelfTradeBehavior::DecrementTake => Self::DecrementTake,
+            SelfTradeBehavior::CancelProvide => Self::CancelProvide,
+            SelfTradeBehavior::AbortTransaction => Self::AbortTransaction,
+        }
+    }
+}
+
+/// The instructions are Borsh encoded, except for `LegacyCreate`. The leading variant index
+/// doubles as the instruction tag and variants should therefore never be reordered.
 #[repr(C)]
-#[derive(Clone, Debug, PartialEq)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Clone, Debug, PartialEq)]
 pub enum PoolInstruction {
     /// Initializes an empty pool account for the bonfida-bot program
     ///
@@ -39,54 +120,41 @@ pub enum PoolInstruction {
         max_number_of_assets: u32,
         number_of_markets: u16,
     },
-    /// Creates a new pool from an empty (uninitialized) one by performing the first deposit
-    /// of any number of different tokens and setting the pubkey of the signal provider.
-    /// The first deposit will fix the initial value of 1 pooltoken (credited to the target)
-    /// with respect to the deposited tokens.
-    /// The init and create operations need to be separated as account data
-    /// allocation needs to be first processed by the network before being overwritten.
+    /// Creates a pool with the layout of the first version of the program, which is still
+    /// decoded so that older clients keep working. Unlike the other instructions it is not Borsh
+    /// encoded: the pool seed is followed by the number of markets (u16), the fee collection
+    /// period (u64), the fee ratio (u16), the markets and the deposit amounts up to the end of
+    /// the data. The fees taken every period are turned into the equivalent annual fee ratio
+    /// and the pool has neither a minimum signal provider stake nor trade limits.
     ///
-    /// Accounts expected by this instruction:
-    ///
-    ///   * Single owner
-    ///   0. `[]` The spl-token program account
-    ///   1. `[]` The clock sysvar account
-    ///   1. `[]` The serum program account
-    ///   1. `[]` The signal provider account
-    ///   2. `[writable]` The pooltoken mint account
-    ///   3. `[writable]` The target account that receives the pooltokens
-    ///   4. `[writable]` The pool account
-    ///   5..M+5. `[writable]` The M pool (associated) token assets accounts in the order of the
-    ///      corresponding PoolAssets in the pool account data.
-    ///   M+5. `[signer]` The source owner account
-    ///   M+6..2M+6. `[writable]` The M source token accounts in the same order as above
-    Create {
+    /// Accounts expected by this instruction: the same as `Create`.
+    LegacyCreate {
         pool_seed: [u8; 32],
         fee_collection_period: u64,
         fee_ratio: u16,
-        deposit_amounts: Vec<u64>,
         markets: Vec<Pubkey>,
+        deposit_amounts: Vec<u64>,
     },
     /// Buy into the pool. The source deposits tokens into the pool and the target receives
     /// a corresponding amount of pool-token in exchange. The program will try to
     /// maximize the deposit sum with regards to the amounts given by the source and
     /// the ratio of tokens present in the pool at that moment. Tokens can only be deposited
     /// in the exact ratio of tokens that are present in the pool.
+    /// The management fees accrued since the last collection are accounted for beforehand, and
+    /// the deposit fees are added to the fees that the recipients can claim.
     ///
     /// Accounts expected by this instruction:
     ///
     ///   * Single owner
     ///   0. `[]` The spl-token program account
-    ///   1. `[writable]` The pooltoken mint account
-    ///   2. `[writable]` The target account that receives the pooltokens
-    ///   3. `[writable]` The signal provider account that receives the pooltoken fees
-    ///   4. `[writable]` The Bonfida fee account that receives the pooltoken fees
-    ///   5. `[writable]` The Bonfida buy and burn account that receives the pooltoken fees
-    ///   6. `[]` The pool account
-    ///   7..M+7. `[writable]` The M pool (associated) token assets accounts in the order of the
+    ///   1. `[]` The clock sysvar account
+    ///   2. `[writable]` The pooltoken mint account
+    ///   3. `[writable]` The target account that receives the pooltokens
+    ///   4. `[writable]` The pool account
+    ///   5..M+5. `[writable]` The M pool (associated) token assets accounts in the order of the
     ///      corresponding PoolAssets in the pool account data.
-    ///   M+7. `[signer]` The source owner account
-    ///   M+8..2M+8. `[writable]` The M source token accounts in the same order as above
+    ///   M+5. `[signer]` The source owner account
+    ///   M+6..2M+6. `[writable]` The M source token accounts in the same order as above
     Deposit {
         pool_seed: [u8; 32],
         // The amount of pool token the source wishes to buy
@@ -94,11 +162,20 @@ pub enum PoolInstruction {
     },
     /// As a signal provider, create a new serum order for the pool.
     /// Amounts are translated into proportions of the pool between 0 and 2**16 - 1
+    /// The associated pooltoken account of the signal provider should hold at least the
+    /// `min_signal_provider_ratio` of the pool token supply. A trader of the pool can sign
+    /// instead of the signal provider, within the limits of its trader account, and only the
+    /// traders can sign once the signal provider has added one. The order is
+    /// checked against the trade limits of the pool and counted in its current trading day, its
+    /// limit price should stay within the price band of the pool around the market book, and its
+    /// order type and self trade behavior should be allowed by the pool.
+    /// Each order needs an empty OpenOrders account : an account used by a previous order has to
+    /// be settled before it can be used again.
     ///
     /// Accounts expected by this instruction:
     ///
     ///   * Single owner
-    ///    0. `[signer]` The signal provider account
+    ///    0. `[signer]` The signal provider or trader account
     ///    1. `[writable]` The market account
     ///    2. `[writable]` The payer pool asset account
     ///    3. `[writable]` The relevant OpenOrders account
@@ -112,12 +189,23 @@ pub enum PoolInstruction {
     ///   11. `[]` The spl_token_program
     ///   12. `[]` The rent sysvar account
     ///   13. `[]` The dex program account
-    ///   14. `[writable]` (optional) The (M)SRM discount account
+    ///   14. `[]` The pooltoken mint account
+    ///   15. `[]` The associated pooltoken account of the signal provider
+    ///   16. `[]` The trader account of the signer, ignored when the signal provider signs
+    ///   17. `[]` The clock sysvar account
+    ///   18. `[writable]` (optional) The (M)SRM discount account
+    ///
+    ///   * Multisignature signal provider
+    ///    0..18. As above, the signal provider account does not sign and the discount account is
+    ///      required
+    ///   19..19+M. `[signer]` M signer accounts of the multisig
     CreateOrder {
         pool_seed: [u8; 32],
         side: Side,
-        limit_price: NonZeroU64,
-        ratio_of_pool_assets_to_trade: NonZeroU16,
+        // Should be non zero
+        limit_price: u64,
+        // Should be non zero
+        ratio_of_pool_assets_to_trade: u16,
         order_type: OrderType,
         client_id: u64,
         self_trade_behavior: SelfTradeBehavior,
@@ -127,14 +215,14 @@ pub enum PoolInstruction {
         coin_lot_size: u64,
         pc_lot_size: u64,
         target_mint: Pubkey,
-        serum_limit: u16
+        serum_limit: u16,
     },
-    /// As a signal provider, cancel a serum order for the pool.
+    /// As a signal provider or trader, cancel a serum order for the pool.
     ///
     /// Accounts expected by this instruction:
     ///
     ///   * Single owner
-    ///    0. `[signer]` The signal provider account
+    ///    0. `[signer]` The signal provider or trader account
     ///    1. `[]` The market account
     ///    2. `[writable]` The relevant OpenOrders account
     ///    3. `[writable]` The Serum market bids
@@ -142,6 +230,11 @@ pub enum PoolInstruction {
     ///    5. `[writable]` The Serum event queue
     ///    6. `[]` The pool account
     ///    7. `[]` The dex program account
+    ///    8. `[]` (optional) The trader account of the signer, when a trader signs
+    ///
+    ///   * Multisignature signal provider
+    ///    0..7. As above, the signal provider account does not sign
+    ///    8..8+M. `[signer]` M signer accounts of the multisig
     CancelOrder {
         pool_seed: [u8; 32],
         side: Side,
@@ -169,8 +262,12 @@ pub enum PoolInstruction {
         coin_index: u64,
     },
     /// Buy out of the pool by redeeming pooltokens.
-    /// This instruction needs to be executed after (and within the same transaction)
-    /// having settled on all possible open orders for the pool.
+    /// An OpenOrders account counts as a pending order until `SettleFunds` moves all of its funds
+    /// back to the pool. While the pool has pending orders, the OpenOrders account of each of them
+    /// has to be given along with its market, and none of them can have orders left on the book.
+    /// Their free funds are included in the pool value and the payout, which is made from the
+    /// pool asset accounts and fails if these do not hold enough settled funds.
+    /// The management fees accrued since the last collection are accounted for beforehand.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -180,419 +277,398 @@ pub enum PoolInstruction {
     ///   2. `[writable]` The pooltoken mint account
     ///   3. `[signer]` The pooltoken source account owner
     ///   4. `[writable]` The pooltoken source account
-    ///   5. `[]` The pool account
+    ///   5. `[writable]` The pool account
     ///   6..M+6. `[writable]` The M pool (associated) token assets accounts in the order of the
     ///      corresponding PoolAssets found in the pool account data.
-    ///   M+7..2M+7. `[writable]` The M target token accounts in the same order as above
+    ///   M+6..2M+6. `[writable]` The M target token accounts in the same order as above
+    ///   2M+6..2M+6+2P. `[]` The market and OpenOrders accounts of the P pending orders, in pairs
     Redeem {
         pool_seed: [u8; 32],
         // The amount of pool token the source wishes to redeem
         pool_token_amount: u64,
     },
-    /// Trigger signal provider and Bonfida fee collection
+    /// Account for the management fees accrued since the last collection, which can then be
+    /// claimed by the signal provider and Bonfida. Fails when there is nothing to collect.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The clock sysvar account
+    ///   1. `[writable]` The pool account
+    ///   2. `[]` The pooltoken mint account
+    CollectFees { pool_seed: [u8; 32] },
+    /// Mint the pool token fees owed to one of the fee recipients : the signal provider (one
+    /// half), Bonfida (one quarter) or the Bonfida buy and burn account (one quarter). The
+    /// management fees accrued since the last collection are accounted for beforehand. Fails when
+    /// there is nothing to claim.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The spl-token program account
+    ///   1. `[]` The clock sysvar account
+    ///   2. `[writable]` The pool account
+    ///   3. `[writable]` The pooltoken mint account
+    ///   4. `[writable]` The associated pooltoken account of the fee recipient
+    ClaimFees { pool_seed: [u8; 32] },
+    /// Queue a redeem which is paid out by `ProcessRedeemQueue` once the pool has no pending
+    /// orders, so that holders can exit without racing the signal provider. The request is
+    /// recorded in an account derived from the pool seed and the next sequence number of the
+    /// queue, and the pooltokens are escrowed in the associated pooltoken account of the request
+    /// account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The system program account
+    ///   1. `[]` The sysvar rent program account
+    ///   2. `[]` The spl-token program account
+    ///   3. `[]` The spl associated token account program account
+    ///   4. `[writable]` The pool account
+    ///   5. `[]` The pooltoken mint account
+    ///   6. `[writable]` The redeem request account
+    ///   7. `[writable]` The associated pooltoken account of the redeem request account
+    ///   8. `[writable, signer]` The pooltoken source account owner, which pays for the request
+    ///      accounts and receives the redeemed assets
+    ///   9. `[writable]` The pooltoken source account
+    RequestRedeem {
+        pool_seed: [u8; 32],
+        // The amount of pool token the source wishes to redeem
+        pool_token_amount: u64,
+    },
+    /// A permissionless crank to pay out the oldest queued redeem requests at the current value
+    /// of the pool, which is only possible while the pool has no pending orders. The redeemed
+    /// assets are transferred to the associated token accounts of the request owners, and the
+    /// rent of the request accounts is returned to them.
+    /// The management fees accrued since the last collection are accounted for beforehand.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The spl-token program account
+    ///   1. `[]` The clock sysvar account
+    ///   2. `[writable]` The pool account
+    ///   3. `[writable]` The pooltoken mint account
+    ///   4..M+4. `[writable]` The M pool (associated) token assets accounts in the order of the
+    ///      corresponding PoolAssets found in the pool account data.
+    ///   Then, for each of the N requests in the order of the queue:
+    ///   * `[writable]` The redeem request account
+    ///   * `[writable]` The associated pooltoken account of the redeem request account
+    ///   * `[writable]` The owner of the redeem request
+    ///   * `[writable]` The M associated token accounts of the owner in the same order as above
+    ProcessRedeemQueue {
+        pool_seed: [u8; 32],
+        // The number of requests to pay out, at most MAX_REDEEM_BATCH_SIZE
+        number_of_requests: u8,
+    },
+    /// Queue a deposit which is processed by `ProcessDepositQueue` once the pool is unlocked and
+    /// has no pending orders. The request is recorded in an account derived from the pool seed
+    /// and the next sequence number of the queue, and the deposited basket is escrowed in the
+    /// associated token accounts of the request account. The basket is the most the depositor
+    /// pays for the pooltokens.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The system program account
+    ///   1. `[]` The sysvar rent program account
+    ///   2. `[]` The spl-token program account
+    ///   3. `[]` The spl associated token account program account
+    ///   4. `[]` The clock sysvar account
+    ///   5. `[writable]` The pool account
+    ///   6. `[]` The pooltoken mint account
+    ///   7. `[writable]` The deposit request account
+    ///   8. `[writable, signer]` The source token accounts owner, which pays for the request
+    ///      accounts and receives the pooltokens or the refunded basket
+    ///   9..M+9. `[]` The M asset mint accounts in the order of the corresponding PoolAssets
+    ///      found in the pool account data.
+    ///   M+9..2M+9. `[writable]` The M source token accounts in the same order as above
+    ///   2M+9..3M+9. `[writable]` The M associated token accounts of the deposit request account
+    ///      in the same order as above
+    RequestDeposit {
+        pool_seed: [u8; 32],
+        // The exact amount of pool token the source wishes to buy, fees included
+        pool_token_amount: u64,
+        // The request is refunded when it is processed after this time
+        expiry_timestamp: u64,
+        // The escrowed amount of each pool asset
+        asset_amounts: Vec<u64>,
+    },
+    /// A permissionless crank to process the oldest queued deposit requests. A request is
+    /// refunded when it has expired, when the pool no longer exists or when its basket does not
+    /// cover the requested pooltokens at the current value of the pool. Otherwise, which is only
+    /// possible while the pool is unlocked and has no pending orders, the pooltokens are minted
+    /// to the owner, and the rest of the basket is refunded. The rent of the request accounts is
+    /// returned to the owner in both cases.
+    /// The management fees accrued since the last collection are accounted for beforehand.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The spl-token program account
+    ///   1. `[]` The clock sysvar account
+    ///   2. `[writable]` The pool account
+    ///   3. `[writable]` The pooltoken mint account
+    ///   4..M+4. `[writable]` The M pool (associated) token assets accounts in the order of the
+    ///      corresponding PoolAssets found in the pool account data.
+    ///   Then, for each of the N requests in the order of the queue:
+    ///   * `[writable]` The deposit request account
+    ///   * `[writable]` The owner of the deposit request
+    ///   * `[writable]` The associated pooltoken account of the owner
+    ///   * `[writable]` The E associated token accounts of the deposit request account in the
+    ///     order of the `escrowed_mints` of the request, which are the pool assets when the
+    ///     request was made. Escrows of assets the pool no longer holds are refunded.
+    ///   * `[writable]` The E associated token accounts of the owner in the same order as above
+    ProcessDepositQueue {
+        pool_seed: [u8; 32],
+        // The number of requests to process, at most MAX_DEPOSIT_BATCH_SIZE
+        number_of_requests: u8,
+    },
+    /// Lock FIDA from the signal provider into the bond of the pool, which is slashed when the
+    /// pool is left with pending orders for too long. The bond is escrowed in the associated FIDA
+    /// account of the bond account, which is derived from the pool seed, and has to reach
+    /// MIN_BOND_AMOUNT. Posting a bond cancels any pending withdrawal request. FIDA is added to
+    /// the pool assets if needed, so that a slashed bond always has an asset slot to go to.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The system program account
+    ///   1. `[]` The sysvar rent program account
+    ///   2. `[]` The spl-token program account
+    ///   3. `[]` The spl associated token account program account
+    ///   4. `[writable]` The pool account
+    ///   5. `[]` The FIDA mint account
+    ///   6. `[]` The bond account
+    ///   7. `[writable]` The associated FIDA account of the bond account
+    ///   8. `[writable, signer]` The signal provider account, which pays for the escrow account
+    ///   9. `[writable]` The source FIDA account of the signal provider
+    ///   10. `[writable]` The associated FIDA account of the pool, created if it does not exist
+    ///
+    ///   * Multisignature signal provider
+    ///   0..10. As above, the signal provider account still signs as the source FIDA account owner
+    ///   11..11+M. `[signer]` M signer accounts of the multisig
+    PostBond {
+        pool_seed: [u8; 32],
+        // The amount of FIDA to add to the bond
+        amount: u64,
+    },
+    /// Start the notice period after which the signal provider can withdraw the bond.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The clock sysvar account
+    ///   1. `[writable]` The pool account
+    ///   2. `[signer]` The signal provider account
+    ///
+    ///   * Multisignature signal provider
+    ///   0..2. As above, the signal provider account does not sign
+    ///   3..3+M. `[signer]` M signer accounts of the multisig
+    RequestBondWithdrawal { pool_seed: [u8; 32] },
+    /// Return the bond to the signal provider once the notice period is over, or at any time
+    /// after the pool has been reset. The escrow account is closed.
     ///
     /// Accounts expected by this instruction:
     ///
     ///   * Single owner
     ///   0. `[]` The spl-token program account
     ///   1. `[]` The clock sysvar account
+    ///   2. `[writable]` The pool account
+    ///   3. `[]` The bond account
+    ///   4. `[writable]` The associated FIDA account of the bond account
+    ///   5. `[writable, signer]` The signal provider account, which receives the escrow rent
+    ///   6. `[writable]` The target FIDA account
+    ///
+    ///   * Multisignature signal provider
+    ///   0..6. As above, the signal provider account does not sign
+    ///   7..7+M. `[signer]` M signer accounts of the multisig
+    WithdrawBond { pool_seed: [u8; 32] },
+    /// A permissionless crank against pools with pending orders. Once the first unsettled
+    /// immediate or cancel order of the pool has been left for MAX_PENDING_ORDER_DURATION, the
+    /// bond is transferred to the FIDA asset of the pool, and the escrow account is closed.
+    /// Resting limit and post only orders do not start the timer, which is reset when the pending
+    /// orders are settled.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The system program account
+    ///   1. `[]` The sysvar rent program account
+    ///   2. `[]` The spl-token program account
+    ///   3. `[]` The spl associated token account program account
+    ///   4. `[]` The clock sysvar account
     ///   5. `[writable]` The pool account
+    ///   6. `[]` The FIDA mint account
+    ///   7. `[]` The bond account
+    ///   8. `[writable]` The associated FIDA account of the bond account
+    ///   9. `[writable]` The associated FIDA account of the pool, created if it does not exist
+    ///   10. `[writable]` The signal provider account, which receives the escrow rent
+    ///   11. `[writable, signer]` The fee payer account, which pays for the pool FIDA account if
+    ///      it has to be created
+    SlashBond { pool_seed: [u8; 32] },
+    /// As the signal provider, allow a key to create and cancel orders for the pool, or update
+    /// the limits of an existing trader. Traders cannot claim fees or manage the pool, and the
+    /// signal provider can no longer create orders itself while it has traders.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The system program account
+    ///   1. `[]` The rent sysvar account
+    ///   2. `[writable]` The pool account
+    ///   3. `[writable]` The trader account, derived from the pool seed, TRADER_SEED, the signal
+    ///      provider key and the trader key
+    ///   4. `[writable, signer]` The signal provider account, which pays for the trader account
+    ///
+    ///   * Multisignature signal provider
+    ///   0..4. As above, the signal provider account still signs as the payer
+    ///   5..5+M. `[signer]` M signer accounts of the multisig
+    AddTrader {
+        pool_seed: [u8; 32],
+        trader: Pubkey,
+        /// The largest share of a pool asset a single order of the trader can trade, as a 16 bit
+        /// fixed point ratio. 0 sets no limit.
+        max_ratio_of_pool_assets_to_trade: u16,
+    },
+    /// As the signal provider, revoke a trader by closing its account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The pool account
+    ///   1. `[writable]` The trader account
+    ///   2. `[writable, signer]` The signal provider account, which receives the rent
+    ///
+    ///   * Multisignature signal provider
+    ///   0..2. As above, the signal provider account does not sign
+    ///   3..3+M. `[signer]` M signer accounts of the multisig
+    RemoveTrader { pool_seed: [u8; 32], trader: Pubkey },
+    /// Replace the multisig configuration of the signal provider. Once a threshold is set, the
+    /// instructions reserved to the signal provider require that many distinct signatures from
+    /// the multisig signers instead of the signature of the signal provider key, which still
+    /// receives the fees and pays for the bond and the trader accounts. A threshold of 0 without
+    /// signers restores the single signal provider.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[writable]` The pool account
+    ///   1. `[signer]` The signal provider account
+    ///
+    ///   * Multisignature signal provider
+    ///   0. `[writable]` The pool account
+    ///   1. `[]` The signal provider account
+    ///   2..2+M. `[signer]` M signer accounts of the current multisig
+    SetMultisig {
+        pool_seed: [u8; 32],
+        /// The number of signers required, at most the number of signers
+        threshold: u8,
+        /// At most MAX_MULTISIG_SIGNERS distinct keys
+        signers: Vec<Pubkey>,
+    },
+    /// Creates a new pool from an empty (uninitialized) one by performing the first deposit
+    /// of any number of different tokens and setting the pubkey of the signal provider.
+    /// The first deposit will fix the initial value of 1 pooltoken (credited to the target)
+    /// with respect to the deposited tokens.
+    /// The init and create operations need to be separated as account data
+    /// allocation needs to be first processed by the network before being overwritten.
+    /// This replaces `LegacyCreate`, whose tag is kept for the original layout.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   * Single owner
+    ///   0. `[]` The spl-token program account
+    ///   1. `[]` The clock sysvar account
+    ///   1. `[]` The serum program account
+    ///   1. `[]` The signal provider account
     ///   2. `[writable]` The pooltoken mint account
-    ///   3. `[writable]` The signal provider account that receives the pooltoken fees
-    ///   4. `[writable]` The Bonfida fee account that receives the pooltoken fees
-    ///   5. `[writable]` The Bonfida buy and burn account that receives the pooltoken fees
-    CollectFees { pool_seed: [u8; 32] },
+    ///   3. `[writable]` The target account that receives the pooltokens
+    ///   4. `[writable]` The pool account
+    ///   5..M+5. `[writable]` The M pool (associated) token assets accounts in the order of the
+    ///      corresponding PoolAssets in the pool account data.
+    ///   M+5. `[signer]` The source owner account
+    ///   M+6..2M+6. `[writable]` The M source token accounts in the same order as above
+    Create {
+        pool_seed: [u8; 32],
+        /// The share of the pool taken as management fees over a year, which accrue continuously.
+        /// This is a 16 bit fixed point ratio of at most 2**14 (25%).
+        annual_fee_ratio: u16,
+        /// The share of the deposited pool tokens taken as fees, as a 16 bit fixed point ratio.
+        fee_ratio: u16,
+        /// The share of the pool token supply the associated pooltoken account of the signal
+        /// provider has to hold, as a 16 bit fixed point ratio. 0 disables the requirement.
+        min_signal_provider_ratio: u16,
+        /// The limits on the size and the number of the orders of the pool.
+        trade_limits: TradeLimits,
+        markets: Vec<Pubkey>,
+        deposit_amounts: Vec<u64>,
+    },
 }
 
 impl PoolInstruction {
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        use BonfidaBotError::InvalidInstruction;
-        let (&tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
-        Ok(match tag {
-            0 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .unwrap();
-                let max_number_of_assets: u32 = rest
-                    .get(32..36)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u32::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let number_of_markets: u16 = rest
-                    .get(36..38)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u16::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::Init {
-                    pool_seed,
-                    max_number_of_assets,
-                    number_of_markets,
-                }
-            }
-            1 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .unwrap();
-                let number_of_markets = rest
-                    .get(32..34)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u16::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let fee_collection_period = rest
-                    .get(34..42)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let fee_ratio = rest
-                    .get(42..44)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u16::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let mut markets = Vec::with_capacity(number_of_markets as usize);
-                let mut offset = 44;
-                for _ in 0..number_of_markets {
-                    markets.push(
-                        rest.get(offset..offset + 32)
-                            .and_then(|slice| slice.try_into().ok())
-                            .map(Pubkey::new)
-                            .ok_or(InvalidInstruction)?,
-                    );
-                    offset = offset + 32;
-                }
-                let mut k = offset;
-                let mut deposit_amounts = vec![];
-                while k != 0 {
-                    match rest.get(k..(k + 8)) {
-                        None => k = 0,
-                        Some(bytes) => {
-                            deposit_amounts.push(u64::from_le_bytes(bytes.try_into().unwrap()));
-                            k = k + 8;
-                        }
-                    }
-                }
-                Self::Create {
-                    pool_seed,
-                    markets,
-                    deposit_amounts,
-                    fee_collection_period,
-                    fee_ratio,
-                }
-            }
-            2 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .unwrap();
-                let pool_token_amount = rest
-                    .get(32..40)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::Deposit {
-                    pool_seed,
-                    pool_token_amount,
-                }
-            }
-            3 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .ok_or(InvalidInstruction)?;
-                let side = match rest.get(32).ok_or(InvalidInstruction)? {
-                    0 => Side::Bid,
-                    1 => Side::Ask,
-                    _ => return Err(InvalidInstruction.into()),
-                };
-                let limit_price = NonZeroU64::new(
-                    rest.get(33..41)
-                        .and_then(|slice| slice.try_into().ok())
-                        .map(u64::from_le_bytes)
-                        .ok_or(InvalidInstruction)?,
-                )
-                .ok_or(InvalidInstruction)?;
-                let ratio_of_pool_assets_to_trade = NonZeroU16::new(
-                    rest.get(41..43)
-                        .and_then(|slice| slice.try_into().ok())
-                        .map(u16::from_le_bytes)
-                        .ok_or(InvalidInstruction)?,
-                )
-                .ok_or(InvalidInstruction)?;
-
-                let order_type = match rest.get(43).ok_or(InvalidInstruction)? {
-                    0 => OrderType::Limit,
-                    1 => OrderType::ImmediateOrCancel,
-                    2 => OrderType::PostOnly,
-                    _ => return Err(InvalidInstruction.into()),
-                };
-                let client_id = rest
-                    .get(44..52)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let self_trade_behavior = match rest.get(52).ok_or(InvalidInstruction)? {
-                    0 => SelfTradeBehavior::DecrementTake,
-                    1 => SelfTradeBehavior::CancelProvide,
-                    2 => SelfTradeBehavior::AbortTransaction,
-                    _ => return Err(InvalidInstruction.into()),
-                };
-                let source_index = rest
-                    .get(53..61)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let target_index = rest
-                    .get(61..69)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let market_index = rest
-                    .get(69..71)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u16::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let coin_lot_size = rest
-                    .get(71..79)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let pc_lot_size = rest
-                    .get(79..87)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let target_mint = rest
-                    .get(87..119)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(Pubkey::new)
-                    .ok_or(InvalidInstruction)?;
-                let serum_limit = rest
-                    .get(119..121)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u16::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::CreateOrder {
-                    pool_seed,
-                    side,
-                    limit_price,
-                    ratio_of_pool_assets_to_trade,
-                    order_type,
-                    client_id,
-                    self_trade_behavior,
-                    source_index,
-                    target_index,
-                    market_index,
-                    coin_lot_size,
-                    pc_lot_size,
-                    target_mint,
-                    serum_limit
-                }
-            }
-            4 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .unwrap();
-                let side = match rest.get(32).ok_or(InvalidInstruction)? {
-                    0 => Side::Bid,
-                    1 => Side::Ask,
-                    _ => return Err(InvalidInstruction.into()),
-                };
-                let order_id = rest
-                    .get(33..49)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u128::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-
-                Self::CancelOrder {
-                    pool_seed,
-                    side,
-                    order_id,
-                }
-            }
-            5 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .unwrap();
-                let pc_index = rest
-                    .get(32..40)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                let coin_index = rest
-                    .get(40..48)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::SettleFunds {
-                    pool_seed,
-                    pc_index,
-                    coin_index,
-                }
-            }
-            6 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .unwrap();
-                let pool_token_amount = rest
-                    .get(32..40)
-                    .and_then(|slice| slice.try_into().ok())
-                    .map(u64::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-                Self::Redeem {
-                    pool_seed,
-                    pool_token_amount,
-                }
-            }
-            7 => {
-                let pool_seed: [u8; 32] = rest
-                    .get(..32)
-                    .and_then(|slice| slice.try_into().ok())
-                    .unwrap();
-                Self::CollectFees { pool_seed }
-            }
-            _ => {
-                msg!("Unsupported tag");
-                return Err(InvalidInstruction.into());
-            }
+        if let Some((&LEGACY_CREATE_TAG, rest)) = input.split_first() {
+            return Self::unpack_legacy_create(rest).ok_or_else(|| {
+                msg!("Failed to deserialize the instruction");
+                BonfidaBotError::InvalidInstruction.into()
+            });
+        }
+        Self::try_from_slice(input).map_err(|_| {
+            msg!("Failed to deserialize the instruction");
+            BonfidaBotError::InvalidInstruction.into()
         })
     }
 
     pub fn pack(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(size_of::<Self>());
         match self {
-            Self::Init {
-                pool_seed,
-                max_number_of_assets,
-                number_of_markets,
-            } => {
-                buf.push(0);
-                buf.extend_from_slice(pool_seed);
-                buf.extend_from_slice(&max_number_of_assets.to_le_bytes());
-                buf.extend_from_slice(&number_of_markets.to_le_bytes());
-            }
-            Self::Create {
+            Self::LegacyCreate {
                 pool_seed,
                 fee_collection_period,
                 fee_ratio,
-                deposit_amounts,
                 markets,
+                deposit_amounts,
             } => {
-                buf.push(1);
-                buf.extend_from_slice(pool_seed);
-                buf.extend_from_slice(&(markets.len() as u16).to_le_bytes());
-                buf.extend_from_slice(&fee_collection_period.to_le_bytes());
-                buf.extend_from_slice(&fee_ratio.to_le_bytes());
+                let mut data = vec![LEGACY_CREATE_TAG];
+                data.extend_from_slice(pool_seed);
+                data.extend_from_slice(&(markets.len() as u16).to_le_bytes());
+                data.extend_from_slice(&fee_collection_period.to_le_bytes());
+                data.extend_from_slice(&fee_ratio.to_le_bytes());
                 for market in markets {
-                    buf.extend_from_slice(&market.to_bytes())
+                    data.extend_from_slice(&market.to_bytes());
                 }
-                for amount in deposit_amounts.iter() {
-                    buf.extend_from_slice(&amount.to_le_bytes());
+                for amount in deposit_amounts {
+                    data.extend_from_slice(&amount.to_le_bytes());
                 }
+                data
             }
-            Self::Deposit {
-                pool_seed,
-                pool_token_amount,
-            } => {
-                buf.push(2);
-                buf.extend_from_slice(pool_seed);
-                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
-            }
-            Self::CreateOrder {
-                pool_seed,
-                side,
-                limit_price,
-                ratio_of_pool_assets_to_trade,
-                order_type,
-                client_id,
-                self_trade_behavior,
-                source_index,
-                target_index,
-                market_index,
-                coin_lot_size,
-                pc_lot_size,
-                target_mint,
-                serum_limit
-            } => {
-                buf.push(3);
-                buf.extend_from_slice(pool_seed);
-                buf.extend_from_slice(
-                    &match side {
-                        Side::Bid => 0u8,
-                        Side::Ask => 1,
-                    }
-                    .to_le_bytes(),
-                );
-                buf.extend_from_slice(&limit_price.get().to_le_bytes());
-                buf.extend_from_slice(&ratio_of_pool_assets_to_trade.get().to_le_bytes());
-                buf.extend_from_slice(
-                    &match order_type {
-                        OrderType::Limit => 0u8,
-                        OrderType::ImmediateOrCancel => 1,
-                        OrderType::PostOnly => 2,
-                    }
-                    .to_le_bytes(),
-                );
-                buf.extend_from_slice(&client_id.to_le_bytes());
-                buf.extend_from_slice(
-                    &match self_trade_behavior {
-                        SelfTradeBehavior::DecrementTake => 0u8,
-                        SelfTradeBehavior::CancelProvide => 1,
-                        SelfTradeBehavior::AbortTransaction => 2,
-                    }
-                    .to_le_bytes(),
-                );
-                buf.extend_from_slice(&source_index.to_le_bytes());
-                buf.extend_from_slice(&target_index.to_le_bytes());
-                buf.extend_from_slice(&market_index.to_le_bytes());
-                buf.extend_from_slice(&coin_lot_size.to_le_bytes());
-                buf.extend_from_slice(&pc_lot_size.to_le_bytes());
-                buf.extend_from_slice(&target_mint.to_bytes());
-                buf.extend_from_slice(&serum_limit.to_le_bytes())
-            }
-            Self::CancelOrder {
-                pool_seed,
-                side,
-                order_id,
-            } => {
-                buf.push(4);
-                buf.extend_from_slice(pool_seed);
-                buf.extend_from_slice(
-                    &match side {
-                        Side::Bid => 0u8,
-                        Side::Ask => 1,
-                    }
-                    .to_le_bytes(),
-                );
-                buf.extend_from_slice(&order_id.to_le_bytes());
-            }
-            Self::SettleFunds {
-                pool_seed,
-                pc_index,
-                coin_index,
-            } => {
-                buf.push(5);
-                buf.extend_from_slice(pool_seed);
-                buf.extend_from_slice(&pc_index.to_le_bytes());
-                buf.extend_from_slice(&coin_index.to_le_bytes());
-            }
-            Self::Redeem {
-                pool_seed,
-                pool_token_amount,
-            } => {
-                buf.push(6);
-                buf.extend_from_slice(pool_seed);
-                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
-            }
-            Self::CollectFees { pool_seed } => {
-                buf.push(7);
-                buf.extend_from_slice(pool_seed);
-            }
-        };
-        buf
+            _ => self.try_to_vec().unwrap(),
+        }
+    }
+
+    fn unpack_legacy_create(rest: &[u8]) -> Option<Self> {
+        let pool_seed: [u8; 32] = rest.get(..32)?.try_into().ok()?;
+        let number_of_markets = rest.get(32..34)?.try_into().map(u16::from_le_bytes).ok()?;
+        let fee_collection_period = rest.get(34..42)?.try_into().map(u64::from_le_bytes).ok()?;
+        let fee_ratio = rest.get(42..44)?.try_into().map(u16::from_le_bytes).ok()?;
+        let deposits_offset = 44 + 32 * number_of_markets as usize;
+        let markets = rest
+            .get(44..deposits_offset)?
+            .chunks_exact(32)
+            .map(Pubkey::new)
+            .collect();
+        // As in the original decoding, trailing bytes that do not make up an amount are ignored
+        let deposit_amounts = rest[deposits_offset..]
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        Some(Self::LegacyCreate {
+            pool_seed,
+            fee_collection_period,
+            fee_ratio,
+            markets,
+            deposit_amounts,
+        })
     }
 }
 
@@ -644,8 +720,10 @@ pub fn create(
     source_asset_keys: &Vec<Pubkey>,
     serum_program_id: &Pubkey,
     signal_provider_key: &Pubkey,
-    fee_collection_period: u64,
+    annual_fee_ratio: u16,
     fee_ratio: u16,
+    min_signal_provider_ratio: u16,
+    trade_limits: TradeLimits,
     deposit_amounts: Vec<u64>,
     markets: Vec<Pubkey>,
 ) -> Result<Instruction, ProgramError> {
@@ -653,8 +731,10 @@ pub fn create(
         pool_seed,
         deposit_amounts,
         markets,
-        fee_collection_period,
+        annual_fee_ratio,
         fee_ratio,
+        min_signal_provider_ratio,
+        trade_limits,
     }
     .pack();
     let mut accounts = vec![
@@ -684,12 +764,12 @@ pub fn create(
 // Creates a `Deposit` instruction
 pub fn deposit(
     spl_token_program_id: &Pubkey,
+    clock_sysvar_id: &Pubkey,
     bonfidabot_program_id: &Pubkey,
     mint_key: &Pubkey,
     pool_key: &Pubkey,
     pool_asset_keys: &Vec<Pubkey>,
     target_pool_token_key: &Pubkey,
-    signal_provider_pool_token_key: &Pubkey,
     source_owner: &Pubkey,
     source_asset_keys: &Vec<Pubkey>,
     pool_seed: [u8; 32],
@@ -700,18 +780,12 @@ pub fn deposit(
         pool_token_amount,
     }
     .pack();
-    let bonfida_fee_pt_account =
-        get_associated_token_address(&Pubkey::from_str(BONFIDA_FEE).unwrap(), mint_key);
-    let bonfida_bnb_pt_account =
-        get_associated_token_address(&Pubkey::from_str(BONFIDA_BNB).unwrap(), mint_key);
     let mut accounts = vec![
         AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
         AccountMeta::new(*mint_key, false),
         AccountMeta::new(*target_pool_token_key, false),
-        AccountMeta::new(*signal_provider_pool_token_key, false),
-        AccountMeta::new(bonfida_fee_pt_account, false),
-        AccountMeta::new(bonfida_bnb_pt_account, false),
-        AccountMeta::new_readonly(*pool_key, false),
+        AccountMeta::new(*pool_key, false),
     ];
     for pool_asset_key in pool_asset_keys.iter() {
         accounts.push(AccountMeta::new(*pool_asset_key, false))
@@ -767,7 +841,8 @@ pub fn redeem(
     })
 }
 
-// Creates a `CreateOrder` instruction
+// Creates a `CreateOrder` instruction, signed by the trader instead of the signal provider when
+// one is given
 pub fn create_order(
     bonfidabot_program_id: &Pubkey,
     signal_provider: &Pubkey,
@@ -786,39 +861,50 @@ pub fn create_order(
     spl_token_program: &Pubkey,
     dex_program: &Pubkey,
     rent_sysvar: &Pubkey,
+    trader: Option<&Pubkey>,
+    clock_sysvar: &Pubkey,
     srm_discount_account: Option<&Pubkey>,
     pool_seed: [u8; 32],
-    side: Side,
+    side: matching::Side,
     limit_price: NonZeroU64,
     market_index: u16,
     coin_lot_size: u64,
     pc_lot_size: u64,
     target_mint: &Pubkey,
     ratio_of_pool_assets_to_trade: NonZeroU16,
-    order_type: OrderType,
+    order_type: matching::OrderType,
     client_id: u64,
-    self_trade_behavior: SelfTradeBehavior,
-    serum_limit: u16
+    self_trade_behavior: SerumSelfTradeBehavior,
+    serum_limit: u16,
 ) -> Result<Instruction, ProgramError> {
+    let mint_key = Pubkey::create_program_address(&[&pool_seed, &[1]], bonfidabot_program_id)?;
     let data = PoolInstruction::CreateOrder {
         pool_seed,
-        side,
-        limit_price,
-        ratio_of_pool_assets_to_trade,
-        order_type,
+        side: side.into(),
+        limit_price: limit_price.get(),
+        ratio_of_pool_assets_to_trade: ratio_of_pool_assets_to_trade.get(),
+        order_type: order_type.into(),
         client_id,
-        self_trade_behavior,
+        self_trade_behavior: self_trade_behavior.into(),
         source_index: payer_pool_asset_index,
         target_index: target_pool_asset_index,
         market_index,
         coin_lot_size,
         pc_lot_size,
         target_mint: *target_mint,
-        serum_limit
+        serum_limit,
     }
     .pack();
+    // The trader account is ignored when the signal provider signs
+    let (signer, trader_account) = match trader {
+        Some(trader) => (
+            trader,
+            find_trader_key(bonfidabot_program_id, &pool_seed, signal_provider, trader).0,
+        ),
+        None => (signal_provider, *signal_provider),
+    };
     let mut accounts = vec![
-        AccountMeta::new_readonly(*signal_provider, true),
+        AccountMeta::new_readonly(*signer, true),
         AccountMeta::new(*market, false),
         AccountMeta::new(*payer_pool_asset_account, false),
         AccountMeta::new(*openorders_account, false),
@@ -832,6 +918,13 @@ pub fn create_order(
         AccountMeta::new_readonly(*spl_token_program, false),
         AccountMeta::new_readonly(*rent_sysvar, false),
         AccountMeta::new_readonly(*dex_program, false),
+        AccountMeta::new_readonly(mint_key, false),
+        AccountMeta::new_readonly(
+            get_associated_token_address(signal_provider, &mint_key),
+            false,
+        ),
+        AccountMeta::new_readonly(trader_account, false),
+        AccountMeta::new_readonly(*clock_sysvar, false),
     ];
     if let Some(key) = srm_discount_account {
         accounts.push(AccountMeta::new(*key, false));
@@ -843,7 +936,8 @@ pub fn create_order(
     })
 }
 
-// Creates a `CancelOrder` instruction
+// Creates a `CancelOrder` instruction, signed by the trader instead of the signal provider when
+// one is given
 pub fn cancel_order(
     bonfidabot_program_id: &Pubkey,
     signal_provider: &Pubkey,
@@ -854,18 +948,19 @@ pub fn cancel_order(
     serum_event_queue: &Pubkey,
     pool_account: &Pubkey,
     dex_program: &Pubkey,
+    trader: Option<&Pubkey>,
     pool_seed: [u8; 32],
-    side: Side,
+    side: matching::Side,
     order_id: u128,
 ) -> Result<Instruction, ProgramError> {
     let data = PoolInstruction::CancelOrder {
         pool_seed,
-        side,
+        side: side.into(),
         order_id,
     }
     .pack();
-    let accounts = vec![
-        AccountMeta::new_readonly(*signal_provider, true),
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*trader.unwrap_or(signal_provider), true),
         AccountMeta::new_readonly(*market, false),
         AccountMeta::new(*openorders_account, false),
         AccountMeta::new(*serum_market_bids, false),
@@ -874,6 +969,11 @@ pub fn cancel_order(
         AccountMeta::new_readonly(*pool_account, false),
         AccountMeta::new_readonly(*dex_program, false),
     ];
+    if let Some(trader) = trader {
+        let (trader_account, _) =
+            find_trader_key(bonfidabot_program_id, &pool_seed, signal_provider, trader);
+        accounts.push(AccountMeta::new_readonly(trader_account, false));
+    }
     Ok(Instruction {
         program_id: *bonfidabot_program_id,
         accounts,
@@ -931,28 +1031,42 @@ pub fn settle_funds(
 }
 
 pub fn collect_fees(
-    spl_token_program_id: &Pubkey,
     clock_sysvar_id: &Pubkey,
     bonfidabot_program_id: &Pubkey,
     pool_key: &Pubkey,
     pool_token_mint: &Pubkey,
-    signal_provider_pool_token_key: &Pubkey,
     pool_seed: [u8; 32],
 ) -> Result<Instruction, ProgramError> {
     let data = PoolInstruction::CollectFees { pool_seed }.pack();
+    let accounts = vec![
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*pool_token_mint, false),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
 
-    let bonfida_fee_pt_account =
-        get_associated_token_address(&Pubkey::from_str(BONFIDA_FEE).unwrap(), pool_token_mint);
-    let bonfida_bnb_pt_account =
-        get_associated_token_address(&Pubkey::from_str(BONFIDA_BNB).unwrap(), pool_token_mint);
+// Creates a `ClaimFees` instruction
+pub fn claim_fees(
+    spl_token_program_id: &Pubkey,
+    clock_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    pool_token_mint: &Pubkey,
+    recipient_pool_token_key: &Pubkey,
+    pool_seed: [u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::ClaimFees { pool_seed }.pack();
     let accounts = vec![
         AccountMeta::new_readonly(*spl_token_program_id, false),
         AccountMeta::new_readonly(*clock_sysvar_id, false),
         AccountMeta::new(*pool_key, false),
         AccountMeta::new(*pool_token_mint, false),
-        AccountMeta::new(*signal_provider_pool_token_key, false),
-        AccountMeta::new(bonfida_fee_pt_account, false),
-        AccountMeta::new(bonfida_bnb_pt_account, false),
+        AccountMeta::new(*recipient_pool_token_key, false),
     ];
     Ok(Instruction {
         program_id: *bonfidabot_program_id,
@@ -961,19 +1075,464 @@ pub fn collect_fees(
     })
 }
 
-#[cfg(test)]
-mod test {
-    use std::num::{NonZeroU16, NonZeroU64};
-
-    use serum_dex::{
-        instruction::SelfTradeBehavior,
-        matching::{OrderType, Side},
-    };
-    use solana_program::pubkey::Pubkey;
-
-    use super::PoolInstruction;
-
-    #[test]
+// Creates a `RequestRedeem` instruction
+pub fn request_redeem(
+    spl_token_program_id: &Pubkey,
+    system_program_id: &Pubkey,
+    rent_program_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    mint_key: &Pubkey,
+    pool_key: &Pubkey,
+    redeem_request_key: &Pubkey,
+    source_pool_token_owner_key: &Pubkey,
+    source_pool_token_key: &Pubkey,
+    pool_seed: [u8; 32],
+    pool_token_amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::RequestRedeem {
+        pool_seed,
+        pool_token_amount,
+    }
+    .pack();
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_program_id, false),
+        AccountMeta::new_readonly(*rent_program_id, false),
+        AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*mint_key, false),
+        AccountMeta::new(*redeem_request_key, false),
+        AccountMeta::new(
+            get_associated_token_address(redeem_request_key, mint_key),
+            false,
+        ),
+        AccountMeta::new(*source_pool_token_owner_key, true),
+        AccountMeta::new(*source_pool_token_key, false),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `ProcessRedeemQueue` instruction. The redeem requests are given as their account
+// key along with their owner, in the order of the queue.
+pub fn process_redeem_queue(
+    spl_token_program_id: &Pubkey,
+    clock_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    mint_key: &Pubkey,
+    pool_key: &Pubkey,
+    pool_asset_mints: &Vec<Pubkey>,
+    redeem_requests: &Vec<(Pubkey, Pubkey)>,
+    pool_seed: [u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::ProcessRedeemQueue {
+        pool_seed,
+        number_of_requests: redeem_requests.len() as u8,
+    }
+    .pack();
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new(*mint_key, false),
+    ];
+    for asset_mint in pool_asset_mints.iter() {
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(pool_key, asset_mint),
+            false,
+        ))
+    }
+    for (redeem_request_key, owner_key) in redeem_requests.iter() {
+        accounts.push(AccountMeta::new(*redeem_request_key, false));
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(redeem_request_key, mint_key),
+            false,
+        ));
+        accounts.push(AccountMeta::new(*owner_key, false));
+        for asset_mint in pool_asset_mints.iter() {
+            accounts.push(AccountMeta::new(
+                get_associated_token_address(owner_key, asset_mint),
+                false,
+            ))
+        }
+    }
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `RequestDeposit` instruction. The source asset keys and amounts are given in the
+// order of the pool assets.
+pub fn request_deposit(
+    spl_token_program_id: &Pubkey,
+    system_program_id: &Pubkey,
+    rent_program_id: &Pubkey,
+    clock_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    mint_key: &Pubkey,
+    pool_key: &Pubkey,
+    deposit_request_key: &Pubkey,
+    source_owner_key: &Pubkey,
+    pool_asset_mints: &Vec<Pubkey>,
+    source_asset_keys: &Vec<Pubkey>,
+    pool_seed: [u8; 32],
+    pool_token_amount: u64,
+    expiry_timestamp: u64,
+    asset_amounts: Vec<u64>,
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::RequestDeposit {
+        pool_seed,
+        pool_token_amount,
+        expiry_timestamp,
+        asset_amounts,
+    }
+    .pack();
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*system_program_id, false),
+        AccountMeta::new_readonly(*rent_program_id, false),
+        AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*mint_key, false),
+        AccountMeta::new(*deposit_request_key, false),
+        AccountMeta::new(*source_owner_key, true),
+    ];
+    for asset_mint in pool_asset_mints.iter() {
+        accounts.push(AccountMeta::new_readonly(*asset_mint, false))
+    }
+    for source_asset_key in source_asset_keys.iter() {
+        accounts.push(AccountMeta::new(*source_asset_key, false))
+    }
+    for asset_mint in pool_asset_mints.iter() {
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(deposit_request_key, asset_mint),
+            false,
+        ))
+    }
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `ProcessDepositQueue` instruction. The deposit requests are given as their account
+// key along with their owner and escrowed mints, in the order of the queue.
+pub fn process_deposit_queue(
+    spl_token_program_id: &Pubkey,
+    clock_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    mint_key: &Pubkey,
+    pool_key: &Pubkey,
+    pool_asset_mints: &Vec<Pubkey>,
+    deposit_requests: &Vec<(Pubkey, Pubkey, Vec<Pubkey>)>,
+    pool_seed: [u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::ProcessDepositQueue {
+        pool_seed,
+        number_of_requests: deposit_requests.len() as u8,
+    }
+    .pack();
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new(*mint_key, false),
+    ];
+    for asset_mint in pool_asset_mints.iter() {
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(pool_key, asset_mint),
+            false,
+        ))
+    }
+    for (deposit_request_key, owner_key, escrowed_mints) in deposit_requests.iter() {
+        accounts.push(AccountMeta::new(*deposit_request_key, false));
+        accounts.push(AccountMeta::new(*owner_key, false));
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(owner_key, mint_key),
+            false,
+        ));
+        for asset_mint in escrowed_mints.iter() {
+            accounts.push(AccountMeta::new(
+                get_associated_token_address(deposit_request_key, asset_mint),
+                false,
+            ))
+        }
+        for asset_mint in escrowed_mints.iter() {
+            accounts.push(AccountMeta::new(
+                get_associated_token_address(owner_key, asset_mint),
+                false,
+            ))
+        }
+    }
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `PostBond` instruction
+pub fn post_bond(
+    spl_token_program_id: &Pubkey,
+    system_program_id: &Pubkey,
+    rent_program_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    fida_mint_key: &Pubkey,
+    bond_key: &Pubkey,
+    signal_provider_key: &Pubkey,
+    source_fida_key: &Pubkey,
+    pool_seed: [u8; 32],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::PostBond { pool_seed, amount }.pack();
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_program_id, false),
+        AccountMeta::new_readonly(*rent_program_id, false),
+        AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*fida_mint_key, false),
+        AccountMeta::new_readonly(*bond_key, false),
+        AccountMeta::new(get_associated_token_address(bond_key, fida_mint_key), false),
+        AccountMeta::new(*signal_provider_key, true),
+        AccountMeta::new(*source_fida_key, false),
+        AccountMeta::new(get_associated_token_address(pool_key, fida_mint_key), false),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `RequestBondWithdrawal` instruction
+pub fn request_bond_withdrawal(
+    clock_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    signal_provider_key: &Pubkey,
+    pool_seed: [u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::RequestBondWithdrawal { pool_seed }.pack();
+    let accounts = vec![
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*signal_provider_key, true),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `WithdrawBond` instruction
+pub fn withdraw_bond(
+    spl_token_program_id: &Pubkey,
+    clock_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    fida_mint_key: &Pubkey,
+    bond_key: &Pubkey,
+    signal_provider_key: &Pubkey,
+    target_fida_key: &Pubkey,
+    pool_seed: [u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::WithdrawBond { pool_seed }.pack();
+    let accounts = vec![
+        AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*bond_key, false),
+        AccountMeta::new(get_associated_token_address(bond_key, fida_mint_key), false),
+        AccountMeta::new(*signal_provider_key, true),
+        AccountMeta::new(*target_fida_key, false),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `SlashBond` instruction
+pub fn slash_bond(
+    spl_token_program_id: &Pubkey,
+    system_program_id: &Pubkey,
+    rent_program_id: &Pubkey,
+    clock_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    fida_mint_key: &Pubkey,
+    bond_key: &Pubkey,
+    signal_provider_key: &Pubkey,
+    payer_key: &Pubkey,
+    pool_seed: [u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::SlashBond { pool_seed }.pack();
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_program_id, false),
+        AccountMeta::new_readonly(*rent_program_id, false),
+        AccountMeta::new_readonly(*spl_token_program_id, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(*clock_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*fida_mint_key, false),
+        AccountMeta::new_readonly(*bond_key, false),
+        AccountMeta::new(get_associated_token_address(bond_key, fida_mint_key), false),
+        AccountMeta::new(get_associated_token_address(pool_key, fida_mint_key), false),
+        AccountMeta::new(*signal_provider_key, false),
+        AccountMeta::new(*payer_key, true),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates an `AddTrader` instruction
+pub fn add_trader(
+    system_program_id: &Pubkey,
+    rent_sysvar_id: &Pubkey,
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    trader_account_key: &Pubkey,
+    signal_provider_key: &Pubkey,
+    pool_seed: [u8; 32],
+    trader: &Pubkey,
+    max_ratio_of_pool_assets_to_trade: u16,
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::AddTrader {
+        pool_seed,
+        trader: *trader,
+        max_ratio_of_pool_assets_to_trade,
+    }
+    .pack();
+    let accounts = vec![
+        AccountMeta::new_readonly(*system_program_id, false),
+        AccountMeta::new_readonly(*rent_sysvar_id, false),
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new(*trader_account_key, false),
+        AccountMeta::new(*signal_provider_key, true),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `RemoveTrader` instruction
+pub fn remove_trader(
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    trader_account_key: &Pubkey,
+    signal_provider_key: &Pubkey,
+    pool_seed: [u8; 32],
+    trader: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::RemoveTrader {
+        pool_seed,
+        trader: *trader,
+    }
+    .pack();
+    let accounts = vec![
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new(*trader_account_key, false),
+        AccountMeta::new(*signal_provider_key, true),
+    ];
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+// Creates a `SetMultisig` instruction, signed by the signal provider when no `signer_pubkeys`
+// of the current multisig are given
+pub fn set_multisig(
+    bonfidabot_program_id: &Pubkey,
+    pool_key: &Pubkey,
+    signal_provider_key: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    pool_seed: [u8; 32],
+    threshold: u8,
+    signers: Vec<Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = PoolInstruction::SetMultisig {
+        pool_seed,
+        threshold,
+        signers,
+    }
+    .pack();
+    let mut accounts = vec![
+        AccountMeta::new(*pool_key, false),
+        AccountMeta::new_readonly(*signal_provider_key, signer_pubkeys.is_empty()),
+    ];
+    accounts.extend(
+        signer_pubkeys
+            .iter()
+            .map(|signer| AccountMeta::new_readonly(**signer, true)),
+    );
+    Ok(Instruction {
+        program_id: *bonfidabot_program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Adapts an instruction reserved to the signal provider to a multisig signal provider : the
+/// `signer_pubkeys` are appended to its accounts and the signal provider account no longer signs.
+/// `PostBond` and `AddTrader` still need the signal provider among the signers.
+pub fn with_multisig_signers(
+    mut instruction: Instruction,
+    signal_provider_key: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Instruction {
+    for account in instruction.accounts.iter_mut() {
+        if &account.pubkey == signal_provider_key {
+            account.is_signer = false;
+        }
+    }
+    instruction.accounts.extend(
+        signer_pubkeys
+            .iter()
+            .map(|signer| AccountMeta::new_readonly(**signer, true)),
+    );
+    instruction
+}
+
+/// Adapts a `Redeem` instruction to a pool with pending orders : the `[market, open_orders]`
+/// pairs of the pending orders are appended to its accounts.
+pub fn with_unsettled_open_orders(
+    mut instruction: Instruction,
+    open_orders: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    for (market_key, open_orders_key) in open_orders {
+        instruction.accounts.extend_from_slice(&[
+            AccountMeta::new_readonly(*market_key, false),
+            AccountMeta::new_readonly(*open_orders_key, false),
+        ]);
+    }
+    instruction
+}
+
+#[cfg(test)]
+mod test {
+    use solana_program::pubkey::Pubkey;
+
+    use super::{OrderType, PoolInstruction, SelfTradeBehavior, Side, TradeLimits};
+
+    #[test]
     fn test_instruction_packing() {
         let original_init = PoolInstruction::Init {
             pool_seed: [50u8; 32],
@@ -994,8 +1553,17 @@ mod test {
                 Pubkey::new_unique(),
                 Pubkey::new_unique(),
             ],
-            fee_collection_period: 10_000,
+            annual_fee_ratio: 1311,
             fee_ratio: 15,
+            min_signal_provider_ratio: 3277,
+            trade_limits: TradeLimits {
+                max_order_ratio: 6554,
+                max_daily_turnover: 100_000,
+                max_daily_orders: 12,
+                max_price_deviation: 1311,
+                allowed_order_types: 0b011,
+                allowed_self_trade_behaviors: 0,
+            },
         };
         let packed_create = original_create.pack();
         let unpacked_create = PoolInstruction::unpack(&packed_create).unwrap();
@@ -1012,8 +1580,8 @@ mod test {
         let original_create_order = PoolInstruction::CreateOrder {
             pool_seed: [50u8; 32],
             side: Side::Ask,
-            limit_price: NonZeroU64::new(23).unwrap(),
-            ratio_of_pool_assets_to_trade: NonZeroU16::new(500).unwrap(),
+            limit_price: 23,
+            ratio_of_pool_assets_to_trade: 500,
             order_type: OrderType::Limit,
             client_id: 0xff44,
             self_trade_behavior: SelfTradeBehavior::DecrementTake,
@@ -1023,7 +1591,7 @@ mod test {
             coin_lot_size: 41,
             pc_lot_size: 41,
             target_mint: Pubkey::new_unique(),
-            serum_limit: 5000
+            serum_limit: 5000,
         };
         let packed_create_order = original_create_order.pack();
         let unpacked_create_order = PoolInstruction::unpack(&packed_create_order).unwrap();
@@ -1062,5 +1630,292 @@ mod test {
         let packed_collect_fees = original_collect_fees.pack();
         let unpacked_collect_fees = PoolInstruction::unpack(&packed_collect_fees).unwrap();
         assert_eq!(original_collect_fees, unpacked_collect_fees);
+
+        let original_claim_fees = PoolInstruction::ClaimFees {
+            pool_seed: [50u8; 32],
+        };
+        let packed_claim_fees = original_claim_fees.pack();
+        let unpacked_claim_fees = PoolInstruction::unpack(&packed_claim_fees).unwrap();
+        assert_eq!(original_claim_fees, unpacked_claim_fees);
+
+        let original_request_redeem = PoolInstruction::RequestRedeem {
+            pool_seed: [50u8; 32],
+            pool_token_amount: 24 as u64,
+        };
+        let packed_request_redeem = original_request_redeem.pack();
+        let unpacked_request_redeem = PoolInstruction::unpack(&packed_request_redeem).unwrap();
+        assert_eq!(original_request_redeem, unpacked_request_redeem);
+
+        let original_process_redeem_queue = PoolInstruction::ProcessRedeemQueue {
+            pool_seed: [50u8; 32],
+            number_of_requests: 3,
+        };
+        let packed_process_redeem_queue = original_process_redeem_queue.pack();
+        let unpacked_process_redeem_queue =
+            PoolInstruction::unpack(&packed_process_redeem_queue).unwrap();
+        assert_eq!(original_process_redeem_queue, unpacked_process_redeem_queue);
+
+        let original_request_deposit = PoolInstruction::RequestDeposit {
+            pool_seed: [50u8; 32],
+            pool_token_amount: 24 as u64,
+            expiry_timestamp: 1_600_000_000,
+            asset_amounts: vec![23 as u64, 43 as u64],
+        };
+        let packed_request_deposit = original_request_deposit.pack();
+        let unpacked_request_deposit = PoolInstruction::unpack(&packed_request_deposit).unwrap();
+        assert_eq!(original_request_deposit, unpacked_request_deposit);
+
+        let original_process_deposit_queue = PoolInstruction::ProcessDepositQueue {
+            pool_seed: [50u8; 32],
+            number_of_requests: 2,
+        };
+        let packed_process_deposit_queue = original_process_deposit_queue.pack();
+        let unpacked_process_deposit_queue =
+            PoolInstruction::unpack(&packed_process_deposit_queue).unwrap();
+        assert_eq!(
+            original_process_deposit_queue,
+            unpacked_process_deposit_queue
+        );
+
+        let original_post_bond = PoolInstruction::PostBond {
+            pool_seed: [50u8; 32],
+            amount: 100_000_000,
+        };
+        let packed_post_bond = original_post_bond.pack();
+        let unpacked_post_bond = PoolInstruction::unpack(&packed_post_bond).unwrap();
+        assert_eq!(original_post_bond, unpacked_post_bond);
+
+        let original_slash_bond = PoolInstruction::SlashBond {
+            pool_seed: [50u8; 32],
+        };
+        let packed_slash_bond = original_slash_bond.pack();
+        let unpacked_slash_bond = PoolInstruction::unpack(&packed_slash_bond).unwrap();
+        assert_eq!(original_slash_bond, unpacked_slash_bond);
+
+        let original_add_trader = PoolInstruction::AddTrader {
+            pool_seed: [50u8; 32],
+            trader: Pubkey::new_unique(),
+            max_ratio_of_pool_assets_to_trade: 6554,
+        };
+        let packed_add_trader = original_add_trader.pack();
+        let unpacked_add_trader = PoolInstruction::unpack(&packed_add_trader).unwrap();
+        assert_eq!(original_add_trader, unpacked_add_trader);
+
+        let original_remove_trader = PoolInstruction::RemoveTrader {
+            pool_seed: [50u8; 32],
+            trader: Pubkey::new_unique(),
+        };
+        let packed_remove_trader = original_remove_trader.pack();
+        let unpacked_remove_trader = PoolInstruction::unpack(&packed_remove_trader).unwrap();
+        assert_eq!(original_remove_trader, unpacked_remove_trader);
+
+        let original_set_multisig = PoolInstruction::SetMultisig {
+            pool_seed: [50u8; 32],
+            threshold: 2,
+            signers: vec![Pubkey::new_unique(); 3],
+        };
+        let packed_set_multisig = original_set_multisig.pack();
+        let unpacked_set_multisig = PoolInstruction::unpack(&packed_set_multisig).unwrap();
+        assert_eq!(original_set_multisig, unpacked_set_multisig);
+    }
+
+    #[test]
+    fn test_instruction_tags() {
+        let packed = [
+            PoolInstruction::Init {
+                pool_seed: [0; 32],
+                max_number_of_assets: 0,
+                number_of_markets: 0,
+            },
+            PoolInstruction::LegacyCreate {
+                pool_seed: [0; 32],
+                fee_collection_period: 0,
+                fee_ratio: 0,
+                markets: vec![],
+                deposit_amounts: vec![],
+            },
+            PoolInstruction::Deposit {
+                pool_seed: [0; 32],
+                pool_token_amount: 0,
+            },
+            PoolInstruction::CreateOrder {
+                pool_seed: [0; 32],
+                side: Side::Bid,
+                limit_price: 1,
+                ratio_of_pool_assets_to_trade: 1,
+                order_type: OrderType::Limit,
+                client_id: 0,
+                self_trade_behavior: SelfTradeBehavior::DecrementTake,
+                source_index: 0,
+                target_index: 0,
+                market_index: 0,
+                coin_lot_size: 0,
+                pc_lot_size: 0,
+                target_mint: Pubkey::default(),
+                serum_limit: 0,
+            },
+            PoolInstruction::CancelOrder {
+                pool_seed: [0; 32],
+                side: Side::Bid,
+                order_id: 0,
+            },
+            PoolInstruction::SettleFunds {
+                pool_seed: [0; 32],
+                pc_index: 0,
+                coin_index: 0,
+            },
+            PoolInstruction::Redeem {
+                pool_seed: [0; 32],
+                pool_token_amount: 0,
+            },
+            PoolInstruction::CollectFees { pool_seed: [0; 32] },
+            PoolInstruction::ClaimFees { pool_seed: [0; 32] },
+            PoolInstruction::RequestRedeem {
+                pool_seed: [0; 32],
+                pool_token_amount: 0,
+            },
+            PoolInstruction::ProcessRedeemQueue {
+                pool_seed: [0; 32],
+                number_of_requests: 0,
+            },
+            PoolInstruction::RequestDeposit {
+                pool_seed: [0; 32],
+                pool_token_amount: 0,
+                expiry_timestamp: 0,
+                asset_amounts: vec![],
+            },
+            PoolInstruction::ProcessDepositQueue {
+                pool_seed: [0; 32],
+                number_of_requests: 0,
+            },
+            PoolInstruction::PostBond {
+                pool_seed: [0; 32],
+                amount: 0,
+            },
+            PoolInstruction::RequestBondWithdrawal { pool_seed: [0; 32] },
+            PoolInstruction::WithdrawBond { pool_seed: [0; 32] },
+            PoolInstruction::SlashBond { pool_seed: [0; 32] },
+            PoolInstruction::AddTrader {
+                pool_seed: [0; 32],
+                trader: Pubkey::default(),
+                max_ratio_of_pool_assets_to_trade: 0,
+            },
+            PoolInstruction::RemoveTrader {
+                pool_seed: [0; 32],
+                trader: Pubkey::default(),
+            },
+            PoolInstruction::SetMultisig {
+                pool_seed: [0; 32],
+                threshold: 0,
+                signers: vec![],
+            },
+            PoolInstruction::Create {
+                pool_seed: [0; 32],
+                annual_fee_ratio: 0,
+                fee_ratio: 0,
+                min_signal_provider_ratio: 0,
+                trade_limits: TradeLimits::default(),
+                markets: vec![],
+                deposit_amounts: vec![],
+            },
+        ];
+        for (tag, instruction) in packed.iter().enumerate() {
+            assert_eq!(instruction.pack()[0], tag as u8);
+        }
+    }
+
+    #[test]
+    fn test_instruction_layout() {
+        // The fixed size instructions keep the layout of the original manual encoding
+        let mut expected = vec![2];
+        expected.extend_from_slice(&[7; 32]);
+        expected.extend_from_slice(&42u64.to_le_bytes());
+        let deposit = PoolInstruction::Deposit {
+            pool_seed: [7; 32],
+            pool_token_amount: 42,
+        };
+        assert_eq!(deposit.pack(), expected);
+
+        let market = Pubkey::new_unique();
+        let mut expected = vec![20];
+        expected.extend_from_slice(&[7; 32]);
+        expected.extend_from_slice(&1311u16.to_le_bytes());
+        expected.extend_from_slice(&15u16.to_le_bytes());
+        expected.extend_from_slice(&3277u16.to_le_bytes());
+        expected.extend_from_slice(&6554u16.to_le_bytes());
+        expected.extend_from_slice(&100_000u32.to_le_bytes());
+        expected.extend_from_slice(&12u16.to_le_bytes());
+        expected.extend_from_slice(&1311u16.to_le_bytes());
+        expected.extend_from_slice(&[0b011, 0]);
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.extend_from_slice(&market.to_bytes());
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(&4u64.to_le_bytes());
+        let create = PoolInstruction::Create {
+            pool_seed: [7; 32],
+            annual_fee_ratio: 1311,
+            fee_ratio: 15,
+            min_signal_provider_ratio: 3277,
+            trade_limits: TradeLimits {
+                max_order_ratio: 6554,
+                max_daily_turnover: 100_000,
+                max_daily_orders: 12,
+                max_price_deviation: 1311,
+                allowed_order_types: 0b011,
+                allowed_self_trade_behaviors: 0,
+            },
+            markets: vec![market],
+            deposit_amounts: vec![3, 4],
+        };
+        assert_eq!(create.pack(), expected);
+
+        // Unknown tags, truncated and trailing data are rejected
+        assert!(PoolInstruction::unpack(&[21]).is_err());
+        assert!(PoolInstruction::unpack(&expected[..expected.len() - 1]).is_err());
+        expected.push(0);
+        assert!(PoolInstruction::unpack(&expected).is_err());
+    }
+
+    #[test]
+    fn test_legacy_create() {
+        // A Create instruction as encoded by the original program
+        let markets = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut data = vec![1];
+        data.extend_from_slice(&[7; 32]);
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&604_800u64.to_le_bytes());
+        data.extend_from_slice(&15u16.to_le_bytes());
+        data.extend_from_slice(&markets[0].to_bytes());
+        data.extend_from_slice(&markets[1].to_bytes());
+        data.extend_from_slice(&3u64.to_le_bytes());
+        data.extend_from_slice(&4u64.to_le_bytes());
+
+        let create = PoolInstruction::unpack(&data).unwrap();
+        assert_eq!(
+            create,
+            PoolInstruction::LegacyCreate {
+                pool_seed: [7; 32],
+                fee_collection_period: 604_800,
+                fee_ratio: 15,
+                markets: markets.to_vec(),
+                deposit_amounts: vec![3, 4],
+            }
+        );
+        assert_eq!(create.pack(), data);
+
+        // Without deposits, and with a trailing partial amount which is ignored as before
+        data.truncate(1 + 44 + 64);
+        data.push(5);
+        match PoolInstruction::unpack(&data).unwrap() {
+            PoolInstruction::LegacyCreate {
+                deposit_amounts, ..
+            } => assert!(deposit_amounts.is_empty()),
+            _ => panic!(),
+        }
+
+        // Missing markets
+        assert!(PoolInstruction::unpack(&data[..1 + 44 + 32]).is_err());
+        assert!(PoolInstruction::unpack(&data[..1 + 43]).is_err());
     }
 }
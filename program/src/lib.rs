@@ -1,14 +1,15 @@
 #[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
 
+pub mod accounting;
 pub mod error;
 pub mod instruction;
+pub mod math;
 pub mod state;
 
 pub mod utils;
 
 pub mod processor;
 
-#[cfg(feature = "fuzz")]
-#[path = "../tests/common/mod.rs"]
-pub mod common;
+#[cfg(feature = "idl")]
+pub mod idl;
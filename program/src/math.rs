@@ -0,0 +1,330 @@
+//! Checked fixed-point arithmetic used for the share, fee and ratio computations of the pools.
+//! Every operation returns `BonfidaBotError::Overflow` instead of wrapping or panicking.
+use std::convert::TryInto;
+
+use crate::error::BonfidaBotError;
+
+/// Computes `floor(a * b / c)` exactly, without intermediate overflow.
+pub fn mul_div_floor(a: u64, b: u64, c: u64) -> Result<u64, BonfidaBotError> {
+    ((a as u128) * (b as u128))
+        .checked_div(c as u128)
+        .ok_or(BonfidaBotError::Overflow)?
+        .try_into()
+        .map_err(|_| BonfidaBotError::Overflow)
+}
+
+/// Computes `ceil(a * b / c)` exactly, without intermediate overflow.
+pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> Result<u64, BonfidaBotError> {
+    let product = (a as u128) * (b as u128);
+    let quotient = product
+        .checked_div(c as u128)
+        .ok_or(BonfidaBotError::Overflow)?;
+    let rounded = if quotient * (c as u128) == product {
+        quotient
+    } else {
+        quotient + 1
+    };
+    rounded.try_into().map_err(|_| BonfidaBotError::Overflow)
+}
+
+/// Unsigned fixed-point number with 16 fractional bits. The fee ratios of the pool headers are
+/// stored in this format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Q16(u64);
+
+impl Q16 {
+    pub const FRACTIONAL_BITS: u32 = 16;
+    pub const ONE: Self = Self(1 << 16);
+    pub const ZERO: Self = Self(0);
+
+    pub fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    pub fn raw(self) -> u64 {
+        self.0
+    }
+
+    /// Rounds `numerator / denominator` down to the nearest representable value.
+    pub fn from_ratio(numerator: u64, denominator: u64) -> Result<Self, BonfidaBotError> {
+        let raw = ((numerator as u128) << 16)
+            .checked_div(denominator as u128)
+            .ok_or(BonfidaBotError::Overflow)?;
+        Ok(Self(raw.try_into().map_err(|_| BonfidaBotError::Overflow)?))
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self, BonfidaBotError> {
+        self.0
+            .checked_add(other.0)
+            .map(Self)
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self, BonfidaBotError> {
+        self.0
+            .checked_sub(other.0)
+            .map(Self)
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    /// Product rounded down.
+    pub fn checked_mul(self, other: Self) -> Result<Self, BonfidaBotError> {
+        let raw = ((self.0 as u128) * (other.0 as u128)) >> 16;
+        Ok(Self(raw.try_into().map_err(|_| BonfidaBotError::Overflow)?))
+    }
+
+    /// Quotient rounded down.
+    pub fn checked_div(self, other: Self) -> Result<Self, BonfidaBotError> {
+        let raw = ((self.0 as u128) << 16)
+            .checked_div(other.0 as u128)
+            .ok_or(BonfidaBotError::Overflow)?;
+        Ok(Self(raw.try_into().map_err(|_| BonfidaBotError::Overflow)?))
+    }
+
+    /// Raises to the power `n` by repeated squaring, rounding down after every product.
+    pub fn checked_pow(self, n: u64) -> Result<Self, BonfidaBotError> {
+        if n == 0 {
+            return Ok(Self::ONE);
+        }
+        let p = self.checked_pow(n >> 1)?;
+        let sq = p.checked_mul(p)?;
+        if n & 1 == 1 {
+            sq.checked_mul(self)
+        } else {
+            Ok(sq)
+        }
+    }
+
+    /// Multiplies an integer amount, rounding down.
+    pub fn mul_floor(self, amount: u64) -> Result<u64, BonfidaBotError> {
+        mul_div_floor(amount, self.0, 1 << 16)
+    }
+}
+
+/// Unsigned fixed-point number with 64 fractional bits, for the ratios that need more precision
+/// than the pool header formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Q64(u128);
+
+impl Q64 {
+    pub const FRACTIONAL_BITS: u32 = 64;
+    pub const ONE: Self = Self(1 << 64);
+    pub const ZERO: Self = Self(0);
+
+    pub fn from_raw(raw: u128) -> Self {
+        Self(raw)
+    }
+
+    pub fn raw(self) -> u128 {
+        self.0
+    }
+
+    /// Rounds `numerator / denominator` down to the nearest representable value.
+    pub fn from_ratio(numerator: u64, denominator: u64) -> Result<Self, BonfidaBotError> {
+        if denominator == 0 {
+            return Err(BonfidaBotError::Overflow);
+        }
+        let integer = (numerator / denominator) as u128;
+        let remainder = (numerator % denominator) as u128;
+        Ok(Self(
+            (integer << 64) + (remainder << 64) / (denominator as u128),
+        ))
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self, BonfidaBotError> {
+        self.0
+            .checked_add(other.0)
+            .map(Self)
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self, BonfidaBotError> {
+        self.0
+            .checked_sub(other.0)
+            .map(Self)
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    /// Product rounded down.
+    pub fn checked_mul(self, other: Self) -> Result<Self, BonfidaBotError> {
+        // Schoolbook multiplication on 64 bit limbs, dropping the lowest limb of the product
+        let (a_hi, a_lo) = (self.0 >> 64, self.0 & (u64::MAX as u128));
+        let (b_hi, b_lo) = (other.0 >> 64, other.0 & (u64::MAX as u128));
+        let hi = a_hi * b_hi;
+        if hi >> 64 != 0 {
+            return Err(BonfidaBotError::Overflow);
+        }
+        let mid_carry = (a_lo * b_lo) >> 64;
+        (hi << 64)
+            .checked_add(a_hi * b_lo)
+            .and_then(|r| r.checked_add(a_lo * b_hi))
+            .and_then(|r| r.checked_add(mid_carry))
+            .map(Self)
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    /// Multiplies an integer amount, rounding down.
+    pub fn mul_floor(self, amount: u64) -> Result<u64, BonfidaBotError> {
+        let (hi, lo) = (self.0 >> 64, self.0 & (u64::MAX as u128));
+        let amount = amount as u128;
+        let result = (amount * hi)
+            .checked_add((amount * lo) >> 64)
+            .ok_or(BonfidaBotError::Overflow)?;
+        result.try_into().map_err(|_| BonfidaBotError::Overflow)
+    }
+}
+
+impl From<Q16> for Q64 {
+    fn from(x: Q16) -> Self {
+        Self((x.0 as u128) << 48)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mul_div_ceil, mul_div_floor, Q16, Q64};
+    use crate::error::BonfidaBotError;
+    use num_bigint::BigInt;
+    use num_rational::BigRational;
+    use num_traits::ToPrimitive;
+    use proptest::prelude::*;
+
+    fn q16(x: Q16) -> BigRational {
+        BigRational::new(BigInt::from(x.raw()), BigInt::from(1u64 << 16))
+    }
+
+    fn q64(x: Q64) -> BigRational {
+        BigRational::new(BigInt::from(x.raw()), BigInt::from(1u128 << 64))
+    }
+
+    fn int(x: u64) -> BigRational {
+        BigRational::from_integer(BigInt::from(x))
+    }
+
+    /// Rounds an exact result down to the raw representation, or `Overflow` when it does not fit.
+    fn reference_q16(exact: BigRational) -> Result<u64, BonfidaBotError> {
+        (exact * int(1 << 16))
+            .floor()
+            .to_integer()
+            .to_u64()
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    fn reference_q64(exact: BigRational) -> Result<u128, BonfidaBotError> {
+        (exact * BigRational::from_integer(BigInt::from(1u128 << 64)))
+            .floor()
+            .to_integer()
+            .to_u128()
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    fn reference_int_ceil(exact: BigRational) -> Result<u64, BonfidaBotError> {
+        exact
+            .ceil()
+            .to_integer()
+            .to_u64()
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    fn reference_int(exact: BigRational) -> Result<u64, BonfidaBotError> {
+        exact
+            .floor()
+            .to_integer()
+            .to_u64()
+            .ok_or(BonfidaBotError::Overflow)
+    }
+
+    #[test]
+    fn test_pow() {
+        let half = Q16::from_raw(1 << 15);
+        for i in 1..16 {
+            assert_eq!(half.checked_pow(i).unwrap().raw(), 1 << (16 - i));
+        }
+        assert_eq!(half.checked_pow(0).unwrap(), Q16::ONE);
+        assert_eq!(
+            Q16::from_raw(1 << 20).checked_pow(16),
+            Err(BonfidaBotError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(mul_div_floor(1, 1, 0), Err(BonfidaBotError::Overflow));
+        assert_eq!(mul_div_ceil(1, 1, 0), Err(BonfidaBotError::Overflow));
+        assert_eq!(Q16::from_ratio(1, 0), Err(BonfidaBotError::Overflow));
+        assert_eq!(Q64::from_ratio(1, 0), Err(BonfidaBotError::Overflow));
+        assert_eq!(
+            Q16::ONE.checked_div(Q16::ZERO),
+            Err(BonfidaBotError::Overflow)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn mul_div_floor_matches_reference(a: u64, b: u64, c: u64) {
+            let expected = if c == 0 {
+                Err(BonfidaBotError::Overflow)
+            } else {
+                reference_int(int(a) * int(b) / int(c))
+            };
+            prop_assert_eq!(mul_div_floor(a, b, c), expected);
+        }
+
+        #[test]
+        fn mul_div_ceil_matches_reference(a: u64, b: u64, c: u64) {
+            let expected = if c == 0 {
+                Err(BonfidaBotError::Overflow)
+            } else {
+                reference_int_ceil(int(a) * int(b) / int(c))
+            };
+            prop_assert_eq!(mul_div_ceil(a, b, c), expected);
+        }
+
+        #[test]
+        fn q16_ops_match_reference(a: u64, b: u64) {
+            let (x, y) = (Q16::from_raw(a), Q16::from_raw(b));
+            prop_assert_eq!(x.checked_add(y).map(Q16::raw), reference_q16(q16(x) + q16(y)));
+            prop_assert_eq!(x.checked_sub(y).map(Q16::raw), reference_q16(q16(x) - q16(y)));
+            prop_assert_eq!(x.checked_mul(y).map(Q16::raw), reference_q16(q16(x) * q16(y)));
+            if b != 0 {
+                prop_assert_eq!(x.checked_div(y).map(Q16::raw), reference_q16(q16(x) / q16(y)));
+                prop_assert_eq!(Q16::from_ratio(a, b).map(Q16::raw), reference_q16(int(a) / int(b)));
+            }
+        }
+
+        #[test]
+        fn q16_mul_floor_matches_reference(ratio in any::<u16>(), amount in any::<u64>()) {
+            let x = Q16::from_raw(ratio as u64);
+            prop_assert_eq!(x.mul_floor(amount), reference_int(q16(x) * int(amount)));
+        }
+
+        #[test]
+        fn q16_pow_never_exceeds_reference(ratio in 0u64..=1 << 16, n in 0u64..64) {
+            let x = Q16::from_raw(ratio);
+            let exact = num_traits::pow::pow(q16(x), n as usize);
+            let result = q16(x.checked_pow(n).unwrap());
+            prop_assert!(result <= exact);
+        }
+
+        #[test]
+        fn q64_ops_match_reference(a: u128, b: u128) {
+            let (x, y) = (Q64::from_raw(a), Q64::from_raw(b));
+            prop_assert_eq!(x.checked_add(y).map(Q64::raw), reference_q64(q64(x) + q64(y)));
+            prop_assert_eq!(x.checked_sub(y).map(Q64::raw), reference_q64(q64(x) - q64(y)));
+            prop_assert_eq!(x.checked_mul(y).map(Q64::raw), reference_q64(q64(x) * q64(y)));
+        }
+
+        #[test]
+        fn q64_ratios_match_reference(a in any::<u64>(), b in 1u64.., amount in any::<u64>()) {
+            let x = Q64::from_ratio(a, b).unwrap();
+            prop_assert_eq!(Ok(x.raw()), reference_q64(int(a) / int(b)));
+            prop_assert_eq!(x.mul_floor(amount), reference_int(q64(x) * int(amount)));
+        }
+
+        #[test]
+        fn q16_to_q64_is_exact(a: u64) {
+            let x = Q16::from_raw(a);
+            prop_assert_eq!(q64(Q64::from(x)), q16(x));
+        }
+    }
+}
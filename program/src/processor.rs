@@ -1,22 +1,32 @@
 use std::{
-    cmp::min,
     convert::TryInto,
+    iter::once,
     mem::zeroed,
     num::{NonZeroU16, NonZeroU64, NonZeroU8},
     str::FromStr,
 };
 
 use crate::{
+    accounting::{compute_deposit, compute_queued_deposit, compute_redeem, owed_fees},
     error::BonfidaBotError,
     instruction::PoolInstruction,
     state::{
-        get_asset_slice, pack_markets, unpack_assets, unpack_market, unpack_unchecked_asset,
-        PoolAsset, PoolHeader, PoolStatus, BONFIDA_BNB, BONFIDA_FEE, PUBKEY_LENGTH,
+        DepositRequest, PoolAsset, PoolHeader, PoolState, PoolStatus, RedeemRequest, TradeLimits,
+        Trader, BOND_NOTICE_PERIOD, BOND_SEED, DEPOSIT_REQUEST_SEED, FIDA_MINT,
+        MAX_ANNUAL_FEE_RATIO, MAX_DEPOSIT_BATCH_SIZE, MAX_MULTISIG_SIGNERS,
+        MAX_PENDING_ORDER_DURATION, MAX_REDEEM_BATCH_SIZE, MIN_BOND_AMOUNT, REDEEM_REQUEST_SEED,
+        TRADER_SEED,
+    },
+    utils::{
+        accrued_fee_tokens, best_book_price, check_bond_accounts, check_multisig_config,
+        check_order_policy, check_pool_key, check_price_band, check_signal_provider,
+        check_signal_provider_stake, check_trader, fill_slice, find_deposit_request_key,
+        find_redeem_request_key, find_trader_key, get_fee_recipient, legacy_annual_fee_ratio,
+        record_order,
     },
-    utils::{check_pool_key, check_signal_provider, fill_slice, pow_fixedpoint_u16},
 };
 use serum_dex::{
-    instruction::{cancel_order, settle_funds, SelfTradeBehavior},
+    instruction::{cancel_order, new_order, settle_funds, SelfTradeBehavior},
     matching::{OrderType, Side},
 };
 use solana_program::{
@@ -32,9 +42,9 @@ use solana_program::{
     system_instruction::create_account,
     sysvar::Sysvar,
 };
-use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
 use spl_token::{
-    instruction::{burn, initialize_mint, mint_to, transfer},
+    instruction::{burn, close_account, initialize_mint, mint_to, transfer},
     state::Account,
     state::Mint,
 };
@@ -69,24 +79,17 @@ impl Processor {
         let pool_key = Pubkey::create_program_address(&[&pool_seed], &program_id)?;
         if pool_key != *pool_account.key {
             msg!("Provided pool account is invalid");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolKey.into());
         }
 
         // Find the non reversible public key for the pool mint account via the seed
         let mint_key = Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id)?;
         if mint_key != *mint_account.key {
             msg!("Provided mint account is invalid");
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        if spl_token_program_account.key != &spl_token::id() {
-            msg!("Provided pool account is invalid");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidMint.into());
         }
 
-        let state_size = PoolHeader::LEN
-            + PUBKEY_LENGTH * (number_of_markets as usize)
-            + max_number_of_assets as usize * PoolAsset::LEN;
+        let state_size = PoolState::size(number_of_markets, max_number_of_assets);
 
         let create_pool_account = create_account(
             &payer_account.key,
@@ -146,8 +149,10 @@ impl Processor {
         pool_seed: [u8; 32],
         deposit_amounts: Vec<u64>,
         markets: Vec<Pubkey>,
-        fee_collection_period: u64,
+        annual_fee_ratio: u16,
         fee_ratio: u16,
+        min_signal_provider_ratio: u16,
+        trade_limits: TradeLimits,
     ) -> ProgramResult {
         let number_of_assets = deposit_amounts.len();
         let accounts_iter = &mut accounts.iter();
@@ -176,44 +181,56 @@ impl Processor {
             source_assets_accounts.push(next_account_info(accounts_iter)?)
         }
 
-        let current_timestamp =
-            Clock::from_account_info(&clock_sysvar_account)?.unix_timestamp as u64;
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
 
         let pool_key = Pubkey::create_program_address(&[&pool_seed], &program_id).unwrap();
         let mint_key = Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
 
         if pool_key != *pool_account.key {
             msg!("Provided pool account is invalid");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolKey.into());
         }
         if mint_key != *mint_account.key {
             msg!("Provided mint account is invalid");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidMint.into());
         }
         // Verifying that no pool was already created with this seed
-        let pool_status =
-            PoolHeader::unpack_from_slice(&pool_account.try_borrow_data()?[..PoolHeader::LEN])
-                .unwrap()
-                .status;
-        if pool_status != PoolStatus::Uninitialized {
+        let previous_header =
+            PoolState::new(&mut pool_account.try_borrow_mut_data()?)?.header_unchecked()?;
+        if previous_header.status != PoolStatus::Uninitialized {
             msg!("Cannot overwrite an existing pool.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::PoolAlreadyExists.into());
+        }
+        // The bond of a reset pool is kept in its header until it is withdrawn
+        if previous_header.bond_amount != 0 {
+            msg!("The bond of the previous pool should be withdrawn first.");
+            return Err(BonfidaBotError::PoolAlreadyExists.into());
+        }
+        // The trader accounts outlive a reset of the pool and are kept by the same signal provider
+        if previous_header.number_of_traders != 0
+            && previous_header.signal_provider != *signal_provider_account.key
+        {
+            msg!("The traders of the previous pool should be removed first.");
+            return Err(BonfidaBotError::PoolAlreadyExists.into());
         }
         if *pool_account.owner != *program_id {
             msg!("Program should own pool account");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
         }
         if !source_owner_account.is_signer {
             msg!("Source token account owner should be a signer.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(ProgramError::MissingRequiredSignature);
         }
         if markets.len() >> 16 != 0 {
             msg!("Number of given markets is too high.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::TooManyMarkets.into());
         }
-        if fee_collection_period < 604800 {
-            msg!("Fee collection period should be longer than a week.");
-            return Err(ProgramError::InvalidArgument);
+        if annual_fee_ratio > MAX_ANNUAL_FEE_RATIO {
+            msg!("Annual fee ratio should be at most 25%.");
+            return Err(BonfidaBotError::FeeRatioTooHigh.into());
         }
 
         let mut pool_assets: Vec<PoolAsset> = vec![];
@@ -226,7 +243,7 @@ impl Processor {
 
             if pool_asset_data.close_authority.is_some() | pool_asset_data.delegate.is_some() {
                 msg!("Invalid pool asset account");
-                return Err(ProgramError::InvalidArgument);
+                return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
             }
 
             let mint_asset_key = pool_asset_data.mint;
@@ -234,7 +251,7 @@ impl Processor {
 
             if pool_asset_key != *pool_assets_accounts[i as usize].key {
                 msg!("Provided pool asset account is invalid");
-                return Err(ProgramError::InvalidArgument);
+                return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
             }
 
             let transfer_instruction = transfer(
@@ -289,21 +306,29 @@ impl Processor {
             status: PoolStatus::Unlocked,
             number_of_markets: markets.len() as u16,
             last_fee_collection_timestamp: current_timestamp,
-            fee_collection_period,
+            annual_fee_ratio,
             fee_ratio,
+            accrued_fees: 0,
+            claimed_fees: [0; 3],
+            redeem_queue_head: 0,
+            redeem_queue_tail: 0,
+            deposit_queue_head: 0,
+            deposit_queue_tail: 0,
+            bond_amount: 0,
+            bond_unlock_timestamp: 0,
+            pending_order_timestamp: 0,
+            min_signal_provider_ratio,
+            multisig_threshold: 0,
+            multisig_signers: [Pubkey::default(); MAX_MULTISIG_SIGNERS],
+            trade_limits,
+            trading_day_start: 0,
+            daily_turnover: 0,
+            daily_orders: 0,
+            number_of_traders: previous_header.number_of_traders,
         };
-        let mut data = pool_account.data.borrow_mut();
-        state_header.pack_into_slice(&mut data);
-
-        // Write the authorized markets to the account data
-        pack_markets(&mut data[PoolHeader::LEN..], &markets)?;
-
-        // Write the assets into the account data
-        let mut offset = PoolHeader::LEN + PUBKEY_LENGTH * markets.len();
-        for asset in pool_assets.iter() {
-            asset.pack_into_slice(&mut data[offset..]);
-            offset += PoolAsset::LEN;
-        }
+        // Write the header, the authorized markets and the assets into the account data
+        let mut pool_data = pool_account.data.borrow_mut();
+        PoolState::new(&mut pool_data)?.init(&state_header, &markets, &pool_assets)?;
 
         Ok(())
     }
@@ -322,18 +347,18 @@ impl Processor {
             msg!("Incorrect spl token program provided");
             return Err(ProgramError::IncorrectProgramId);
         }
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
         let mint_account = next_account_info(accounts_iter)?;
 
         let target_pool_token_account = next_account_info(accounts_iter)?;
-        let signal_provider_pt_account = next_account_info(accounts_iter)?;
-        let bonfida_fee_pt_account = next_account_info(accounts_iter)?;
-        let bonfida_bnb_pt_account = next_account_info(accounts_iter)?;
-
         let pool_account = next_account_info(accounts_iter)?;
 
-        let pool_header = PoolHeader::unpack(&pool_account.data.borrow()[..PoolHeader::LEN])?;
-        let asset_offset = PoolHeader::LEN + PUBKEY_LENGTH * pool_header.number_of_markets as usize;
-        let pool_assets = unpack_assets(&pool_account.data.borrow()[asset_offset..])?;
+        let (mut pool_header, pool_assets) = {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let pool_state = PoolState::new(&mut pool_data)?;
+            let pool_assets: Vec<PoolAsset> = pool_state.assets().map(|(_, a)| a).collect();
+            (pool_state.header()?, pool_assets)
+        };
         let nb_assets = pool_assets.len();
 
         let mut pool_assets_accounts: Vec<&AccountInfo> = vec![];
@@ -350,46 +375,23 @@ impl Processor {
         let pool_mint_key =
             Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
 
-        let signal_provider_pt_key =
-            get_associated_token_address(&pool_header.signal_provider, &pool_mint_key);
-        let bonfida_fee_pt_key =
-            get_associated_token_address(&Pubkey::from_str(BONFIDA_FEE).unwrap(), &pool_mint_key);
-        let bonfida_bnb_pt_key =
-            get_associated_token_address(&Pubkey::from_str(BONFIDA_BNB).unwrap(), &pool_mint_key);
-
         // Safety verifications
         if pool_key != *pool_account.key {
             msg!("Provided pool account doesn't match the provided pool seed.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolKey.into());
         }
         if pool_mint_key != *mint_account.key {
             msg!("Provided mint account is invalid.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidMint.into());
         }
         if !source_owner_account.is_signer {
             msg!("Source token account owner should be a signer.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(ProgramError::MissingRequiredSignature);
         }
         if *pool_account.owner != *program_id {
             msg!("Program should own pool account.");
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        if signal_provider_pt_account.key != &signal_provider_pt_key {
-            msg!("The provided signal provider pool token account is invalid.");
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        if bonfida_fee_pt_account.key != &bonfida_fee_pt_key {
-            msg!("The provided bonfida fee pool token account is invalid.");
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        if bonfida_bnb_pt_account.key != &bonfida_bnb_pt_key {
-            msg!("The provided bonfida buy and burn pool token account is invalid.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
         }
-
         // Doing a match on all cases here would be more idiomatic
         match pool_header.status {
             PoolStatus::Unlocked => (),
@@ -399,48 +401,55 @@ impl Processor {
             }
             PoolStatus::PendingOrder(_) => {
                 msg!("The pool has one or more pending orders. No buy-ins are possible for now. Try again later.");
-                return Err(BonfidaBotError::LockedOperation.into());
+                return Err(BonfidaBotError::PendingOrders.into());
             }
             PoolStatus::Uninitialized => unreachable!(),
         };
 
-        let total_pooltokens = Mint::unpack(&mint_account.data.borrow())?.supply;
+        // The management fees accrued so far are paid by the current holders only
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        Self::accrue_management_fees(mint_account, &mut pool_header, current_timestamp)?;
+
+        let total_pooltokens = Self::total_pooltokens(mint_account, &pool_header)?;
         let mut pool_asset_amounts = Vec::with_capacity(nb_assets);
-        // Compute buy-in amount. The effective buy-in amount can be less than the
-        // input_token_amount as the source accounts need to satisfy the pool asset ratios
-        let mut pool_token_effective_amount = std::u64::MAX;
+        let mut source_asset_amounts = Vec::with_capacity(nb_assets);
         for i in 0..nb_assets {
-            let pool_asset_amount = Account::unpack(&pool_assets_accounts[i].data.borrow())?.amount;
-            pool_asset_amounts.push(pool_asset_amount);
-
-            let source_asset_amount =
-                Account::unpack(&source_assets_accounts[i].data.borrow())?.amount;
-            pool_token_effective_amount = min(
-                ((source_asset_amount as u128) * (total_pooltokens as u128))
-                    .checked_div(pool_asset_amount as u128)
-                    .unwrap_or(std::u64::MAX.into()) as u64,
-                pool_token_effective_amount,
-            );
+            pool_asset_amounts
+                .push(Account::unpack(&pool_assets_accounts[i].data.borrow())?.amount);
+            source_asset_amounts
+                .push(Account::unpack(&source_assets_accounts[i].data.borrow())?.amount);
         }
-        pool_token_effective_amount = min(pool_token_amount, pool_token_effective_amount);
+        // Compute buy-in amount. The effective buy-in amount can be less than the
+        // input_token_amount as the source accounts need to satisfy the pool asset ratios
+        let deposit = compute_deposit(
+            pool_token_amount,
+            total_pooltokens,
+            &pool_asset_amounts,
+            &source_asset_amounts,
+            pool_header.fee_ratio,
+        )?;
+        pool_header.accrued_fees = pool_header
+            .accrued_fees
+            .checked_add(deposit.fee)
+            .ok_or(BonfidaBotError::Overflow)?;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
 
         // Execute buy in
-        let mut amounts_all_zero = true;
         for i in 0..nb_assets {
             let pool_asset_key =
                 get_associated_token_address(&pool_key, &pool_assets[i].mint_address);
 
             if pool_asset_key != *pool_assets_accounts[i as usize].key {
                 msg!("Provided pool asset account is invalid");
-                return Err(ProgramError::InvalidArgument);
+                return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
             }
 
-            let amount = ((pool_token_effective_amount as u128) * (pool_asset_amounts[i] as u128))
-                / (total_pooltokens as u128);
+            let amount = deposit.asset_amounts[i];
             if amount == 0 {
                 continue;
-            } else {
-                amounts_all_zero = false;
             }
 
             let instruction = transfer(
@@ -449,7 +458,7 @@ impl Processor {
                 pool_assets_accounts[i].key,
                 source_owner_account.key,
                 &[],
-                amount as u64,
+                amount,
             )?;
             invoke(
                 &instruction,
@@ -461,16 +470,6 @@ impl Processor {
                 ],
             )?;
         }
-        if amounts_all_zero {
-            msg!("The provided amounts cannot be all zero.");
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        let cast_fee_ratio = pool_header.fee_ratio as u128;
-
-        let pool_token_fee = ((cast_fee_ratio * pool_token_effective_amount as u128) >> 16) as u64;
-
-        let pool_token_amount_after_fee = pool_token_effective_amount - pool_token_fee;
 
         // Mint the effective amount of pooltokens to the target
         let instruction = mint_to(
@@ -479,7 +478,7 @@ impl Processor {
             target_pool_token_account.key,
             &pool_key,
             &[],
-            pool_token_amount_after_fee,
+            deposit.pool_tokens,
         )?;
 
         invoke_signed(
@@ -493,72 +492,74 @@ impl Processor {
             &[&[&pool_seed]],
         )?;
 
-        // Mint the effective amount of pooltokens to the target
-        let signal_provider_fee = pool_token_fee / 2;
-        let instruction = mint_to(
-            spl_token_account.key,
-            &pool_mint_key,
-            signal_provider_pt_account.key,
-            &pool_key,
-            &[],
-            signal_provider_fee,
-        )?;
-
-        invoke_signed(
-            &instruction,
-            &[
-                spl_token_account.clone(),
-                mint_account.clone(),
-                signal_provider_pt_account.clone(),
-                pool_account.clone(),
-            ],
-            &[&[&pool_seed]],
-        )?;
-
-        // Mint the effective amount of pooltokens to the target
-        let bonfida_fee = pool_token_fee / 4;
-        let instruction = mint_to(
-            spl_token_account.key,
-            &pool_mint_key,
-            bonfida_fee_pt_account.key,
-            &pool_key,
-            &[],
-            bonfida_fee,
-        )?;
+        Ok(())
+    }
 
-        invoke_signed(
-            &instruction,
-            &[
-                spl_token_account.clone(),
-                mint_account.clone(),
-                bonfida_fee_pt_account.clone(),
-                pool_account.clone(),
-            ],
-            &[&[&pool_seed]],
-        )?;
+    /// Returns the pool token supply, including the accrued fees which have not been claimed yet.
+    fn total_pooltokens(
+        mint_account: &AccountInfo,
+        pool_header: &PoolHeader,
+    ) -> Result<u64, ProgramError> {
+        let supply = Mint::unpack(&mint_account.data.borrow())?.supply;
+        let unclaimed_fees = owed_fees(pool_header.accrued_fees, &pool_header.claimed_fees)?
+            .iter()
+            .try_fold(0u64, |total, fee| total.checked_add(*fee))
+            .ok_or(BonfidaBotError::Overflow)?;
+        Ok(supply
+            .checked_add(unclaimed_fees)
+            .ok_or(BonfidaBotError::Overflow)?)
+    }
 
-        // Mint the effective amount of pooltokens to the target
-        let instruction = mint_to(
-            spl_token_account.key,
-            &pool_mint_key,
-            bonfida_bnb_pt_account.key,
-            &pool_key,
-            &[],
-            pool_token_fee - bonfida_fee - signal_provider_fee,
-        )?;
+    /// Checks that redeeming `pool_token_amount` out of the source pool token account leaves the
+    /// signal provider with its minimum share of the pool, when the source is its associated pool
+    /// token account. The share is computed once the redeemed pool tokens are burned.
+    fn check_signal_provider_redeem(
+        pool_header: &PoolHeader,
+        mint_key: &Pubkey,
+        source_pool_token_account: &AccountInfo,
+        pool_token_amount: u64,
+        total_pooltokens: u64,
+    ) -> ProgramResult {
+        if pool_header.min_signal_provider_ratio == 0
+            || get_associated_token_address(&pool_header.signal_provider, mint_key)
+                != *source_pool_token_account.key
+        {
+            return Ok(());
+        }
+        let signal_provider_pool_tokens =
+            Account::unpack(&source_pool_token_account.data.borrow())?.amount;
+        check_signal_provider_stake(
+            pool_header,
+            signal_provider_pool_tokens.saturating_sub(pool_token_amount),
+            total_pooltokens.saturating_sub(pool_token_amount),
+        )
+    }
 
-        invoke_signed(
-            &instruction,
-            &[
-                spl_token_account.clone(),
-                mint_account.clone(),
-                bonfida_bnb_pt_account.clone(),
-                pool_account.clone(),
-            ],
-            &[&[&pool_seed]],
+    /// Adds the management fees accrued since the last collection to the pool header and moves
+    /// the collection timestamp forward. The header still has to be written back. Returns the
+    /// amount of pool tokens accrued.
+    fn accrue_management_fees(
+        mint_account: &AccountInfo,
+        pool_header: &mut PoolHeader,
+        current_timestamp: u64,
+    ) -> Result<u64, ProgramError> {
+        let total_pooltokens = Self::total_pooltokens(mint_account, pool_header)?;
+        // A clock running backwards accrues no fees
+        let fees = accrued_fee_tokens(
+            total_pooltokens,
+            pool_header.annual_fee_ratio,
+            current_timestamp.saturating_sub(pool_header.last_fee_collection_timestamp),
         )?;
-
-        Ok(())
+        if fees == 0 {
+            // The timestamp is left untouched so that the fees keep accruing
+            return Ok(0);
+        }
+        pool_header.accrued_fees = pool_header
+            .accrued_fees
+            .checked_add(fees)
+            .ok_or(BonfidaBotError::Overflow)?;
+        pool_header.last_fee_collection_timestamp = current_timestamp;
+        Ok(fees)
     }
 
     pub fn process_create_order(
@@ -579,234 +580,290 @@ impl Processor {
         target_index: usize,
         serum_limit: u16,
     ) -> ProgramResult {
-        // TODO : Enforce one order limit on openorders accounts
-        msg!("Disabled endpoint");
-        Err(ProgramError::Custom(0))
-
-        // let account_iter = &mut accounts.iter();
-
-        // let signal_provider_account = next_account_info(account_iter)?;
-        // let market = next_account_info(account_iter)?;
-        // let pool_asset_token_account = next_account_info(account_iter)?;
-        // let openorders_account = next_account_info(account_iter)?;
-        // let event_queue = next_account_info(account_iter)?;
-        // let request_queue = next_account_info(account_iter)?;
-        // let market_bids = next_account_info(account_iter)?;
-        // let market_asks = next_account_info(account_iter)?;
-        // let pool_account = next_account_info(account_iter)?;
-        // let coin_vault = next_account_info(account_iter)?;
-        // let pc_vault = next_account_info(account_iter)?;
-        // let spl_token_program = next_account_info(account_iter)?;
-        // if spl_token_program.key != &spl_token::id() {
-        //     msg!("Incorrect spl token program provided");
-        //     return Err(ProgramError::IncorrectProgramId);
-        // }
-        // let rent_sysvar_account = next_account_info(account_iter)?;
-        // let dex_program = next_account_info(account_iter)?;
-        // let discount_account = next_account_info(account_iter).ok();
-
-        // check_pool_key(program_id, pool_account.key, &pool_seed)?;
-
-        // let source_account =
-        //     Account::unpack(&pool_asset_token_account.data.borrow()).or_else(|e| {
-        //         msg!("Invalid pool asset token account provided");
-        //         Err(e)
-        //     })?;
-        // let source_token_account_key =
-        //     get_associated_token_address(pool_account.key, &source_account.mint);
-
-        // if pool_asset_token_account.key != &source_token_account_key {
-        //     msg!("Source token account should be associated to the pool account");
-        //     return Err(ProgramError::InvalidArgument);
-        // }
-        // if order_type != OrderType::ImmediateOrCancel {
-        //     msg!("Order needs to be of type ImmediateOrCancel");
-        //     return Err(ProgramError::InvalidArgument);
-        // }
-
-        // let mut pool_header = PoolHeader::unpack(&pool_account.data.borrow()[..PoolHeader::LEN])?;
-        // if &pool_header.serum_program_id != dex_program.key {
-        //     msg!("The provided serum program account is invalid for this pool.");
-        //     return Err(ProgramError::InvalidArgument);
-        // }
-        // if !signal_provider_account.is_signer {
-        //     msg!("The signal provider's signature is required.");
-        //     return Err(ProgramError::MissingRequiredSignature);
-        // }
-        // if signal_provider_account.key != &pool_header.signal_provider {
-        //     msg!("A wrong signal provider account was provided.");
-        //     return Err(ProgramError::MissingRequiredSignature);
-        // }
-        // if market.key
-        //     != &unpack_market(&pool_account.data.borrow()[PoolHeader::LEN..], market_index)
-        // {
-        //     msg!("The given market account is not authorized.");
-        //     return Err(ProgramError::MissingRequiredSignature);
-        // }
-
-        // let openorders_total_pc = openorders_account
-        //     .data
-        //     .borrow()
-        //     .get(101..109)
-        //     .and_then(|slice| slice.try_into().ok())
-        //     .map(u64::from_le_bytes)
-        //     .ok_or(ProgramError::InvalidAccountData)?;
-
-        // let openorders_total_coin = openorders_account
-        //     .data
-        //     .borrow()
-        //     .get(85..93)
-        //     .and_then(|slice| slice.try_into().ok())
-        //     .map(u64::from_le_bytes)
-        //     .ok_or(ProgramError::InvalidAccountData)?;
-
-        // let new_open_order = (openorders_total_coin == 0) && (openorders_total_pc == 0);
-        // match (&pool_header.status, new_open_order) {
-        //     (PoolStatus::Uninitialized, _) => return Err(ProgramError::UninitializedAccount),
-        //     (PoolStatus::Unlocked, _) => {
-        //         pool_header.status = PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap())
-        //     }
-        //     (PoolStatus::Locked, _) => {
-        //         pool_header.status = PoolStatus::LockedPendingOrder(NonZeroU8::new(1).unwrap())
-        //     }
-        //     (PoolStatus::PendingOrder(n), true) | (PoolStatus::LockedPendingOrder(n), true) => {
-        //         if n.get() == 64 {
-        //             msg!("Maximum number of active orders has been reached. Settle or cancel a pending order.");
-        //             return Err(BonfidaBotError::Overflow.into());
-        //         }
-        //         let pending_orders = NonZeroU8::new(n.get() + 1).unwrap();
-        //         pool_header.status = match pool_header.status {
-        //             PoolStatus::PendingOrder(_) => PoolStatus::PendingOrder(pending_orders),
-        //             PoolStatus::LockedPendingOrder(_) => {
-        //                 PoolStatus::LockedPendingOrder(pending_orders)
-        //             }
-        //             _ => {
-        //                 unreachable!()
-        //             }
-        //         }
-        //     }
-        //     _ => {} // This happens in the case when the openorder account is already counted in the pending orders.
-        // };
-        // pool_header.pack_into_slice(&mut pool_account.data.borrow_mut()[..PoolHeader::LEN]);
-
-        // let asset_offset = PoolHeader::LEN + PUBKEY_LENGTH * pool_header.number_of_markets as usize;
-        // let source_asset =
-        //     unpack_unchecked_asset(&pool_account.data.borrow()[asset_offset..], source_index)?;
-        // let mut target_asset =
-        //     unpack_unchecked_asset(&pool_account.data.borrow()[asset_offset..], target_index)?;
-
-        // if !source_asset.is_initialized() {
-        //     msg!("The pool has no account at the specificed source index");
-        //     return Err(ProgramError::InvalidArgument);
-        // }
-
-        // if source_asset.mint_address != source_account.mint {
-        //     msg!("Provided coin account does not match the pool source asset");
-        //     return Err(ProgramError::InvalidArgument);
-        // }
-
-        // if &source_account.owner != pool_account.key {
-        //     msg!("Provided coin account should be owned by the pool");
-        //     return Err(ProgramError::InvalidArgument);
-        // }
-
-        // if target_asset.is_initialized() {
-        //     if target_asset.mint_address != target_mint {
-        //         msg!("Target asset mint does not match given target mint");
-        //         return Err(ProgramError::InvalidArgument);
-        //     }
-        // } else {
-        //     target_asset.mint_address = target_mint;
-        //     &target_asset.pack_into_slice(get_asset_slice(
-        //         &mut pool_account.data.borrow_mut()[asset_offset..],
-        //         target_index,
-        //     )?);
-        // }
-
-        // let pool_asset_amount = Account::unpack(&pool_asset_token_account.data.borrow())?.amount;
-
-        // let amount_to_trade = (((pool_asset_amount as u128)
-        //     * (max_ratio_of_pool_to_sell_to_another_fellow_trader.get() as u128))
-        //     >> 16) as u64;
-
-        // let lots_to_trade = amount_to_trade
-        //     .checked_div(match side {
-        //         Side::Bid => pc_lot_size,
-        //         Side::Ask => coin_lot_size,
-        //     })
-        //     .ok_or(BonfidaBotError::Overflow)?;
-
-        // if pool_asset_amount == amount_to_trade {
-        //     // If order empties a pool asset, reset it
-
-        //     fill_slice(
-        //         get_asset_slice(
-        //             &mut pool_account.data.borrow_mut()[asset_offset..],
-        //             source_index,
-        //         )?,
-        //         0u8,
-        //     );
-        // }
-
-        // let max_native_pc_qty_including_fees = match side {
-        //     Side::Bid => NonZeroU64::new(amount_to_trade).ok_or_else(|| {
-        //         msg!("Operation too small");
-        //         BonfidaBotError::OperationTooSmall
-        //     })?,
-        //     Side::Ask => NonZeroU64::new(1).unwrap(),
-        // };
-
-        // let new_order_instruction = new_order(
-        //     market.key,
-        //     openorders_account.key,
-        //     request_queue.key,
-        //     event_queue.key,
-        //     market_bids.key,
-        //     market_asks.key,
-        //     pool_asset_token_account.key,
-        //     pool_account.key,
-        //     coin_vault.key,
-        //     pc_vault.key,
-        //     spl_token_program.key,
-        //     rent_sysvar_account.key,
-        //     discount_account.map(|account| account.key),
-        //     dex_program.key,
-        //     side,
-        //     limit_price,
-        //     NonZeroU64::new(lots_to_trade).ok_or_else(|| {
-        //         msg!("Operation too small");
-        //         BonfidaBotError::OperationTooSmall
-        //     })?,
-        //     order_type,
-        //     client_id,
-        //     self_trade_behavior,
-        //     serum_limit,
-        //     max_native_pc_qty_including_fees,
-        // )?;
-
-        // let mut account_infos = vec![
-        //     dex_program.clone(),
-        //     market.clone(),
-        //     openorders_account.clone(),
-        //     request_queue.clone(),
-        //     event_queue.clone(),
-        //     market_bids.clone(),
-        //     market_asks.clone(),
-        //     pool_asset_token_account.clone(),
-        //     pool_account.clone(),
-        //     coin_vault.clone(),
-        //     pc_vault.clone(),
-        //     spl_token_program.clone(),
-        //     rent_sysvar_account.clone(),
-        // ];
-
-        // if let Some(account) = discount_account {
-        //     account_infos.push(account.clone());
-        // }
-
-        // invoke_signed(&new_order_instruction, &account_infos, &[&[&pool_seed]])?;
-
-        // Ok(())
+        let account_iter = &mut accounts.iter();
+
+        let signal_provider_account = next_account_info(account_iter)?;
+        let market = next_account_info(account_iter)?;
+        let pool_asset_token_account = next_account_info(account_iter)?;
+        let openorders_account = next_account_info(account_iter)?;
+        let event_queue = next_account_info(account_iter)?;
+        let request_queue = next_account_info(account_iter)?;
+        let market_bids = next_account_info(account_iter)?;
+        let market_asks = next_account_info(account_iter)?;
+        let pool_account = next_account_info(account_iter)?;
+        let coin_vault = next_account_info(account_iter)?;
+        let pc_vault = next_account_info(account_iter)?;
+        let spl_token_program = next_account_info(account_iter)?;
+        if spl_token_program.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let rent_sysvar_account = next_account_info(account_iter)?;
+        let dex_program = next_account_info(account_iter)?;
+        let mint_account = next_account_info(account_iter)?;
+        let signal_provider_pool_token_account = next_account_info(account_iter)?;
+        let trader_account = next_account_info(account_iter)?;
+        let clock_sysvar_account = next_account_info(account_iter)?;
+        let discount_account = next_account_info(account_iter).ok();
+
+        check_pool_key(program_id, pool_account.key, &pool_seed)?;
+
+        let source_account =
+            Account::unpack(&pool_asset_token_account.data.borrow()).or_else(|e| {
+                msg!("Invalid pool asset token account provided");
+                Err(e)
+            })?;
+        let source_token_account_key =
+            get_associated_token_address(pool_account.key, &source_account.mint);
+
+        if pool_asset_token_account.key != &source_token_account_key {
+            msg!("Source token account should be associated to the pool account");
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
+        }
+
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        if &pool_header.serum_program_id != dex_program.key {
+            msg!("The provided serum program account is invalid for this pool.");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let trader = check_trader(
+            program_id,
+            &pool_seed,
+            &pool_header,
+            signal_provider_account,
+            Some(trader_account),
+            accounts,
+        )?;
+        match trader {
+            Some(trader) => {
+                if trader.max_ratio_of_pool_assets_to_trade != 0
+                    && max_ratio_of_pool_to_sell_to_another_fellow_trader.get()
+                        > trader.max_ratio_of_pool_assets_to_trade
+                {
+                    msg!("The order is larger than the limit of the trader.");
+                    return Err(BonfidaBotError::TradeLimitExceeded.into());
+                }
+            }
+            // Once trading is delegated, orders are only created by the traders
+            None if pool_header.number_of_traders != 0 => {
+                msg!("The signal provider cannot create orders while it has traders.");
+                return Err(BonfidaBotError::TradingDelegated.into());
+            }
+            None => (),
+        }
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        record_order(
+            &mut pool_header,
+            max_ratio_of_pool_to_sell_to_another_fellow_trader.get(),
+            current_timestamp,
+        )?;
+        let mint_key = Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+        if mint_account.key != &mint_key {
+            msg!("Provided mint account is invalid");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+        if signal_provider_pool_token_account.key
+            != &get_associated_token_address(&pool_header.signal_provider, &mint_key)
+        {
+            msg!("Provided signal provider pooltoken account is invalid");
+            return Err(BonfidaBotError::InvalidSignalProvider.into());
+        }
+        // The pooltoken account of the signal provider may not exist when no stake is required
+        if pool_header.min_signal_provider_ratio != 0 {
+            let signal_provider_pool_tokens =
+                Account::unpack(&signal_provider_pool_token_account.data.borrow())?.amount;
+            check_signal_provider_stake(
+                &pool_header,
+                signal_provider_pool_tokens,
+                Self::total_pooltokens(mint_account, &pool_header)?,
+            )?;
+        }
+        if market.key
+            != &PoolState::new(&mut pool_account.data.borrow_mut())?.market(market_index)?
+        {
+            msg!("The given market account is not authorized.");
+            return Err(BonfidaBotError::UnauthorizedMarket.into());
+        }
+        if market.owner != dex_program.key {
+            msg!("The market account is not owned by the serum program.");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if market.data.borrow().get(285..317) != Some(&market_bids.key.to_bytes()[..])
+            || market.data.borrow().get(317..349) != Some(&market_asks.key.to_bytes()[..])
+        {
+            msg!("The market bids and asks accounts do not match the market.");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let best_bid = best_book_price(&market_bids.data.borrow(), Side::Bid)?;
+        let best_ask = best_book_price(&market_asks.data.borrow(), Side::Ask)?;
+        check_price_band(&pool_header, side, limit_price.get(), best_bid, best_ask)?;
+        check_order_policy(&pool_header, order_type.into(), self_trade_behavior.into())?;
+
+        let openorders_total_pc = openorders_account
+            .data
+            .borrow()
+            .get(101..109)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(ProgramError::InvalidAccountData)?;
+
+        let openorders_total_coin = openorders_account
+            .data
+            .borrow()
+            .get(85..93)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(ProgramError::InvalidAccountData)?;
+
+        // Each pending order has an OpenOrders account of its own, which is counted once and
+        // cleared by a single settlement
+        if (openorders_total_coin != 0) || (openorders_total_pc != 0) {
+            msg!("The OpenOrders account is in use. Settle it or use another one.");
+            return Err(BonfidaBotError::OpenOrdersInUse.into());
+        }
+        match pool_header.status {
+            PoolStatus::Uninitialized => return Err(ProgramError::UninitializedAccount),
+            PoolStatus::Unlocked => {
+                pool_header.status = PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap())
+            }
+            PoolStatus::Locked => {
+                pool_header.status = PoolStatus::LockedPendingOrder(NonZeroU8::new(1).unwrap())
+            }
+            PoolStatus::PendingOrder(n) | PoolStatus::LockedPendingOrder(n) => {
+                if n.get() == 64 {
+                    msg!("Maximum number of active orders has been reached. Settle or cancel a pending order.");
+                    return Err(BonfidaBotError::Overflow.into());
+                }
+                let pending_orders = NonZeroU8::new(n.get() + 1).unwrap();
+                pool_header.status = match pool_header.status {
+                    PoolStatus::PendingOrder(_) => PoolStatus::PendingOrder(pending_orders),
+                    PoolStatus::LockedPendingOrder(_) => {
+                        PoolStatus::LockedPendingOrder(pending_orders)
+                    }
+                    _ => {
+                        unreachable!()
+                    }
+                }
+            }
+        };
+        // Resting orders can legitimately stay on the book and do not start the slashing timer
+        if order_type == OrderType::ImmediateOrCancel && pool_header.pending_order_timestamp == 0 {
+            pool_header.pending_order_timestamp = current_timestamp;
+        }
+        let mut pool_data = pool_account.data.borrow_mut();
+        let mut pool_state = PoolState::new(&mut pool_data)?;
+        pool_state.set_header(&pool_header)?;
+
+        let source_asset = pool_state.asset(source_index)?;
+        let mut target_asset = pool_state.asset(target_index)?;
+
+        if !source_asset.is_initialized() {
+            msg!("The pool has no account at the specificed source index");
+            return Err(BonfidaBotError::InvalidAssetIndex.into());
+        }
+
+        if source_asset.mint_address != source_account.mint {
+            msg!("Provided coin account does not match the pool source asset");
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
+        }
+
+        if &source_account.owner != pool_account.key {
+            msg!("Provided coin account should be owned by the pool");
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
+        }
+
+        if target_asset.is_initialized() {
+            if target_asset.mint_address != target_mint {
+                msg!("Target asset mint does not match given target mint");
+                return Err(BonfidaBotError::MarketAssetMismatch.into());
+            }
+        } else {
+            target_asset.mint_address = target_mint;
+            target_asset.pack_into_slice(pool_state.asset_mut(target_index)?);
+        }
+
+        let pool_asset_amount = Account::unpack(&pool_asset_token_account.data.borrow())?.amount;
+
+        let amount_to_trade = (((pool_asset_amount as u128)
+            * (max_ratio_of_pool_to_sell_to_another_fellow_trader.get() as u128))
+            >> 16) as u64;
+
+        let lots_to_trade = amount_to_trade
+            .checked_div(match side {
+                Side::Bid => pc_lot_size,
+                Side::Ask => coin_lot_size,
+            })
+            .ok_or(BonfidaBotError::Overflow)?;
+
+        if pool_asset_amount == amount_to_trade {
+            // If order empties a pool asset, reset it
+
+            fill_slice(pool_state.asset_mut(source_index)?, 0u8);
+        }
+        drop(pool_data);
+
+        let max_native_pc_qty_including_fees = match side {
+            Side::Bid => NonZeroU64::new(amount_to_trade).ok_or_else(|| {
+                msg!("Operation too small");
+                BonfidaBotError::OperationTooSmall
+            })?,
+            Side::Ask => NonZeroU64::new(1).unwrap(),
+        };
+
+        let new_order_instruction = new_order(
+            market.key,
+            openorders_account.key,
+            request_queue.key,
+            event_queue.key,
+            market_bids.key,
+            market_asks.key,
+            pool_asset_token_account.key,
+            pool_account.key,
+            coin_vault.key,
+            pc_vault.key,
+            spl_token_program.key,
+            rent_sysvar_account.key,
+            discount_account.map(|account| account.key),
+            dex_program.key,
+            side,
+            limit_price,
+            NonZeroU64::new(lots_to_trade).ok_or_else(|| {
+                msg!("Operation too small");
+                BonfidaBotError::OperationTooSmall
+            })?,
+            order_type,
+            client_id,
+            self_trade_behavior,
+            serum_limit,
+            max_native_pc_qty_including_fees,
+        )?;
+
+        let mut account_infos = vec![
+            dex_program.clone(),
+            market.clone(),
+            openorders_account.clone(),
+            request_queue.clone(),
+            event_queue.clone(),
+            market_bids.clone(),
+            market_asks.clone(),
+            pool_asset_token_account.clone(),
+            pool_account.clone(),
+            coin_vault.clone(),
+            pc_vault.clone(),
+            spl_token_program.clone(),
+            rent_sysvar_account.clone(),
+        ];
+
+        if let Some(account) = discount_account {
+            account_infos.push(account.clone());
+        }
+
+        invoke_signed(&new_order_instruction, &account_infos, &[&[&pool_seed]])?;
+
+        Ok(())
     }
 
     pub fn process_settle(
@@ -847,43 +904,45 @@ impl Processor {
 
         if &pool_mint_key != pool_token_mint.key {
             msg!("Provided pool mint account is invalid.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidMint.into());
         }
 
         if &pool_coin_account_key != pool_coin_wallet.key {
             msg!("Provided pool coin account does not match the pool coin asset");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
         }
         if &pool_pc_account_key != pool_pc_wallet.key {
             msg!("Provided pool pc account does not match the pool pc asset");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
         }
 
         let pool_coin_account = Account::unpack(&pool_coin_wallet.data.borrow())?;
         let pool_pc_account = Account::unpack(&pool_pc_wallet.data.borrow())?;
 
-        let mut pool_header = PoolHeader::unpack(&pool_account.data.borrow()[..PoolHeader::LEN])?;
-
-        let asset_offset = PoolHeader::LEN + PUBKEY_LENGTH * pool_header.number_of_markets as usize;
-        let mut pool_coin_asset =
-            unpack_unchecked_asset(&pool_account.data.borrow()[asset_offset..], coin_index)?;
-        let mut pool_pc_asset =
-            unpack_unchecked_asset(&pool_account.data.borrow()[asset_offset..], pc_index)?;
+        let (mut pool_header, mut pool_coin_asset, mut pool_pc_asset) = {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let pool_state = PoolState::new(&mut pool_data)?;
+            (
+                pool_state.header()?,
+                pool_state.asset(coin_index)?,
+                pool_state.asset(pc_index)?,
+            )
+        };
 
         if &pool_coin_account.owner != pool_account.key {
             msg!("Pool should own the provided coin account");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
         }
 
         if &pool_pc_account.owner != pool_account.key {
             msg!("Pool should own the provided price coin account");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
         }
 
         if pool_coin_asset.is_initialized() {
             if pool_coin_asset.mint_address != coin_mint {
                 msg!("Coin asset does not match market coin token");
-                return Err(ProgramError::InvalidArgument);
+                return Err(BonfidaBotError::MarketAssetMismatch.into());
             }
         } else {
             pool_coin_asset.mint_address = coin_mint
@@ -892,7 +951,7 @@ impl Processor {
         if pool_pc_asset.is_initialized() {
             if pool_pc_asset.mint_address != pc_mint {
                 msg!("Coin asset does not match market pc token");
-                return Err(ProgramError::InvalidArgument);
+                return Err(BonfidaBotError::MarketAssetMismatch.into());
             }
         } else {
             pool_pc_asset.mint_address = pc_mint
@@ -959,25 +1018,26 @@ impl Processor {
                 }
                 _ => {
                     msg!("The pool has no pending orders.");
-                    return Err(ProgramError::InvalidAccountData);
+                    return Err(BonfidaBotError::NoPendingOrders.into());
                 }
+            };
+            // The pending orders are cleared, the slashing timer starts over
+            if let PoolStatus::Unlocked | PoolStatus::Locked = pool_header.status {
+                pool_header.pending_order_timestamp = 0;
             }
         }
-        pool_header.pack_into_slice(&mut pool_account.data.borrow_mut()[..PoolHeader::LEN]);
-
         if (openorders_free_pc == 0) & (openorders_free_coin == 0) {
             msg!("No funds to settle.");
-            return Err(BonfidaBotError::LockedOperation.into());
+            return Err(BonfidaBotError::NothingToSettle.into());
         }
 
-        &pool_coin_asset.pack_into_slice(get_asset_slice(
-            &mut pool_account.data.borrow_mut()[asset_offset..],
-            coin_index,
-        )?);
-        &pool_pc_asset.pack_into_slice(get_asset_slice(
-            &mut pool_account.data.borrow_mut()[asset_offset..],
-            pc_index,
-        )?);
+        {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let mut pool_state = PoolState::new(&mut pool_data)?;
+            pool_state.set_header(&pool_header)?;
+            pool_coin_asset.pack_into_slice(pool_state.asset_mut(coin_index)?);
+            pool_pc_asset.pack_into_slice(pool_state.asset_mut(pc_index)?);
+        }
 
         let instruction = settle_funds(
             dex_program.key,
@@ -1024,7 +1084,7 @@ impl Processor {
     ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
 
-        let signal_provider = next_account_info(accounts_iter)?;
+        let signer_account = next_account_info(accounts_iter)?;
         let market = next_account_info(accounts_iter)?;
         let openorders_account = next_account_info(accounts_iter)?;
         let serum_market_bids = next_account_info(accounts_iter)?;
@@ -1032,11 +1092,19 @@ impl Processor {
         let event_queue = next_account_info(accounts_iter)?;
         let pool_account = next_account_info(accounts_iter)?;
         let dex_program = next_account_info(accounts_iter)?;
+        let trader_account = next_account_info(accounts_iter).ok();
 
         check_pool_key(program_id, pool_account.key, &pool_seed)?;
 
-        let pool_header = PoolHeader::unpack(&pool_account.data.borrow()[..PoolHeader::LEN])?;
-        check_signal_provider(&pool_header, signal_provider, true)?;
+        let pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        check_trader(
+            program_id,
+            &pool_seed,
+            &pool_header,
+            signer_account,
+            trader_account,
+            accounts,
+        )?;
 
         let instruction = cancel_order(
             &dex_program.key,
@@ -1088,9 +1156,12 @@ impl Processor {
         let source_pool_token_account = next_account_info(accounts_iter)?;
         let pool_account = next_account_info(accounts_iter)?;
 
-        let mut pool_header = PoolHeader::unpack(&pool_account.data.borrow()[..PoolHeader::LEN])?;
-        let asset_offset = PoolHeader::LEN + PUBKEY_LENGTH * pool_header.number_of_markets as usize;
-        let pool_assets = unpack_assets(&pool_account.data.borrow()[asset_offset..])?;
+        let (mut pool_header, pool_assets) = {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let pool_state = PoolState::new(&mut pool_data)?;
+            let pool_assets: Vec<PoolAsset> = pool_state.assets().map(|(_, a)| a).collect();
+            (pool_state.header()?, pool_assets)
+        };
         let nb_assets = pool_assets.len();
 
         let mut pool_assets_accounts: Vec<&AccountInfo> = vec![];
@@ -1108,34 +1179,33 @@ impl Processor {
             Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
         if pool_mint_key != *mint_account.key {
             msg!("Provided mint account is invalid");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidMint.into());
         }
         if !source_pool_token_owner_account.is_signer {
             msg!("Source pooltoken account owner should be a signer.");
-            return Err(ProgramError::InvalidArgument);
+            return Err(ProgramError::MissingRequiredSignature);
         }
         if *pool_account.owner != *program_id {
             msg!("Program should own pool account");
-            return Err(ProgramError::InvalidArgument);
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
         }
-        match pool_header.status {
-            PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) => {
-                msg!("The pool has one or more pending orders. No buy-outs are possible for now. Try again later.");
-                return Err(BonfidaBotError::LockedOperation.into());
-            }
-            _ => (),
-        };
+        // Funds of filled orders which have not been settled yet are still part of the pool value
+        let unsettled_amounts = Self::unsettled_funds(
+            &pool_header,
+            pool_account.key,
+            &pool_assets,
+            accounts_iter.as_slice(),
+        )?;
 
-        let current_timestamp =
-            Clock::from_account_info(clock_sysvar_account)?.unix_timestamp as u64;
-        if current_timestamp - pool_header.last_fee_collection_timestamp
-            > pool_header.fee_collection_period
-        {
-            msg!("Fees should be collected before redeeming.");
-            return Err(BonfidaBotError::LockedOperation.into());
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        if Self::accrue_management_fees(mint_account, &mut pool_header, current_timestamp)? > 0 {
+            PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
         }
 
-        let total_pooltokens = Mint::unpack(&mint_account.data.borrow())?.supply;
+        let total_pooltokens = Self::total_pooltokens(mint_account, &pool_header)?;
         let total_user_pooltokens =
             Account::unpack(&source_pool_token_account.data.borrow())?.amount;
 
@@ -1143,6 +1213,26 @@ impl Processor {
             msg!("Insufficient pool token funds");
             return Err(ProgramError::InsufficientFunds);
         }
+        Self::check_signal_provider_redeem(
+            &pool_header,
+            &pool_mint_key,
+            source_pool_token_account,
+            pool_token_amount,
+            total_pooltokens,
+        )?;
+
+        let mut pool_asset_amounts = Vec::with_capacity(nb_assets);
+        let mut pool_value = Vec::with_capacity(nb_assets);
+        for i in 0..nb_assets {
+            let amount = Account::unpack(&pool_assets_accounts[i].data.borrow())?.amount;
+            pool_asset_amounts.push(amount);
+            pool_value.push(
+                amount
+                    .checked_add(unsettled_amounts[i])
+                    .ok_or(BonfidaBotError::Overflow)?,
+            );
+        }
+        let amounts = compute_redeem(pool_token_amount, total_pooltokens, &pool_value)?;
 
         // Execute buy out
         for i in 0..nb_assets {
@@ -1151,19 +1241,17 @@ impl Processor {
 
             if pool_asset_key != *pool_assets_accounts[i].key {
                 msg!("Provided pool asset account is invalid");
-                return Err(ProgramError::InvalidArgument);
+                return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
             }
 
-            let pool_asset_amount = Account::unpack(&pool_assets_accounts[i].data.borrow())?.amount;
-
-            let amount: u64 = (((pool_token_amount as u128) * (pool_asset_amount as u128))
-                / (total_pooltokens as u128))
-                .try_into()
-                .map_err(|_| BonfidaBotError::Overflow)?;
-
+            let amount = amounts[i];
             if amount == 0 {
                 continue;
             }
+            if amount > pool_asset_amounts[i] {
+                msg!("The pool lacks settled funds for this redeem. Settle the pending orders first.");
+                return Err(BonfidaBotError::PendingOrders.into());
+            }
             let instruction = transfer(
                 spl_token_account.key,
                 pool_assets_accounts[i].key,
@@ -1176,50 +1264,1233 @@ impl Processor {
                 &instruction,
                 &[
                     spl_token_account.clone(),
-                    pool_assets_accounts[i].clone(),
-                    target_assets_accounts[i].clone(),
-                    pool_account.clone(),
+                    pool_assets_accounts[i].clone(),
+                    target_assets_accounts[i].clone(),
+                    pool_account.clone(),
+                ],
+                &[&[&pool_seed]],
+            )?;
+        }
+
+        // Burn the redeemed pooltokens
+        let instruction = burn(
+            spl_token_account.key,
+            &source_pool_token_account.key,
+            mint_account.key,
+            &source_pool_token_owner_account.key,
+            &[],
+            pool_token_amount,
+        )?;
+
+        invoke(
+            &instruction,
+            &[
+                spl_token_account.clone(),
+                source_pool_token_account.clone(),
+                mint_account.clone(),
+                source_pool_token_owner_account.clone(),
+            ],
+        )?;
+
+        // Queued deposits still need the pool assets to be refunded
+        if pool_token_amount == total_pooltokens
+            && pool_header.deposit_queue_head == pool_header.deposit_queue_tail
+        {
+            // Reset the pool data, keeping the pool header mostly intact to preserve pool seeds
+            PoolState::new(&mut pool_account.data.borrow_mut())?.reset()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sums the free funds of the pool OpenOrders accounts by pool asset. The accounts are given
+    /// as `[market, open_orders]` pairs, which have to include every OpenOrders account counted
+    /// as a pending order. None of them can have orders left on the book, as the assets these
+    /// orders will end up in are unknown.
+    fn unsettled_funds(
+        pool_header: &PoolHeader,
+        pool_key: &Pubkey,
+        pool_assets: &[PoolAsset],
+        accounts: &[AccountInfo],
+    ) -> Result<Vec<u64>, ProgramError> {
+        let pending_orders = match pool_header.status {
+            PoolStatus::PendingOrder(n) | PoolStatus::LockedPendingOrder(n) => n.get() as usize,
+            _ => 0,
+        };
+        if accounts.len() % 2 != 0 {
+            msg!("OpenOrders accounts should be given along with their market account");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let mut amounts = vec![0u64; pool_assets.len()];
+        let mut open_orders_keys: Vec<&Pubkey> = vec![];
+        for pair in accounts.chunks_exact(2) {
+            let (market, open_orders) = (&pair[0], &pair[1]);
+            if market.owner != &pool_header.serum_program_id
+                || open_orders.owner != &pool_header.serum_program_id
+            {
+                msg!("The market and OpenOrders accounts should belong to the serum program of the pool.");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            if open_orders_keys.contains(&open_orders.key) {
+                msg!("OpenOrders accounts should only be given once");
+                return Err(ProgramError::InvalidArgument);
+            }
+            let open_orders_data = open_orders.data.borrow();
+            if open_orders_data.get(13..45) != Some(&market.key.to_bytes()[..])
+                || open_orders_data.get(45..77) != Some(&pool_key.to_bytes()[..])
+            {
+                msg!("The OpenOrders account should belong to the pool on the given market");
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let read_u64 = |offset: usize| {
+                open_orders_data
+                    .get(offset..offset + 8)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u64::from_le_bytes)
+                    .ok_or(ProgramError::InvalidAccountData)
+            };
+            let free_coin = read_u64(77)?;
+            let total_coin = read_u64(85)?;
+            let free_pc = read_u64(93)?;
+            let total_pc = read_u64(101)?;
+            if total_coin == 0 && total_pc == 0 {
+                continue;
+            }
+            if free_coin != total_coin || free_pc != total_pc {
+                msg!("The pool has orders on the book. No buy-outs are possible for now. Try again later.");
+                return Err(BonfidaBotError::PendingOrders.into());
+            }
+            open_orders_keys.push(open_orders.key);
+
+            let market_data = market.data.borrow();
+            for &(mint_offset, amount) in &[(53, free_coin), (85, free_pc)] {
+                if amount == 0 {
+                    continue;
+                }
+                let mint = market_data
+                    .get(mint_offset..mint_offset + 32)
+                    .map(Pubkey::new)
+                    .ok_or(ProgramError::InvalidAccountData)?;
+                let index = pool_assets
+                    .iter()
+                    .position(|asset| asset.mint_address == mint)
+                    .ok_or_else(|| {
+                        msg!("The unsettled funds do not match a pool asset. Settle them first.");
+                        BonfidaBotError::MarketAssetMismatch
+                    })?;
+                amounts[index] = amounts[index]
+                    .checked_add(amount)
+                    .ok_or(BonfidaBotError::Overflow)?;
+            }
+        }
+        if open_orders_keys.len() != pending_orders {
+            msg!("The pool has one or more pending orders. Their OpenOrders accounts should be provided, or settled first.");
+            return Err(BonfidaBotError::PendingOrders.into());
+        }
+        Ok(amounts)
+    }
+
+    pub fn process_collect_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let mint_account = next_account_info(accounts_iter)?;
+
+        check_pool_key(program_id, pool_account.key, &pool_seed)?;
+
+        let pool_mint_key =
+            Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+        if pool_mint_key != *mint_account.key {
+            msg!("Provided mint account is invalid.");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        let collected_fees =
+            Self::accrue_management_fees(mint_account, &mut pool_header, current_timestamp)?;
+        if collected_fees == 0 {
+            msg!("There are currently no fees to collect");
+            return Err(BonfidaBotError::NoFeesToCollect.into());
+        }
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        Ok(())
+    }
+
+    pub fn process_claim_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let spl_token_account = next_account_info(accounts_iter)?;
+        if spl_token_account.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let mint_account = next_account_info(accounts_iter)?;
+        let recipient_pt_account = next_account_info(accounts_iter)?;
+
+        check_pool_key(program_id, pool_account.key, &pool_seed)?;
+
+        let pool_mint_key =
+            Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+        if pool_mint_key != *mint_account.key {
+            msg!("Provided mint account is invalid.");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        let recipient = get_fee_recipient(&pool_header, &pool_mint_key, recipient_pt_account)?;
+
+        // The claim includes the management fees accrued up to now
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        Self::accrue_management_fees(mint_account, &mut pool_header, current_timestamp)?;
+
+        let amount = owed_fees(pool_header.accrued_fees, &pool_header.claimed_fees)?[recipient];
+        if amount == 0 {
+            msg!("There are currently no fees to claim");
+            return Err(BonfidaBotError::NoFeesToCollect.into());
+        }
+        pool_header.claimed_fees[recipient] = pool_header.claimed_fees[recipient]
+            .checked_add(amount)
+            .ok_or(BonfidaBotError::Overflow)?;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        let instruction = mint_to(
+            spl_token_account.key,
+            mint_account.key,
+            recipient_pt_account.key,
+            pool_account.key,
+            &[],
+            amount,
+        )?;
+        invoke_signed(
+            &instruction,
+            &[
+                spl_token_account.clone(),
+                mint_account.clone(),
+                recipient_pt_account.clone(),
+                pool_account.clone(),
+            ],
+            &[&[&pool_seed]],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn process_request_redeem(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        // The amount of pooltokens wished to be redeemed
+        pool_token_amount: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let system_program_account = next_account_info(accounts_iter)?;
+        let rent_sysvar_account = next_account_info(accounts_iter)?;
+        let spl_token_account = next_account_info(accounts_iter)?;
+        if spl_token_account.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let associated_token_program_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let mint_account = next_account_info(accounts_iter)?;
+        let redeem_request_account = next_account_info(accounts_iter)?;
+        let escrow_account = next_account_info(accounts_iter)?;
+        let source_pool_token_owner_account = next_account_info(accounts_iter)?;
+        let source_pool_token_account = next_account_info(accounts_iter)?;
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let pool_mint_key =
+            Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+        if pool_mint_key != *mint_account.key {
+            msg!("Provided mint account is invalid");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+        if !source_pool_token_owner_account.is_signer {
+            msg!("Source pooltoken account owner should be a signer.");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *pool_account.owner != *program_id {
+            msg!("Program should own pool account");
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
+        }
+        if pool_token_amount == 0 {
+            msg!("A redeem request should be for a positive amount of pooltokens.");
+            return Err(BonfidaBotError::OperationTooSmall.into());
+        }
+
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        // The escrowed pooltokens still count towards the supply until they are burned
+        Self::check_signal_provider_redeem(
+            &pool_header,
+            &pool_mint_key,
+            source_pool_token_account,
+            pool_token_amount,
+            Self::total_pooltokens(mint_account, &pool_header)?,
+        )?;
+        let sequence = pool_header.redeem_queue_tail;
+        let (redeem_request_key, nonce) = find_redeem_request_key(program_id, &pool_seed, sequence);
+        if redeem_request_key != *redeem_request_account.key {
+            msg!("Provided redeem request account does not match the next sequence number of the queue.");
+            return Err(BonfidaBotError::InvalidRedeemRequest.into());
+        }
+        if get_associated_token_address(&redeem_request_key, &pool_mint_key) != *escrow_account.key
+        {
+            msg!("Provided redeem request pooltoken account is invalid.");
+            return Err(BonfidaBotError::InvalidRedeemRequest.into());
+        }
+
+        // Record the request
+        let rent = Rent::from_account_info(rent_sysvar_account)?;
+        let create_redeem_request_account = create_account(
+            &source_pool_token_owner_account.key,
+            &redeem_request_key,
+            rent.minimum_balance(RedeemRequest::LEN),
+            RedeemRequest::LEN as u64,
+            &program_id,
+        );
+        invoke_signed(
+            &create_redeem_request_account,
+            &[
+                system_program_account.clone(),
+                source_pool_token_owner_account.clone(),
+                redeem_request_account.clone(),
+            ],
+            &[&[
+                &pool_seed,
+                REDEEM_REQUEST_SEED,
+                &sequence.to_le_bytes(),
+                &[nonce],
+            ]],
+        )?;
+        RedeemRequest {
+            owner: *source_pool_token_owner_account.key,
+            pool_token_amount,
+            nonce,
+        }
+        .pack_into_slice(&mut redeem_request_account.data.borrow_mut());
+
+        // Escrow the pooltokens
+        let instruction = create_associated_token_account(
+            &source_pool_token_owner_account.key,
+            &redeem_request_key,
+            &pool_mint_key,
+        );
+        invoke(
+            &instruction,
+            &[
+                source_pool_token_owner_account.clone(),
+                escrow_account.clone(),
+                redeem_request_account.clone(),
+                mint_account.clone(),
+                system_program_account.clone(),
+                spl_token_account.clone(),
+                rent_sysvar_account.clone(),
+                associated_token_program_account.clone(),
+            ],
+        )?;
+        let instruction = transfer(
+            spl_token_account.key,
+            source_pool_token_account.key,
+            escrow_account.key,
+            source_pool_token_owner_account.key,
+            &[],
+            pool_token_amount,
+        )?;
+        invoke(
+            &instruction,
+            &[
+                source_pool_token_account.clone(),
+                escrow_account.clone(),
+                spl_token_account.clone(),
+                source_pool_token_owner_account.clone(),
+            ],
+        )?;
+
+        pool_header.redeem_queue_tail += 1;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        Ok(())
+    }
+
+    pub fn process_redeem_queue(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        number_of_requests: u8,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let spl_token_account = next_account_info(accounts_iter)?;
+        if spl_token_account.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let mint_account = next_account_info(accounts_iter)?;
+
+        let (mut pool_header, pool_assets) = {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let pool_state = PoolState::new(&mut pool_data)?;
+            let pool_assets: Vec<PoolAsset> = pool_state.assets().map(|(_, a)| a).collect();
+            (pool_state.header()?, pool_assets)
+        };
+        let nb_assets = pool_assets.len();
+
+        let mut pool_assets_accounts: Vec<&AccountInfo> = vec![];
+        for _ in 0..nb_assets {
+            pool_assets_accounts.push(next_account_info(accounts_iter)?)
+        }
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let pool_mint_key =
+            Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+        if pool_mint_key != *mint_account.key {
+            msg!("Provided mint account is invalid");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+        if *pool_account.owner != *program_id {
+            msg!("Program should own pool account");
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
+        }
+        for i in 0..nb_assets {
+            let pool_asset_key =
+                get_associated_token_address(&pool_account.key, &pool_assets[i].mint_address);
+            if pool_asset_key != *pool_assets_accounts[i].key {
+                msg!("Provided pool asset account is invalid");
+                return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
+            }
+        }
+        match pool_header.status {
+            PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) => {
+                msg!("The pool has one or more pending orders. Redeem requests are paid out once they are settled.");
+                return Err(BonfidaBotError::PendingOrders.into());
+            }
+            _ => (),
+        };
+        if number_of_requests == 0 || number_of_requests > MAX_REDEEM_BATCH_SIZE {
+            msg!(
+                "Between 1 and {} redeem requests can be paid out at once.",
+                MAX_REDEEM_BATCH_SIZE
+            );
+            return Err(BonfidaBotError::InvalidInstruction.into());
+        }
+        if pool_header.redeem_queue_tail - pool_header.redeem_queue_head < number_of_requests as u64
+        {
+            msg!("There are not enough queued redeem requests.");
+            return Err(BonfidaBotError::NotEnoughRedeemRequests.into());
+        }
+
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        Self::accrue_management_fees(mint_account, &mut pool_header, current_timestamp)?;
+
+        for _ in 0..number_of_requests {
+            let redeem_request_account = next_account_info(accounts_iter)?;
+            let escrow_account = next_account_info(accounts_iter)?;
+            let owner_account = next_account_info(accounts_iter)?;
+            let mut target_assets_accounts: Vec<&AccountInfo> = vec![];
+            for _ in 0..nb_assets {
+                target_assets_accounts.push(next_account_info(accounts_iter)?)
+            }
+
+            // The request has to be the head of the queue
+            if *redeem_request_account.owner != *program_id {
+                msg!("Program should own the redeem request account");
+                return Err(BonfidaBotError::InvalidRedeemRequest.into());
+            }
+            let redeem_request = RedeemRequest::unpack(&redeem_request_account.data.borrow())?;
+            let sequence = pool_header.redeem_queue_head.to_le_bytes();
+            let redeem_request_seeds: &[&[u8]] = &[
+                &pool_seed,
+                REDEEM_REQUEST_SEED,
+                &sequence,
+                &[redeem_request.nonce],
+            ];
+            let redeem_request_key =
+                Pubkey::create_program_address(redeem_request_seeds, &program_id)?;
+            if redeem_request_key != *redeem_request_account.key {
+                msg!("Provided redeem request account is not the next one in the queue.");
+                return Err(BonfidaBotError::InvalidRedeemRequest.into());
+            }
+            if get_associated_token_address(&redeem_request_key, &pool_mint_key)
+                != *escrow_account.key
+            {
+                msg!("Provided redeem request pooltoken account is invalid.");
+                return Err(BonfidaBotError::InvalidRedeemRequest.into());
+            }
+            if redeem_request.owner != *owner_account.key {
+                msg!("Provided redeem request owner account is invalid.");
+                return Err(BonfidaBotError::InvalidRedeemRequest.into());
+            }
+
+            // Every request is paid out at the current value of the pool
+            let total_pooltokens = Self::total_pooltokens(mint_account, &pool_header)?;
+            let mut pool_asset_amounts = Vec::with_capacity(nb_assets);
+            for i in 0..nb_assets {
+                pool_asset_amounts
+                    .push(Account::unpack(&pool_assets_accounts[i].data.borrow())?.amount);
+            }
+            let amounts = compute_redeem(
+                redeem_request.pool_token_amount,
+                total_pooltokens,
+                &pool_asset_amounts,
+            )?;
+
+            for i in 0..nb_assets {
+                let target_asset_key = get_associated_token_address(
+                    &redeem_request.owner,
+                    &pool_assets[i].mint_address,
+                );
+                if target_asset_key != *target_assets_accounts[i].key {
+                    msg!("Provided redeem request target asset account is invalid");
+                    return Err(BonfidaBotError::InvalidRedeemRequest.into());
+                }
+
+                let amount = amounts[i];
+                if amount == 0 {
+                    continue;
+                }
+                let instruction = transfer(
+                    spl_token_account.key,
+                    pool_assets_accounts[i].key,
+                    target_assets_accounts[i].key,
+                    pool_account.key,
+                    &[],
+                    amount,
+                )?;
+                invoke_signed(
+                    &instruction,
+                    &[
+                        spl_token_account.clone(),
+                        pool_assets_accounts[i].clone(),
+                        target_assets_accounts[i].clone(),
+                        pool_account.clone(),
+                    ],
+                    &[&[&pool_seed]],
+                )?;
+            }
+
+            // Burn the escrowed pooltokens and close the request accounts
+            let instruction = burn(
+                spl_token_account.key,
+                escrow_account.key,
+                mint_account.key,
+                &redeem_request_key,
+                &[],
+                redeem_request.pool_token_amount,
+            )?;
+            invoke_signed(
+                &instruction,
+                &[
+                    spl_token_account.clone(),
+                    escrow_account.clone(),
+                    mint_account.clone(),
+                    redeem_request_account.clone(),
+                ],
+                &[redeem_request_seeds],
+            )?;
+            let instruction = close_account(
+                spl_token_account.key,
+                escrow_account.key,
+                owner_account.key,
+                &redeem_request_key,
+                &[],
+            )?;
+            invoke_signed(
+                &instruction,
+                &[
+                    spl_token_account.clone(),
+                    escrow_account.clone(),
+                    owner_account.clone(),
+                    redeem_request_account.clone(),
+                ],
+                &[redeem_request_seeds],
+            )?;
+            let refund = owner_account
+                .lamports()
+                .checked_add(redeem_request_account.lamports())
+                .ok_or(BonfidaBotError::Overflow)?;
+            **owner_account.lamports.borrow_mut() = refund;
+            **redeem_request_account.lamports.borrow_mut() = 0;
+            fill_slice(&mut redeem_request_account.data.borrow_mut(), 0u8);
+
+            pool_header.redeem_queue_head += 1;
+        }
+
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+        // Queued deposits still need the pool assets to be refunded
+        if Self::total_pooltokens(mint_account, &pool_header)? == 0
+            && pool_header.deposit_queue_head == pool_header.deposit_queue_tail
+        {
+            // Reset the pool data, keeping the pool header mostly intact to preserve pool seeds
+            PoolState::new(&mut pool_account.data.borrow_mut())?.reset()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn process_request_deposit(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        // The exact amount of pooltokens wished to be bought, fees included
+        pool_token_amount: u64,
+        expiry_timestamp: u64,
+        // The amounts of each pool asset to escrow
+        asset_amounts: Vec<u64>,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let system_program_account = next_account_info(accounts_iter)?;
+        let rent_sysvar_account = next_account_info(accounts_iter)?;
+        let spl_token_account = next_account_info(accounts_iter)?;
+        if spl_token_account.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let associated_token_program_account = next_account_info(accounts_iter)?;
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let mint_account = next_account_info(accounts_iter)?;
+        let deposit_request_account = next_account_info(accounts_iter)?;
+        let source_owner_account = next_account_info(accounts_iter)?;
+
+        let (mut pool_header, pool_assets) = {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let pool_state = PoolState::new(&mut pool_data)?;
+            let pool_assets: Vec<PoolAsset> = pool_state.assets().map(|(_, a)| a).collect();
+            (pool_state.header()?, pool_assets)
+        };
+        let nb_assets = pool_assets.len();
+
+        let mut asset_mint_accounts: Vec<&AccountInfo> = vec![];
+        let mut source_assets_accounts: Vec<&AccountInfo> = vec![];
+        let mut escrow_accounts: Vec<&AccountInfo> = vec![];
+        for _ in 0..nb_assets {
+            asset_mint_accounts.push(next_account_info(accounts_iter)?)
+        }
+        for _ in 0..nb_assets {
+            source_assets_accounts.push(next_account_info(accounts_iter)?)
+        }
+        for _ in 0..nb_assets {
+            escrow_accounts.push(next_account_info(accounts_iter)?)
+        }
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let pool_mint_key =
+            Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+        if pool_mint_key != *mint_account.key {
+            msg!("Provided mint account is invalid");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+        if !source_owner_account.is_signer {
+            msg!("Source token account owner should be a signer.");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *pool_account.owner != *program_id {
+            msg!("Program should own pool account");
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
+        }
+        if pool_token_amount == 0 {
+            msg!("A deposit request should be for a positive amount of pooltokens.");
+            return Err(BonfidaBotError::OperationTooSmall.into());
+        }
+        if asset_amounts.len() != nb_assets {
+            msg!("One amount should be provided for each pool asset.");
+            return Err(BonfidaBotError::InvalidInstruction.into());
+        }
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        if expiry_timestamp <= current_timestamp {
+            msg!("A deposit request should expire in the future.");
+            return Err(BonfidaBotError::InvalidDepositRequest.into());
+        }
+
+        let sequence = pool_header.deposit_queue_tail;
+        let (deposit_request_key, nonce) =
+            find_deposit_request_key(program_id, &pool_seed, sequence);
+        if deposit_request_key != *deposit_request_account.key {
+            msg!("Provided deposit request account does not match the next sequence number of the queue.");
+            return Err(BonfidaBotError::InvalidDepositRequest.into());
+        }
+        for i in 0..nb_assets {
+            if pool_assets[i].mint_address != *asset_mint_accounts[i].key {
+                msg!("Provided asset mint account is invalid");
+                return Err(BonfidaBotError::InvalidDepositRequest.into());
+            }
+            if get_associated_token_address(&deposit_request_key, asset_mint_accounts[i].key)
+                != *escrow_accounts[i].key
+            {
+                msg!("Provided deposit request asset account is invalid.");
+                return Err(BonfidaBotError::InvalidDepositRequest.into());
+            }
+        }
+
+        // Record the request
+        let rent = Rent::from_account_info(rent_sysvar_account)?;
+        let deposit_request_size = DepositRequest::size(nb_assets);
+        let create_deposit_request_account = create_account(
+            &source_owner_account.key,
+            &deposit_request_key,
+            rent.minimum_balance(deposit_request_size),
+            deposit_request_size as u64,
+            &program_id,
+        );
+        invoke_signed(
+            &create_deposit_request_account,
+            &[
+                system_program_account.clone(),
+                source_owner_account.clone(),
+                deposit_request_account.clone(),
+            ],
+            &[&[
+                &pool_seed,
+                DEPOSIT_REQUEST_SEED,
+                &sequence.to_le_bytes(),
+                &[nonce],
+            ]],
+        )?;
+        DepositRequest {
+            owner: *source_owner_account.key,
+            pool_token_amount,
+            expiry_timestamp,
+            nonce,
+            escrowed_mints: pool_assets.iter().map(|a| a.mint_address).collect(),
+        }
+        .pack_into_slice(&mut deposit_request_account.data.borrow_mut());
+
+        // Escrow the basket
+        for i in 0..nb_assets {
+            let instruction = create_associated_token_account(
+                &source_owner_account.key,
+                &deposit_request_key,
+                asset_mint_accounts[i].key,
+            );
+            invoke(
+                &instruction,
+                &[
+                    source_owner_account.clone(),
+                    escrow_accounts[i].clone(),
+                    deposit_request_account.clone(),
+                    asset_mint_accounts[i].clone(),
+                    system_program_account.clone(),
+                    spl_token_account.clone(),
+                    rent_sysvar_account.clone(),
+                    associated_token_program_account.clone(),
+                ],
+            )?;
+
+            let amount = asset_amounts[i];
+            if amount == 0 {
+                continue;
+            }
+            let instruction = transfer(
+                spl_token_account.key,
+                source_assets_accounts[i].key,
+                escrow_accounts[i].key,
+                source_owner_account.key,
+                &[],
+                amount,
+            )?;
+            invoke(
+                &instruction,
+                &[
+                    source_assets_accounts[i].clone(),
+                    escrow_accounts[i].clone(),
+                    spl_token_account.clone(),
+                    source_owner_account.clone(),
+                ],
+            )?;
+        }
+
+        pool_header.deposit_queue_tail += 1;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        Ok(())
+    }
+
+    pub fn process_deposit_queue(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        number_of_requests: u8,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let spl_token_account = next_account_info(accounts_iter)?;
+        if spl_token_account.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let mint_account = next_account_info(accounts_iter)?;
+
+        let (mut pool_header, pool_assets) = {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let pool_state = PoolState::new(&mut pool_data)?;
+            let pool_assets: Vec<PoolAsset> = pool_state.assets().map(|(_, a)| a).collect();
+            (pool_state.header()?, pool_assets)
+        };
+        let nb_assets = pool_assets.len();
+
+        let mut pool_assets_accounts: Vec<&AccountInfo> = vec![];
+        for _ in 0..nb_assets {
+            pool_assets_accounts.push(next_account_info(accounts_iter)?)
+        }
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let pool_mint_key =
+            Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+        if pool_mint_key != *mint_account.key {
+            msg!("Provided mint account is invalid");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+        if *pool_account.owner != *program_id {
+            msg!("Program should own pool account");
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
+        }
+        for i in 0..nb_assets {
+            let pool_asset_key =
+                get_associated_token_address(&pool_account.key, &pool_assets[i].mint_address);
+            if pool_asset_key != *pool_assets_accounts[i].key {
+                msg!("Provided pool asset account is invalid");
+                return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
+            }
+        }
+        if number_of_requests == 0 || number_of_requests > MAX_DEPOSIT_BATCH_SIZE {
+            msg!(
+                "Between 1 and {} deposit requests can be processed at once.",
+                MAX_DEPOSIT_BATCH_SIZE
+            );
+            return Err(BonfidaBotError::InvalidInstruction.into());
+        }
+        if pool_header.deposit_queue_tail - pool_header.deposit_queue_head
+            < number_of_requests as u64
+        {
+            msg!("There are not enough queued deposit requests.");
+            return Err(BonfidaBotError::NotEnoughDepositRequests.into());
+        }
+
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        Self::accrue_management_fees(mint_account, &mut pool_header, current_timestamp)?;
+
+        for _ in 0..number_of_requests {
+            let deposit_request_account = next_account_info(accounts_iter)?;
+            let owner_account = next_account_info(accounts_iter)?;
+            let target_pool_token_account = next_account_info(accounts_iter)?;
+
+            // The request has to be the head of the queue
+            if *deposit_request_account.owner != *program_id {
+                msg!("Program should own the deposit request account");
+                return Err(BonfidaBotError::InvalidDepositRequest.into());
+            }
+            let deposit_request = DepositRequest::unpack(&deposit_request_account.data.borrow())?;
+            let sequence = pool_header.deposit_queue_head.to_le_bytes();
+            let deposit_request_seeds: &[&[u8]] = &[
+                &pool_seed,
+                DEPOSIT_REQUEST_SEED,
+                &sequence,
+                &[deposit_request.nonce],
+            ];
+            let deposit_request_key =
+                Pubkey::create_program_address(deposit_request_seeds, &program_id)?;
+            if deposit_request_key != *deposit_request_account.key {
+                msg!("Provided deposit request account is not the next one in the queue.");
+                return Err(BonfidaBotError::InvalidDepositRequest.into());
+            }
+            if deposit_request.owner != *owner_account.key {
+                msg!("Provided deposit request owner account is invalid.");
+                return Err(BonfidaBotError::InvalidDepositRequest.into());
+            }
+            if get_associated_token_address(&deposit_request.owner, &pool_mint_key)
+                != *target_pool_token_account.key
+            {
+                msg!("Provided deposit request target pooltoken account is invalid.");
+                return Err(BonfidaBotError::InvalidDepositRequest.into());
+            }
+
+            // The escrows follow the pool assets at the time of the request. Assets added to the
+            // pool since then have no escrow, and the escrows of the assets the pool dropped are
+            // refunded in full.
+            let nb_escrows = deposit_request.escrowed_mints.len();
+            let mut escrow_accounts: Vec<&AccountInfo> = vec![];
+            let mut owner_assets_accounts: Vec<&AccountInfo> = vec![];
+            for _ in 0..nb_escrows {
+                escrow_accounts.push(next_account_info(accounts_iter)?)
+            }
+            for _ in 0..nb_escrows {
+                owner_assets_accounts.push(next_account_info(accounts_iter)?)
+            }
+            let mut pool_asset_indices = Vec::with_capacity(nb_escrows);
+            let mut escrow_balances = Vec::with_capacity(nb_escrows);
+            let mut escrowed_amounts = vec![0; nb_assets];
+            for (k, mint) in deposit_request.escrowed_mints.iter().enumerate() {
+                if get_associated_token_address(&deposit_request_key, mint)
+                    != *escrow_accounts[k].key
+                    || get_associated_token_address(&deposit_request.owner, mint)
+                        != *owner_assets_accounts[k].key
+                {
+                    msg!("Provided deposit request asset account is invalid.");
+                    return Err(BonfidaBotError::InvalidDepositRequest.into());
+                }
+                let balance = Account::unpack(&escrow_accounts[k].data.borrow())?.amount;
+                let pool_asset_index = pool_assets.iter().position(|a| a.mint_address == *mint);
+                if let Some(i) = pool_asset_index {
+                    escrowed_amounts[i] = balance;
+                }
+                pool_asset_indices.push(pool_asset_index);
+                escrow_balances.push(balance);
+            }
+
+            let total_pooltokens = Self::total_pooltokens(mint_account, &pool_header)?;
+            let deposit = if deposit_request.expiry_timestamp < current_timestamp {
+                msg!("The deposit request has expired and is refunded.");
+                None
+            } else if total_pooltokens == 0 {
+                msg!("The pool has been emptied, the deposit request is refunded.");
+                None
+            } else {
+                match pool_header.status {
+                    PoolStatus::Unlocked => (),
+                    PoolStatus::Locked | PoolStatus::LockedPendingOrder(_) => {
+                        msg!("The signal provider has currently locked the pool. Deposit requests are processed once it is unlocked.");
+                        return Err(BonfidaBotError::LockedOperation.into());
+                    }
+                    PoolStatus::PendingOrder(_) => {
+                        msg!("The pool has one or more pending orders. Deposit requests are processed once they are settled.");
+                        return Err(BonfidaBotError::PendingOrders.into());
+                    }
+                    PoolStatus::Uninitialized => unreachable!(),
+                };
+                // Every request is processed at the current value of the pool
+                let mut pool_asset_amounts = Vec::with_capacity(nb_assets);
+                for i in 0..nb_assets {
+                    pool_asset_amounts
+                        .push(Account::unpack(&pool_assets_accounts[i].data.borrow())?.amount);
+                }
+                let deposit = compute_queued_deposit(
+                    deposit_request.pool_token_amount,
+                    total_pooltokens,
+                    &pool_asset_amounts,
+                    &escrowed_amounts,
+                    pool_header.fee_ratio,
+                )?;
+                if deposit.is_none() {
+                    msg!("The escrowed basket does not cover the requested pooltokens, the deposit request is refunded.");
+                }
+                deposit
+            };
+
+            // Move the basket to the pool and refund the rest, then close the escrow accounts
+            for k in 0..nb_escrows {
+                let (pool_asset_account, deposited) = match (&deposit, pool_asset_indices[k]) {
+                    (Some(deposit), Some(i)) => {
+                        (Some(pool_assets_accounts[i]), deposit.asset_amounts[i])
+                    }
+                    _ => (None, 0),
+                };
+                let refunded = escrow_balances[k] - deposited;
+                for (target_account, amount) in pool_asset_account
+                    .map(|account| (account, deposited))
+                    .into_iter()
+                    .chain(once((owner_assets_accounts[k], refunded)))
+                {
+                    if amount == 0 {
+                        continue;
+                    }
+                    let instruction = transfer(
+                        spl_token_account.key,
+                        escrow_accounts[k].key,
+                        target_account.key,
+                        &deposit_request_key,
+                        &[],
+                        amount,
+                    )?;
+                    invoke_signed(
+                        &instruction,
+                        &[
+                            spl_token_account.clone(),
+                            escrow_accounts[k].clone(),
+                            target_account.clone(),
+                            deposit_request_account.clone(),
+                        ],
+                        &[deposit_request_seeds],
+                    )?;
+                }
+                let instruction = close_account(
+                    spl_token_account.key,
+                    escrow_accounts[k].key,
+                    owner_account.key,
+                    &deposit_request_key,
+                    &[],
+                )?;
+                invoke_signed(
+                    &instruction,
+                    &[
+                        spl_token_account.clone(),
+                        escrow_accounts[k].clone(),
+                        owner_account.clone(),
+                        deposit_request_account.clone(),
+                    ],
+                    &[deposit_request_seeds],
+                )?;
+            }
+
+            if let Some(deposit) = deposit {
+                pool_header.accrued_fees = pool_header
+                    .accrued_fees
+                    .checked_add(deposit.fee)
+                    .ok_or(BonfidaBotError::Overflow)?;
+                let instruction = mint_to(
+                    spl_token_account.key,
+                    &pool_mint_key,
+                    target_pool_token_account.key,
+                    &pool_account.key,
+                    &[],
+                    deposit.pool_tokens,
+                )?;
+                invoke_signed(
+                    &instruction,
+                    &[
+                        spl_token_account.clone(),
+                        mint_account.clone(),
+                        target_pool_token_account.clone(),
+                        pool_account.clone(),
+                    ],
+                    &[&[&pool_seed]],
+                )?;
+            }
+
+            // Close the request account
+            let refund = owner_account
+                .lamports()
+                .checked_add(deposit_request_account.lamports())
+                .ok_or(BonfidaBotError::Overflow)?;
+            **owner_account.lamports.borrow_mut() = refund;
+            **deposit_request_account.lamports.borrow_mut() = 0;
+            fill_slice(&mut deposit_request_account.data.borrow_mut(), 0u8);
+
+            pool_header.deposit_queue_head += 1;
+        }
+
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+        // The pool can be emptied by redeems while deposits are queued
+        if Self::total_pooltokens(mint_account, &pool_header)? == 0
+            && pool_header.deposit_queue_head == pool_header.deposit_queue_tail
+        {
+            // Reset the pool data, keeping the pool header mostly intact to preserve pool seeds
+            PoolState::new(&mut pool_account.data.borrow_mut())?.reset()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn process_post_bond(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        amount: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let system_program_account = next_account_info(accounts_iter)?;
+        let rent_sysvar_account = next_account_info(accounts_iter)?;
+        let spl_token_account = next_account_info(accounts_iter)?;
+        if spl_token_account.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let associated_token_program_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let fida_mint_account = next_account_info(accounts_iter)?;
+        let bond_account = next_account_info(accounts_iter)?;
+        let escrow_account = next_account_info(accounts_iter)?;
+        let signal_provider_account = next_account_info(accounts_iter)?;
+        let source_fida_account = next_account_info(accounts_iter)?;
+        let pool_fida_account = next_account_info(accounts_iter)?;
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        check_signal_provider(&pool_header, signal_provider_account, true, accounts)?;
+        check_bond_accounts(
+            program_id,
+            &pool_seed,
+            fida_mint_account.key,
+            bond_account,
+            escrow_account,
+        )?;
+        if get_associated_token_address(pool_account.key, fida_mint_account.key)
+            != *pool_fida_account.key
+        {
+            msg!("Provided pool FIDA account is invalid");
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
+        }
+        if amount == 0 {
+            msg!("A bond should be posted for a positive amount of FIDA.");
+            return Err(BonfidaBotError::OperationTooSmall.into());
+        }
+        let bond_amount = pool_header
+            .bond_amount
+            .checked_add(amount)
+            .ok_or(BonfidaBotError::Overflow)?;
+        if bond_amount < MIN_BOND_AMOUNT {
+            msg!("The bond should be of at least {:?} FIDA.", MIN_BOND_AMOUNT);
+            return Err(BonfidaBotError::NotEnoughFIDA.into());
+        }
+
+        // Reserve the pool asset slot which receives the bond if it is slashed
+        {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let mut pool_state = PoolState::new(&mut pool_data)?;
+            if pool_state.find_asset(fida_mint_account.key).is_none() {
+                let slot = pool_state.free_asset_slot().ok_or_else(|| {
+                    msg!("The pool has no free asset slot for FIDA.");
+                    BonfidaBotError::InvalidAssetIndex
+                })?;
+                PoolAsset {
+                    mint_address: *fida_mint_account.key,
+                }
+                .pack_into_slice(pool_state.asset_mut(slot)?);
+            }
+        }
+        if pool_fida_account.data_is_empty() {
+            let instruction = create_associated_token_account(
+                &signal_provider_account.key,
+                &pool_account.key,
+                &fida_mint_account.key,
+            );
+            invoke(
+                &instruction,
+                &[
+                    signal_provider_account.clone(),
+                    pool_fida_account.clone(),
+                    pool_account.clone(),
+                    fida_mint_account.clone(),
+                    system_program_account.clone(),
+                    spl_token_account.clone(),
+                    rent_sysvar_account.clone(),
+                    associated_token_program_account.clone(),
+                ],
+            )?;
+        }
+
+        // Escrow the FIDA
+        if escrow_account.data_is_empty() {
+            let instruction = create_associated_token_account(
+                &signal_provider_account.key,
+                &bond_account.key,
+                &fida_mint_account.key,
+            );
+            invoke(
+                &instruction,
+                &[
+                    signal_provider_account.clone(),
+                    escrow_account.clone(),
+                    bond_account.clone(),
+                    fida_mint_account.clone(),
+                    system_program_account.clone(),
+                    spl_token_account.clone(),
+                    rent_sysvar_account.clone(),
+                    associated_token_program_account.clone(),
                 ],
-                &[&[&pool_seed]],
             )?;
         }
-
-        // Burn the redeemed pooltokens
-        let instruction = burn(
+        let instruction = transfer(
             spl_token_account.key,
-            &source_pool_token_account.key,
-            mint_account.key,
-            &source_pool_token_owner_account.key,
+            source_fida_account.key,
+            escrow_account.key,
+            signal_provider_account.key,
             &[],
-            pool_token_amount,
+            amount,
         )?;
-
         invoke(
             &instruction,
             &[
+                source_fida_account.clone(),
+                escrow_account.clone(),
                 spl_token_account.clone(),
-                source_pool_token_account.clone(),
-                mint_account.clone(),
-                source_pool_token_owner_account.clone(),
+                signal_provider_account.clone(),
             ],
         )?;
 
-        if pool_token_amount == total_pooltokens {
-            // Reset the pool data, keeping the pool header mostly intact to preserve pool seeds
-            fill_slice(&mut pool_account.data.borrow_mut()[PoolHeader::LEN..], 0u8);
-            pool_header.status = PoolStatus::Uninitialized;
-            pool_header.pack_into_slice(&mut pool_account.data.borrow_mut()[..PoolHeader::LEN]);
+        pool_header.bond_amount = bond_amount;
+        pool_header.bond_unlock_timestamp = 0;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        Ok(())
+    }
+
+    pub fn process_request_bond_withdrawal(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let signal_provider_account = next_account_info(accounts_iter)?;
+
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        check_signal_provider(&pool_header, signal_provider_account, true, accounts)?;
+        if pool_header.bond_amount == 0 {
+            msg!("The pool has no bond to withdraw.");
+            return Err(BonfidaBotError::NoBond.into());
         }
 
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        pool_header.bond_unlock_timestamp = current_timestamp
+            .checked_add(BOND_NOTICE_PERIOD)
+            .ok_or(BonfidaBotError::Overflow)?;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
         Ok(())
     }
 
-    pub fn process_collect_fees(
+    pub fn process_withdraw_bond(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         pool_seed: [u8; 32],
     ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
+
         let spl_token_account = next_account_info(accounts_iter)?;
         if spl_token_account.key != &spl_token::id() {
             msg!("Incorrect spl token program provided");
@@ -1227,145 +2498,398 @@ impl Processor {
         }
         let clock_sysvar_account = next_account_info(accounts_iter)?;
         let pool_account = next_account_info(accounts_iter)?;
+        let bond_account = next_account_info(accounts_iter)?;
+        let escrow_account = next_account_info(accounts_iter)?;
+        let signal_provider_account = next_account_info(accounts_iter)?;
+        let target_fida_account = next_account_info(accounts_iter)?;
 
-        let mint_account = next_account_info(accounts_iter)?;
-        let signal_provider_pt_account = next_account_info(accounts_iter)?;
-        let bonfida_fee_pt_account = next_account_info(accounts_iter)?;
-        let bonfida_bnb_pt_account = next_account_info(accounts_iter)?;
-
-        check_pool_key(program_id, pool_account.key, &pool_seed)?;
-
-        let pool_mint_key =
-            Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
-        if pool_mint_key != *mint_account.key {
-            msg!("Provided mint account is invalid.");
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        let mut pool_header = PoolHeader::unpack(&pool_account.data.borrow()[..PoolHeader::LEN])?;
-
-        let signal_provider_pt_key =
-            get_associated_token_address(&pool_header.signal_provider, &pool_mint_key);
-        let bonfida_fee_pt_key =
-            get_associated_token_address(&Pubkey::from_str(BONFIDA_FEE).unwrap(), &pool_mint_key);
-        let bonfida_bnb_pt_key =
-            get_associated_token_address(&Pubkey::from_str(BONFIDA_BNB).unwrap(), &pool_mint_key);
-
-        if signal_provider_pt_account.key != &signal_provider_pt_key {
-            msg!("The provided signal provider pool token account is invalid.");
-            return Err(ProgramError::InvalidArgument);
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        if *pool_account.owner != *program_id {
+            msg!("Program should own pool account");
+            return Err(BonfidaBotError::InvalidPoolOwner.into());
         }
-
-        if bonfida_fee_pt_account.key != &bonfida_fee_pt_key {
-            msg!("The provided bonfida fee pool token account is invalid.");
-            return Err(ProgramError::InvalidArgument);
+        // The bond of a reset pool can still be withdrawn
+        let mut pool_header =
+            PoolState::new(&mut pool_account.data.borrow_mut())?.header_unchecked()?;
+        check_signal_provider(&pool_header, signal_provider_account, true, accounts)?;
+        let bond_nonce = check_bond_accounts(
+            program_id,
+            &pool_seed,
+            &Pubkey::from_str(FIDA_MINT).unwrap(),
+            bond_account,
+            escrow_account,
+        )?;
+        if pool_header.bond_amount == 0 {
+            msg!("The pool has no bond to withdraw.");
+            return Err(BonfidaBotError::NoBond.into());
         }
 
-        if bonfida_bnb_pt_account.key != &bonfida_bnb_pt_key {
-            msg!("The provided bonfida buy and burn pool token account is invalid.");
-            return Err(ProgramError::InvalidArgument);
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        match pool_header.status {
+            PoolStatus::Uninitialized => {}
+            PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) => {
+                msg!("The bond cannot be withdrawn while the pool has pending orders.");
+                return Err(BonfidaBotError::PendingOrders.into());
+            }
+            _ => {
+                if pool_header.bond_unlock_timestamp == 0
+                    || current_timestamp < pool_header.bond_unlock_timestamp
+                {
+                    msg!("The bond can only be withdrawn once the notice period is over.");
+                    return Err(BonfidaBotError::BondLocked.into());
+                }
+            }
         }
 
-        let current_timestamp =
-            Clock::from_account_info(clock_sysvar_account)?.unix_timestamp as u64;
-        let fee_cycles_to_collect = (current_timestamp - pool_header.last_fee_collection_timestamp)
-            / pool_header.fee_collection_period;
-
-        if fee_cycles_to_collect == 0 {
-            msg!("There are currently no fees to collect");
-            return Err(BonfidaBotError::LockedOperation.into());
-        }
-
-        // 2**-16 = 1.52587890625e-5_f32
-        // let feeless_ratio_u16 = (((!pool_header.fee_ratio) as f32 * 1.52587890625e-5_f32).powi(
-        //     fee_cycles_to_collect
-        //         .try_into()
-        //         .map_err(|_| BonfidaBotError::Overflow)?,
-        // ) * 65536.) as u16;
-        let feeless_ratio_u16 =
-            pow_fixedpoint_u16(!pool_header.fee_ratio as u32, fee_cycles_to_collect) as u16;
-        let collect_ratio = (!feeless_ratio_u16) as u128;
-        let feeless_ratio = feeless_ratio_u16 as u128;
-        pool_header.last_fee_collection_timestamp +=
-            fee_cycles_to_collect * pool_header.fee_collection_period;
-
-        let total_pooltokens = Mint::unpack(&mint_account.data.borrow())?.supply as u128;
-
-        let tokens_to_mint = (collect_ratio * total_pooltokens / feeless_ratio) as u64;
-
-        // Mint the required amount of pooltokens to the signal provider
-        //
-        // Like with deposit, these will often not be minted in the quantity
-        // expected, unless it's always divisible by 4
-        let signal_provider_fee = tokens_to_mint / 2;
-        let mint_to_sp_instruction = mint_to(
+        // Return the escrowed FIDA and close the escrow account
+        let bond_seeds: &[&[u8]] = &[&pool_seed, BOND_SEED, &[bond_nonce]];
+        let escrow_amount = Account::unpack(&escrow_account.data.borrow())?.amount;
+        let instruction = transfer(
             spl_token_account.key,
-            &pool_mint_key,
-            signal_provider_pt_account.key,
-            &pool_account.key,
+            escrow_account.key,
+            target_fida_account.key,
+            bond_account.key,
             &[],
-            signal_provider_fee,
+            escrow_amount,
         )?;
-
         invoke_signed(
-            &mint_to_sp_instruction,
+            &instruction,
             &[
                 spl_token_account.clone(),
-                mint_account.clone(),
-                signal_provider_pt_account.clone(),
-                pool_account.clone(),
+                escrow_account.clone(),
+                target_fida_account.clone(),
+                bond_account.clone(),
             ],
-            &[&[&pool_seed]],
+            &[bond_seeds],
         )?;
-
-        // Mint the required amount of pooltokens to the bonfida fee account
-        let bonfida_fee = tokens_to_mint / 4;
-        let mint_to_bonfida_fee_instruction = mint_to(
+        let instruction = close_account(
             spl_token_account.key,
-            &pool_mint_key,
-            &bonfida_fee_pt_key,
-            &pool_account.key,
+            escrow_account.key,
+            signal_provider_account.key,
+            bond_account.key,
             &[],
-            bonfida_fee,
         )?;
-
         invoke_signed(
-            &mint_to_bonfida_fee_instruction,
+            &instruction,
             &[
                 spl_token_account.clone(),
-                mint_account.clone(),
-                bonfida_fee_pt_account.clone(),
-                pool_account.clone(),
+                escrow_account.clone(),
+                signal_provider_account.clone(),
+                bond_account.clone(),
             ],
-            &[&[&pool_seed]],
+            &[bond_seeds],
+        )?;
+
+        pool_header.bond_amount = 0;
+        pool_header.bond_unlock_timestamp = 0;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        Ok(())
+    }
+
+    pub fn process_slash_bond(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let system_program_account = next_account_info(accounts_iter)?;
+        let rent_sysvar_account = next_account_info(accounts_iter)?;
+        let spl_token_account = next_account_info(accounts_iter)?;
+        if spl_token_account.key != &spl_token::id() {
+            msg!("Incorrect spl token program provided");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let associated_token_program_account = next_account_info(accounts_iter)?;
+        let clock_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let fida_mint_account = next_account_info(accounts_iter)?;
+        let bond_account = next_account_info(accounts_iter)?;
+        let escrow_account = next_account_info(accounts_iter)?;
+        let pool_fida_account = next_account_info(accounts_iter)?;
+        let signal_provider_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        check_signal_provider(&pool_header, signal_provider_account, false, accounts)?;
+        let fida_mint_key = Pubkey::from_str(FIDA_MINT).unwrap();
+        if fida_mint_key != *fida_mint_account.key {
+            msg!("Provided FIDA mint account is invalid");
+            return Err(BonfidaBotError::InvalidMint.into());
+        }
+        let bond_nonce = check_bond_accounts(
+            program_id,
+            &pool_seed,
+            &fida_mint_key,
+            bond_account,
+            escrow_account,
         )?;
+        if get_associated_token_address(pool_account.key, &fida_mint_key) != *pool_fida_account.key
+        {
+            msg!("Provided pool FIDA account is invalid");
+            return Err(BonfidaBotError::InvalidPoolAssetAccount.into());
+        }
+        if pool_header.bond_amount == 0 {
+            msg!("The pool has no bond to slash.");
+            return Err(BonfidaBotError::NoBond.into());
+        }
+        match pool_header.status {
+            PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) => {}
+            _ => {
+                msg!("The bond can only be slashed while the pool has pending orders.");
+                return Err(BonfidaBotError::NotSlashable.into());
+            }
+        }
+
+        let current_timestamp: u64 = Clock::from_account_info(clock_sysvar_account)?
+            .unix_timestamp
+            .try_into()
+            .map_err(|_| BonfidaBotError::Overflow)?;
+        if pool_header.pending_order_timestamp == 0 {
+            msg!("The pool has no pending immediate or cancel order.");
+            return Err(BonfidaBotError::NotSlashable.into());
+        }
+        if current_timestamp
+            < pool_header
+                .pending_order_timestamp
+                .saturating_add(MAX_PENDING_ORDER_DURATION)
+        {
+            msg!("The pending orders have not been left long enough for the bond to be slashed.");
+            return Err(BonfidaBotError::NotSlashable.into());
+        }
 
-        // Mint the required amount of pooltokens to the bonfida fee account
-        let mint_to_bonfida_bnb_instruction = mint_to(
+        // The slashed FIDA becomes a pool asset. Posting the bond already reserves its slot and
+        // creates the pool FIDA account, they are only set up here when missing.
+        {
+            let mut pool_data = pool_account.data.borrow_mut();
+            let mut pool_state = PoolState::new(&mut pool_data)?;
+            if pool_state.find_asset(&fida_mint_key).is_none() {
+                let slot = pool_state.free_asset_slot().ok_or_else(|| {
+                    msg!("The pool has no free asset slot for FIDA.");
+                    BonfidaBotError::InvalidAssetIndex
+                })?;
+                PoolAsset {
+                    mint_address: fida_mint_key,
+                }
+                .pack_into_slice(pool_state.asset_mut(slot)?);
+            }
+        }
+        if pool_fida_account.data_is_empty() {
+            let instruction = create_associated_token_account(
+                &payer_account.key,
+                &pool_account.key,
+                &fida_mint_key,
+            );
+            invoke(
+                &instruction,
+                &[
+                    payer_account.clone(),
+                    pool_fida_account.clone(),
+                    pool_account.clone(),
+                    fida_mint_account.clone(),
+                    system_program_account.clone(),
+                    spl_token_account.clone(),
+                    rent_sysvar_account.clone(),
+                    associated_token_program_account.clone(),
+                ],
+            )?;
+        }
+        let bond_seeds: &[&[u8]] = &[&pool_seed, BOND_SEED, &[bond_nonce]];
+        let escrow_amount = Account::unpack(&escrow_account.data.borrow())?.amount;
+        let instruction = transfer(
             spl_token_account.key,
-            &pool_mint_key,
-            &bonfida_bnb_pt_key,
-            &pool_account.key,
+            escrow_account.key,
+            pool_fida_account.key,
+            bond_account.key,
             &[],
-            tokens_to_mint - bonfida_fee - signal_provider_fee,
+            escrow_amount,
         )?;
-
         invoke_signed(
-            &mint_to_bonfida_bnb_instruction,
+            &instruction,
             &[
                 spl_token_account.clone(),
-                mint_account.clone(),
-                bonfida_bnb_pt_account.clone(),
-                pool_account.clone(),
+                escrow_account.clone(),
+                pool_fida_account.clone(),
+                bond_account.clone(),
             ],
-            &[&[&pool_seed]],
+            &[bond_seeds],
         )?;
-
-        PoolHeader::pack(
-            pool_header,
-            &mut pool_account.data.borrow_mut()[..PoolHeader::LEN],
+        let instruction = close_account(
+            spl_token_account.key,
+            escrow_account.key,
+            signal_provider_account.key,
+            bond_account.key,
+            &[],
+        )?;
+        invoke_signed(
+            &instruction,
+            &[
+                spl_token_account.clone(),
+                escrow_account.clone(),
+                signal_provider_account.clone(),
+                bond_account.clone(),
+            ],
+            &[bond_seeds],
         )?;
 
+        pool_header.bond_amount = 0;
+        pool_header.bond_unlock_timestamp = 0;
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        Ok(())
+    }
+
+    pub fn process_add_trader(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        trader: Pubkey,
+        max_ratio_of_pool_assets_to_trade: u16,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let system_program_account = next_account_info(accounts_iter)?;
+        let rent_sysvar_account = next_account_info(accounts_iter)?;
+        let pool_account = next_account_info(accounts_iter)?;
+        let trader_account = next_account_info(accounts_iter)?;
+        let signal_provider_account = next_account_info(accounts_iter)?;
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        check_signal_provider(&pool_header, signal_provider_account, true, accounts)?;
+        if trader == Pubkey::default() || trader == pool_header.signal_provider {
+            msg!("The signal provider cannot be added as a trader.");
+            return Err(BonfidaBotError::InvalidTrader.into());
+        }
+        let (trader_key, nonce) = find_trader_key(
+            program_id,
+            &pool_seed,
+            &pool_header.signal_provider,
+            &trader,
+        );
+        if trader_key != *trader_account.key {
+            msg!("Provided trader account does not match the trader key.");
+            return Err(BonfidaBotError::InvalidTrader.into());
+        }
+
+        // Adding an existing trader updates its limits
+        if trader_account.data_is_empty() {
+            let rent = Rent::from_account_info(rent_sysvar_account)?;
+            let instruction = create_account(
+                &signal_provider_account.key,
+                &trader_key,
+                rent.minimum_balance(Trader::LEN),
+                Trader::LEN as u64,
+                &program_id,
+            );
+            invoke_signed(
+                &instruction,
+                &[
+                    system_program_account.clone(),
+                    signal_provider_account.clone(),
+                    trader_account.clone(),
+                ],
+                &[&[
+                    &pool_seed,
+                    TRADER_SEED,
+                    &pool_header.signal_provider.to_bytes(),
+                    &trader.to_bytes(),
+                    &[nonce],
+                ]],
+            )?;
+            pool_header.number_of_traders = pool_header
+                .number_of_traders
+                .checked_add(1)
+                .ok_or(BonfidaBotError::Overflow)?;
+            PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+        } else if trader_account.owner != program_id {
+            msg!("Program should own the trader account");
+            return Err(BonfidaBotError::InvalidTrader.into());
+        }
+        Trader {
+            trader,
+            max_ratio_of_pool_assets_to_trade,
+            nonce,
+        }
+        .pack_into_slice(&mut trader_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    pub fn process_remove_trader(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        trader: Pubkey,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let pool_account = next_account_info(accounts_iter)?;
+        let trader_account = next_account_info(accounts_iter)?;
+        let signal_provider_account = next_account_info(accounts_iter)?;
+
+        // Safety verifications
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        // Traders of an emptied pool can still be removed to recover the rent
+        let mut pool_header =
+            PoolState::new(&mut pool_account.data.borrow_mut())?.header_unchecked()?;
+        check_signal_provider(&pool_header, signal_provider_account, true, accounts)?;
+        let (trader_key, _) = find_trader_key(
+            program_id,
+            &pool_seed,
+            &pool_header.signal_provider,
+            &trader,
+        );
+        if trader_key != *trader_account.key || trader_account.owner != program_id {
+            msg!("Provided trader account does not match the trader key.");
+            return Err(BonfidaBotError::InvalidTrader.into());
+        }
+
+        // Close the trader account
+        let refund = signal_provider_account
+            .lamports()
+            .checked_add(trader_account.lamports())
+            .ok_or(BonfidaBotError::Overflow)?;
+        **signal_provider_account.lamports.borrow_mut() = refund;
+        **trader_account.lamports.borrow_mut() = 0;
+        fill_slice(&mut trader_account.data.borrow_mut(), 0u8);
+        pool_header.number_of_traders = pool_header.number_of_traders.saturating_sub(1);
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
+        Ok(())
+    }
+
+    pub fn process_set_multisig(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_seed: [u8; 32],
+        threshold: u8,
+        signers: Vec<Pubkey>,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let pool_account = next_account_info(accounts_iter)?;
+        let signal_provider_account = next_account_info(accounts_iter)?;
+
+        // Safety verifications, the current configuration approves the new one
+        check_pool_key(&program_id, &pool_account.key, &pool_seed)?;
+        let mut pool_header = PoolState::new(&mut pool_account.data.borrow_mut())?.header()?;
+        check_signal_provider(&pool_header, signal_provider_account, true, accounts)?;
+        check_multisig_config(threshold, &signers)?;
+
+        pool_header.multisig_threshold = threshold;
+        pool_header.multisig_signers = [Pubkey::default(); MAX_MULTISIG_SIGNERS];
+        pool_header.multisig_signers[..signers.len()].copy_from_slice(&signers);
+        PoolState::new(&mut pool_account.data.borrow_mut())?.set_header(&pool_header)?;
+
         Ok(())
     }
 
@@ -1395,8 +2919,10 @@ impl Processor {
             }
             PoolInstruction::Create {
                 pool_seed,
-                fee_collection_period,
+                annual_fee_ratio,
                 fee_ratio,
+                min_signal_provider_ratio,
+                trade_limits,
                 deposit_amounts,
                 markets,
             } => {
@@ -1407,8 +2933,31 @@ impl Processor {
                     pool_seed,
                     deposit_amounts,
                     markets,
-                    fee_collection_period,
+                    annual_fee_ratio,
+                    fee_ratio,
+                    min_signal_provider_ratio,
+                    trade_limits,
+                )
+            }
+            PoolInstruction::LegacyCreate {
+                pool_seed,
+                fee_collection_period,
+                fee_ratio,
+                markets,
+                deposit_amounts,
+            } => {
+                msg!("Instruction: Create Pool (legacy layout)");
+                let annual_fee_ratio = legacy_annual_fee_ratio(fee_collection_period, fee_ratio)?;
+                Self::process_create(
+                    program_id,
+                    accounts,
+                    pool_seed,
+                    deposit_amounts,
+                    markets,
+                    annual_fee_ratio,
                     fee_ratio,
+                    0,
+                    TradeLimits::default(),
                 )
             }
             PoolInstruction::Deposit {
@@ -1439,16 +2988,17 @@ impl Processor {
                     program_id,
                     accounts,
                     pool_seed,
-                    side,
-                    limit_price,
-                    ratio_of_pool_assets_to_trade,
-                    order_type,
+                    side.into(),
+                    NonZeroU64::new(limit_price).ok_or(BonfidaBotError::InvalidInstruction)?,
+                    NonZeroU16::new(ratio_of_pool_assets_to_trade)
+                        .ok_or(BonfidaBotError::InvalidInstruction)?,
+                    order_type.into(),
                     market_index,
                     coin_lot_size,
                     pc_lot_size,
                     target_mint,
                     client_id,
-                    self_trade_behavior,
+                    self_trade_behavior.into(),
                     source_index as usize,
                     target_index as usize,
                     serum_limit,
@@ -1474,7 +3024,7 @@ impl Processor {
                 order_id,
             } => {
                 msg!("Instruction: Cancel Order for Pool");
-                Self::process_cancel(program_id, accounts, pool_seed, side, order_id)
+                Self::process_cancel(program_id, accounts, pool_seed, side.into(), order_id)
             }
             PoolInstruction::Redeem {
                 pool_seed,
@@ -1487,6 +3037,89 @@ impl Processor {
                 msg!("Instruction: Collect Fees for Pool");
                 Self::process_collect_fees(program_id, accounts, pool_seed)
             }
+            PoolInstruction::ClaimFees { pool_seed } => {
+                msg!("Instruction: Claim Fees for Pool");
+                Self::process_claim_fees(program_id, accounts, pool_seed)
+            }
+            PoolInstruction::RequestRedeem {
+                pool_seed,
+                pool_token_amount,
+            } => {
+                msg!("Instruction: Request Redeem out of Pool");
+                Self::process_request_redeem(program_id, accounts, pool_seed, pool_token_amount)
+            }
+            PoolInstruction::ProcessRedeemQueue {
+                pool_seed,
+                number_of_requests,
+            } => {
+                msg!("Instruction: Process Redeem Queue of Pool");
+                Self::process_redeem_queue(program_id, accounts, pool_seed, number_of_requests)
+            }
+            PoolInstruction::RequestDeposit {
+                pool_seed,
+                pool_token_amount,
+                expiry_timestamp,
+                asset_amounts,
+            } => {
+                msg!("Instruction: Request Deposit into Pool");
+                Self::process_request_deposit(
+                    program_id,
+                    accounts,
+                    pool_seed,
+                    pool_token_amount,
+                    expiry_timestamp,
+                    asset_amounts,
+                )
+            }
+            PoolInstruction::ProcessDepositQueue {
+                pool_seed,
+                number_of_requests,
+            } => {
+                msg!("Instruction: Process Deposit Queue of Pool");
+                Self::process_deposit_queue(program_id, accounts, pool_seed, number_of_requests)
+            }
+            PoolInstruction::PostBond { pool_seed, amount } => {
+                msg!("Instruction: Post Bond for Pool");
+                Self::process_post_bond(program_id, accounts, pool_seed, amount)
+            }
+            PoolInstruction::RequestBondWithdrawal { pool_seed } => {
+                msg!("Instruction: Request Bond Withdrawal for Pool");
+                Self::process_request_bond_withdrawal(program_id, accounts, pool_seed)
+            }
+            PoolInstruction::WithdrawBond { pool_seed } => {
+                msg!("Instruction: Withdraw Bond of Pool");
+                Self::process_withdraw_bond(program_id, accounts, pool_seed)
+            }
+            PoolInstruction::SlashBond { pool_seed } => {
+                msg!("Instruction: Slash Bond of Pool");
+                Self::process_slash_bond(program_id, accounts, pool_seed)
+            }
+            PoolInstruction::AddTrader {
+                pool_seed,
+                trader,
+                max_ratio_of_pool_assets_to_trade,
+            } => {
+                msg!("Instruction: Add Trader to Pool");
+                Self::process_add_trader(
+                    program_id,
+                    accounts,
+                    pool_seed,
+                    trader,
+                    max_ratio_of_pool_assets_to_trade,
+                )
+            }
+            PoolInstruction::RemoveTrader { pool_seed, trader } => {
+                msg!("Instruction: Remove Trader from Pool");
+                Self::process_remove_trader(program_id, accounts, pool_seed, trader)
+            }
+            PoolInstruction::SetMultisig {
+                pool_seed,
+                threshold,
+                signers,
+            } => {
+                msg!("Instruction: Set Multisig Signal Provider of Pool");
+                Self::process_set_multisig(program_id, accounts, pool_seed, threshold, signers)
+            }
         }
     }
 }
@@ -1,22 +1,70 @@
+use borsh::{
+    schema::{Declaration, Definition},
+    BorshDeserialize, BorshSchema, BorshSerialize,
+};
+use crate::{error::BonfidaBotError, utils::fill_slice};
 use solana_program::{
+    msg,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack, Sealed},
     pubkey::Pubkey,
 };
-use std::{convert::TryInto, num::NonZeroU8};
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Write},
+    num::NonZeroU8,
+    ops::Range,
+};
 
 pub const PUBKEY_LENGTH: usize = 32;
 
 pub const BONFIDA_FEE: &str = "31LVSggbVz4VcwBSPdtK8HJ3Lt1cKTJUVQTRNNYMfqBq";
 pub const BONFIDA_BNB: &str = "3oQzjfjzUkJ5qHsERk2JPEpAKo34dxAQjUriBqursfxU";
 
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+/// The highest annual management fee ratio a pool can be created with (25%).
+pub const MAX_ANNUAL_FEE_RATIO: u16 = 1 << 14;
+/// The shortest fee collection period accepted by `LegacyCreate` (one week).
+pub const LEGACY_MIN_FEE_COLLECTION_PERIOD: u64 = 604_800;
+/// Seed of the redeem request accounts, which are derived from the pool seed, this seed and the
+/// little endian sequence number of the request.
+pub const REDEEM_REQUEST_SEED: &[u8] = b"redeem_request";
+/// The highest number of redeem requests which can be paid out by a single instruction.
+pub const MAX_REDEEM_BATCH_SIZE: u8 = 4;
+/// Seed of the deposit request accounts, which are derived from the pool seed, this seed and the
+/// little endian sequence number of the request.
+pub const DEPOSIT_REQUEST_SEED: &[u8] = b"deposit_request";
+/// The highest number of deposit requests which can be processed by a single instruction. Each
+/// request takes two accounts per pool asset.
+pub const MAX_DEPOSIT_BATCH_SIZE: u8 = 2;
+/// The mint of the tokens the signal provider bonds.
+pub const FIDA_MINT: &str = "EchesyfXePKdLtoiZSL8pBe8Myagyy8ZRqsACNCFGnvp";
+/// Seed of the account owning the bond escrow, which is derived from the pool seed and this seed.
+/// The escrow is the associated FIDA account of that account.
+pub const BOND_SEED: &[u8] = b"bond";
+/// The smallest bond a signal provider can lock (100 FIDA).
+pub const MIN_BOND_AMOUNT: u64 = 100_000_000;
+/// Time between a bond withdrawal request and the moment the bond can be withdrawn, during which
+/// it can still be slashed (one week).
+pub const BOND_NOTICE_PERIOD: u64 = 604_800;
+/// Time after which a pool left with an unsettled immediate or cancel order gets its bond slashed
+/// (three days).
+pub const MAX_PENDING_ORDER_DURATION: u64 = 259_200;
+/// Seed of the trader accounts, which are derived from the pool seed, this seed, the signal
+/// provider key and the trader key.
+pub const TRADER_SEED: &[u8] = b"trader";
+/// The largest number of keys of a multisig signal provider.
+pub const MAX_MULTISIG_SIGNERS: usize = 5;
+/// Duration of the trading days over which the daily trade limits of a pool apply.
+pub const TRADING_DAY_DURATION: u64 = 86_400;
+
 // Pool state is composed of PoolHeader, Array of markets (pubkeys) and array of poolassets
 
-#[derive(Debug, PartialEq)]
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, PartialEq)]
 pub struct PoolAsset {
     pub mint_address: Pubkey,
 }
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PoolStatus {
     Uninitialized,
     Unlocked,
@@ -26,16 +74,149 @@ pub enum PoolStatus {
     LockedPendingOrder(NonZeroU8),
 }
 
-#[derive(Debug, PartialEq)]
+impl Default for PoolStatus {
+    fn default() -> Self {
+        PoolStatus::Uninitialized
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Default, PartialEq)]
 pub struct PoolHeader {
     pub serum_program_id: Pubkey,
     pub seed: [u8; 32],
+    /// The manager authority of the pool. It receives the signal provider fees and manages the
+    /// bond and the traders. It can create orders itself only as long as it has no traders, so
+    /// that it can be kept cold once trading is delegated, but it can always cancel orders.
     pub signal_provider: Pubkey,
     pub status: PoolStatus,
     pub number_of_markets: u16,
+    /// Share of the deposited pool tokens taken as fees, as a 16 bit fixed point ratio.
     pub fee_ratio: u16,
+    /// Time up to which the management fees have been minted.
     pub last_fee_collection_timestamp: u64,
-    pub fee_collection_period: u64,
+    /// Share of the pool taken as management fees over a year, as a 16 bit fixed point ratio.
+    /// The fees accrue continuously and are accounted for on each deposit, redeem and fee
+    /// collection.
+    pub annual_fee_ratio: u16,
+    /// Pool tokens accrued as deposit and management fees since the pool creation. The fees
+    /// which have not been claimed yet count towards the pool token supply.
+    pub accrued_fees: u64,
+    /// Pool tokens claimed by the signal provider, Bonfida and the Bonfida buy and burn account,
+    /// in that order.
+    pub claimed_fees: [u64; 3],
+    /// Sequence number of the next redeem request to be paid out.
+    pub redeem_queue_head: u64,
+    /// Sequence number of the next redeem request to be queued. The requests from the head up to
+    /// the tail are pending.
+    pub redeem_queue_tail: u64,
+    /// Sequence number of the next deposit request to be processed.
+    pub deposit_queue_head: u64,
+    /// Sequence number of the next deposit request to be queued.
+    pub deposit_queue_tail: u64,
+    /// FIDA locked by the signal provider in the bond escrow, 0 when the pool is not bonded.
+    pub bond_amount: u64,
+    /// Time from which the bond can be withdrawn, 0 when no withdrawal has been requested.
+    pub bond_unlock_timestamp: u64,
+    /// Time at which the pool created its first immediate or cancel order since it last cleared
+    /// its pending orders, 0 when there is none.
+    pub pending_order_timestamp: u64,
+    /// Share of the pool token supply that the associated pooltoken account of the signal
+    /// provider has to hold, as a 16 bit fixed point ratio. Orders cannot be created below it and
+    /// redeems from that account cannot take it below it. 0 disables the requirement.
+    pub min_signal_provider_ratio: u16,
+    /// Number of distinct `multisig_signers` which have to sign the instructions reserved to the
+    /// signal provider, in place of the signal provider key. 0 disables the multisig.
+    pub multisig_threshold: u8,
+    /// The keys of a multisig signal provider, unused slots hold the default key.
+    pub multisig_signers: [Pubkey; MAX_MULTISIG_SIGNERS],
+    /// The limits on the orders of the pool.
+    pub trade_limits: TradeLimits,
+    /// Start of the current trading day. A trading day begins with the first order created after
+    /// the previous one has ended.
+    pub trading_day_start: u64,
+    /// Sum of the ratios of the orders created since the start of the trading day.
+    pub daily_turnover: u32,
+    /// Number of orders created since the start of the trading day.
+    pub daily_orders: u16,
+    /// Number of trader accounts of the signal provider.
+    pub number_of_traders: u16,
+}
+
+/// Risk parameters of the orders of a pool, set at creation. A limit of 0 is disabled, and an
+/// empty set of allowed values allows all of them.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Clone, Copy, Debug, Default, PartialEq)]
+pub struct TradeLimits {
+    /// The largest share of a pool asset a single order can trade, as a 16 bit fixed point
+    /// ratio.
+    pub max_order_ratio: u16,
+    /// The largest sum of the order ratios over a trading day, as a 16 bit fixed point ratio
+    /// which can exceed 1.
+    pub max_daily_turnover: u32,
+    /// The largest number of orders over a trading day.
+    pub max_daily_orders: u16,
+    /// The largest deviation of the limit price of an order from the best price on the other side
+    /// of the market book, as a 16 bit fixed point ratio.
+    pub max_price_deviation: u16,
+    /// The set of the allowed order types, where bit `i` stands for the `OrderType` of index `i`.
+    pub allowed_order_types: u8,
+    /// The set of the allowed self trade behaviors, where bit `i` stands for the
+    /// `SelfTradeBehavior` of index `i`.
+    pub allowed_self_trade_behaviors: u8,
+}
+
+/// A queued redeem, stored in a program account derived from the pool seed and its sequence
+/// number. The pooltokens are escrowed in the associated pooltoken account of the request
+/// account until the request is paid out.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, PartialEq)]
+pub struct RedeemRequest {
+    /// Receives the redeemed assets in its associated token accounts, as well as the rent of the
+    /// request accounts.
+    pub owner: Pubkey,
+    pub pool_token_amount: u64,
+    /// The bump seed of the request account address.
+    pub nonce: u8,
+}
+
+/// A queued deposit, stored in a program account derived from the pool seed and its sequence
+/// number. The deposited basket is escrowed in the associated token accounts of the request
+/// account, and bounds the price paid for the pooltokens : the request is refunded when the
+/// basket does not cover `pool_token_amount` at the value of the pool when it is processed.
+/// The account is sized for the escrowed mints, see `DepositRequest::size`.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, PartialEq)]
+pub struct DepositRequest {
+    /// Receives the pooltokens or the refunded basket in its associated token accounts, as well
+    /// as the rent of the request accounts.
+    pub owner: Pubkey,
+    /// The exact amount of pooltokens bought, fees included.
+    pub pool_token_amount: u64,
+    /// The request is refunded when it is processed after this time.
+    pub expiry_timestamp: u64,
+    /// The bump seed of the request account address.
+    pub nonce: u8,
+    /// The mints of the escrow accounts, which are the pool assets when the request was made.
+    /// The pool can drop an asset before the request is processed, in which case its escrow is
+    /// refunded.
+    pub escrowed_mints: Vec<Pubkey>,
+}
+
+impl DepositRequest {
+    /// The size of the account of a request escrowing `number_of_assets` assets.
+    pub fn size(number_of_assets: usize) -> usize {
+        Self::LEN + PUBKEY_LENGTH * number_of_assets
+    }
+}
+
+/// A key allowed by the signal provider to create and cancel orders for the pool, stored in a
+/// program account derived from the pool seed and the key. Traders have no other rights over the
+/// pool, and the signal provider revokes them by closing their account.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, PartialEq)]
+pub struct Trader {
+    pub trader: Pubkey,
+    /// The largest share of a pool asset a single order can trade, as a 16 bit fixed point
+    /// ratio. 0 sets no limit.
+    pub max_ratio_of_pool_assets_to_trade: u16,
+    /// The bump seed of the trader account address.
+    pub nonce: u8,
 }
 
 const STATUS_PENDING_ORDER_FLAG: u8 = 1 << 6;
@@ -43,18 +224,9 @@ const STATUS_PENDING_ORDER_MASK: u8 = 0x3f;
 const STATUS_LOCKED_FLAG: u8 = 2 << 6;
 const STATUS_UNLOCKED_FLAG: u8 = STATUS_PENDING_ORDER_MASK;
 
-impl Sealed for PoolHeader {}
-
-impl Pack for PoolHeader {
-    const LEN: usize = 117;
-
-    fn pack_into_slice(&self, target: &mut [u8]) {
-        let serum_program_id_bytes = self.serum_program_id.to_bytes();
-        target[0..32].copy_from_slice(&serum_program_id_bytes);
-        target[32..64].copy_from_slice(&self.seed);
-        let signal_provider_bytes = self.signal_provider.to_bytes();
-        target[64..96].copy_from_slice(&signal_provider_bytes);
-        target[96] = match self.status {
+impl PoolStatus {
+    fn to_u8(&self) -> u8 {
+        match self {
             PoolStatus::Uninitialized => 0,
             PoolStatus::Unlocked => STATUS_UNLOCKED_FLAG,
             PoolStatus::Locked => STATUS_LOCKED_FLAG,
@@ -66,49 +238,61 @@ impl Pack for PoolHeader {
                     | STATUS_PENDING_ORDER_FLAG
                     | (STATUS_PENDING_ORDER_MASK & (n.get() - 1))
             }
-        };
-        let number_of_markets_bytes = self.number_of_markets.to_le_bytes();
-        target[97..99].copy_from_slice(&number_of_markets_bytes);
-        target[99..101].copy_from_slice(&self.fee_ratio.to_le_bytes());
-        target[101..109].copy_from_slice(&self.last_fee_collection_timestamp.to_le_bytes());
-        target[109..117].copy_from_slice(&self.fee_collection_period.to_le_bytes());
+        }
+    }
+
+    fn from_u8(status: u8) -> Option<Self> {
+        if status == 0 {
+            return Some(PoolStatus::Uninitialized);
+        }
+        match status >> 6 {
+            0 => Some(PoolStatus::Unlocked),
+            1 => NonZeroU8::new((status & STATUS_PENDING_ORDER_MASK) + 1)
+                .map(PoolStatus::PendingOrder),
+            2 => Some(PoolStatus::Locked),
+            3 => NonZeroU8::new((status & STATUS_PENDING_ORDER_MASK) + 1)
+                .map(PoolStatus::LockedPendingOrder),
+            _ => None,
+        }
+    }
+}
+
+// The status is packed into a single byte (see the flags above) rather than using the Borsh
+// enum encoding.
+impl BorshSerialize for PoolStatus {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.to_u8().serialize(writer)
+    }
+}
+
+impl BorshDeserialize for PoolStatus {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        let status = u8::deserialize(buf)?;
+        Self::from_u8(status).ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid pool status"))
+    }
+}
+
+impl BorshSchema for PoolStatus {
+    fn add_definitions_recursively(_definitions: &mut HashMap<Declaration, Definition>) {}
+
+    fn declaration() -> Declaration {
+        u8::declaration()
+    }
+}
+
+impl Sealed for PoolHeader {}
+
+impl Pack for PoolHeader {
+    /// Pools of the first version of the program have a shorter header, which `PoolState` still
+    /// reads (see `LEGACY_HEADER_LEN`).
+    const LEN: usize = 390;
+
+    fn pack_into_slice(&self, target: &mut [u8]) {
+        self.serialize(&mut &mut target[..]).unwrap();
     }
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let serum_program_id = Pubkey::new(&src[..32]);
-        let seed: [u8; 32] = src[32..64].try_into().unwrap();
-        let signal_provider = Pubkey::new(&src[64..96]);
-        let status = if src[96] == 0 {
-            PoolStatus::Uninitialized
-        } else {
-            match src[96] >> 6 {
-                0 => PoolStatus::Unlocked,
-                1 => PoolStatus::PendingOrder(
-                    NonZeroU8::new((src[96] & STATUS_PENDING_ORDER_MASK) + 1)
-                        .ok_or(ProgramError::InvalidArgument)?,
-                ),
-                2 => PoolStatus::Locked,
-                3 => PoolStatus::LockedPendingOrder(
-                    NonZeroU8::new((src[96] & STATUS_PENDING_ORDER_MASK) + 1)
-                        .ok_or(ProgramError::InvalidArgument)?,
-                ),
-                _ => return Err(ProgramError::InvalidAccountData),
-            }
-        };
-        let number_of_markets = u16::from_le_bytes(src[97..99].try_into().unwrap());
-        let fee_ratio = u16::from_le_bytes(src[99..101].try_into().unwrap());
-        let last_fee_collection_timestamp = u64::from_le_bytes(src[101..109].try_into().unwrap());
-        let fee_collection_period = u64::from_le_bytes(src[109..117].try_into().unwrap());
-        Ok(Self {
-            serum_program_id,
-            seed,
-            signal_provider,
-            status,
-            number_of_markets,
-            fee_ratio,
-            last_fee_collection_timestamp,
-            fee_collection_period,
-        })
+        Self::deserialize(&mut &src[..]).map_err(|_| ProgramError::InvalidAccountData)
     }
 
     fn unpack(input: &[u8]) -> Result<Self, ProgramError>
@@ -148,6 +332,74 @@ impl IsInitialized for PoolHeader {
     }
 }
 
+impl Sealed for RedeemRequest {}
+
+impl IsInitialized for RedeemRequest {
+    fn is_initialized(&self) -> bool {
+        self.pool_token_amount != 0
+    }
+}
+
+impl Pack for RedeemRequest {
+    const LEN: usize = 41;
+
+    fn pack_into_slice(&self, target: &mut [u8]) {
+        self.serialize(&mut &mut target[..]).unwrap();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &src[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+impl Sealed for DepositRequest {}
+
+impl IsInitialized for DepositRequest {
+    fn is_initialized(&self) -> bool {
+        self.pool_token_amount != 0
+    }
+}
+
+impl Pack for DepositRequest {
+    /// The size of a request without escrowed mints.
+    const LEN: usize = 53;
+
+    fn pack_into_slice(&self, target: &mut [u8]) {
+        self.serialize(&mut &mut target[..]).unwrap();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &src[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self::unpack_from_slice(input)?)
+    }
+}
+
+impl Sealed for Trader {}
+
+impl IsInitialized for Trader {
+    fn is_initialized(&self) -> bool {
+        self.trader != Pubkey::default()
+    }
+}
+
+impl Pack for Trader {
+    const LEN: usize = 35;
+
+    fn pack_into_slice(&self, target: &mut [u8]) {
+        self.serialize(&mut &mut target[..]).unwrap();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &src[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
 impl Sealed for PoolAsset {}
 
 impl IsInitialized for PoolAsset {
@@ -160,111 +412,454 @@ impl Pack for PoolAsset {
     const LEN: usize = 32;
 
     fn pack_into_slice(&self, target: &mut [u8]) {
-        let mint_address_bytes = self.mint_address.to_bytes();
-        target[0..32].copy_from_slice(&mint_address_bytes);
+        self.serialize(&mut &mut target[..]).unwrap();
     }
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let mint_address = Pubkey::new(&src[..32]);
-        Ok(Self { mint_address })
+        Self::deserialize(&mut &src[..]).map_err(|_| ProgramError::InvalidAccountData)
     }
 }
 
-pub fn unpack_assets(input: &[u8]) -> Result<Vec<PoolAsset>, ProgramError> {
-    let number_of_assets = input.len() / PoolAsset::LEN;
-    let mut output: Vec<PoolAsset> = Vec::with_capacity(number_of_assets);
-    let mut offset = 0;
-    for _ in 0..number_of_assets {
-        PoolAsset::unpack(&input[offset..offset + PoolAsset::LEN])
-            .and_then(|asset| Ok(output.push(asset)))
-            .unwrap_or(());
-        offset += PoolAsset::LEN;
-    }
-    Ok(output)
-}
+/// Offset of the `number_of_markets` field in the packed PoolHeader.
+const STATUS_OFFSET: usize = 96;
+const NUMBER_OF_MARKETS_OFFSET: usize = 97;
 
-pub fn unpack_unchecked_asset(input: &[u8], index: usize) -> Result<PoolAsset, ProgramError> {
-    let offset = index * PoolAsset::LEN;
-    input
-        .get(offset..offset + PoolAsset::LEN)
-        .ok_or(ProgramError::InvalidArgument)
-        .and_then(|slice| PoolAsset::unpack_unchecked(slice))
-}
+/// Length of the pool header written by the first version of the program. Its first
+/// `LEGACY_HEADER_PREFIX_LEN` bytes have the layout of the current header and are followed by
+/// the fee collection period.
+pub const LEGACY_HEADER_LEN: usize = 117;
+const LEGACY_HEADER_PREFIX_LEN: usize = 109;
 
-pub fn get_asset_slice(target: &mut [u8], index: usize) -> Result<&mut [u8], ProgramError> {
-    let offset = index * PoolAsset::LEN;
-    target
-        .get_mut(offset..offset + PoolAsset::LEN)
-        .ok_or(ProgramError::InvalidArgument)
+/// Zero-copy accessor over the whole pool account data, which is laid out as the PoolHeader,
+/// followed by `number_of_markets` market keys and the asset slots.
+///
+/// Accounts cannot grow, so the pools of the first version of the program keep their
+/// `LEGACY_HEADER_LEN` byte header. They are told apart by their length, as the market and asset
+/// slots are 32 bytes long. Their header reads with the fields added since then at their default
+/// values, and only its status can be written, which is enough to settle, cancel, deposit into
+/// and redeem from these pools.
+pub struct PoolState<'a> {
+    data: &'a mut [u8],
+    header_len: usize,
 }
 
-pub fn unpack_market(input: &[u8], market_index: u16) -> Pubkey {
-    let offset = 32 * (market_index as usize);
-    return Pubkey::new(&input[offset..offset + 32]);
-}
+impl<'a> PoolState<'a> {
+    /// Wraps the pool account data, checking that it is large enough to hold the header and the
+    /// markets it declares, and that the remaining space is a whole number of asset slots.
+    pub fn new(data: &'a mut [u8]) -> Result<Self, ProgramError> {
+        let header_len = if data.len() % PoolAsset::LEN == LEGACY_HEADER_LEN % PoolAsset::LEN {
+            LEGACY_HEADER_LEN
+        } else {
+            PoolHeader::LEN
+        };
+        if data.len() < header_len {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let state = Self { data, header_len };
+        let assets_offset = state.assets_offset();
+        if state.data.len() < assets_offset
+            || (state.data.len() - assets_offset) % PoolAsset::LEN != 0
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(state)
+    }
+
+    /// The size of the account data of a pool.
+    pub fn size(number_of_markets: u16, max_number_of_assets: u32) -> usize {
+        PoolHeader::LEN
+            + PUBKEY_LENGTH * (number_of_markets as usize)
+            + PoolAsset::LEN * (max_number_of_assets as usize)
+    }
+
+    /// Whether the pool was created by the first version of the program.
+    pub fn is_legacy(&self) -> bool {
+        self.header_len == LEGACY_HEADER_LEN
+    }
+
+    pub fn number_of_markets(&self) -> u16 {
+        u16::from_le_bytes([
+            self.data[NUMBER_OF_MARKETS_OFFSET],
+            self.data[NUMBER_OF_MARKETS_OFFSET + 1],
+        ])
+    }
+
+    fn assets_offset(&self) -> usize {
+        self.header_len + PUBKEY_LENGTH * (self.number_of_markets() as usize)
+    }
+
+    /// Unpacks the header, failing if the pool is uninitialized.
+    pub fn header(&self) -> Result<PoolHeader, ProgramError> {
+        let header = self.header_unchecked()?;
+        if !header.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok(header)
+    }
+
+    pub fn header_unchecked(&self) -> Result<PoolHeader, ProgramError> {
+        if !self.is_legacy() {
+            return PoolHeader::unpack_unchecked(&self.data[..PoolHeader::LEN]);
+        }
+        let mut data = [0u8; PoolHeader::LEN];
+        data[..LEGACY_HEADER_PREFIX_LEN].copy_from_slice(&self.data[..LEGACY_HEADER_PREFIX_LEN]);
+        let mut header = PoolHeader::unpack_unchecked(&data)?;
+        // The first version took its fees once per fee collection period, which the current
+        // fee accounting cannot store in this header
+        header.fee_ratio = 0;
+        Ok(header)
+    }
+
+    /// Writes the header. The number of markets defines the layout and cannot be changed.
+    pub fn set_header(&mut self, header: &PoolHeader) -> Result<(), ProgramError> {
+        if header.number_of_markets != self.number_of_markets() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if self.is_legacy() {
+            let mut legacy_header = self.header_unchecked()?;
+            legacy_header.status = header.status;
+            if legacy_header != *header {
+                msg!("Pools of the first program version can only change their status.");
+                return Err(BonfidaBotError::LegacyPoolLayout.into());
+            }
+            self.data[STATUS_OFFSET] = header.status.to_u8();
+            return Ok(());
+        }
+        header.pack_into_slice(&mut self.data[..PoolHeader::LEN]);
+        Ok(())
+    }
+
+    /// Writes the header, the markets and the assets of a new pool, which always uses the current
+    /// layout.
+    pub fn init(
+        &mut self,
+        header: &PoolHeader,
+        markets: &[Pubkey],
+        assets: &[PoolAsset],
+    ) -> Result<(), ProgramError> {
+        let assets_offset = PoolHeader::LEN + PUBKEY_LENGTH * markets.len();
+        if self.is_legacy()
+            || header.number_of_markets as usize != markets.len()
+            || self.data.len() < assets_offset + PoolAsset::LEN * assets.len()
+            || (self.data.len() - assets_offset) % PoolAsset::LEN != 0
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+        header.pack_into_slice(&mut self.data[..PoolHeader::LEN]);
+        for (slot, market) in self.data[PoolHeader::LEN..assets_offset]
+            .chunks_exact_mut(PUBKEY_LENGTH)
+            .zip(markets)
+        {
+            slot.copy_from_slice(&market.to_bytes());
+        }
+        for (slot, asset) in self.data[assets_offset..]
+            .chunks_exact_mut(PoolAsset::LEN)
+            .zip(assets)
+        {
+            asset.pack_into_slice(slot);
+        }
+        Ok(())
+    }
+
+    pub fn market(&self, index: u16) -> Result<Pubkey, ProgramError> {
+        if index >= self.number_of_markets() {
+            return Err(BonfidaBotError::UnauthorizedMarket.into());
+        }
+        let offset = self.header_len + PUBKEY_LENGTH * index as usize;
+        Ok(Pubkey::new(&self.data[offset..offset + PUBKEY_LENGTH]))
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        self.data[self.header_len..self.assets_offset()]
+            .chunks_exact(PUBKEY_LENGTH)
+            .map(Pubkey::new)
+    }
+
+    pub fn number_of_asset_slots(&self) -> usize {
+        (self.data.len() - self.assets_offset()) / PoolAsset::LEN
+    }
+
+    /// Returns the asset in a slot, which is uninitialized when the slot is free.
+    pub fn asset(&self, index: usize) -> Result<PoolAsset, ProgramError> {
+        let range = self.asset_range(index)?;
+        self.data
+            .get(range)
+            .ok_or(BonfidaBotError::InvalidAssetIndex.into())
+            .and_then(PoolAsset::unpack_unchecked)
+    }
+
+    /// Iterates over the initialized assets along with their slot index.
+    pub fn assets(&self) -> impl Iterator<Item = (usize, PoolAsset)> + '_ {
+        self.data[self.assets_offset()..]
+            .chunks_exact(PoolAsset::LEN)
+            .enumerate()
+            .filter_map(|(i, slot)| PoolAsset::unpack(slot).ok().map(|asset| (i, asset)))
+    }
+
+    pub fn asset_mut(&mut self, index: usize) -> Result<&mut [u8], ProgramError> {
+        let range = self.asset_range(index)?;
+        self.data
+            .get_mut(range)
+            .ok_or(BonfidaBotError::InvalidAssetIndex.into())
+    }
+
+    fn asset_range(&self, index: usize) -> Result<Range<usize>, ProgramError> {
+        let offset = index
+            .checked_mul(PoolAsset::LEN)
+            .and_then(|o| o.checked_add(self.assets_offset()))
+            .ok_or(BonfidaBotError::InvalidAssetIndex)?;
+        let end = offset
+            .checked_add(PoolAsset::LEN)
+            .ok_or(BonfidaBotError::InvalidAssetIndex)?;
+        Ok(offset..end)
+    }
+
+    /// Returns the slot index of the asset with the given mint.
+    pub fn find_asset(&self, mint: &Pubkey) -> Option<usize> {
+        self.assets()
+            .find(|(_, asset)| &asset.mint_address == mint)
+            .map(|(i, _)| i)
+    }
+
+    pub fn free_asset_slot(&self) -> Option<usize> {
+        self.data[self.assets_offset()..]
+            .chunks_exact(PoolAsset::LEN)
+            .position(|slot| slot.iter().all(|b| *b == 0))
+    }
 
-pub fn pack_markets(target: &mut [u8], markets: &Vec<Pubkey>) -> Result<(), ProgramError> {
-    for i in 0..markets.len() {
-        target[32 * i..32 * (i + 1)].copy_from_slice(&markets[i].to_bytes());
+    /// Erases the markets and assets and marks the pool as uninitialized. The rest of the
+    /// header is kept to preserve the pool seed.
+    pub fn reset(&mut self) -> Result<(), ProgramError> {
+        let mut header = self.header_unchecked()?;
+        fill_slice(&mut self.data[self.header_len..], 0u8);
+        header.status = PoolStatus::Uninitialized;
+        self.set_header(&header)
     }
-    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use std::num::NonZeroU8;
 
-    use super::{pack_markets, unpack_assets, unpack_market, PoolAsset, PoolHeader, PoolStatus};
+    use crate::error::BonfidaBotError;
+
+    use super::{
+        DepositRequest, PoolAsset, PoolHeader, PoolState, PoolStatus, RedeemRequest, TradeLimits,
+        Trader, LEGACY_HEADER_LEN, MAX_MULTISIG_SIGNERS, PUBKEY_LENGTH,
+    };
     use solana_program::{
+        program_error::ProgramError,
         program_pack::{IsInitialized, Pack},
         pubkey::Pubkey,
     };
 
-    #[test]
-    fn test_state_packing() {
-        let header_state = PoolHeader {
+    fn get_header(number_of_markets: u16) -> PoolHeader {
+        PoolHeader {
             serum_program_id: Pubkey::new_unique(),
-            seed: [0u8; 32],
             signal_provider: Pubkey::new_unique(),
             status: PoolStatus::PendingOrder(NonZeroU8::new(39).unwrap()),
-            number_of_markets: 234,
+            number_of_markets,
             fee_ratio: 15,
             last_fee_collection_timestamp: 1_000_000_000,
-            fee_collection_period: 10_000,
-        };
+            annual_fee_ratio: 1311,
+            ..PoolHeader::default()
+        }
+    }
 
-        let header_size = PoolHeader::LEN;
+    #[test]
+    fn test_state_packing() {
+        let header_state = get_header(0);
         let mut state_array = [0u8; PoolHeader::LEN + 2 * PoolAsset::LEN];
-        header_state.pack_into_slice(&mut state_array[..header_size]);
-
         let pool_asset = PoolAsset {
             mint_address: Pubkey::new_unique(),
         };
         let pool_asset_2 = PoolAsset {
             mint_address: Pubkey::new_unique(),
         };
-        pool_asset.pack_into_slice(&mut state_array[header_size..]);
-        pool_asset_2.pack_into_slice(&mut state_array[header_size + PoolAsset::LEN..]);
+        let mut state = PoolState::new(&mut state_array).unwrap();
+        state
+            .init(
+                &header_state,
+                &[],
+                &[
+                    PoolAsset {
+                        mint_address: pool_asset.mint_address,
+                    },
+                    PoolAsset {
+                        mint_address: pool_asset_2.mint_address,
+                    },
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(state.header().unwrap(), header_state);
+        let unpacked_pool_assets: Vec<PoolAsset> = state.assets().map(|(_, a)| a).collect();
+        assert_eq!(unpacked_pool_assets, vec![pool_asset, pool_asset_2]);
+    }
+
+    #[test]
+    fn test_pool_state() {
+        let markets = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let mints = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut data = vec![0u8; PoolState::size(2, 4)];
+        let mut state = PoolState::new(&mut data).unwrap();
+        assert!(state.header().is_err());
+        state
+            .init(
+                &get_header(2),
+                &markets,
+                &[PoolAsset {
+                    mint_address: mints[0],
+                }],
+            )
+            .unwrap();
+
+        assert_eq!(state.markets().collect::<Vec<_>>(), markets);
+        assert_eq!(state.market(1).unwrap(), markets[1]);
+        assert!(state.market(2).is_err());
+        assert_eq!(state.number_of_asset_slots(), 4);
+        assert_eq!(state.find_asset(&mints[0]), Some(0));
+        assert_eq!(state.find_asset(&mints[1]), None);
+        assert_eq!(state.free_asset_slot(), Some(1));
+
+        // Fill the second and last slots
+        PoolAsset {
+            mint_address: mints[1],
+        }
+        .pack_into_slice(state.asset_mut(3).unwrap());
+        assert_eq!(state.find_asset(&mints[1]), Some(3));
+        assert_eq!(state.free_asset_slot(), Some(1));
+        assert!(!state.asset(2).unwrap().is_initialized());
+        assert!(state.asset(4).is_err());
+        assert!(state.asset_mut(4).is_err());
+        assert!(state.asset(usize::MAX).is_err());
+        assert!(state.asset_mut(usize::MAX / PoolAsset::LEN).is_err());
+        assert_eq!(
+            state.assets().map(|(i, _)| i).collect::<Vec<_>>(),
+            vec![0, 3]
+        );
+
+        // The layout cannot be changed through the header
+        assert_eq!(
+            state.set_header(&get_header(3)),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        state.reset().unwrap();
+        assert!(state.header().is_err());
+        assert_eq!(state.assets().count(), 0);
+        assert_eq!(state.free_asset_slot(), Some(0));
+    }
+
+    #[test]
+    fn test_legacy_pool_layout() {
+        // The current layout is never mistaken for the legacy one
+        assert_ne!(
+            PoolHeader::LEN % PoolAsset::LEN,
+            LEGACY_HEADER_LEN % PoolAsset::LEN
+        );
+
+        let header = PoolHeader {
+            status: PoolStatus::Unlocked,
+            fee_ratio: 0,
+            annual_fee_ratio: 0,
+            ..get_header(2)
+        };
+        let markets = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mints = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut data = vec![0u8; LEGACY_HEADER_LEN + 2 * PUBKEY_LENGTH + 3 * PoolAsset::LEN];
+        data[..32].copy_from_slice(&header.serum_program_id.to_bytes());
+        data[64..96].copy_from_slice(&header.signal_provider.to_bytes());
+        data[96] = 0x3f;
+        data[97..99].copy_from_slice(&2u16.to_le_bytes());
+        // Fee ratio and fee collection period of the first version
+        data[99..101].copy_from_slice(&15u16.to_le_bytes());
+        data[101..109].copy_from_slice(&1_000_000_000u64.to_le_bytes());
+        data[109..117].copy_from_slice(&604_800u64.to_le_bytes());
+        for (i, key) in markets.iter().chain(mints.iter()).enumerate() {
+            let offset = LEGACY_HEADER_LEN + i * PUBKEY_LENGTH;
+            data[offset..offset + PUBKEY_LENGTH].copy_from_slice(&key.to_bytes());
+        }
+
+        let mut state = PoolState::new(&mut data).unwrap();
+        assert!(state.is_legacy());
+        assert_eq!(state.header().unwrap(), header);
+        assert_eq!(state.markets().collect::<Vec<_>>(), markets);
+        assert_eq!(state.market(1).unwrap(), markets[1]);
+        let assets: Vec<Pubkey> = state.assets().map(|(_, a)| a.mint_address).collect();
+        assert_eq!(assets, mints);
+        assert_eq!(state.free_asset_slot(), Some(2));
 
-        let unpacked_header = PoolHeader::unpack(&state_array[..PoolHeader::LEN]).unwrap();
-        assert_eq!(unpacked_header, header_state);
+        // Only the status can be written
+        let pending = PoolHeader {
+            status: PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap()),
+            ..state.header().unwrap()
+        };
+        state.set_header(&pending).unwrap();
+        assert_eq!(state.header().unwrap(), pending);
+        assert_eq!(
+            state.set_header(&PoolHeader {
+                accrued_fees: 1,
+                ..state.header().unwrap()
+            }),
+            Err(BonfidaBotError::LegacyPoolLayout.into())
+        );
+        assert!(state.init(&header, &markets, &[]).is_err());
 
-        let unpacked_pool_assets = unpack_assets(&state_array[PoolHeader::LEN..]).unwrap();
-        assert_eq!(unpacked_pool_assets[0], pool_asset);
-        assert_eq!(unpacked_pool_assets[1], pool_asset_2);
+        state.reset().unwrap();
+        assert!(state.header().is_err());
+        assert_eq!(state.assets().count(), 0);
+        // The fees of the first version are left untouched
+        assert_eq!(data[96], 0);
+        assert_eq!(&data[99..101], &15u16.to_le_bytes());
+        assert_eq!(&data[109..117], &604_800u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_pool_state_malformed() {
+        // Too small for the header
+        let mut data = vec![0u8; PoolHeader::LEN - 1];
+        assert!(PoolState::new(&mut data).is_err());
+
+        // Partial asset slot
+        let mut data = vec![0u8; PoolHeader::LEN + PoolAsset::LEN + 1];
+        assert!(PoolState::new(&mut data).is_err());
+
+        // The header declares more markets than the account can hold
+        let mut data = vec![0u8; PoolState::size(1, 0)];
+        get_header(2).pack_into_slice(&mut data[..PoolHeader::LEN]);
+        assert!(PoolState::new(&mut data).is_err());
+
+        // Not enough space for the markets and assets
+        let mut data = vec![0u8; PoolState::size(1, 1)];
+        let mut state = PoolState::new(&mut data).unwrap();
+        let assets = [
+            PoolAsset {
+                mint_address: Pubkey::new_unique(),
+            },
+            PoolAsset {
+                mint_address: Pubkey::new_unique(),
+            },
+        ];
+        assert!(state
+            .init(&get_header(1), &[Pubkey::new_unique()], &assets)
+            .is_err());
+        assert!(state
+            .init(&get_header(2), &[Pubkey::new_unique(); 2], &assets[..1])
+            .is_err());
+        assert!(state
+            .init(&get_header(1), &[Pubkey::new_unique()], &assets[..1])
+            .is_ok());
+        assert_eq!(data.len(), PoolHeader::LEN + PUBKEY_LENGTH + PoolAsset::LEN);
     }
 
     #[test]
     fn test_header_packing() {
         let mut header_state = PoolHeader {
-            serum_program_id: Pubkey::new_unique(),
-            seed: [0u8; 32],
-            signal_provider: Pubkey::new_unique(),
             status: PoolStatus::PendingOrder(NonZeroU8::new(39).unwrap()),
-            number_of_markets: 234,
-            fee_ratio: 15,
-            last_fee_collection_timestamp: 1_000_000_000,
-            fee_collection_period: 10_000,
+            multisig_threshold: 2,
+            multisig_signers: [Pubkey::new_unique(); MAX_MULTISIG_SIGNERS],
+            ..get_header(234)
         };
         assert_eq!(
             header_state,
@@ -272,14 +867,8 @@ mod tests {
         );
 
         header_state = PoolHeader {
-            serum_program_id: Pubkey::new_unique(),
-            seed: [0u8; 32],
-            signal_provider: Pubkey::new_unique(),
             status: PoolStatus::LockedPendingOrder(NonZeroU8::new(64).unwrap()),
-            number_of_markets: 234,
-            fee_ratio: 15,
-            last_fee_collection_timestamp: 1_000_000_000,
-            fee_collection_period: 10_000,
+            ..get_header(234)
         };
         assert_eq!(
             header_state,
@@ -287,14 +876,8 @@ mod tests {
         );
 
         header_state = PoolHeader {
-            serum_program_id: Pubkey::new_unique(),
-            seed: [0u8; 32],
-            signal_provider: Pubkey::new_unique(),
             status: PoolStatus::Locked,
-            number_of_markets: 234,
-            fee_ratio: 15,
-            last_fee_collection_timestamp: 1_000_000_000,
-            fee_collection_period: 10_000,
+            ..get_header(234)
         };
         assert_eq!(
             header_state,
@@ -302,14 +885,8 @@ mod tests {
         );
 
         header_state = PoolHeader {
-            serum_program_id: Pubkey::new_unique(),
-            seed: [0u8; 32],
-            signal_provider: Pubkey::new_unique(),
             status: PoolStatus::Unlocked,
-            number_of_markets: 234,
-            fee_ratio: 15,
-            last_fee_collection_timestamp: 1_000_000_000,
-            fee_collection_period: 10_000,
+            ..get_header(234)
         };
         assert_eq!(
             header_state,
@@ -317,16 +894,157 @@ mod tests {
         );
 
         header_state = PoolHeader {
+            status: PoolStatus::Uninitialized,
+            ..get_header(234)
+        };
+        assert!(PoolHeader::unpack(&get_packed(&header_state)).is_err());
+    }
+
+    #[test]
+    fn test_header_layout() {
+        let header_state = PoolHeader {
             serum_program_id: Pubkey::new_unique(),
-            seed: [0u8; 32],
+            seed: [3u8; 32],
             signal_provider: Pubkey::new_unique(),
-            status: PoolStatus::Uninitialized,
+            status: PoolStatus::LockedPendingOrder(NonZeroU8::new(2).unwrap()),
             number_of_markets: 234,
             fee_ratio: 15,
             last_fee_collection_timestamp: 1_000_000_000,
-            fee_collection_period: 10_000,
+            annual_fee_ratio: 1311,
+            accrued_fees: 5_000,
+            claimed_fees: [1_000, 500, 250],
+            redeem_queue_head: 7,
+            redeem_queue_tail: 12,
+            deposit_queue_head: 3,
+            deposit_queue_tail: 5,
+            bond_amount: 100_000_000,
+            bond_unlock_timestamp: 1_000_604_800,
+            pending_order_timestamp: 1_000_000_100,
+            min_signal_provider_ratio: 3277,
+            multisig_threshold: 2,
+            multisig_signers: [
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::default(),
+                Pubkey::default(),
+            ],
+            trade_limits: TradeLimits {
+                max_order_ratio: 6554,
+                max_daily_turnover: 1 << 17,
+                max_daily_orders: 24,
+                max_price_deviation: 3277,
+                allowed_order_types: 0b010,
+                allowed_self_trade_behaviors: 0b101,
+            },
+            trading_day_start: 1_000_000_050,
+            daily_turnover: 70_000,
+            daily_orders: 3,
+            number_of_traders: 2,
         };
-        assert!(PoolHeader::unpack(&get_packed(&header_state)).is_err());
+        let packed = get_packed(&header_state);
+        assert_eq!(&packed[..32], &header_state.serum_program_id.to_bytes());
+        assert_eq!(&packed[32..64], &[3u8; 32]);
+        assert_eq!(&packed[64..96], &header_state.signal_provider.to_bytes());
+        assert_eq!(packed[96], 0xc1);
+        assert_eq!(&packed[97..99], &234u16.to_le_bytes());
+        assert_eq!(&packed[99..101], &15u16.to_le_bytes());
+        assert_eq!(&packed[101..109], &1_000_000_000u64.to_le_bytes());
+        assert_eq!(&packed[109..111], &1311u16.to_le_bytes());
+        assert_eq!(&packed[111..119], &5_000u64.to_le_bytes());
+        assert_eq!(&packed[119..127], &1_000u64.to_le_bytes());
+        assert_eq!(&packed[127..135], &500u64.to_le_bytes());
+        assert_eq!(&packed[135..143], &250u64.to_le_bytes());
+        assert_eq!(&packed[143..151], &7u64.to_le_bytes());
+        assert_eq!(&packed[151..159], &12u64.to_le_bytes());
+        assert_eq!(&packed[159..167], &3u64.to_le_bytes());
+        assert_eq!(&packed[167..175], &5u64.to_le_bytes());
+        assert_eq!(&packed[175..183], &100_000_000u64.to_le_bytes());
+        assert_eq!(&packed[183..191], &1_000_604_800u64.to_le_bytes());
+        assert_eq!(&packed[191..199], &1_000_000_100u64.to_le_bytes());
+        assert_eq!(&packed[199..201], &3277u16.to_le_bytes());
+        assert_eq!(packed[201], 2);
+        assert_eq!(
+            &packed[234..266],
+            &header_state.multisig_signers[1].to_bytes()
+        );
+        assert_eq!(&packed[298..362], &[0u8; 64][..]);
+        assert_eq!(&packed[362..364], &6554u16.to_le_bytes());
+        assert_eq!(&packed[364..368], &(1u32 << 17).to_le_bytes());
+        assert_eq!(&packed[368..370], &24u16.to_le_bytes());
+        assert_eq!(&packed[370..372], &3277u16.to_le_bytes());
+        assert_eq!(packed[372], 0b010);
+        assert_eq!(packed[373], 0b101);
+        assert_eq!(&packed[374..382], &1_000_000_050u64.to_le_bytes());
+        assert_eq!(&packed[382..386], &70_000u32.to_le_bytes());
+        assert_eq!(&packed[386..388], &3u16.to_le_bytes());
+        assert_eq!(&packed[388..390], &2u16.to_le_bytes());
+    }
+
+    #[test]
+    fn test_redeem_request_packing() {
+        let request = RedeemRequest {
+            owner: Pubkey::new_unique(),
+            pool_token_amount: 1_000_000,
+            nonce: 254,
+        };
+        let packed = get_packed(&request);
+        assert_eq!(&packed[..32], &request.owner.to_bytes());
+        assert_eq!(&packed[32..40], &1_000_000u64.to_le_bytes());
+        assert_eq!(packed[40], 254);
+        assert_eq!(RedeemRequest::unpack(&packed).unwrap(), request);
+
+        // Closed request accounts are zeroed
+        assert_eq!(
+            RedeemRequest::unpack(&[0; RedeemRequest::LEN]),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    #[test]
+    fn test_deposit_request_packing() {
+        let request = DepositRequest {
+            owner: Pubkey::new_unique(),
+            pool_token_amount: 1_000_000,
+            expiry_timestamp: 1_600_000_000,
+            nonce: 253,
+            escrowed_mints: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+        };
+        let mut packed = vec![0u8; DepositRequest::size(2)];
+        request.pack_into_slice(&mut packed);
+        assert_eq!(&packed[..32], &request.owner.to_bytes());
+        assert_eq!(&packed[32..40], &1_000_000u64.to_le_bytes());
+        assert_eq!(&packed[40..48], &1_600_000_000u64.to_le_bytes());
+        assert_eq!(packed[48], 253);
+        assert_eq!(&packed[49..53], &2u32.to_le_bytes());
+        assert_eq!(&packed[53..85], &request.escrowed_mints[0].to_bytes());
+        assert_eq!(&packed[85..], &request.escrowed_mints[1].to_bytes());
+        assert_eq!(DepositRequest::unpack(&packed).unwrap(), request);
+        assert!(DepositRequest::unpack(&packed[..DepositRequest::size(2) - 1]).is_err());
+        assert_eq!(
+            DepositRequest::unpack(&[0; DepositRequest::LEN]),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    #[test]
+    fn test_trader_packing() {
+        let trader = Trader {
+            trader: Pubkey::new_unique(),
+            max_ratio_of_pool_assets_to_trade: 6554,
+            nonce: 252,
+        };
+        let packed = get_packed(&trader);
+        assert_eq!(&packed[..32], &trader.trader.to_bytes());
+        assert_eq!(&packed[32..34], &6554u16.to_le_bytes());
+        assert_eq!(packed[34], 252);
+        assert_eq!(Trader::unpack(&packed).unwrap(), trader);
+
+        // Revoked trader accounts are zeroed
+        assert_eq!(
+            Trader::unpack(&[0; Trader::LEN]),
+            Err(ProgramError::UninitializedAccount)
+        );
     }
 
     fn get_packed<T: Pack>(obj: &T) -> Vec<u8> {
@@ -340,19 +1058,4 @@ mod tests {
         let pool_asset = PoolAsset::unpack_unchecked(&[0u8; PoolAsset::LEN]).unwrap();
         assert!(!pool_asset.is_initialized());
     }
-
-    #[test]
-    fn test_market_packing() {
-        let markets = vec![
-            Pubkey::new_unique(),
-            Pubkey::new_unique(),
-            Pubkey::new_unique(),
-            Pubkey::new_unique(),
-        ];
-        let mut output_array = [0u8; 4 * 32];
-        pack_markets(&mut output_array, &markets).unwrap();
-        for i in 0..4 {
-            assert_eq!(markets[i], unpack_market(&output_array, i as u16));
-        }
-    }
 }
@@ -1,70 +1,733 @@
+use std::{cmp::min, convert::TryInto, str::FromStr};
+
+use serum_dex::matching::Side;
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
-    pubkey::Pubkey,
+    program_pack::Pack, pubkey::Pubkey,
 };
+use spl_associated_token_account::get_associated_token_address;
 
-use crate::state::PoolHeader;
+use crate::{
+    error::BonfidaBotError,
+    instruction::{OrderType, SelfTradeBehavior},
+    math::mul_div_floor,
+    state::{
+        PoolHeader, Trader, BOND_SEED, BONFIDA_BNB, BONFIDA_FEE, DEPOSIT_REQUEST_SEED, FIDA_MINT,
+        LEGACY_MIN_FEE_COLLECTION_PERIOD, MAX_MULTISIG_SIGNERS, REDEEM_REQUEST_SEED,
+        SECONDS_PER_YEAR, TRADER_SEED, TRADING_DAY_DURATION,
+    },
+};
 
 pub fn check_pool_key(program_id: &Pubkey, key: &Pubkey, pool_seed: &[u8; 32]) -> ProgramResult {
     let expected_key = Pubkey::create_program_address(&[pool_seed], program_id)?;
 
     if &expected_key != key {
         msg!("Provided pool account does not match the provided pool seed");
-        return Err(ProgramError::InvalidArgument);
+        return Err(BonfidaBotError::InvalidPoolKey.into());
     }
 
     Ok(())
 }
 
+/// Returns the address of the redeem request of a pool with the given sequence number, along with
+/// its bump seed.
+pub fn find_redeem_request_key(
+    program_id: &Pubkey,
+    pool_seed: &[u8; 32],
+    sequence: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[pool_seed, REDEEM_REQUEST_SEED, &sequence.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Returns the address of the deposit request of a pool with the given sequence number, along
+/// with its bump seed.
+pub fn find_deposit_request_key(
+    program_id: &Pubkey,
+    pool_seed: &[u8; 32],
+    sequence: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[pool_seed, DEPOSIT_REQUEST_SEED, &sequence.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Returns the address of the account owning the bond escrow of a pool, along with its bump seed.
+pub fn find_bond_key(program_id: &Pubkey, pool_seed: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[pool_seed, BOND_SEED], program_id)
+}
+
+/// Checks the bond account and its associated FIDA escrow account against the pool seed, and
+/// returns the bump seed of the bond account.
+pub fn check_bond_accounts(
+    program_id: &Pubkey,
+    pool_seed: &[u8; 32],
+    fida_mint_key: &Pubkey,
+    bond_account: &AccountInfo,
+    escrow_account: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    if fida_mint_key != &Pubkey::from_str(FIDA_MINT).unwrap() {
+        msg!("Provided FIDA mint account is invalid");
+        return Err(BonfidaBotError::InvalidMint.into());
+    }
+    let (bond_key, nonce) = find_bond_key(program_id, pool_seed);
+    if &bond_key != bond_account.key {
+        msg!("Provided bond account does not match the pool seed");
+        return Err(BonfidaBotError::InvalidBondAccount.into());
+    }
+    if &get_associated_token_address(&bond_key, fida_mint_key) != escrow_account.key {
+        msg!("Provided bond escrow account is invalid");
+        return Err(BonfidaBotError::InvalidBondAccount.into());
+    }
+    Ok(nonce)
+}
+
+/// Checks the signal provider account and, when `is_signer` is set, its signature. For a pool
+/// with a multisig signal provider, the signature is replaced by `multisig_threshold` distinct
+/// multisig signers among the signing `accounts` of the instruction.
 pub fn check_signal_provider(
     pool_header: &PoolHeader,
     signal_provider_account: &AccountInfo,
     is_signer: bool,
+    accounts: &[AccountInfo],
 ) -> ProgramResult {
     if &pool_header.signal_provider != signal_provider_account.key {
         msg!("A wrong signal provider account was provided.");
-        return Err(ProgramError::MissingRequiredSignature);
+        return Err(BonfidaBotError::InvalidSignalProvider.into());
+    }
+    if !is_signer {
+        return Ok(());
+    }
+    if pool_header.multisig_threshold == 0 {
+        if !signal_provider_account.is_signer {
+            msg!("The signal provider's signature is required.");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        return Ok(());
+    }
+    // The multisig signers are distinct, an account passed twice is only counted once
+    let number_of_signers = pool_header
+        .multisig_signers
+        .iter()
+        .filter(|k| **k != Pubkey::default())
+        .filter(|k| accounts.iter().any(|a| a.is_signer && a.key == *k))
+        .count();
+    if number_of_signers < pool_header.multisig_threshold as usize {
+        msg!(
+            "{} of the multisig signers have signed, {} are required.",
+            number_of_signers,
+            pool_header.multisig_threshold
+        );
+        return Err(BonfidaBotError::NotEnoughSigners.into());
+    }
+    Ok(())
+}
+
+/// Checks a multisig signal provider configuration : a threshold of 0 without signers disables
+/// the multisig, otherwise the threshold is at most the number of signers, which are distinct.
+pub fn check_multisig_config(threshold: u8, signers: &[Pubkey]) -> ProgramResult {
+    let is_valid = if threshold == 0 {
+        signers.is_empty()
+    } else {
+        signers.len() <= MAX_MULTISIG_SIGNERS
+            && threshold as usize <= signers.len()
+            && signers.iter().all(|k| *k != Pubkey::default())
+            && signers
+                .iter()
+                .enumerate()
+                .all(|(i, k)| !signers[..i].contains(k))
+    };
+    if !is_valid {
+        msg!("The multisig threshold or signers are invalid.");
+        return Err(BonfidaBotError::InvalidMultisig.into());
+    }
+    Ok(())
+}
+
+/// Returns the address of the account allowing `trader` to trade for a pool, along with its bump
+/// seed. The signal provider is part of the seeds so that the traders it added are not carried
+/// over to a pool created again on the same seed by another signal provider.
+pub fn find_trader_key(
+    program_id: &Pubkey,
+    pool_seed: &[u8; 32],
+    signal_provider: &Pubkey,
+    trader: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            pool_seed,
+            TRADER_SEED,
+            &signal_provider.to_bytes(),
+            &trader.to_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Checks that the signing account is allowed to trade for the pool, which is the case of the
+/// signal provider and of the keys with a trader account. Returns the trader account data when
+/// the signer is not the signal provider. A multisig signal provider is checked against the
+/// signing `accounts` of the instruction.
+pub fn check_trader(
+    program_id: &Pubkey,
+    pool_seed: &[u8; 32],
+    pool_header: &PoolHeader,
+    signer_account: &AccountInfo,
+    trader_account: Option<&AccountInfo>,
+    accounts: &[AccountInfo],
+) -> Result<Option<Trader>, ProgramError> {
+    if &pool_header.signal_provider == signer_account.key {
+        check_signal_provider(pool_header, signer_account, true, accounts)?;
+        return Ok(None);
     }
-    if is_signer & !signal_provider_account.is_signer {
-        msg!("The signal provider's signature is required.");
+    if !signer_account.is_signer {
+        msg!("The signature of the signal provider or of a trader is required.");
         return Err(ProgramError::MissingRequiredSignature);
     }
+    let trader_account = trader_account.ok_or_else(|| {
+        msg!("A trader account should be provided when the signal provider does not sign.");
+        BonfidaBotError::InvalidTrader
+    })?;
+    let (trader_key, _) = find_trader_key(
+        program_id,
+        pool_seed,
+        &pool_header.signal_provider,
+        signer_account.key,
+    );
+    if &trader_key != trader_account.key || trader_account.owner != program_id {
+        msg!("Provided trader account does not match the signer.");
+        return Err(BonfidaBotError::InvalidTrader.into());
+    }
+    // Revoked trader accounts are closed and zeroed
+    let trader = Trader::unpack(&trader_account.data.borrow()).map_err(|_| {
+        msg!("The signer is not a trader of the pool.");
+        BonfidaBotError::InvalidTrader
+    })?;
+    Ok(Some(trader))
+}
+
+/// Checks that the associated pool token account of the signal provider holds at least
+/// `min_signal_provider_ratio` of the pool tokens, given its balance and the pool token supply.
+pub fn check_signal_provider_stake(
+    pool_header: &PoolHeader,
+    signal_provider_pool_tokens: u64,
+    total_pooltokens: u64,
+) -> ProgramResult {
+    if ((signal_provider_pool_tokens as u128) << 16)
+        < total_pooltokens as u128 * pool_header.min_signal_provider_ratio as u128
+    {
+        msg!("The signal provider should hold a larger share of the pool tokens.");
+        return Err(BonfidaBotError::SignalProviderStakeTooLow.into());
+    }
     Ok(())
 }
 
-pub fn fill_slice(target: &mut [u8], val: u8) {
-    for i in 0..target.len() {
-        target[i] = val;
+/// Checks an order trading `ratio` of a pool asset against the trade limits of the pool and
+/// accounts for it in the current trading day, which is restarted once it has ended.
+pub fn record_order(
+    pool_header: &mut PoolHeader,
+    ratio: u16,
+    current_timestamp: u64,
+) -> ProgramResult {
+    let limits = pool_header.trade_limits;
+    if limits.max_order_ratio != 0 && ratio > limits.max_order_ratio {
+        msg!("The order trades a larger share of the pool asset than allowed.");
+        return Err(BonfidaBotError::OrderTooLarge.into());
+    }
+    let trading_day_end = pool_header
+        .trading_day_start
+        .saturating_add(TRADING_DAY_DURATION);
+    if current_timestamp >= trading_day_end {
+        pool_header.trading_day_start = current_timestamp;
+        pool_header.daily_turnover = 0;
+        pool_header.daily_orders = 0;
+    }
+    let daily_turnover = pool_header.daily_turnover.saturating_add(ratio as u32);
+    if limits.max_daily_turnover != 0 && daily_turnover > limits.max_daily_turnover {
+        msg!("The order exceeds the daily turnover of the pool.");
+        return Err(BonfidaBotError::DailyTurnoverExceeded.into());
+    }
+    let daily_orders = pool_header.daily_orders.saturating_add(1);
+    if limits.max_daily_orders != 0 && daily_orders > limits.max_daily_orders {
+        msg!("The pool has created its maximum number of orders for the day.");
+        return Err(BonfidaBotError::TooManyDailyOrders.into());
     }
+    pool_header.daily_turnover = daily_turnover;
+    pool_header.daily_orders = daily_orders;
+    Ok(())
 }
 
-pub fn pow_fixedpoint_u16(x: u32, n: u64) -> u32 {
-    if n == 1{
-        x
-    } else {
-        let q = n >> 1;
-        if q == 0 {
-            return x
-        }
-        let p = pow_fixedpoint_u16(x, n >> 1);
-        let sq = (p * p) >> 16;
-        if n & 1 == 1 {
-            (sq * x) >> 16
-        } else {
-            sq
+/// Checks that the limit price of an order is within the price band of the pool. The reference is
+/// the best price on the other side of the market book, or on the same side when it is empty.
+/// Buying above or selling below the reference by more than `max_price_deviation` is rejected.
+pub fn check_price_band(
+    pool_header: &PoolHeader,
+    side: Side,
+    limit_price: u64,
+    best_bid: Option<u64>,
+    best_ask: Option<u64>,
+) -> ProgramResult {
+    let max_price_deviation = pool_header.trade_limits.max_price_deviation as u128;
+    if max_price_deviation == 0 {
+        return Ok(());
+    }
+    let reference_price = match side {
+        Side::Bid => best_ask.or(best_bid),
+        Side::Ask => best_bid.or(best_ask),
+    }
+    .ok_or_else(|| {
+        msg!("The market book is empty, there is no reference price.");
+        BonfidaBotError::PriceOutOfBand
+    })? as u128;
+    let limit_price = (limit_price as u128) << 16;
+    let in_band = match side {
+        Side::Bid => limit_price <= reference_price * ((1 << 16) + max_price_deviation),
+        Side::Ask => limit_price >= reference_price * ((1 << 16) - max_price_deviation),
+    };
+    if !in_band {
+        msg!("The limit price deviates too much from the market price.");
+        return Err(BonfidaBotError::PriceOutOfBand.into());
+    }
+    Ok(())
+}
+
+// Serum order book slab layout, after the account head padding and flags
+const SLAB_OFFSET: usize = 13;
+const SLAB_HEADER_LEN: usize = 32;
+const SLAB_NODE_LEN: usize = 72;
+const INNER_NODE_TAG: u32 = 1;
+const LEAF_NODE_TAG: u32 = 2;
+
+/// Returns the best price of a Serum order book given the data of its bids or asks account, or
+/// `None` when the book is empty. The best bid is the largest key of the critbit tree and the
+/// best ask the smallest one, the price being the upper half of the key.
+pub fn best_book_price(book_data: &[u8], side: Side) -> Result<Option<u64>, ProgramError> {
+    let read_u32 = |offset: usize| {
+        book_data
+            .get(offset..offset + 4)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or(ProgramError::InvalidAccountData)
+    };
+    let leaf_count = book_data
+        .get(SLAB_OFFSET + 24..SLAB_OFFSET + 32)
+        .and_then(|slice| slice.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    if leaf_count == 0 {
+        return Ok(None);
+    }
+    let child_offset = match side {
+        Side::Bid => 28,
+        Side::Ask => 24,
+    };
+    let mut index = read_u32(SLAB_OFFSET + 20)?;
+    // A critbit tree over 128 bit keys is at most 128 inner nodes deep
+    for _ in 0..=128 {
+        let node = SLAB_OFFSET + SLAB_HEADER_LEN + index as usize * SLAB_NODE_LEN;
+        match read_u32(node)? {
+            INNER_NODE_TAG => index = read_u32(node + child_offset)?,
+            LEAF_NODE_TAG => {
+                let key = book_data
+                    .get(node + 8..node + 24)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u128::from_le_bytes)
+                    .ok_or(ProgramError::InvalidAccountData)?;
+                return Ok(Some((key >> 64) as u64));
+            }
+            _ => break,
         }
     }
+    msg!("The market book could not be read.");
+    Err(ProgramError::InvalidAccountData)
+}
+
+/// Checks that the order type and the self trade behavior of an order are allowed by the pool.
+pub fn check_order_policy(
+    pool_header: &PoolHeader,
+    order_type: OrderType,
+    self_trade_behavior: SelfTradeBehavior,
+) -> ProgramResult {
+    let allowed_order_types = pool_header.trade_limits.allowed_order_types;
+    if allowed_order_types != 0 && allowed_order_types & (1 << order_type as u8) == 0 {
+        msg!("The order type is not allowed by the pool.");
+        return Err(BonfidaBotError::OrderTypeNotAllowed.into());
+    }
+    let allowed_self_trade_behaviors = pool_header.trade_limits.allowed_self_trade_behaviors;
+    if allowed_self_trade_behaviors != 0
+        && allowed_self_trade_behaviors & (1 << self_trade_behavior as u8) == 0
+    {
+        msg!("The self trade behavior is not allowed by the pool.");
+        return Err(BonfidaBotError::SelfTradeBehaviorNotAllowed.into());
+    }
+    Ok(())
+}
+
+/// Returns the index of the fee recipient owning a pool token account, in the order of
+/// `split_fees`. The account has to be the associated pool token account of the signal provider,
+/// Bonfida fee or Bonfida buy and burn owner.
+pub fn get_fee_recipient(
+    pool_header: &PoolHeader,
+    pool_mint_key: &Pubkey,
+    pool_token_account: &AccountInfo,
+) -> Result<usize, ProgramError> {
+    let recipients = [
+        pool_header.signal_provider,
+        Pubkey::from_str(BONFIDA_FEE).unwrap(),
+        Pubkey::from_str(BONFIDA_BNB).unwrap(),
+    ];
+    recipients
+        .iter()
+        .position(|owner| {
+            &get_associated_token_address(owner, pool_mint_key) == pool_token_account.key
+        })
+        .ok_or_else(|| {
+            msg!("The provided pool token account does not belong to a fee recipient.");
+            BonfidaBotError::InvalidFeeAccount.into()
+        })
+}
+
+/// Computes the amount of pool tokens accrued as management fees for `elapsed` seconds, so that
+/// the fee recipients end up with `annual_fee_ratio * elapsed / SECONDS_PER_YEAR` of the new supply.
+/// The share taken by a single collection is capped at one half of the pool.
+pub fn accrued_fee_tokens(
+    supply: u64,
+    annual_fee_ratio: u16,
+    elapsed: u64,
+) -> Result<u64, BonfidaBotError> {
+    let year = SECONDS_PER_YEAR << 16;
+    let fee_share: u64 = min(
+        annual_fee_ratio as u128 * elapsed as u128,
+        (year / 2) as u128,
+    )
+    .try_into()
+    .map_err(|_| BonfidaBotError::Overflow)?;
+    mul_div_floor(supply, fee_share, year - fee_share)
+}
+
+/// Converts the fee taken every `fee_collection_period` by the original program into the annual
+/// fee ratio taking the same share of the pool over a year.
+pub fn legacy_annual_fee_ratio(
+    fee_collection_period: u64,
+    fee_ratio: u16,
+) -> Result<u16, ProgramError> {
+    if fee_collection_period < LEGACY_MIN_FEE_COLLECTION_PERIOD {
+        msg!("Fee collection period should be longer than a week.");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let annual_fee_ratio =
+        fee_ratio as u128 * SECONDS_PER_YEAR as u128 / fee_collection_period as u128;
+    // Ratios that do not fit are rejected by the annual fee ratio cap
+    Ok(min(annual_fee_ratio, u16::MAX as u128) as u16)
+}
+
+pub fn fill_slice(target: &mut [u8], val: u8) {
+    for i in 0..target.len() {
+        target[i] = val;
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::pow_fixedpoint_u16;
+    use std::num::NonZeroU8;
+
+    use serum_dex::matching::Side;
+    use solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+
+    use super::{
+        accrued_fee_tokens, best_book_price, check_multisig_config, check_order_policy,
+        check_price_band, check_signal_provider, check_signal_provider_stake,
+        legacy_annual_fee_ratio, record_order,
+    };
+    use crate::{
+        error::BonfidaBotError,
+        instruction::{OrderType, SelfTradeBehavior},
+        state::{
+            PoolHeader, PoolStatus, TradeLimits, MAX_MULTISIG_SIGNERS, SECONDS_PER_YEAR,
+            TRADING_DAY_DURATION,
+        },
+    };
+
+    #[test]
+    fn test_accrued_fee_tokens() {
+        let supply = 1_000_000_000_000;
+        // A 2^-4 annual fee over a full year dilutes the holders by exactly 2^-4
+        let fees = accrued_fee_tokens(supply, 1 << 12, SECONDS_PER_YEAR).unwrap();
+        assert_eq!(fees, supply / 15);
+        // Fees accrue by the second and are not lost to rounding on short intervals
+        assert!(accrued_fee_tokens(supply, 1311, 30).unwrap() > 0);
+        assert!(
+            accrued_fee_tokens(supply, 1311, 7200).unwrap()
+                >= accrued_fee_tokens(supply, 1311, 3600).unwrap() * 2
+        );
+        assert_eq!(accrued_fee_tokens(supply, 1311, 0).unwrap(), 0);
+        assert_eq!(accrued_fee_tokens(0, 1311, SECONDS_PER_YEAR).unwrap(), 0);
+        // A single collection never takes more than half of the pool
+        assert_eq!(
+            accrued_fee_tokens(supply, u16::MAX, 100 * SECONDS_PER_YEAR).unwrap(),
+            supply
+        );
+    }
+
+    #[test]
+    fn test_legacy_annual_fee_ratio() {
+        // A weekly fee is taken 52 times a year
+        assert_eq!(legacy_annual_fee_ratio(604_800, 15).unwrap(), 782);
+        assert_eq!(
+            legacy_annual_fee_ratio(SECONDS_PER_YEAR, 1311).unwrap(),
+            1311
+        );
+        assert_eq!(
+            legacy_annual_fee_ratio(604_800, u16::MAX).unwrap(),
+            u16::MAX
+        );
+        assert!(legacy_annual_fee_ratio(604_799, 15).is_err());
+    }
+
+    fn get_header() -> PoolHeader {
+        PoolHeader {
+            serum_program_id: Pubkey::new_unique(),
+            signal_provider: Pubkey::new_unique(),
+            status: PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap()),
+            number_of_markets: 1,
+            fee_ratio: 15,
+            annual_fee_ratio: 1311,
+            ..PoolHeader::default()
+        }
+    }
+
+    #[test]
+    fn test_check_signal_provider_stake() {
+        let mut header = get_header();
+        // No requirement
+        assert!(check_signal_provider_stake(&header, 0, 1_000_000).is_ok());
+
+        // A quarter of the supply
+        header.min_signal_provider_ratio = 1 << 14;
+        assert!(check_signal_provider_stake(&header, 250_000, 1_000_000).is_ok());
+        assert_eq!(
+            check_signal_provider_stake(&header, 249_999, 1_000_000),
+            Err(BonfidaBotError::SignalProviderStakeTooLow.into())
+        );
+        // An empty pool has no requirement left
+        assert!(check_signal_provider_stake(&header, 0, 0).is_ok());
+        assert!(check_signal_provider_stake(&header, u64::MAX, u64::MAX).is_ok());
+    }
 
     #[test]
-    fn test_exp(){
-        let half:u16 = 1<<15;
-        for i in 1..16 {
-            assert_eq!(pow_fixedpoint_u16(half as u32, i), 1<<(16 - i));
+    fn test_check_signal_provider_multisig() {
+        let mut header = get_header();
+        let keys = [
+            header.signal_provider,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let owner = Pubkey::new_unique();
+        let mut lamports = [0u64; 3];
+        let mut data = [[0u8; 0]; 3];
+        let mut lamports_iter = lamports.iter_mut();
+        let mut data_iter = data.iter_mut();
+        let mut accounts: Vec<AccountInfo> = keys
+            .iter()
+            .map(|key| {
+                AccountInfo::new(
+                    key,
+                    true,
+                    false,
+                    lamports_iter.next().unwrap(),
+                    data_iter.next().unwrap(),
+                    &owner,
+                    false,
+                    0,
+                )
+            })
+            .collect();
+
+        // Single signal provider
+        assert!(check_signal_provider(&header, &accounts[0], true, &accounts).is_ok());
+        assert!(check_signal_provider(&header, &accounts[1], false, &accounts).is_err());
+
+        // Two of three, the signal provider signature alone is not enough
+        header.multisig_threshold = 2;
+        header.multisig_signers[..3].copy_from_slice(&keys);
+        accounts[1].is_signer = false;
+        accounts[2].is_signer = false;
+        assert_eq!(
+            check_signal_provider(&header, &accounts[0], true, &accounts),
+            Err(BonfidaBotError::NotEnoughSigners.into())
+        );
+        // A signer passed twice counts once
+        let duplicate = accounts[0].clone();
+        accounts.push(duplicate);
+        assert!(check_signal_provider(&header, &accounts[0], true, &accounts).is_err());
+        // The signal provider account does not have to sign
+        accounts[0].is_signer = false;
+        accounts[3].is_signer = false;
+        accounts[1].is_signer = true;
+        accounts[2].is_signer = true;
+        assert!(check_signal_provider(&header, &accounts[0], true, &accounts).is_ok());
+        // No signature is needed when the signal provider is only checked
+        accounts[2].is_signer = false;
+        assert!(check_signal_provider(&header, &accounts[0], false, &accounts).is_ok());
+    }
+
+    #[test]
+    fn test_record_order() {
+        let mut header = get_header();
+        let start = 1_000_000;
+        // No limits
+        assert!(record_order(&mut header, u16::MAX, start).is_ok());
+        assert_eq!(header.trading_day_start, start);
+        assert_eq!(header.daily_turnover, u16::MAX as u32);
+        assert_eq!(header.daily_orders, 1);
+
+        header.trade_limits = TradeLimits {
+            max_order_ratio: 1 << 14,
+            max_daily_turnover: 1 << 16,
+            max_daily_orders: 3,
+            ..TradeLimits::default()
+        };
+        header.daily_turnover = 0;
+        header.daily_orders = 0;
+        assert_eq!(
+            record_order(&mut header, (1 << 14) + 1, start),
+            Err(BonfidaBotError::OrderTooLarge.into())
+        );
+        assert!(record_order(&mut header, 1 << 14, start).is_ok());
+        assert!(record_order(&mut header, 1 << 14, start + 10).is_ok());
+        assert!(record_order(&mut header, 1 << 14, start + 20).is_ok());
+        assert_eq!(
+            record_order(&mut header, 1 << 14, start + 30),
+            Err(BonfidaBotError::TooManyDailyOrders.into())
+        );
+        // Rejected orders are not counted
+        assert_eq!(header.daily_orders, 3);
+        assert_eq!(header.daily_turnover, 3 << 14);
+
+        // A new trading day starts with the first order after the end of the previous one
+        let next_day = start + TRADING_DAY_DURATION + 100;
+        assert!(record_order(&mut header, 1 << 14, next_day).is_ok());
+        assert_eq!(header.trading_day_start, next_day);
+        assert_eq!(header.daily_orders, 1);
+        header.trade_limits.max_daily_orders = 0;
+        assert!(record_order(&mut header, 1 << 14, next_day + 1).is_ok());
+        assert!(record_order(&mut header, 1 << 14, next_day + 2).is_ok());
+        assert!(record_order(&mut header, 1 << 14, next_day + 3).is_ok());
+        assert_eq!(
+            record_order(&mut header, 1, next_day + 4),
+            Err(BonfidaBotError::DailyTurnoverExceeded.into())
+        );
+    }
+
+    #[test]
+    fn test_check_price_band() {
+        let mut header = get_header();
+        // No band
+        assert!(check_price_band(&header, Side::Ask, 1, None, None).is_ok());
+
+        // 5% around the best price on the other side
+        header.trade_limits.max_price_deviation = 3277;
+        assert!(check_price_band(&header, Side::Ask, 950, Some(1000), Some(1010)).is_ok());
+        assert_eq!(
+            check_price_band(&header, Side::Ask, 949, Some(1000), Some(1010)),
+            Err(BonfidaBotError::PriceOutOfBand.into())
+        );
+        assert!(check_price_band(&header, Side::Ask, u64::MAX, Some(1000), None).is_ok());
+        assert!(check_price_band(&header, Side::Bid, 1060, Some(1000), Some(1010)).is_ok());
+        assert!(check_price_band(&header, Side::Bid, 1061, Some(1000), Some(1010)).is_err());
+        assert!(check_price_band(&header, Side::Bid, 1, Some(1000), Some(1010)).is_ok());
+        // The same side is used when the other one is empty
+        assert!(check_price_band(&header, Side::Ask, 949, None, Some(1000)).is_err());
+        assert!(check_price_band(&header, Side::Bid, 1050, Some(1000), None).is_ok());
+        assert_eq!(
+            check_price_band(&header, Side::Bid, 1000, None, None),
+            Err(BonfidaBotError::PriceOutOfBand.into())
+        );
+    }
+
+    #[test]
+    fn test_best_book_price() {
+        // Header with the root at node 0, an inner node over two leaves priced 1000 and 1010
+        let mut book = vec![0u8; 13 + 32 + 3 * 72];
+        book[13 + 20..13 + 24].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(best_book_price(&book, Side::Bid), Ok(None));
+        book[13 + 24..13 + 32].copy_from_slice(&2u64.to_le_bytes());
+        let node = |i: usize| 13 + 32 + i * 72;
+        book[node(0)..node(0) + 4].copy_from_slice(&1u32.to_le_bytes());
+        book[node(0) + 24..node(0) + 28].copy_from_slice(&1u32.to_le_bytes());
+        book[node(0) + 28..node(0) + 32].copy_from_slice(&2u32.to_le_bytes());
+        for (i, price) in [(1, 1000u128), (2, 1010)].iter() {
+            book[node(*i)..node(*i) + 4].copy_from_slice(&2u32.to_le_bytes());
+            book[node(*i) + 8..node(*i) + 24].copy_from_slice(&(price << 64 | 7).to_le_bytes());
         }
+        assert_eq!(best_book_price(&book, Side::Bid), Ok(Some(1010)));
+        assert_eq!(best_book_price(&book, Side::Ask), Ok(Some(1000)));
+
+        // Unknown node tags and truncated books are rejected
+        book[node(2)..node(2) + 4].copy_from_slice(&3u32.to_le_bytes());
+        assert!(best_book_price(&book, Side::Bid).is_err());
+        assert!(best_book_price(&book[..node(1)], Side::Ask).is_err());
+    }
+
+    #[test]
+    fn test_check_order_policy() {
+        let mut header = get_header();
+        // Everything is allowed by default
+        assert!(check_order_policy(
+            &header,
+            OrderType::PostOnly,
+            SelfTradeBehavior::AbortTransaction
+        )
+        .is_ok());
+
+        // Immediate or cancel orders only, without aborting on self trades
+        header.trade_limits.allowed_order_types = 1 << OrderType::ImmediateOrCancel as u8;
+        header.trade_limits.allowed_self_trade_behaviors = (1
+            << SelfTradeBehavior::DecrementTake as u8)
+            | (1 << SelfTradeBehavior::CancelProvide as u8);
+        assert!(check_order_policy(
+            &header,
+            OrderType::ImmediateOrCancel,
+            SelfTradeBehavior::CancelProvide
+        )
+        .is_ok());
+        assert_eq!(
+            check_order_policy(&header, OrderType::Limit, SelfTradeBehavior::DecrementTake),
+            Err(BonfidaBotError::OrderTypeNotAllowed.into())
+        );
+        assert_eq!(
+            check_order_policy(
+                &header,
+                OrderType::PostOnly,
+                SelfTradeBehavior::DecrementTake
+            ),
+            Err(BonfidaBotError::OrderTypeNotAllowed.into())
+        );
+        assert_eq!(
+            check_order_policy(
+                &header,
+                OrderType::ImmediateOrCancel,
+                SelfTradeBehavior::AbortTransaction
+            ),
+            Err(BonfidaBotError::SelfTradeBehaviorNotAllowed.into())
+        );
+    }
+
+    #[test]
+    fn test_check_multisig_config() {
+        let keys: Vec<Pubkey> = (0..MAX_MULTISIG_SIGNERS + 1)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        assert!(check_multisig_config(0, &[]).is_ok());
+        assert!(check_multisig_config(2, &keys[..3]).is_ok());
+        assert!(check_multisig_config(1, &keys[..MAX_MULTISIG_SIGNERS]).is_ok());
+        assert!(check_multisig_config(0, &keys[..1]).is_err());
+        assert!(check_multisig_config(3, &keys[..2]).is_err());
+        assert!(check_multisig_config(1, &keys).is_err());
+        assert!(check_multisig_config(1, &[keys[0], keys[0]]).is_err());
+        assert!(check_multisig_config(1, &[Pubkey::default()]).is_err());
     }
 }
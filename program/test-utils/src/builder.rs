@@ -0,0 +1,244 @@
+//! Builder setting up a created pool, its Serum market and funded actors in a few calls.
+use std::num::{NonZeroU16, NonZeroU64};
+
+use bonfida_bot::state::TradeLimits;
+use serum_dex::{
+    instruction::SelfTradeBehavior,
+    matching::{OrderType, Side},
+};
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transport::TransportError,
+};
+use spl_token::state::Account;
+
+use super::{
+    market::SerumMarket,
+    pool::{Order, TestPool},
+    simulation::Actor,
+    utils::Context,
+};
+
+pub struct PoolBuilder {
+    number_of_mints: usize,
+    deposit_amounts: Option<Vec<u64>>,
+    annual_fee_ratio: u16,
+    fee_ratio: u16,
+    min_signal_provider_ratio: u16,
+    trade_limits: TradeLimits,
+    without_fida: bool,
+}
+
+/// A created pool trading on a Serum market between its last two assets, the last one being the
+/// coin and the one before the price currency.
+pub struct TestEnvironment {
+    pub pool: TestPool,
+    pub market: SerumMarket,
+    /// Holder of the pool tokens minted on creation
+    pub creator: Actor,
+}
+
+impl Default for PoolBuilder {
+    fn default() -> Self {
+        Self {
+            number_of_mints: 4,
+            deposit_amounts: None,
+            annual_fee_ratio: 1311,
+            fee_ratio: 100,
+            min_signal_provider_ratio: 0,
+            trade_limits: TradeLimits::default(),
+            without_fida: false,
+        }
+    }
+}
+
+impl PoolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of assets of the pool, at least two. Mints are created as needed.
+    pub fn mints(mut self, number_of_mints: usize) -> Self {
+        assert!(number_of_mints >= 2, "The pool market needs two assets");
+        self.number_of_mints = number_of_mints;
+        self
+    }
+
+    /// Sets the initial deposit of each asset, 1 000 000 tokens of each by default.
+    pub fn deposit_amounts(mut self, deposit_amounts: Vec<u64>) -> Self {
+        self.deposit_amounts = Some(deposit_amounts);
+        self
+    }
+
+    /// Sets the annual management fee ratio, in 16 bit fixed point.
+    pub fn annual_fee_ratio(mut self, annual_fee_ratio: u16) -> Self {
+        self.annual_fee_ratio = annual_fee_ratio;
+        self
+    }
+
+    /// Sets the deposit fee ratio, in 16 bit fixed point.
+    pub fn fee_ratio(mut self, fee_ratio: u16) -> Self {
+        self.fee_ratio = fee_ratio;
+        self
+    }
+
+    /// Sets the share of the pool tokens the signal provider has to hold, in 16 bit fixed point.
+    pub fn min_signal_provider_ratio(mut self, min_signal_provider_ratio: u16) -> Self {
+        self.min_signal_provider_ratio = min_signal_provider_ratio;
+        self
+    }
+
+    /// Sets the limits on the orders of the pool, none by default.
+    pub fn trade_limits(mut self, trade_limits: TradeLimits) -> Self {
+        self.trade_limits = trade_limits;
+        self
+    }
+
+    /// Leaves FIDA out of the pool assets, which otherwise start with the FIDA mint.
+    pub fn without_fida(mut self) -> Self {
+        self.without_fida = true;
+        self
+    }
+
+    /// Creates the mints, the Serum market and the pool, funded by a new creator.
+    pub async fn build(self, ctx: &mut Context) -> Result<TestEnvironment, TransportError> {
+        let mut mints = ctx.get_mints();
+        if self.without_fida {
+            mints.retain(|(key, _)| *key != ctx.fida_mint.0);
+        }
+        mints.truncate(self.number_of_mints);
+        while mints.len() < self.number_of_mints {
+            mints.push(ctx.create_mint(6).await?);
+        }
+        let deposit_amounts = self
+            .deposit_amounts
+            .unwrap_or_else(|| vec![1_000_000; self.number_of_mints]);
+
+        let mut pool = TestPool::new(ctx);
+        for mint_info in &mints {
+            pool.add_mint(None, mint_info);
+        }
+        ctx.refresh_blockhash().await;
+        pool.setup(ctx).await;
+
+        let market = SerumMarket::initialize_market_accounts(
+            ctx,
+            &mints[self.number_of_mints - 1].0,
+            &mints[self.number_of_mints - 2].0,
+        )
+        .await?;
+
+        let mut creator = pool.fund_actor(ctx, Keypair::new()).await;
+        pool.create(
+            ctx,
+            creator.pool_token_account.as_ref().unwrap(),
+            &creator.key,
+            &creator.asset_accounts,
+            deposit_amounts,
+            &market.market_key.pubkey(),
+            self.annual_fee_ratio,
+            self.fee_ratio,
+            self.min_signal_provider_ratio,
+            self.trade_limits,
+        )
+        .await?;
+        creator.pool_token_balance =
+            token_balance(ctx, creator.pool_token_account.as_ref().unwrap()).await;
+        Ok(TestEnvironment {
+            pool,
+            market,
+            creator,
+        })
+    }
+}
+
+impl TestEnvironment {
+    /// Places an immediate or cancel order on behalf of the pool with at most `max_qty` of the
+    /// asset it sells, in 16 bit fixed point ratio of the pool holdings.
+    pub async fn place_order(
+        &self,
+        ctx: &mut Context,
+        side: Side,
+        limit_price: NonZeroU64,
+        max_qty: NonZeroU16,
+    ) -> Result<Order, TransportError> {
+        self.place_order_with(
+            ctx,
+            side,
+            limit_price,
+            max_qty,
+            OrderType::ImmediateOrCancel,
+            SelfTradeBehavior::DecrementTake,
+            None,
+        )
+        .await
+    }
+
+    /// Places an order on behalf of the pool as [`Self::place_order`] does, with the given order
+    /// type and self trade behavior, signed by `trader` or by the signal provider when none is
+    /// given.
+    pub async fn place_order_with(
+        &self,
+        ctx: &mut Context,
+        side: Side,
+        limit_price: NonZeroU64,
+        max_qty: NonZeroU16,
+        order_type: OrderType,
+        self_trade_behavior: SelfTradeBehavior,
+        trader: Option<&Keypair>,
+    ) -> Result<Order, TransportError> {
+        let coin_index = self.pool.mints.len() as u64 - 1;
+        let pc_index = coin_index - 1;
+        let (source_index, target_index) = match side {
+            Side::Bid => (pc_index, coin_index),
+            Side::Ask => (coin_index, pc_index),
+        };
+        let order = self.pool.initialize_new_order(ctx).await?;
+        self.pool
+            .create_new_order_with(
+                ctx,
+                &self.market,
+                source_index,
+                target_index,
+                &order,
+                side,
+                limit_price,
+                max_qty,
+                order_type,
+                self_trade_behavior,
+                trader,
+            )
+            .await?;
+        Ok(order)
+    }
+
+    /// Consumes the events of a pool order which matched the resting orders of the
+    /// `counter_open_orders` account, crediting the fills to both OpenOrders accounts.
+    pub async fn consume_events(&self, ctx: &Context, order: &Order, counter_open_orders: &Pubkey) {
+        self.market
+            .crank(ctx, vec![counter_open_orders, &order.open_orders_account])
+            .await
+    }
+
+    /// Settles the funds of a pool order back to the pool.
+    pub async fn settle(&self, ctx: &Context, order: &Order) -> Result<(), TransportError> {
+        let coin_index = self.pool.mints.len() as u64 - 1;
+        self.pool
+            .settle(ctx, &self.market, coin_index, coin_index - 1, order)
+            .await
+    }
+}
+
+pub async fn token_balance(ctx: &Context, key: &Pubkey) -> u64 {
+    let data = ctx
+        .test_state
+        .banks_client
+        .to_owned()
+        .get_account(*key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    Account::unpack(&data).unwrap().amount
+}
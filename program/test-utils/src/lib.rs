@@ -0,0 +1,25 @@
+//! Test harness for programs integrating with the bonfida-bot pools, running on
+//! `solana-program-test` with a native mock of the Serum dex.
+//!
+//! The bonfida-bot program is loaded as BPF code, so `BPF_OUT_DIR` should point to the folder
+//! containing `bonfida_bot.so`. A typical test goes through the [`builder::PoolBuilder`]:
+//!
+//! ```ignore
+//! let mut ctx = Context::init().await;
+//! let env = PoolBuilder::new().mints(3).fee_ratio(0).build(&mut ctx).await?;
+//! let investor = env.pool.fund_actor(&ctx, Keypair::new()).await;
+//! let maker = env
+//!     .market
+//!     .place_maker_order(&ctx, Side::Ask, price, lots, &ctx.mint_authority)
+//!     .await?;
+//! let order = env.place_order(&mut ctx, Side::Bid, price, max_qty).await?;
+//! env.consume_events(&ctx, &order, &maker).await;
+//! env.settle(&ctx, &order).await?;
+//! ctx.advance_clock(1000).await;
+//! ```
+pub mod builder;
+pub mod market;
+pub mod mock_dex;
+pub mod utils;
+pub mod pool;
+pub mod simulation;
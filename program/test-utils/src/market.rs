@@ -177,6 +177,78 @@ impl SerumMarket {
         Ok((key, create_account_instr))
     }
 
+    /// Places a limit order of `max_qty` lots from a new trader funded with the asset it sells,
+    /// leaving it on the book when nothing matches. Returns the OpenOrders account of the trader.
+    pub async fn place_maker_order(
+        &self,
+        ctx: &Context,
+        side: Side,
+        limit_price: NonZeroU64,
+        max_qty: NonZeroU64,
+        asset_mint_authority: &Keypair,
+    ) -> Result<Pubkey, TransportError> {
+        let source_mint = match side {
+            Side::Bid => &self.pc_mint,
+            Side::Ask => &self.coin_mint,
+        };
+        let source = Keypair::new();
+        let source_owner = Keypair::new();
+        ctx.test_state
+            .banks_client
+            .to_owned()
+            .process_transaction(create_token_account(
+                ctx,
+                source_mint,
+                &source,
+                &source_owner.pubkey(),
+            ))
+            .await?;
+        let (open_orders, create_open_orders) = Self::create_dex_account(ctx, 3216)?;
+        let mint_to_source = mint_to(
+            &spl_token::id(),
+            source_mint,
+            &source.pubkey(),
+            &asset_mint_authority.pubkey(),
+            &[],
+            1 << 40,
+        )
+        .unwrap();
+        wrap_process_transaction(
+            ctx,
+            vec![create_open_orders, mint_to_source],
+            vec![&open_orders, asset_mint_authority],
+        )
+        .await?;
+
+        let instruction = serum_dex::instruction::new_order(
+            &self.market_key.pubkey(),
+            &open_orders.pubkey(),
+            &self.req_q_key.pubkey(),
+            &self.event_q_key.pubkey(),
+            &self.bids_key.pubkey(),
+            &self.asks_key.pubkey(),
+            &source.pubkey(),
+            &source_owner.pubkey(),
+            &self.coin_vault,
+            &self.pc_vault,
+            &spl_token::id(),
+            &sysvar::rent::id(),
+            None,
+            &ctx.serum_program_id,
+            side,
+            limit_price,
+            max_qty,
+            OrderType::Limit,
+            0,
+            SelfTradeBehavior::DecrementTake,
+            1000,
+            NonZeroU64::new(u64::MAX).unwrap(),
+        )
+        .unwrap();
+        wrap_process_transaction(ctx, vec![instruction], vec![&source_owner]).await?;
+        Ok(open_orders.pubkey())
+    }
+
     pub async fn match_and_crank_order(
         &self,
         ctx: &Context,
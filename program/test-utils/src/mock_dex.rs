@@ -0,0 +1,1058 @@
+//! Minimal order book standing in for the Serum dex during tests and fuzzing, loaded as a native
+//! program so that no `serum_dex.so` needs to be built.
+//!
+//! It decodes Serum instructions and keeps the layouts of the Serum market, OpenOrders and order
+//! book accounts, which are the only ones read by the pool program and the test helpers. Orders are
+//! matched as soon as they are placed and `MatchOrders` is a no-op. As on Serum, fills and
+//! cancellations go through the event queue and only reach the OpenOrders accounts once consumed.
+//! Fees, discounts and referrals are not implemented, and self trades always cancel the resting
+//! order unless the taker asked to abort the transaction.
+use std::{cell::Ref, convert::TryInto};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serum_dex::{
+    instruction::{
+        CancelOrderInstructionV2, InitializeMarketInstruction, MarketInstruction,
+        NewOrderInstructionV3, SelfTradeBehavior,
+    },
+    matching::{OrderType, Side},
+};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token::{instruction::transfer, state::Account};
+
+const ACCOUNT_HEAD_PADDING: &[u8; 5] = b"serum";
+
+const FLAG_INITIALIZED: u64 = 1;
+const FLAG_MARKET: u64 = 1 << 1;
+const FLAG_OPEN_ORDERS: u64 = 1 << 2;
+const FLAG_REQUEST_QUEUE: u64 = 1 << 3;
+const FLAG_EVENT_QUEUE: u64 = 1 << 4;
+const FLAG_BIDS: u64 = 1 << 5;
+const FLAG_ASKS: u64 = 1 << 6;
+
+/// Offset of the account flags, right after the head padding. The content of the queues and
+/// books starts after them.
+const FLAGS_OFFSET: usize = 5;
+const CONTENT_OFFSET: usize = 13;
+
+// Market account layout
+const MARKET_LEN: usize = 388;
+const MARKET_VAULT_SIGNER_NONCE: usize = 45;
+const MARKET_COIN_MINT: usize = 53;
+const MARKET_PC_MINT: usize = 85;
+const MARKET_COIN_VAULT: usize = 117;
+const MARKET_PC_VAULT: usize = 165;
+const MARKET_PC_DUST_THRESHOLD: usize = 213;
+const MARKET_REQUEST_QUEUE: usize = 221;
+const MARKET_EVENT_QUEUE: usize = 253;
+const MARKET_BIDS: usize = 285;
+const MARKET_ASKS: usize = 317;
+const MARKET_COIN_LOT_SIZE: usize = 349;
+const MARKET_PC_LOT_SIZE: usize = 357;
+const MARKET_FEE_RATE_BPS: usize = 365;
+
+// OpenOrders account layout
+const OPEN_ORDERS_LEN: usize = 3228;
+const OPEN_ORDERS_MARKET: usize = 13;
+const OPEN_ORDERS_OWNER: usize = 45;
+const OPEN_ORDERS_NATIVE_COIN_FREE: usize = 77;
+const OPEN_ORDERS_NATIVE_COIN_TOTAL: usize = 85;
+const OPEN_ORDERS_NATIVE_PC_FREE: usize = 93;
+const OPEN_ORDERS_NATIVE_PC_TOTAL: usize = 101;
+const OPEN_ORDERS_FREE_SLOT_BITS: usize = 109;
+const OPEN_ORDERS_IS_BID_BITS: usize = 125;
+const OPEN_ORDERS_ORDERS: usize = 141;
+const OPEN_ORDERS_CLIENT_ORDER_IDS: usize = 2189;
+const MAX_OPEN_ORDERS: usize = 128;
+
+// Order book slab layout, the nodes following the header
+const SLAB_BUMP_INDEX: usize = 13;
+const SLAB_ROOT: usize = 33;
+const SLAB_LEAF_COUNT: usize = 37;
+const SLAB_NODES: usize = 45;
+const SLAB_NODE_LEN: usize = 72;
+const INNER_NODE_TAG: u32 = 1;
+const LEAF_NODE_TAG: u32 = 2;
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_u128(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(data[offset..offset + 16].try_into().unwrap())
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new(&data[offset..offset + 32])
+}
+
+fn write(data: &mut [u8], offset: usize, bytes: &[u8]) {
+    data[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+/// Checks that an account belongs to the mock dex and carries the expected flags, returning its
+/// data.
+fn check_account<'a, 'b>(
+    program_id: &Pubkey,
+    account: &'a AccountInfo<'b>,
+    flags: u64,
+) -> Result<Ref<'a, &'b mut [u8]>, ProgramError> {
+    if account.owner != program_id {
+        msg!(
+            "Dex account {} is not owned by the dex program",
+            account.key
+        );
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let data = account.data.borrow();
+    if data.len() < CONTENT_OFFSET || read_u64(&data, FLAGS_OFFSET) != FLAG_INITIALIZED | flags {
+        msg!("Dex account {} is not of the expected kind", account.key);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(data)
+}
+
+fn check_key(key: &Pubkey, expected: &Pubkey, name: &str) -> ProgramResult {
+    if key != expected {
+        msg!("Invalid {} account provided", name);
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+fn overflow() -> ProgramError {
+    msg!("Operation overflow");
+    ProgramError::InvalidArgument
+}
+
+struct Market {
+    key: Pubkey,
+    vault_signer_nonce: u64,
+    coin_vault: Pubkey,
+    pc_vault: Pubkey,
+    request_queue: Pubkey,
+    event_queue: Pubkey,
+    bids: Pubkey,
+    asks: Pubkey,
+    coin_lot_size: u64,
+    pc_lot_size: u64,
+}
+
+impl Market {
+    fn load(program_id: &Pubkey, account: &AccountInfo) -> Result<Self, ProgramError> {
+        let data = check_account(program_id, account, FLAG_MARKET)?;
+        Ok(Self {
+            key: *account.key,
+            vault_signer_nonce: read_u64(&data, MARKET_VAULT_SIGNER_NONCE),
+            coin_vault: read_pubkey(&data, MARKET_COIN_VAULT),
+            pc_vault: read_pubkey(&data, MARKET_PC_VAULT),
+            request_queue: read_pubkey(&data, MARKET_REQUEST_QUEUE),
+            event_queue: read_pubkey(&data, MARKET_EVENT_QUEUE),
+            bids: read_pubkey(&data, MARKET_BIDS),
+            asks: read_pubkey(&data, MARKET_ASKS),
+            coin_lot_size: read_u64(&data, MARKET_COIN_LOT_SIZE),
+            pc_lot_size: read_u64(&data, MARKET_PC_LOT_SIZE),
+        })
+    }
+
+    fn vault_signer_seeds(&self) -> [Vec<u8>; 2] {
+        [
+            self.key.to_bytes().to_vec(),
+            self.vault_signer_nonce.to_le_bytes().to_vec(),
+        ]
+    }
+
+    /// Native amount locked by an order of `quantity` lots at `price`.
+    fn locked_amount(&self, is_bid: bool, price: u64, quantity: u64) -> Result<u64, ProgramError> {
+        if is_bid {
+            quantity
+                .checked_mul(price)
+                .and_then(|n| n.checked_mul(self.pc_lot_size))
+        } else {
+            quantity.checked_mul(self.coin_lot_size)
+        }
+        .ok_or_else(overflow)
+    }
+}
+
+struct OpenOrders {
+    market: Pubkey,
+    owner: Pubkey,
+    native_coin_free: u64,
+    native_coin_total: u64,
+    native_pc_free: u64,
+    native_pc_total: u64,
+    free_slot_bits: u128,
+    is_bid_bits: u128,
+    orders: Vec<u128>,
+    client_order_ids: Vec<u64>,
+}
+
+impl OpenOrders {
+    /// Loads an OpenOrders account, initializing it on first use as Serum does.
+    fn load_or_init(
+        program_id: &Pubkey,
+        account: &AccountInfo,
+        market: &Pubkey,
+        owner: &Pubkey,
+    ) -> Result<Self, ProgramError> {
+        if account.owner != program_id {
+            msg!("The OpenOrders account is not owned by the dex program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let is_new = {
+            let data = account.data.borrow();
+            if data.len() != OPEN_ORDERS_LEN {
+                msg!("The OpenOrders account has an invalid size");
+                return Err(ProgramError::InvalidAccountData);
+            }
+            read_u64(&data, FLAGS_OFFSET) == 0
+        };
+        if is_new {
+            return Ok(Self {
+                market: *market,
+                owner: *owner,
+                native_coin_free: 0,
+                native_coin_total: 0,
+                native_pc_free: 0,
+                native_pc_total: 0,
+                free_slot_bits: u128::MAX,
+                is_bid_bits: 0,
+                orders: vec![0; MAX_OPEN_ORDERS],
+                client_order_ids: vec![0; MAX_OPEN_ORDERS],
+            });
+        }
+        let open_orders = Self::load(program_id, account)?;
+        check_key(&open_orders.market, market, "market")?;
+        Ok(open_orders)
+    }
+
+    fn load(program_id: &Pubkey, account: &AccountInfo) -> Result<Self, ProgramError> {
+        let data = check_account(program_id, account, FLAG_OPEN_ORDERS)?;
+        if data.len() != OPEN_ORDERS_LEN {
+            msg!("The OpenOrders account has an invalid size");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            market: read_pubkey(&data, OPEN_ORDERS_MARKET),
+            owner: read_pubkey(&data, OPEN_ORDERS_OWNER),
+            native_coin_free: read_u64(&data, OPEN_ORDERS_NATIVE_COIN_FREE),
+            native_coin_total: read_u64(&data, OPEN_ORDERS_NATIVE_COIN_TOTAL),
+            native_pc_free: read_u64(&data, OPEN_ORDERS_NATIVE_PC_FREE),
+            native_pc_total: read_u64(&data, OPEN_ORDERS_NATIVE_PC_TOTAL),
+            free_slot_bits: read_u128(&data, OPEN_ORDERS_FREE_SLOT_BITS),
+            is_bid_bits: read_u128(&data, OPEN_ORDERS_IS_BID_BITS),
+            orders: (0..MAX_OPEN_ORDERS)
+                .map(|i| read_u128(&data, OPEN_ORDERS_ORDERS + 16 * i))
+                .collect(),
+            client_order_ids: (0..MAX_OPEN_ORDERS)
+                .map(|i| read_u64(&data, OPEN_ORDERS_CLIENT_ORDER_IDS + 8 * i))
+                .collect(),
+        })
+    }
+
+    fn store(&self, account: &AccountInfo) {
+        let mut data = account.data.borrow_mut();
+        write(&mut data, 0, ACCOUNT_HEAD_PADDING);
+        write(
+            &mut data,
+            FLAGS_OFFSET,
+            &(FLAG_INITIALIZED | FLAG_OPEN_ORDERS).to_le_bytes(),
+        );
+        write(&mut data, OPEN_ORDERS_MARKET, &self.market.to_bytes());
+        write(&mut data, OPEN_ORDERS_OWNER, &self.owner.to_bytes());
+        write(
+            &mut data,
+            OPEN_ORDERS_NATIVE_COIN_FREE,
+            &self.native_coin_free.to_le_bytes(),
+        );
+        write(
+            &mut data,
+            OPEN_ORDERS_NATIVE_COIN_TOTAL,
+            &self.native_coin_total.to_le_bytes(),
+        );
+        write(
+            &mut data,
+            OPEN_ORDERS_NATIVE_PC_FREE,
+            &self.native_pc_free.to_le_bytes(),
+        );
+        write(
+            &mut data,
+            OPEN_ORDERS_NATIVE_PC_TOTAL,
+            &self.native_pc_total.to_le_bytes(),
+        );
+        write(
+            &mut data,
+            OPEN_ORDERS_FREE_SLOT_BITS,
+            &self.free_slot_bits.to_le_bytes(),
+        );
+        write(
+            &mut data,
+            OPEN_ORDERS_IS_BID_BITS,
+            &self.is_bid_bits.to_le_bytes(),
+        );
+        for i in 0..MAX_OPEN_ORDERS {
+            write(
+                &mut data,
+                OPEN_ORDERS_ORDERS + 16 * i,
+                &self.orders[i].to_le_bytes(),
+            );
+            write(
+                &mut data,
+                OPEN_ORDERS_CLIENT_ORDER_IDS + 8 * i,
+                &self.client_order_ids[i].to_le_bytes(),
+            );
+        }
+        let padding_offset = data.len() - 7;
+        write(&mut data, padding_offset, b"padding");
+    }
+
+    fn check_owner(&self, owner: &AccountInfo) -> ProgramResult {
+        check_key(owner.key, &self.owner, "OpenOrders owner")?;
+        if !owner.is_signer {
+            msg!("The OpenOrders owner's signature is required.");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, event: &Event) -> ProgramResult {
+        let (paid_total, received_free, received_total, unlocked_free) = if event.is_bid {
+            (
+                &mut self.native_pc_total,
+                &mut self.native_coin_free,
+                &mut self.native_coin_total,
+                &mut self.native_pc_free,
+            )
+        } else {
+            (
+                &mut self.native_coin_total,
+                &mut self.native_pc_free,
+                &mut self.native_pc_total,
+                &mut self.native_coin_free,
+            )
+        };
+        *paid_total = paid_total
+            .checked_sub(event.native_qty_paid)
+            .ok_or_else(overflow)?;
+        *received_free = received_free
+            .checked_add(event.native_qty_received)
+            .ok_or_else(overflow)?;
+        *received_total = received_total
+            .checked_add(event.native_qty_received)
+            .ok_or_else(overflow)?;
+        *unlocked_free = unlocked_free
+            .checked_add(event.native_qty_unlocked)
+            .ok_or_else(overflow)?;
+        if let Some(slot) = event.released_slot {
+            self.free_slot_bits |= 1 << slot;
+            self.is_bid_bits &= !(1 << slot);
+            self.orders[slot as usize] = 0;
+            self.client_order_ids[slot as usize] = 0;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RestingOrder {
+    order_id: u128,
+    open_orders: [u8; 32],
+    owner_slot: u8,
+    price: u64,
+    quantity: u64,
+}
+
+/// Reads the orders of a book, best first. The book is kept as a Serum slab so that the pool
+/// program reads the same layout on the mock and on the real dex.
+fn load_book(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    is_bid: bool,
+) -> Result<Vec<RestingOrder>, ProgramError> {
+    let flags = if is_bid { FLAG_BIDS } else { FLAG_ASKS };
+    let data = check_account(program_id, account, flags)?;
+    let mut orders = vec![];
+    if data.len() < SLAB_NODES || read_u64(&data, SLAB_LEAF_COUNT) == 0 {
+        return Ok(orders);
+    }
+    let node_offset = |index: u32| SLAB_NODES + index as usize * SLAB_NODE_LEN;
+    let mut stack = vec![read_u32(&data, SLAB_ROOT)];
+    while let Some(index) = stack.pop() {
+        let offset = node_offset(index);
+        if offset + SLAB_NODE_LEN > data.len() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        match read_u32(&data, offset) {
+            INNER_NODE_TAG => {
+                stack.push(read_u32(&data, offset + 28));
+                stack.push(read_u32(&data, offset + 24));
+            }
+            LEAF_NODE_TAG => {
+                let order_id = read_u128(&data, offset + 8);
+                orders.push(RestingOrder {
+                    order_id,
+                    open_orders: data[offset + 24..offset + 56].try_into().unwrap(),
+                    owner_slot: data[offset + 4],
+                    price: (order_id >> 64) as u64,
+                    quantity: read_u64(&data, offset + 56),
+                });
+            }
+            _ => return Err(ProgramError::InvalidAccountData),
+        }
+    }
+    // The traversal yields ascending keys, bids are best at the highest key
+    if is_bid {
+        orders.reverse();
+    }
+    Ok(orders)
+}
+
+/// Writes the orders of a book as a Serum critbit slab, the root being the first node.
+fn store_book(account: &AccountInfo, orders: &[RestingOrder]) -> ProgramResult {
+    let mut orders = orders.to_vec();
+    orders.sort_by_key(|o| o.order_id);
+    let mut data = account.data.borrow_mut();
+    let end = data.len() - 7;
+    let node_count = orders.len().saturating_mul(2).saturating_sub(1);
+    if SLAB_NODES + node_count * SLAB_NODE_LEN > end {
+        msg!("Dex account {} is full", account.key);
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    write(
+        &mut data,
+        SLAB_BUMP_INDEX,
+        &(node_count as u64).to_le_bytes(),
+    );
+    write(&mut data, SLAB_BUMP_INDEX + 8, &0u64.to_le_bytes());
+    write(&mut data, SLAB_BUMP_INDEX + 16, &0u32.to_le_bytes());
+    write(&mut data, SLAB_ROOT, &0u32.to_le_bytes());
+    write(
+        &mut data,
+        SLAB_LEAF_COUNT,
+        &(orders.len() as u64).to_le_bytes(),
+    );
+    if !orders.is_empty() {
+        let mut next_index = 0;
+        write_slab_node(&mut data, &orders, &mut next_index);
+    }
+    Ok(())
+}
+
+/// Writes the subtree of sorted orders at the next free node, children after their parent.
+fn write_slab_node(data: &mut [u8], orders: &[RestingOrder], next_index: &mut u32) -> u32 {
+    let index = *next_index;
+    *next_index += 1;
+    let offset = SLAB_NODES + index as usize * SLAB_NODE_LEN;
+    data[offset..offset + SLAB_NODE_LEN].copy_from_slice(&[0; SLAB_NODE_LEN]);
+    let first = orders[0].order_id;
+    if orders.len() == 1 {
+        let order = &orders[0];
+        write(data, offset, &LEAF_NODE_TAG.to_le_bytes());
+        data[offset + 4] = order.owner_slot;
+        write(data, offset + 8, &order.order_id.to_le_bytes());
+        write(data, offset + 24, &order.open_orders);
+        write(data, offset + 56, &order.quantity.to_le_bytes());
+        return index;
+    }
+    let prefix_len = (first ^ orders[orders.len() - 1].order_id).leading_zeros();
+    let split = orders
+        .iter()
+        .position(|o| (o.order_id >> (127 - prefix_len)) & 1 == 1)
+        .unwrap();
+    let left = write_slab_node(data, &orders[..split], next_index);
+    let right = write_slab_node(data, &orders[split..], next_index);
+    write(data, offset, &INNER_NODE_TAG.to_le_bytes());
+    write(data, offset + 4, &prefix_len.to_le_bytes());
+    write(data, offset + 8, &first.to_le_bytes());
+    write(data, offset + 24, &left.to_le_bytes());
+    write(data, offset + 28, &right.to_le_bytes());
+    index
+}
+
+/// Change to an OpenOrders account, applied when the event is consumed.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug)]
+struct Event {
+    open_orders: [u8; 32],
+    is_bid: bool,
+    native_qty_paid: u64,
+    native_qty_received: u64,
+    native_qty_unlocked: u64,
+    released_slot: Option<u8>,
+}
+
+/// Reads the content of a queue account. A freshly initialized account decodes as empty.
+fn load_content<T: BorshDeserialize>(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    flags: u64,
+) -> Result<T, ProgramError> {
+    let data = check_account(program_id, account, flags)?;
+    T::deserialize(&mut &data[CONTENT_OFFSET..]).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+fn store_content<T: BorshSerialize>(account: &AccountInfo, content: &T) -> ProgramResult {
+    let mut data = account.data.borrow_mut();
+    let end = data.len() - 7;
+    content
+        .serialize(&mut &mut data[CONTENT_OFFSET..end])
+        .map_err(|_| {
+            msg!("Dex account {} is full", account.key);
+            ProgramError::AccountDataTooSmall
+        })
+}
+
+fn transfer_from_vault<'a>(
+    market: &Market,
+    vault: &AccountInfo<'a>,
+    wallet: &AccountInfo<'a>,
+    vault_signer: &AccountInfo<'a>,
+    spl_token_program: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+    let instruction = transfer(
+        spl_token_program.key,
+        vault.key,
+        wallet.key,
+        vault_signer.key,
+        &[],
+        amount,
+    )?;
+    let seeds = market.vault_signer_seeds();
+    invoke_signed(
+        &instruction,
+        &[
+            spl_token_program.clone(),
+            vault.clone(),
+            wallet.clone(),
+            vault_signer.clone(),
+        ],
+        &[&[&seeds[0], &seeds[1]]],
+    )
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction =
+        MarketInstruction::unpack(instruction_data).ok_or(ProgramError::InvalidInstructionData)?;
+    match instruction {
+        MarketInstruction::InitializeMarket(ref instruction) => {
+            process_initialize_market(program_id, accounts, instruction)
+        }
+        MarketInstruction::NewOrderV3(ref instruction) => {
+            process_new_order(program_id, accounts, instruction)
+        }
+        MarketInstruction::MatchOrders(_) => {
+            Market::load(program_id, next_account_info(&mut accounts.iter())?)?;
+            Ok(())
+        }
+        MarketInstruction::ConsumeEvents(limit) => {
+            process_consume_events(program_id, accounts, limit)
+        }
+        MarketInstruction::CancelOrderV2(ref instruction) => {
+            process_cancel_order(program_id, accounts, instruction)
+        }
+        MarketInstruction::SettleFunds => process_settle_funds(program_id, accounts),
+        _ => {
+            msg!("Instruction not supported by the mock dex");
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+fn process_initialize_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &InitializeMarketInstruction,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let market = next_account_info(accounts_iter)?;
+    let request_queue = next_account_info(accounts_iter)?;
+    let event_queue = next_account_info(accounts_iter)?;
+    let bids = next_account_info(accounts_iter)?;
+    let asks = next_account_info(accounts_iter)?;
+    let coin_vault = next_account_info(accounts_iter)?;
+    let pc_vault = next_account_info(accounts_iter)?;
+    let coin_mint = next_account_info(accounts_iter)?;
+    let pc_mint = next_account_info(accounts_iter)?;
+
+    let vault_signer = Pubkey::create_program_address(
+        &[
+            &market.key.to_bytes(),
+            &instruction.vault_signer_nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+    .map_err(|_| {
+        msg!("Invalid vault signer nonce");
+        ProgramError::InvalidArgument
+    })?;
+    for (vault, mint) in [(coin_vault, coin_mint), (pc_vault, pc_mint)].iter() {
+        let vault = Account::unpack(&vault.data.borrow())?;
+        check_key(&vault.owner, &vault_signer, "vault owner")?;
+        check_key(&vault.mint, mint.key, "vault mint")?;
+    }
+    if instruction.coin_lot_size == 0 || instruction.pc_lot_size == 0 {
+        msg!("Lot sizes cannot be zero");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    for (account, flags, len) in [
+        (market, FLAG_MARKET, Some(MARKET_LEN)),
+        (request_queue, FLAG_REQUEST_QUEUE, None),
+        (event_queue, FLAG_EVENT_QUEUE, None),
+        (bids, FLAG_BIDS, None),
+        (asks, FLAG_ASKS, None),
+    ]
+    .iter()
+    {
+        if account.owner != program_id {
+            msg!(
+                "Dex account {} is not owned by the dex program",
+                account.key
+            );
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut data = account.data.borrow_mut();
+        if data.len() < CONTENT_OFFSET + 8 || len.map_or(false, |len| data.len() != len) {
+            msg!("Dex account {} has an invalid size", account.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if read_u64(&data, FLAGS_OFFSET) != 0 {
+            msg!("Dex account {} is already initialized", account.key);
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        write(&mut data, 0, ACCOUNT_HEAD_PADDING);
+        write(
+            &mut data,
+            FLAGS_OFFSET,
+            &(FLAG_INITIALIZED | flags).to_le_bytes(),
+        );
+        let padding_offset = data.len() - 7;
+        write(&mut data, padding_offset, b"padding");
+    }
+
+    let mut data = market.data.borrow_mut();
+    write(&mut data, CONTENT_OFFSET, &market.key.to_bytes());
+    write(
+        &mut data,
+        MARKET_VAULT_SIGNER_NONCE,
+        &instruction.vault_signer_nonce.to_le_bytes(),
+    );
+    write(&mut data, MARKET_COIN_MINT, &coin_mint.key.to_bytes());
+    write(&mut data, MARKET_PC_MINT, &pc_mint.key.to_bytes());
+    write(&mut data, MARKET_COIN_VAULT, &coin_vault.key.to_bytes());
+    write(&mut data, MARKET_PC_VAULT, &pc_vault.key.to_bytes());
+    write(
+        &mut data,
+        MARKET_PC_DUST_THRESHOLD,
+        &instruction.pc_dust_threshold.to_le_bytes(),
+    );
+    write(
+        &mut data,
+        MARKET_REQUEST_QUEUE,
+        &request_queue.key.to_bytes(),
+    );
+    write(&mut data, MARKET_EVENT_QUEUE, &event_queue.key.to_bytes());
+    write(&mut data, MARKET_BIDS, &bids.key.to_bytes());
+    write(&mut data, MARKET_ASKS, &asks.key.to_bytes());
+    write(
+        &mut data,
+        MARKET_COIN_LOT_SIZE,
+        &instruction.coin_lot_size.to_le_bytes(),
+    );
+    write(
+        &mut data,
+        MARKET_PC_LOT_SIZE,
+        &instruction.pc_lot_size.to_le_bytes(),
+    );
+    write(
+        &mut data,
+        MARKET_FEE_RATE_BPS,
+        &(instruction.fee_rate_bps as u64).to_le_bytes(),
+    );
+    Ok(())
+}
+
+fn process_new_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &NewOrderInstructionV3,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let market_account = next_account_info(accounts_iter)?;
+    let open_orders_account = next_account_info(accounts_iter)?;
+    let request_queue = next_account_info(accounts_iter)?;
+    let event_queue = next_account_info(accounts_iter)?;
+    let bids = next_account_info(accounts_iter)?;
+    let asks = next_account_info(accounts_iter)?;
+    let order_payer = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let coin_vault = next_account_info(accounts_iter)?;
+    let pc_vault = next_account_info(accounts_iter)?;
+    let spl_token_program = next_account_info(accounts_iter)?;
+
+    let market = Market::load(program_id, market_account)?;
+    check_key(request_queue.key, &market.request_queue, "request queue")?;
+    check_key(event_queue.key, &market.event_queue, "event queue")?;
+    check_key(bids.key, &market.bids, "bids")?;
+    check_key(asks.key, &market.asks, "asks")?;
+    check_key(coin_vault.key, &market.coin_vault, "coin vault")?;
+    check_key(pc_vault.key, &market.pc_vault, "pc vault")?;
+    check_key(spl_token_program.key, &spl_token::id(), "spl token program")?;
+
+    let mut open_orders =
+        OpenOrders::load_or_init(program_id, open_orders_account, &market.key, owner.key)?;
+    open_orders.check_owner(owner)?;
+
+    let is_bid = instruction.side == Side::Bid;
+    let price = instruction.limit_price.get();
+    let quantity = if is_bid {
+        let lot_price = price.checked_mul(market.pc_lot_size).ok_or_else(overflow)?;
+        std::cmp::min(
+            instruction.max_coin_qty.get(),
+            instruction.max_native_pc_qty_including_fees.get() / lot_price,
+        )
+    } else {
+        instruction.max_coin_qty.get()
+    };
+    if quantity == 0 {
+        msg!("Order too small");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Funds are taken from the free balance first, the rest is deposited to the vault
+    let locked = market.locked_amount(is_bid, price, quantity)?;
+    let (free, total, vault) = if is_bid {
+        (
+            &mut open_orders.native_pc_free,
+            &mut open_orders.native_pc_total,
+            pc_vault,
+        )
+    } else {
+        (
+            &mut open_orders.native_coin_free,
+            &mut open_orders.native_coin_total,
+            coin_vault,
+        )
+    };
+    let from_free = std::cmp::min(*free, locked);
+    let deposit = locked - from_free;
+    *free -= from_free;
+    *total = total.checked_add(deposit).ok_or_else(overflow)?;
+
+    // Match against the opposite side of the book
+    let (own_book_account, opposite_book_account) =
+        if is_bid { (bids, asks) } else { (asks, bids) };
+    let mut opposite_book = load_book(program_id, opposite_book_account, !is_bid)?;
+    let mut events: Vec<Event> = load_content(program_id, event_queue, FLAG_EVENT_QUEUE)?;
+    let crosses = |maker_price: u64| {
+        if is_bid {
+            maker_price <= price
+        } else {
+            maker_price >= price
+        }
+    };
+
+    let mut remaining = quantity;
+    let mut native_qty_paid = 0u64;
+    let mut native_qty_received = 0u64;
+    while remaining > 0 && !opposite_book.is_empty() && crosses(opposite_book[0].price) {
+        if instruction.order_type == OrderType::PostOnly {
+            msg!("Post only order would cross the book");
+            return Err(ProgramError::InvalidArgument);
+        }
+        if opposite_book[0].open_orders == open_orders_account.key.to_bytes() {
+            if instruction.self_trade_behavior == SelfTradeBehavior::AbortTransaction {
+                msg!("Order would self trade");
+                return Err(ProgramError::InvalidArgument);
+            }
+            let maker = opposite_book.remove(0);
+            events.push(Event {
+                open_orders: maker.open_orders,
+                is_bid: !is_bid,
+                native_qty_paid: 0,
+                native_qty_received: 0,
+                native_qty_unlocked: market.locked_amount(!is_bid, maker.price, maker.quantity)?,
+                released_slot: Some(maker.owner_slot),
+            });
+            continue;
+        }
+
+        let maker = &mut opposite_book[0];
+        let fill = std::cmp::min(remaining, maker.quantity);
+        let native_coin = market.locked_amount(false, maker.price, fill)?;
+        let native_pc = market.locked_amount(true, maker.price, fill)?;
+        let (maker_paid, maker_received) = if is_bid {
+            (native_coin, native_pc)
+        } else {
+            (native_pc, native_coin)
+        };
+        maker.quantity -= fill;
+        events.push(Event {
+            open_orders: maker.open_orders,
+            is_bid: !is_bid,
+            native_qty_paid: maker_paid,
+            native_qty_received: maker_received,
+            native_qty_unlocked: 0,
+            released_slot: if maker.quantity == 0 {
+                Some(maker.owner_slot)
+            } else {
+                None
+            },
+        });
+        if maker.quantity == 0 {
+            opposite_book.remove(0);
+        }
+        native_qty_paid = native_qty_paid
+            .checked_add(maker_received)
+            .ok_or_else(overflow)?;
+        native_qty_received = native_qty_received
+            .checked_add(maker_paid)
+            .ok_or_else(overflow)?;
+        remaining -= fill;
+    }
+
+    let rests = remaining > 0 && instruction.order_type != OrderType::ImmediateOrCancel;
+    let resting_lock = if rests {
+        market.locked_amount(is_bid, price, remaining)?
+    } else {
+        0
+    };
+    let native_qty_unlocked = locked
+        .checked_sub(native_qty_paid)
+        .and_then(|n| n.checked_sub(resting_lock))
+        .ok_or_else(overflow)?;
+    if native_qty_paid > 0 || native_qty_unlocked > 0 {
+        events.push(Event {
+            open_orders: open_orders_account.key.to_bytes(),
+            is_bid,
+            native_qty_paid,
+            native_qty_received,
+            native_qty_unlocked,
+            released_slot: None,
+        });
+    }
+
+    if rests {
+        if open_orders.free_slot_bits == 0 {
+            msg!("Too many open orders");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let slot = open_orders.free_slot_bits.trailing_zeros() as u8;
+        let mut sequence_number: u64 = load_content(program_id, request_queue, FLAG_REQUEST_QUEUE)?;
+        sequence_number += 1;
+        store_content(request_queue, &sequence_number)?;
+        let order_id = ((price as u128) << 64)
+            | if is_bid {
+                !sequence_number
+            } else {
+                sequence_number
+            } as u128;
+
+        open_orders.free_slot_bits &= !(1 << slot);
+        if is_bid {
+            open_orders.is_bid_bits |= 1 << slot;
+        }
+        open_orders.orders[slot as usize] = order_id;
+        open_orders.client_order_ids[slot as usize] = instruction.client_order_id;
+
+        let mut own_book = load_book(program_id, own_book_account, is_bid)?;
+        let position = own_book
+            .iter()
+            .position(|o| {
+                if is_bid {
+                    o.price < price
+                } else {
+                    o.price > price
+                }
+            })
+            .unwrap_or_else(|| own_book.len());
+        own_book.insert(
+            position,
+            RestingOrder {
+                order_id,
+                open_orders: open_orders_account.key.to_bytes(),
+                owner_slot: slot,
+                price,
+                quantity: remaining,
+            },
+        );
+        store_book(own_book_account, &own_book)?;
+    }
+
+    store_book(opposite_book_account, &opposite_book)?;
+    store_content(event_queue, &events)?;
+    open_orders.store(open_orders_account);
+
+    if deposit > 0 {
+        let instruction = transfer(
+            spl_token_program.key,
+            order_payer.key,
+            vault.key,
+            owner.key,
+            &[],
+            deposit,
+        )?;
+        invoke(
+            &instruction,
+            &[
+                spl_token_program.clone(),
+                order_payer.clone(),
+                vault.clone(),
+                owner.clone(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn process_cancel_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &CancelOrderInstructionV2,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let market_account = next_account_info(accounts_iter)?;
+    let bids = next_account_info(accounts_iter)?;
+    let asks = next_account_info(accounts_iter)?;
+    let open_orders_account = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let event_queue = next_account_info(accounts_iter)?;
+
+    let market = Market::load(program_id, market_account)?;
+    check_key(bids.key, &market.bids, "bids")?;
+    check_key(asks.key, &market.asks, "asks")?;
+    check_key(event_queue.key, &market.event_queue, "event queue")?;
+    let open_orders = OpenOrders::load(program_id, open_orders_account)?;
+    check_key(&open_orders.market, &market.key, "market")?;
+    open_orders.check_owner(owner)?;
+
+    let is_bid = instruction.side == Side::Bid;
+    let book_account = if is_bid { bids } else { asks };
+    let mut book = load_book(program_id, book_account, is_bid)?;
+    let position = book
+        .iter()
+        .position(|o| {
+            o.order_id == instruction.order_id
+                && o.open_orders == open_orders_account.key.to_bytes()
+        })
+        .ok_or_else(|| {
+            msg!("Order not found");
+            ProgramError::InvalidArgument
+        })?;
+    let order = book.remove(position);
+
+    let mut events: Vec<Event> = load_content(program_id, event_queue, FLAG_EVENT_QUEUE)?;
+    events.push(Event {
+        open_orders: order.open_orders,
+        is_bid,
+        native_qty_paid: 0,
+        native_qty_received: 0,
+        native_qty_unlocked: market.locked_amount(is_bid, order.price, order.quantity)?,
+        released_slot: Some(order.owner_slot),
+    });
+    store_book(book_account, &book)?;
+    store_content(event_queue, &events)
+}
+
+fn process_consume_events(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    limit: u16,
+) -> ProgramResult {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let (open_orders_accounts, accounts) = accounts.split_at(accounts.len() - 4);
+    let market = Market::load(program_id, &accounts[0])?;
+    let event_queue = &accounts[1];
+    check_key(event_queue.key, &market.event_queue, "event queue")?;
+
+    let mut events: Vec<Event> = load_content(program_id, event_queue, FLAG_EVENT_QUEUE)?;
+    let mut consumed = 0;
+    for event in events.iter().take(limit as usize) {
+        // As on Serum, consumption stops at the first event whose account is missing
+        let account = match open_orders_accounts
+            .iter()
+            .find(|a| a.key.to_bytes() == event.open_orders)
+        {
+            Some(a) => a,
+            None => break,
+        };
+        let mut open_orders = OpenOrders::load(program_id, account)?;
+        open_orders.apply(event)?;
+        open_orders.store(account);
+        consumed += 1;
+    }
+    events.drain(..consumed);
+    store_content(event_queue, &events)
+}
+
+fn process_settle_funds(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let market_account = next_account_info(accounts_iter)?;
+    let open_orders_account = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let coin_vault = next_account_info(accounts_iter)?;
+    let pc_vault = next_account_info(accounts_iter)?;
+    let coin_wallet = next_account_info(accounts_iter)?;
+    let pc_wallet = next_account_info(accounts_iter)?;
+    let vault_signer = next_account_info(accounts_iter)?;
+    let spl_token_program = next_account_info(accounts_iter)?;
+
+    let market = Market::load(program_id, market_account)?;
+    check_key(coin_vault.key, &market.coin_vault, "coin vault")?;
+    check_key(pc_vault.key, &market.pc_vault, "pc vault")?;
+    check_key(spl_token_program.key, &spl_token::id(), "spl token program")?;
+    let seeds = market.vault_signer_seeds();
+    let vault_signer_key = Pubkey::create_program_address(&[&seeds[0], &seeds[1]], program_id)?;
+    check_key(vault_signer.key, &vault_signer_key, "vault signer")?;
+
+    let mut open_orders = OpenOrders::load(program_id, open_orders_account)?;
+    check_key(&open_orders.market, &market.key, "market")?;
+    open_orders.check_owner(owner)?;
+
+    let coin_amount = open_orders.native_coin_free;
+    let pc_amount = open_orders.native_pc_free;
+    open_orders.native_coin_total -= coin_amount;
+    open_orders.native_coin_free = 0;
+    open_orders.native_pc_total -= pc_amount;
+    open_orders.native_pc_free = 0;
+    open_orders.store(open_orders_account);
+
+    transfer_from_vault(
+        &market,
+        coin_vault,
+        coin_wallet,
+        vault_signer,
+        spl_token_program,
+        coin_amount,
+    )?;
+    transfer_from_vault(
+        &market,
+        pc_vault,
+        pc_wallet,
+        vault_signer,
+        spl_token_program,
+        pc_amount,
+    )
+}
@@ -0,0 +1,791 @@
+use std::{
+    num::{NonZeroU16, NonZeroU64},
+    str::FromStr,
+};
+
+use bonfida_bot::{
+    instruction::{
+        add_trader, cancel_order, claim_fees, collect_fees, create, create_order, deposit, init,
+        post_bond, process_deposit_queue, process_redeem_queue, redeem, remove_trader,
+        request_bond_withdrawal, request_deposit, request_redeem, set_multisig, settle_funds,
+        slash_bond, with_unsettled_open_orders, withdraw_bond,
+    },
+    state::{
+        DepositRequest, PoolHeader, PoolState, TradeLimits, BONFIDA_BNB, BONFIDA_FEE, FIDA_MINT,
+    },
+    utils::{find_bond_key, find_deposit_request_key, find_redeem_request_key, find_trader_key},
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serum_dex::{
+    instruction::SelfTradeBehavior,
+    matching::{OrderType, Side},
+};
+use solana_program::{program_pack::Pack, pubkey::Pubkey, system_program, sysvar};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transport::TransportError,
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::{instruction::mint_to, state::Mint};
+
+use super::{
+    market::SerumMarket,
+    simulation::Actor,
+    utils::{
+        create_and_get_associated_token_address, wrap_process_transaction, Context, MintInfo,
+        OpenOrderView,
+    },
+};
+pub struct TestPool {
+    pub seeds: [u8; 32],
+    pub mint_key: Pubkey,
+    pub key: Pubkey,
+    pub signal_provider: Keypair,
+    pub mints: Vec<TestMint>,
+    program_id: Pubkey,
+}
+
+impl TestPool {
+    pub fn new(ctx: &Context) -> Self {
+        let mut pool_seeds;
+        loop {
+            pool_seeds = rand::thread_rng().gen::<[u8; 32]>();
+            let (_, bump) =
+                Pubkey::find_program_address(&[&pool_seeds[..31]], &ctx.bonfidabot_program_id);
+            pool_seeds[31] = bump;
+            if Pubkey::create_program_address(&[&pool_seeds, &[1]], &ctx.bonfidabot_program_id)
+                .is_ok()
+            {
+                break;
+            };
+        }
+        let mint_key =
+            Pubkey::create_program_address(&[&pool_seeds, &[1]], &ctx.bonfidabot_program_id)
+                .unwrap();
+        Self {
+            seeds: pool_seeds,
+            key: Pubkey::create_program_address(&[&pool_seeds], &ctx.bonfidabot_program_id)
+                .unwrap(),
+            mint_key,
+            mints: vec![],
+            program_id: ctx.bonfidabot_program_id,
+            signal_provider: Keypair::new(),
+        }
+    }
+
+    pub fn add_mint(&mut self, name: Option<&str>, mint_info: &MintInfo) {
+        let name = name.map(String::from).unwrap_or_else(|| {
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(char::from)
+                .collect()
+        });
+
+        let pool_asset_key = get_associated_token_address(&self.key, &mint_info.0);
+
+        self.mints.push(TestMint {
+            name,
+            key: mint_info.0,
+            mint_params: mint_info.1,
+            pool_asset_key,
+        });
+    }
+
+    pub async fn setup(&self, ctx: &Context) {
+        // Initialize the pool
+        let init_instruction = init(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &self.mint_key,
+            &ctx.test_state.payer.pubkey(),
+            &self.key,
+            self.seeds,
+            100,
+            1,
+        )
+        .unwrap();
+        let mut instructions = Vec::with_capacity(self.mints.len() + 1);
+        instructions.push(init_instruction);
+
+        instructions.extend(self.mints.iter().map(|m| {
+            create_associated_token_account(&ctx.test_state.payer.pubkey(), &self.key, &m.key)
+        }));
+
+        wrap_process_transaction(&ctx, instructions, vec![])
+            .await
+            .unwrap();
+
+        // Initialize fee accounts
+        self.get_pt_account(ctx, &Pubkey::from_str(BONFIDA_FEE).unwrap())
+            .await;
+        self.get_pt_account(ctx, &Pubkey::from_str(BONFIDA_BNB).unwrap())
+            .await;
+    }
+
+    pub async fn get_pt_account(&self, ctx: &Context, owner: &Pubkey) -> Pubkey {
+        let (initialize_target_pt_account_instruction, pooltoken_target_key) =
+            create_and_get_associated_token_address(
+                &ctx.test_state.payer.pubkey(),
+                owner,
+                &self.mint_key,
+            );
+        wrap_process_transaction(&ctx, vec![initialize_target_pt_account_instruction], vec![])
+            .await
+            .unwrap();
+        pooltoken_target_key
+    }
+
+    pub async fn get_funded_token_accounts(
+        &self,
+        ctx: &Context,
+        owner_address: &Pubkey,
+    ) -> Vec<Pubkey> {
+        let mut accounts = Vec::with_capacity(self.mints.len());
+        let mut instructions = Vec::with_capacity(self.mints.len());
+        for m in self.mints.iter() {
+            let (create_instruction, address) = create_and_get_associated_token_address(
+                &ctx.test_state.payer.pubkey(),
+                owner_address,
+                &m.key,
+            );
+            let mint_to_instruction = mint_to(
+                &spl_token::id(),
+                &m.key,
+                &address,
+                &ctx.mint_authority.pubkey(),
+                &[],
+                1 << 25,
+            )
+            .unwrap();
+            accounts.push(address);
+            instructions.push(create_instruction);
+            instructions.push(mint_to_instruction);
+        }
+        wrap_process_transaction(ctx, instructions, vec![&ctx.mint_authority])
+            .await
+            .unwrap();
+        accounts
+    }
+
+    /// Creates funded asset accounts and an empty pool token account for `key`.
+    pub async fn fund_actor(&self, ctx: &Context, key: Keypair) -> Actor {
+        let asset_accounts = self.get_funded_token_accounts(ctx, &key.pubkey()).await;
+        let pool_token_account = self.get_pt_account(ctx, &key.pubkey()).await;
+        Actor {
+            signal_provider: key.pubkey() == self.signal_provider.pubkey(),
+            key,
+            asset_accounts,
+            pool_token_balance: 0,
+            pool_token_account: Some(pool_token_account),
+        }
+    }
+
+    pub async fn create(
+        &self,
+        ctx: &Context,
+        target_pool_token_account: &Pubkey,
+        source_owner: &Keypair,
+        source_asset_keys: &Vec<Pubkey>,
+        deposit_amounts: Vec<u64>,
+        market: &Pubkey,
+        annual_fee_ratio: u16,
+        fee_ratio: u16,
+        min_signal_provider_ratio: u16,
+        trade_limits: TradeLimits,
+    ) -> Result<(), TransportError> {
+        println!("Deposit amounts length {:#?}", deposit_amounts.len());
+        let create_instruction = create(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.mint_key,
+            &self.key,
+            self.seeds,
+            &self.mints.iter().map(|m| m.pool_asset_key).collect(),
+            target_pool_token_account,
+            &source_owner.pubkey(),
+            &source_asset_keys,
+            &ctx.serum_program_id,
+            &self.signal_provider.pubkey(),
+            annual_fee_ratio,
+            fee_ratio,
+            min_signal_provider_ratio,
+            trade_limits,
+            deposit_amounts,
+            vec![market.clone()],
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![create_instruction], vec![&source_owner]).await
+    }
+
+    pub async fn deposit(
+        &self,
+        ctx: &Context,
+        amount: u64,
+        pooltoken_target_key: &Pubkey,
+        source_owner: &Keypair,
+        source_asset_keys: &Vec<Pubkey>,
+    ) -> Result<(), TransportError> {
+        let deposit_instruction = deposit(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.mint_key,
+            &self.key,
+            &self.mints.iter().map(|m| m.pool_asset_key).collect(),
+            &pooltoken_target_key,
+            &source_owner.pubkey(),
+            &source_asset_keys,
+            self.seeds,
+            amount,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![deposit_instruction], vec![&source_owner]).await
+    }
+
+    pub async fn initialize_new_order(&self, ctx: &Context) -> Result<Order, TransportError> {
+        let (open_order, create_open_order_instruction) =
+            SerumMarket::create_dex_account(&ctx, 3216).unwrap();
+
+        wrap_process_transaction(&ctx, vec![create_open_order_instruction], vec![&open_order])
+            .await?;
+        Ok(Order {
+            open_orders_account: open_order.pubkey(),
+        })
+    }
+
+    /// Places an immediate or cancel order for the pool as its signal provider.
+    pub async fn create_new_order(
+        &self,
+        ctx: &mut Context,
+        serum_market: &SerumMarket,
+        source_asset_index: u64,
+        target_asset_index: u64,
+        order: &Order,
+        side: Side,
+        limit_price: NonZeroU64,
+        max_qty: NonZeroU16,
+    ) -> Result<(), TransportError> {
+        self.create_new_order_with(
+            ctx,
+            serum_market,
+            source_asset_index,
+            target_asset_index,
+            order,
+            side,
+            limit_price,
+            max_qty,
+            OrderType::ImmediateOrCancel,
+            SelfTradeBehavior::DecrementTake,
+            None,
+        )
+        .await
+    }
+
+    /// Places an order for the pool with the given order type and self trade behavior, signed by
+    /// `trader` or by the signal provider when none is given.
+    pub async fn create_new_order_with(
+        &self,
+        ctx: &mut Context,
+        serum_market: &SerumMarket,
+        source_asset_index: u64,
+        target_asset_index: u64,
+        order: &Order,
+        side: Side,
+        limit_price: NonZeroU64,
+        max_qty: NonZeroU16,
+        order_type: OrderType,
+        self_trade_behavior: SelfTradeBehavior,
+        trader: Option<&Keypair>,
+    ) -> Result<(), TransportError> {
+        let create_order_instruction = create_order(
+            &self.program_id,
+            &self.signal_provider.pubkey(),
+            &serum_market.market_key.pubkey(),
+            &self.mints[source_asset_index as usize].pool_asset_key,
+            source_asset_index,
+            target_asset_index,
+            &order.open_orders_account,
+            &serum_market.event_q_key.pubkey(),
+            &serum_market.req_q_key.pubkey(),
+            &serum_market.bids_key.pubkey(),
+            &serum_market.asks_key.pubkey(),
+            &self.key,
+            &serum_market.coin_vault,
+            &serum_market.pc_vault,
+            &spl_token::id(),
+            &ctx.serum_program_id,
+            &sysvar::rent::id(),
+            trader.map(|t| t.pubkey()).as_ref(),
+            &sysvar::clock::id(),
+            None,
+            self.seeds,
+            side,
+            limit_price,
+            0,
+            serum_market.coin_lot_size,
+            serum_market.pc_lot_size,
+            &self.mints[target_asset_index as usize].key,
+            max_qty,
+            order_type,
+            0,
+            self_trade_behavior,
+            1000
+        )
+        .unwrap();
+        wrap_process_transaction(
+            &ctx,
+            vec![create_order_instruction],
+            vec![trader.unwrap_or(&self.signal_provider)],
+        )
+        .await
+    }
+
+    pub async fn settle(
+        &self,
+        ctx: &Context,
+        serum_market: &SerumMarket,
+        coin_asset_index: u64,
+        pc_asset_index: u64,
+        order: &Order,
+    ) -> Result<(), TransportError> {
+        let settle_instruction = settle_funds(
+            &self.program_id,
+            &serum_market.market_key.pubkey(),
+            &order.open_orders_account,
+            &self.key,
+            &self.mint_key,
+            &serum_market.coin_vault,
+            &serum_market.pc_vault,
+            &self.mints[coin_asset_index as usize].pool_asset_key,
+            &self.mints[pc_asset_index as usize].pool_asset_key,
+            &serum_market.vault_signer_pk,
+            &spl_token::id(),
+            &ctx.serum_program_id,
+            None,
+            self.seeds,
+            pc_asset_index,
+            coin_asset_index,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![settle_instruction], vec![]).await
+    }
+
+    pub async fn cancel_order(
+        &self,
+        ctx: &Context,
+        serum_market: &SerumMarket,
+        order: &Order,
+    ) -> Result<(), TransportError> {
+        let openorder_view =
+            OpenOrderView::get(order.open_orders_account, &ctx.test_state.banks_client).await?;
+        let cancel_instruction = cancel_order(
+            &self.program_id,
+            &self.signal_provider.pubkey(),
+            &serum_market.market_key.pubkey(),
+            &order.open_orders_account,
+            &serum_market.bids_key.pubkey(),
+            &serum_market.asks_key.pubkey(),
+            &serum_market.event_q_key.pubkey(),
+            &self.key,
+            &ctx.serum_program_id,
+            None,
+            self.seeds,
+            Side::Bid,
+            openorder_view.orders[0],
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![cancel_instruction], vec![&self.signal_provider]).await
+    }
+
+    pub async fn redeem(
+        &self,
+        ctx: &Context,
+        amount: u64,
+        source_owner: &Keypair,
+        pooltoken_target_key: &Pubkey,
+        source_asset_keys: &Vec<Pubkey>,
+    ) -> Result<(), TransportError> {
+        self.redeem_with_open_orders(
+            ctx,
+            amount,
+            source_owner,
+            pooltoken_target_key,
+            source_asset_keys,
+            &[],
+        )
+        .await
+    }
+
+    /// Redeems while the pool has pending orders, given the `(market, open_orders)` pairs of
+    /// their OpenOrders accounts.
+    pub async fn redeem_with_open_orders(
+        &self,
+        ctx: &Context,
+        amount: u64,
+        source_owner: &Keypair,
+        pooltoken_target_key: &Pubkey,
+        source_asset_keys: &Vec<Pubkey>,
+        open_orders: &[(Pubkey, Pubkey)],
+    ) -> Result<(), TransportError> {
+        let redeem_instruction = redeem(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.mint_key,
+            &self.key,
+            &self.mints.iter().map(|m| m.pool_asset_key).collect(),
+            &source_owner.pubkey(),
+            &pooltoken_target_key,
+            &source_asset_keys,
+            self.seeds,
+            amount,
+        )
+        .unwrap();
+        let redeem_instruction = with_unsettled_open_orders(redeem_instruction, open_orders);
+        wrap_process_transaction(&ctx, vec![redeem_instruction], vec![&source_owner]).await
+    }
+
+    pub async fn collect_fees(&self, ctx: &Context) -> Result<(), TransportError> {
+        let instruction = collect_fees(
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.key,
+            &self.mint_key,
+            self.seeds.clone(),
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![]).await
+    }
+
+    /// Claims the fees owed to the owner of a fee recipient pool token account.
+    pub async fn claim_fees(
+        &self,
+        ctx: &Context,
+        recipient_pool_token_key: &Pubkey,
+    ) -> Result<(), TransportError> {
+        let instruction = claim_fees(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.key,
+            &self.mint_key,
+            recipient_pool_token_key,
+            self.seeds,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![]).await
+    }
+
+    /// Queues a redeem and returns the key of the redeem request account.
+    pub async fn header(&self, ctx: &Context) -> PoolHeader {
+        let mut pool_data = ctx
+            .test_state
+            .banks_client
+            .to_owned()
+            .get_account(self.key)
+            .await
+            .unwrap()
+            .unwrap()
+            .data;
+        PoolState::new(&mut pool_data).unwrap().header().unwrap()
+    }
+
+    pub async fn request_redeem(
+        &self,
+        ctx: &Context,
+        amount: u64,
+        source_owner: &Keypair,
+        pooltoken_source_key: &Pubkey,
+    ) -> Result<Pubkey, TransportError> {
+        let sequence = self.header(ctx).await.redeem_queue_tail;
+        let (redeem_request_key, _) =
+            find_redeem_request_key(&self.program_id, &self.seeds, sequence);
+        let instruction = request_redeem(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &self.mint_key,
+            &self.key,
+            &redeem_request_key,
+            &source_owner.pubkey(),
+            pooltoken_source_key,
+            self.seeds,
+            amount,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![source_owner]).await?;
+        Ok(redeem_request_key)
+    }
+
+    /// Pays out redeem requests, given as their account key along with their owner in the order
+    /// of the queue.
+    pub async fn process_redeem_queue(
+        &self,
+        ctx: &Context,
+        redeem_requests: &Vec<(Pubkey, Pubkey)>,
+    ) -> Result<(), TransportError> {
+        let instruction = process_redeem_queue(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.mint_key,
+            &self.key,
+            &self.mints.iter().map(|m| m.key).collect(),
+            redeem_requests,
+            self.seeds,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![]).await
+    }
+
+    pub async fn request_deposit(
+        &self,
+        ctx: &Context,
+        amount: u64,
+        expiry_timestamp: u64,
+        source_owner: &Keypair,
+        source_asset_keys: &Vec<Pubkey>,
+        asset_amounts: Vec<u64>,
+    ) -> Result<Pubkey, TransportError> {
+        let sequence = self.header(ctx).await.deposit_queue_tail;
+        let (deposit_request_key, _) =
+            find_deposit_request_key(&self.program_id, &self.seeds, sequence);
+        let instruction = request_deposit(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.mint_key,
+            &self.key,
+            &deposit_request_key,
+            &source_owner.pubkey(),
+            &self.mints.iter().map(|m| m.key).collect(),
+            source_asset_keys,
+            self.seeds,
+            amount,
+            expiry_timestamp,
+            asset_amounts,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![source_owner]).await?;
+        Ok(deposit_request_key)
+    }
+
+    /// Processes deposit requests, given as their account key along with their owner in the
+    /// order of the queue. Their escrowed mints are read from the request accounts.
+    pub async fn process_deposit_queue(
+        &self,
+        ctx: &Context,
+        deposit_requests: &Vec<(Pubkey, Pubkey)>,
+    ) -> Result<(), TransportError> {
+        let mut requests = Vec::with_capacity(deposit_requests.len());
+        for (deposit_request_key, owner) in deposit_requests {
+            let escrowed_mints = ctx
+                .test_state
+                .banks_client
+                .to_owned()
+                .get_account(*deposit_request_key)
+                .await
+                .unwrap()
+                .and_then(|account| DepositRequest::unpack(&account.data).ok())
+                .map_or(vec![], |request| request.escrowed_mints);
+            requests.push((*deposit_request_key, *owner, escrowed_mints));
+        }
+        let instruction = process_deposit_queue(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.mint_key,
+            &self.key,
+            &self.mints.iter().map(|m| m.key).collect(),
+            &requests,
+            self.seeds,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![]).await
+    }
+
+    /// Returns the key of the associated FIDA account of the bond account.
+    pub fn bond_escrow_key(&self) -> Pubkey {
+        let (bond_key, _) = find_bond_key(&self.program_id, &self.seeds);
+        get_associated_token_address(&bond_key, &Pubkey::from_str(FIDA_MINT).unwrap())
+    }
+
+    /// Posts a bond from a FIDA account of the signal provider, which pays for the escrow and the
+    /// FIDA account of the pool.
+    pub async fn post_bond(
+        &self,
+        ctx: &Context,
+        amount: u64,
+        source_fida_key: &Pubkey,
+    ) -> Result<(), TransportError> {
+        let (bond_key, _) = find_bond_key(&self.program_id, &self.seeds);
+        let instruction = post_bond(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &self.key,
+            &Pubkey::from_str(FIDA_MINT).unwrap(),
+            &bond_key,
+            &self.signal_provider.pubkey(),
+            source_fida_key,
+            self.seeds,
+            amount,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![&self.signal_provider]).await
+    }
+
+    pub async fn request_bond_withdrawal(&self, ctx: &Context) -> Result<(), TransportError> {
+        let instruction = request_bond_withdrawal(
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.key,
+            &self.signal_provider.pubkey(),
+            self.seeds,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![&self.signal_provider]).await
+    }
+
+    pub async fn withdraw_bond(
+        &self,
+        ctx: &Context,
+        target_fida_key: &Pubkey,
+    ) -> Result<(), TransportError> {
+        let (bond_key, _) = find_bond_key(&self.program_id, &self.seeds);
+        let instruction = withdraw_bond(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.key,
+            &Pubkey::from_str(FIDA_MINT).unwrap(),
+            &bond_key,
+            &self.signal_provider.pubkey(),
+            target_fida_key,
+            self.seeds,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![&self.signal_provider]).await
+    }
+
+    pub async fn slash_bond(&self, ctx: &Context) -> Result<(), TransportError> {
+        let (bond_key, _) = find_bond_key(&self.program_id, &self.seeds);
+        let instruction = slash_bond(
+            &spl_token::id(),
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &sysvar::clock::id(),
+            &self.program_id,
+            &self.key,
+            &Pubkey::from_str(FIDA_MINT).unwrap(),
+            &bond_key,
+            &self.signal_provider.pubkey(),
+            &ctx.test_state.payer.pubkey(),
+            self.seeds,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![]).await
+    }
+
+    /// Allows `trader` to trade for the pool, signed by `signal_provider` which should be the
+    /// pool signal provider for the instruction to succeed. Returns the trader account key.
+    pub async fn add_trader(
+        &self,
+        ctx: &Context,
+        signal_provider: &Keypair,
+        trader: &Pubkey,
+        max_ratio_of_pool_assets_to_trade: u16,
+    ) -> Result<Pubkey, TransportError> {
+        let (trader_account_key, _) = find_trader_key(
+            &self.program_id,
+            &self.seeds,
+            &self.signal_provider.pubkey(),
+            trader,
+        );
+        let instruction = add_trader(
+            &system_program::id(),
+            &sysvar::rent::id(),
+            &self.program_id,
+            &self.key,
+            &trader_account_key,
+            &signal_provider.pubkey(),
+            self.seeds,
+            trader,
+            max_ratio_of_pool_assets_to_trade,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![signal_provider]).await?;
+        Ok(trader_account_key)
+    }
+
+    pub async fn remove_trader(
+        &self,
+        ctx: &Context,
+        signal_provider: &Keypair,
+        trader: &Pubkey,
+    ) -> Result<(), TransportError> {
+        let (trader_account_key, _) = find_trader_key(
+            &self.program_id,
+            &self.seeds,
+            &self.signal_provider.pubkey(),
+            trader,
+        );
+        let instruction = remove_trader(
+            &self.program_id,
+            &self.key,
+            &trader_account_key,
+            &signal_provider.pubkey(),
+            self.seeds,
+            trader,
+        )
+        .unwrap();
+        wrap_process_transaction(&ctx, vec![instruction], vec![signal_provider]).await
+    }
+
+    /// Replaces the multisig configuration of the signal provider, approved by the `signers` of
+    /// the current multisig, or by the signal provider when none are given.
+    pub async fn set_multisig(
+        &self,
+        ctx: &Context,
+        signers: &[&Keypair],
+        threshold: u8,
+        multisig_signers: Vec<Pubkey>,
+    ) -> Result<(), TransportError> {
+        let signer_pubkeys: Vec<Pubkey> = signers.iter().map(|s| s.pubkey()).collect();
+        let instruction = set_multisig(
+            &self.program_id,
+            &self.key,
+            &self.signal_provider.pubkey(),
+            &signer_pubkeys.iter().collect::<Vec<_>>(),
+            self.seeds,
+            threshold,
+            multisig_signers,
+        )
+        .unwrap();
+        let transaction_signers = if signers.is_empty() {
+            vec![&self.signal_provider]
+        } else {
+            signers.to_vec()
+        };
+        wrap_process_transaction(&ctx, vec![instruction], transaction_signers).await
+    }
+}
+
+pub struct TestMint {
+    pub name: String,
+    pub key: Pubkey,
+    pub mint_params: Mint,
+    pub pool_asset_key: Pubkey,
+}
+#[derive(Clone)]
+pub struct Order {
+    pub open_orders_account: Pubkey,
+}
@@ -0,0 +1,1206 @@
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    fmt, fs,
+    num::{NonZeroU16, NonZeroU64},
+    path::Path,
+    str::FromStr,
+};
+
+#[cfg(feature = "fuzz")]
+use arbitrary::{Arbitrary, Unstructured};
+
+use bonfida_bot::state::{
+    PoolState, PoolStatus, TradeLimits, BONFIDA_BNB, BONFIDA_FEE, SECONDS_PER_YEAR,
+};
+
+use serde::{Deserialize, Serialize};
+use serum_dex::matching::Side;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transport::TransportError,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account, Mint};
+
+use super::{
+    market::SerumMarket,
+    pool::{Order, TestPool},
+    utils::{clone_keypair, get_element_from_seed, result_err_filter, Context, MintInfo},
+};
+
+#[cfg(feature = "fuzz")]
+use super::utils::arbitraryNonZeroU8;
+
+#[derive(Serialize, Deserialize)]
+#[serde(into = "ActorRecord", try_from = "ActorRecord")]
+pub struct Actor {
+    pub key: Keypair,
+    pub asset_accounts: Vec<Pubkey>,
+    pub pool_token_balance: u64,
+    pub pool_token_account: Option<Pubkey>,
+    pub signal_provider: bool,
+}
+
+impl fmt::Debug for Actor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Actor")
+            .field("key", &self.key.pubkey())
+            .field("signal_provider", &self.signal_provider)
+            .finish()
+    }
+}
+
+impl Clone for Actor {
+    fn clone(&self) -> Self {
+        Self {
+            key: clone_keypair(&self.key),
+            asset_accounts: self.asset_accounts.clone(),
+            pool_token_balance: self.pool_token_balance,
+            pool_token_account: self.pool_token_account,
+            signal_provider: self.signal_provider,
+        }
+    }
+}
+
+#[cfg(feature = "fuzz")]
+impl Arbitrary<'_> for Actor {
+    fn arbitrary(_: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(Actor {
+            key: Keypair::new(),
+            asset_accounts: vec![],
+            pool_token_balance: 0,
+            pool_token_account: None,
+            signal_provider: false,
+        })
+    }
+}
+
+impl Actor {
+    pub fn is_bought_in(&self) -> bool {
+        self.pool_token_balance == 0
+    }
+}
+
+/// Human readable form of an actor, with keys in base 58. A missing keypair is generated.
+#[derive(Serialize, Deserialize)]
+struct ActorRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(default)]
+    asset_accounts: Vec<String>,
+    #[serde(default)]
+    pool_token_balance: u64,
+    #[serde(default)]
+    pool_token_account: Option<String>,
+    #[serde(default)]
+    signal_provider: bool,
+}
+
+impl From<Actor> for ActorRecord {
+    fn from(actor: Actor) -> Self {
+        Self {
+            key: Some(actor.key.to_base58_string()),
+            asset_accounts: actor.asset_accounts.iter().map(|k| k.to_string()).collect(),
+            pool_token_balance: actor.pool_token_balance,
+            pool_token_account: actor.pool_token_account.map(|k| k.to_string()),
+            signal_provider: actor.signal_provider,
+        }
+    }
+}
+
+impl TryFrom<ActorRecord> for Actor {
+    type Error = String;
+
+    fn try_from(record: ActorRecord) -> Result<Self, Self::Error> {
+        let parse = |k: &String| Pubkey::from_str(k).map_err(|e| format!("{} : {}", k, e));
+        let key = record
+            .key
+            .map_or_else(Keypair::new, |k| Keypair::from_base58_string(&k));
+        Ok(Self {
+            key,
+            asset_accounts: record
+                .asset_accounts
+                .iter()
+                .map(parse)
+                .collect::<Result<_, _>>()?,
+            pool_token_balance: record.pool_token_balance,
+            pool_token_account: record.pool_token_account.as_ref().map(parse).transpose()?,
+            signal_provider: record.signal_provider,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Side")]
+enum SideDef {
+    Bid,
+    Ask,
+}
+
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Intention {
+    Idle,
+    BuyIn(u8),
+    BuyOutPartial(u8),
+    BuyOut,
+    Attack(u64),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Signal {
+    Idle,
+    CreateOrder {
+        #[serde(with = "SideDef")]
+        side: Side,
+        limit_price: NonZeroU64,
+        max_qty: NonZeroU16,
+        cancel_after: u8,
+    },
+}
+
+#[cfg(feature = "fuzz")]
+impl Arbitrary<'_> for Signal {
+    fn arbitrary(u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        let result = match u.choose(&[0, 1])? {
+            0 => Self::Idle,
+            1 => Self::CreateOrder {
+                side: *u.choose(&[Side::Ask, Side::Bid])?,
+                limit_price: arbitraryNonZeroU8(u)?.into(),
+                max_qty: arbitraryNonZeroU8(u)?.into(),
+                cancel_after: u.arbitrary()?,
+            },
+            _ => {
+                unreachable!()
+            }
+        };
+        Ok(result)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Turn {
+    signal_intention: Signal,
+    actor_intentions: Vec<Intention>,
+}
+
+pub struct Universe {
+    cycle: u8,
+    known_accounts: Vec<Pubkey>,
+    active_orders: Vec<(u8, Order)>,
+    pool: TestPool,
+    model: Option<ReferenceModel>,
+    serum_market: Option<SerumMarket>,
+    actors: Vec<Actor>,
+}
+
+/// A scenario played by the signal provider and the subscribers. It is recorded as JSON, see
+/// `tests/scenarios`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Execution {
+    subscribers: Vec<Actor>,
+    initial_deposit_amounts: Vec<u8>,
+    turns: Vec<Turn>,
+}
+
+#[cfg(feature = "fuzz")]
+impl Arbitrary<'_> for Execution {
+    fn arbitrary(u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        let number_of_subscribers: u8 = u.arbitrary::<u8>()? >> 4;
+        let mut subscribers = Vec::with_capacity(number_of_subscribers as usize);
+        for _ in 0..number_of_subscribers {
+            subscribers.push(u.arbitrary()?)
+        }
+        let mut turns = Vec::with_capacity(100);
+        for _ in 0..20 {
+            let mut actor_intentions = Vec::with_capacity((number_of_subscribers as usize) + 1);
+            for _ in 0..((number_of_subscribers as u16) + 1) {
+                actor_intentions.push(u.arbitrary()?)
+            }
+            turns.push(Turn {
+                signal_intention: u.arbitrary()?,
+                actor_intentions,
+            })
+        }
+        let mut initial_deposit_amounts = Vec::with_capacity(4);
+        for _ in 0..4 {
+            initial_deposit_amounts.push(u.arbitrary()?);
+        }
+        initial_deposit_amounts[0] = 10.max(initial_deposit_amounts[0]);
+        println!(
+            "Initial deposit amounts length : {:?}",
+            initial_deposit_amounts.len()
+        );
+        Ok(Execution {
+            subscribers,
+            initial_deposit_amounts,
+            turns,
+        })
+    }
+}
+
+/// Observable state of a pool : its status, pool token supply, fee ledger and the balances of the
+/// tracked token accounts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolSnapshot {
+    pub status: PoolStatus,
+    pub pool_token_supply: u64,
+    pub last_fee_collection_timestamp: u64,
+    pub accrued_fees: u64,
+    pub claimed_fees: [u64; 3],
+    pub balances: BTreeMap<Pubkey, u64>,
+}
+
+impl PoolSnapshot {
+    pub async fn fetch(ctx: &Context, pool: &TestPool, accounts: Vec<Pubkey>) -> Self {
+        let mut pool_data = get_account_data(ctx, pool.key).await.unwrap();
+        let header = PoolState::new(&mut pool_data)
+            .unwrap()
+            .header_unchecked()
+            .unwrap();
+        let pool_token_supply = Mint::unpack(&get_account_data(ctx, pool.mint_key).await.unwrap())
+            .unwrap()
+            .supply;
+        let mut balances = BTreeMap::new();
+        for key in accounts {
+            let balance = get_account_data(ctx, key)
+                .await
+                .and_then(|data| Account::unpack(&data).ok())
+                .map_or(0, |a| a.amount);
+            balances.insert(key, balance);
+        }
+        Self {
+            status: header.status,
+            pool_token_supply,
+            last_fee_collection_timestamp: header.last_fee_collection_timestamp,
+            accrued_fees: header.accrued_fees,
+            claimed_fees: header.claimed_fees,
+            balances,
+        }
+    }
+
+    /// Lists the fields of `actual` which differ from `self`.
+    pub fn diff(&self, actual: &Self) -> Vec<String> {
+        let mut differences = vec![];
+        if self.status != actual.status {
+            differences.push(format!(
+                "status: expected {:?}, found {:?}",
+                self.status, actual.status
+            ));
+        }
+        if self.pool_token_supply != actual.pool_token_supply {
+            differences.push(format!(
+                "pool token supply: expected {}, found {}",
+                self.pool_token_supply, actual.pool_token_supply
+            ));
+        }
+        if self.last_fee_collection_timestamp != actual.last_fee_collection_timestamp {
+            differences.push(format!(
+                "last fee collection timestamp: expected {}, found {}",
+                self.last_fee_collection_timestamp, actual.last_fee_collection_timestamp
+            ));
+        }
+        if self.accrued_fees != actual.accrued_fees {
+            differences.push(format!(
+                "accrued fees: expected {}, found {}",
+                self.accrued_fees, actual.accrued_fees
+            ));
+        }
+        if self.claimed_fees != actual.claimed_fees {
+            differences.push(format!(
+                "claimed fees: expected {:?}, found {:?}",
+                self.claimed_fees, actual.claimed_fees
+            ));
+        }
+        for (key, expected) in &self.balances {
+            let found = actual.balances.get(key).copied().unwrap_or(0);
+            if *expected != found {
+                differences.push(format!(
+                    "balance of {}: expected {}, found {}",
+                    key, expected, found
+                ));
+            }
+        }
+        differences
+    }
+}
+
+/// In-memory reference of a pool. Every operation which succeeds on chain is applied to the model
+/// as well, after which the on-chain accounts should match `expected`. The model computes the
+/// amounts from the specification rather than with the program's own accounting.
+#[derive(Clone, Debug)]
+pub struct ReferenceModel {
+    pub expected: PoolSnapshot,
+    pool_asset_accounts: Vec<Pubkey>,
+    /// Signal provider, Bonfida fee and Bonfida buy and burn pool token accounts
+    fee_accounts: [Pubkey; 3],
+    fee_ratio: u16,
+    annual_fee_ratio: u16,
+}
+
+impl ReferenceModel {
+    pub async fn fetch(ctx: &Context, pool: &TestPool, tracked_accounts: &[Pubkey]) -> Self {
+        let mut pool_data = get_account_data(ctx, pool.key).await.unwrap();
+        let pool_state = PoolState::new(&mut pool_data).unwrap();
+        let header = pool_state.header().unwrap();
+        let pool_asset_accounts = pool_state
+            .assets()
+            .map(|(_, a)| get_associated_token_address(&pool.key, &a.mint_address))
+            .collect();
+        let fee_accounts = [
+            get_associated_token_address(&header.signal_provider, &pool.mint_key),
+            get_associated_token_address(&Pubkey::from_str(BONFIDA_FEE).unwrap(), &pool.mint_key),
+            get_associated_token_address(&Pubkey::from_str(BONFIDA_BNB).unwrap(), &pool.mint_key),
+        ];
+        let mut accounts = tracked_accounts.to_vec();
+        accounts.extend_from_slice(&fee_accounts);
+        Self {
+            expected: PoolSnapshot::fetch(ctx, pool, accounts).await,
+            pool_asset_accounts,
+            fee_accounts,
+            fee_ratio: header.fee_ratio,
+            annual_fee_ratio: header.annual_fee_ratio,
+        }
+    }
+
+    pub fn balance(&self, key: &Pubkey) -> u64 {
+        self.expected.balances.get(key).copied().unwrap_or(0)
+    }
+
+    fn debit(&mut self, key: &Pubkey, amount: u64) -> Result<(), String> {
+        let balance = self.balance(key);
+        if balance < amount {
+            return Err(format!("{} holds {} < {}", key, balance, amount));
+        }
+        self.expected.balances.insert(*key, balance - amount);
+        Ok(())
+    }
+
+    fn credit(&mut self, key: &Pubkey, amount: u64) -> Result<(), String> {
+        let balance = self
+            .balance(key)
+            .checked_add(amount)
+            .ok_or_else(|| format!("balance of {} overflows", key))?;
+        self.expected.balances.insert(*key, balance);
+        Ok(())
+    }
+
+    fn mint(&mut self, key: &Pubkey, amount: u64) -> Result<(), String> {
+        self.expected.pool_token_supply = self
+            .expected
+            .pool_token_supply
+            .checked_add(amount)
+            .ok_or("pool token supply overflows")?;
+        self.credit(key, amount)
+    }
+
+    fn check_pool_asset_accounts(&self, pool_asset_accounts: &[Pubkey]) -> Result<(), String> {
+        if pool_asset_accounts != &self.pool_asset_accounts[..] {
+            return Err("wrong pool asset accounts were accepted".to_owned());
+        }
+        Ok(())
+    }
+
+    fn accrue_fees(&mut self, amount: u64) -> Result<(), String> {
+        self.expected.accrued_fees = self
+            .expected
+            .accrued_fees
+            .checked_add(amount)
+            .ok_or("accrued fees overflow")?;
+        Ok(())
+    }
+
+    /// Pool tokens owed to the signal provider (one half, rounded up), Bonfida (one quarter) and
+    /// the Bonfida buy and burn account (one quarter, rounded down) out of all the fees accrued.
+    fn owed_fees(&self) -> [u64; 3] {
+        let accrued = self.expected.accrued_fees;
+        let shares = [
+            ((accrued as u128 + 1) / 2) as u64,
+            ((accrued as u128 + 2) / 4) as u64,
+            accrued / 4,
+        ];
+        let mut owed = [0; 3];
+        for i in 0..3 {
+            owed[i] = shares[i] - self.expected.claimed_fees[i];
+        }
+        owed
+    }
+
+    /// The pool token supply, including the fees which have not been claimed yet.
+    pub fn total_pooltokens(&self) -> u64 {
+        self.expected.pool_token_supply + self.owed_fees().iter().sum::<u64>()
+    }
+
+    /// Management fees dilute the holders by `annual_fee_ratio * elapsed / SECONDS_PER_YEAR`,
+    /// at most one half at a time.
+    fn settle_management_fees(&mut self, now: u64) -> Result<u64, String> {
+        let elapsed = now.saturating_sub(self.expected.last_fee_collection_timestamp) as u128;
+        let year = (SECONDS_PER_YEAR as u128) << 16;
+        let share = (self.annual_fee_ratio as u128 * elapsed).min(year / 2);
+        let fees = (self.total_pooltokens() as u128 * share / (year - share)) as u64;
+        if fees != 0 {
+            self.accrue_fees(fees)?;
+            self.expected.last_fee_collection_timestamp = now;
+        }
+        Ok(fees)
+    }
+
+    pub fn deposit(
+        &mut self,
+        now: u64,
+        pool_asset_accounts: &[Pubkey],
+        target: &Pubkey,
+        sources: &[Pubkey],
+        amount: u64,
+    ) -> Result<(), String> {
+        self.check_pool_asset_accounts(pool_asset_accounts)?;
+        if self.expected.status != PoolStatus::Unlocked {
+            return Err(format!("deposit into a {:?} pool", self.expected.status));
+        }
+        self.settle_management_fees(now)?;
+
+        // The depositor gets as many pool tokens as the scarcest asset allows
+        let supply = self.total_pooltokens() as u128;
+        let mut effective = amount as u128;
+        for (pool_asset, source) in pool_asset_accounts.iter().zip(sources) {
+            let pool_amount = self.balance(pool_asset) as u128;
+            if pool_amount != 0 {
+                effective = effective.min(self.balance(source) as u128 * supply / pool_amount);
+            }
+        }
+        // Depositors pay every asset rounded up
+        let paid: Vec<u64> = pool_asset_accounts
+            .iter()
+            .map(|k| ((effective * self.balance(k) as u128 + supply - 1) / supply) as u64)
+            .collect();
+        if paid.iter().all(|p| *p == 0) {
+            return Err("deposit of nothing".to_owned());
+        }
+        for ((pool_asset, source), amount) in pool_asset_accounts.iter().zip(sources).zip(paid) {
+            self.debit(source, amount)?;
+            self.credit(pool_asset, amount)?;
+        }
+
+        let fee = ((effective * self.fee_ratio as u128) >> 16) as u64;
+        self.accrue_fees(fee)?;
+        self.mint(target, effective as u64 - fee)
+    }
+
+    pub fn redeem(
+        &mut self,
+        now: u64,
+        pool_asset_accounts: &[Pubkey],
+        source: &Pubkey,
+        targets: &[Pubkey],
+        amount: u64,
+    ) -> Result<(), String> {
+        self.check_pool_asset_accounts(pool_asset_accounts)?;
+        if let PoolStatus::PendingOrder(_) | PoolStatus::LockedPendingOrder(_) =
+            self.expected.status
+        {
+            return Err(format!("redeem from a {:?} pool", self.expected.status));
+        }
+        self.settle_management_fees(now)?;
+
+        // Redeemers receive every asset rounded down
+        let supply = self.total_pooltokens();
+        for (pool_asset, target) in pool_asset_accounts.iter().zip(targets) {
+            let paid = (amount as u128 * self.balance(pool_asset) as u128 / supply as u128) as u64;
+            self.debit(pool_asset, paid)?;
+            self.credit(target, paid)?;
+        }
+        self.debit(source, amount)?;
+        self.expected.pool_token_supply -= amount;
+        if self.total_pooltokens() == 0 {
+            self.expected.status = PoolStatus::Uninitialized;
+        }
+        Ok(())
+    }
+
+    pub fn collect_fees(&mut self, now: u64) -> Result<(), String> {
+        match self.settle_management_fees(now)? {
+            0 => Err("collection of no fees".to_owned()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Mints the fees owed to the recipient owning `target`, after the management fees accrued.
+    pub fn claim_fees(&mut self, now: u64, target: &Pubkey) -> Result<(), String> {
+        let recipient = self
+            .fee_accounts
+            .iter()
+            .position(|k| k == target)
+            .ok_or_else(|| format!("claim of fees to {}", target))?;
+        self.settle_management_fees(now)?;
+        let amount = self.owed_fees()[recipient];
+        if amount == 0 {
+            return Err("claim of no fees".to_owned());
+        }
+        self.expected.claimed_fees[recipient] += amount;
+        self.mint(target, amount)
+    }
+}
+
+/// A turn at which the on-chain accounts and the reference model disagree.
+#[derive(Debug)]
+pub struct Divergence {
+    pub turn: Option<usize>,
+    pub actor: Option<usize>,
+    pub operation: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Divergence at turn {:?}, actor {:?}, during {} : {}",
+            self.turn, self.actor, self.operation, self.reason
+        )
+    }
+}
+
+async fn get_account_data(ctx: &Context, key: Pubkey) -> Option<Vec<u8>> {
+    ctx.test_state
+        .banks_client
+        .to_owned()
+        .get_account(key)
+        .await
+        .unwrap()
+        .map(|a| a.data)
+}
+
+async fn current_timestamp(ctx: &Context) -> u64 {
+    ctx.clock().await.unix_timestamp as u64
+}
+
+impl Execution {
+    /// Applies every turn to both the program and the reference model and returns the first
+    /// divergence between them.
+    pub async fn run(&self, ctx: &mut Context, mints: &Vec<MintInfo>) -> Result<(), Divergence> {
+        let mut universe = Universe::new(&ctx, mints);
+
+        for a in &self.subscribers {
+            universe.add_actor(a.clone())
+        }
+
+        println!("=========== Universe init ===========");
+
+        let init_result = universe
+            .init(
+                ctx,
+                self.initial_deposit_amounts
+                    .iter()
+                    .map(|a| u64::from(*a) * 100_000)
+                    .collect(),
+            )
+            .await;
+        if let Err(e) = result_err_filter(init_result) {
+            return Err(Divergence {
+                turn: None,
+                actor: None,
+                operation: "init".to_owned(),
+                reason: format!("unexpected error {:?}", e),
+            });
+        }
+        if universe.model.is_none() {
+            // The pool could not be created, there is nothing to compare
+            return Ok(());
+        }
+        println!("=========== Simulation Turns ===========");
+        for (i, turn) in self.turns.iter().enumerate() {
+            universe.consume_turn(ctx, i, turn).await?;
+            if universe.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Shrinks a diverging execution by dropping turns and subscribers and by idling intentions
+    /// for as long as it keeps diverging. Every candidate is replayed from a fresh context.
+    pub async fn minimize(&self, divergence: Divergence) -> (Execution, Divergence) {
+        let mut best = self.clone();
+        let mut best_divergence = divergence;
+        if let Some(turn) = best_divergence.turn {
+            best.turns.truncate(turn + 1);
+        }
+
+        let mut i = best.turns.len();
+        while i > 0 {
+            i -= 1;
+            let mut candidate = best.clone();
+            candidate.turns.remove(i);
+            if let Err(d) = candidate.replay().await {
+                best = candidate;
+                best_divergence = d;
+            }
+        }
+
+        // Actor 0 is the signal provider, subscribers come after it
+        let mut i = best.subscribers.len();
+        while i > 0 {
+            i -= 1;
+            let mut candidate = best.clone();
+            candidate.subscribers.remove(i);
+            for turn in &mut candidate.turns {
+                turn.actor_intentions.remove(i + 1);
+            }
+            if let Err(d) = candidate.replay().await {
+                best = candidate;
+                best_divergence = d;
+            }
+        }
+
+        for i in 0..best.turns.len() {
+            if !matches!(best.turns[i].signal_intention, Signal::Idle) {
+                let mut candidate = best.clone();
+                candidate.turns[i].signal_intention = Signal::Idle;
+                if let Err(d) = candidate.replay().await {
+                    best = candidate;
+                    best_divergence = d;
+                }
+            }
+            for j in 0..best.turns[i].actor_intentions.len() {
+                if matches!(best.turns[i].actor_intentions[j], Intention::Idle) {
+                    continue;
+                }
+                let mut candidate = best.clone();
+                candidate.turns[i].actor_intentions[j] = Intention::Idle;
+                if let Err(d) = candidate.replay().await {
+                    best = candidate;
+                    best_divergence = d;
+                }
+            }
+        }
+        (best, best_divergence)
+    }
+
+    /// Runs the execution from a fresh context.
+    pub async fn replay(&self) -> Result<(), Divergence> {
+        let mut ctx = Context::init().await;
+        let mints = ctx.get_mints();
+        self.run(&mut ctx, &mints).await
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&data).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl Universe {
+    pub fn new(ctx: &Context, mints: &Vec<MintInfo>) -> Self {
+        let mut pool = TestPool::new(&ctx);
+        for mint_info in mints {
+            pool.add_mint(None, mint_info);
+        }
+        let known_accounts = pool
+            .mints
+            .iter()
+            .map(|m| m.pool_asset_key)
+            .collect::<Vec<_>>();
+
+        let signal_provider = Actor {
+            key: clone_keypair(&pool.signal_provider),
+            asset_accounts: vec![],
+            signal_provider: true,
+            pool_token_balance: 0,
+            pool_token_account: None,
+        };
+
+        Self {
+            cycle: 0,
+            known_accounts,
+            pool,
+            model: None,
+            actors: vec![signal_provider],
+            active_orders: vec![],
+            serum_market: None,
+        }
+    }
+
+    pub fn add_actor(&mut self, actor: Actor) {
+        self.actors.push(actor)
+    }
+
+    pub async fn init(
+        &mut self,
+        ctx: &mut Context,
+        deposit_amounts: Vec<u64>,
+    ) -> Result<(), TransportError> {
+        self.serum_market = Some(
+            SerumMarket::initialize_market_accounts(
+                ctx,
+                &self.pool.mints[2].key,
+                &self.pool.mints[1].key,
+            )
+            .await?,
+        );
+        self.pool.setup(&ctx).await;
+        for actor in &mut self.actors {
+            actor.asset_accounts = self
+                .pool
+                .get_funded_token_accounts(ctx, &actor.key.pubkey())
+                .await;
+            self.known_accounts.extend(actor.asset_accounts.iter());
+            actor.pool_token_account =
+                Some(self.pool.get_pt_account(ctx, &actor.key.pubkey()).await);
+            self.known_accounts.push(actor.pool_token_account.unwrap());
+        }
+        self.pool
+            .create(
+                &ctx,
+                self.actors[0].pool_token_account.as_ref().unwrap(),
+                &self.actors[0].key,
+                &self.actors[0].asset_accounts,
+                deposit_amounts,
+                &self.serum_market.as_ref().unwrap().market_key.pubkey(),
+                1311,
+                15,
+                0,
+                TradeLimits::default(),
+            )
+            .await?;
+        self.model = Some(ReferenceModel::fetch(ctx, &self.pool, &self.known_accounts).await);
+        self.sync_actors();
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.model
+            .as_ref()
+            .map_or(true, |m| m.total_pooltokens() == 0)
+    }
+
+    fn pool_asset_keys(&self) -> Vec<Pubkey> {
+        self.pool.mints.iter().map(|m| m.pool_asset_key).collect()
+    }
+
+    fn sync_actors(&mut self) {
+        let model = self.model.as_ref().unwrap();
+        for actor in &mut self.actors {
+            actor.pool_token_balance = model.balance(actor.pool_token_account.as_ref().unwrap());
+        }
+    }
+
+    /// Applies a successful operation to the model with `apply`, then compares the on-chain
+    /// accounts with the model. Failed transactions should leave the accounts untouched.
+    async fn check<F>(
+        &mut self,
+        ctx: &Context,
+        turn: usize,
+        actor: Option<usize>,
+        operation: &str,
+        result: Result<(), TransportError>,
+        apply: F,
+    ) -> Result<(), Divergence>
+    where
+        F: FnOnce(&mut ReferenceModel, u64) -> Result<(), String>,
+    {
+        let divergence = |reason: String| Divergence {
+            turn: Some(turn),
+            actor,
+            operation: operation.to_owned(),
+            reason,
+        };
+        match result {
+            Ok(()) => {
+                let now = current_timestamp(ctx).await;
+                apply(self.model.as_mut().unwrap(), now).map_err(|r| {
+                    divergence(format!("succeeded on chain but not in the model, {}", r))
+                })?;
+            }
+            Err(e) => {
+                result_err_filter(Err(e))
+                    .map_err(|e| divergence(format!("unexpected error {:?}", e)))?;
+            }
+        }
+        let model = self.model.as_ref().unwrap();
+        let actual = PoolSnapshot::fetch(
+            ctx,
+            &self.pool,
+            model.expected.balances.keys().copied().collect(),
+        )
+        .await;
+        let differences = model.expected.diff(&actual);
+        self.sync_actors();
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(divergence(differences.join(", ")))
+        }
+    }
+
+    /// Serum moves funds in and out of the pool in ways the model does not follow. The model picks
+    /// up the on-chain state again after such operations.
+    async fn resync(&mut self, ctx: &Context) {
+        let model = self.model.as_mut().unwrap();
+        model.expected = PoolSnapshot::fetch(
+            ctx,
+            &self.pool,
+            model.expected.balances.keys().copied().collect(),
+        )
+        .await;
+        self.sync_actors();
+    }
+
+    pub async fn consume_turn(
+        &mut self,
+        ctx: &mut Context,
+        turn_index: usize,
+        turn: &Turn,
+    ) -> Result<(), Divergence> {
+        if let Signal::CreateOrder {
+            side,
+            limit_price,
+            max_qty,
+            cancel_after,
+        } = turn.signal_intention
+        {
+            let order = self.pool.initialize_new_order(ctx).await;
+            let order_result = match order {
+                Ok(order) => {
+                    let (source_asset_index, target_asset_index) = match side {
+                        Side::Bid => (1, 2),
+                        Side::Ask => (2, 1),
+                    };
+                    let order_result = self
+                        .pool
+                        .create_new_order(
+                            ctx,
+                            self.serum_market.as_ref().unwrap(),
+                            source_asset_index,
+                            target_asset_index,
+                            &order,
+                            side,
+                            limit_price,
+                            max_qty,
+                        )
+                        .await;
+                    self.known_accounts.push(order.open_orders_account);
+                    if order_result.is_ok() {
+                        self.active_orders.push((cancel_after + self.cycle, order));
+                    }
+                    order_result
+                }
+                Err(e) => Err(e),
+            };
+            if order_result.is_ok() {
+                self.resync(ctx).await;
+            } else {
+                self.check(
+                    ctx,
+                    turn_index,
+                    Some(0),
+                    "create order",
+                    order_result,
+                    |_, _| Ok(()),
+                )
+                .await?;
+            }
+        }
+        // let mut active_orders = vec![];
+        // for (cancel_after, order) in &self.active_orders {
+        //     if *cancel_after >= self.cycle {
+        //         self.pool
+        //             .cancel_order(ctx, self.serum_market.as_ref().unwrap(), order)
+        //             .await?;
+        //     } else {
+        //         active_orders.push((*cancel_after, order.clone()))
+        //     }
+        // }
+        // self.active_orders = active_orders;
+        for i in 0..self.actors.len() {
+            let actor = self.actors[i].clone();
+            let pool_token_account = actor.pool_token_account.unwrap();
+            let pool_asset_keys = self.pool_asset_keys();
+            match turn.actor_intentions[i] {
+                Intention::Idle => {}
+                Intention::BuyIn(amount) => {
+                    println!("Buying in");
+                    let desired_amount = (amount as u64) * 100_000;
+                    let result = self
+                        .pool
+                        .deposit(
+                            ctx,
+                            desired_amount,
+                            &pool_token_account,
+                            &actor.key,
+                            &actor.asset_accounts,
+                        )
+                        .await;
+                    self.check(ctx, turn_index, Some(i), "deposit", result, |model, now| {
+                        model.deposit(
+                            now,
+                            &pool_asset_keys,
+                            &pool_token_account,
+                            &actor.asset_accounts,
+                            desired_amount,
+                        )
+                    })
+                    .await?;
+                }
+                Intention::BuyOutPartial(amount) => {
+                    println!("Buying out partially");
+                    let actual_amount = actor.pool_token_balance.min((amount as u64) * 100_000);
+                    if actual_amount != 0 {
+                        let result = self
+                            .pool
+                            .redeem(
+                                ctx,
+                                actual_amount,
+                                &actor.key,
+                                &pool_token_account,
+                                &actor.asset_accounts,
+                            )
+                            .await;
+                        self.check(ctx, turn_index, Some(i), "redeem", result, |model, now| {
+                            model.redeem(
+                                now,
+                                &pool_asset_keys,
+                                &pool_token_account,
+                                &actor.asset_accounts,
+                                actual_amount,
+                            )
+                        })
+                        .await?;
+                    }
+                }
+                Intention::BuyOut => {
+                    println!("Buying out");
+                    if actor.pool_token_balance != 0 {
+                        let result = self
+                            .pool
+                            .redeem(
+                                ctx,
+                                actor.pool_token_balance,
+                                &actor.key,
+                                &pool_token_account,
+                                &actor.asset_accounts,
+                            )
+                            .await;
+                        self.check(ctx, turn_index, Some(i), "redeem", result, |model, now| {
+                            model.redeem(
+                                now,
+                                &pool_asset_keys,
+                                &pool_token_account,
+                                &actor.asset_accounts,
+                                actor.pool_token_balance,
+                            )
+                        })
+                        .await?;
+                    }
+                }
+                Intention::Attack(seed) => {
+                    println!("Attacking");
+                    self.attack(ctx, turn_index, i, &actor, seed).await?;
+                }
+            }
+            if self.is_empty() {
+                println!("Pool is empty and has been deleted");
+                break;
+            }
+        }
+        if self.active_orders.len() != 0 {
+            self.serum_market
+                .as_ref()
+                .unwrap()
+                .crank(
+                    ctx,
+                    self.active_orders
+                        .iter()
+                        .map(|o| &o.1.open_orders_account)
+                        .collect(),
+                )
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Sends an instruction built from arbitrary known accounts. Those should either fail or be
+    /// legitimate operations which the model can follow.
+    async fn attack(
+        &mut self,
+        ctx: &mut Context,
+        turn: usize,
+        i: usize,
+        actor: &Actor,
+        seed: u64,
+    ) -> Result<(), Divergence> {
+        let known_accounts = self.known_accounts.clone();
+        let known =
+            |shift: u64| *get_element_from_seed(&known_accounts, ((seed >> shift) & 0x3f) as u8);
+        let pool_asset_keys = self.pool_asset_keys();
+        let instruction_tag = seed >> 61;
+        match instruction_tag {
+            0 => {
+                let target_pool_token_account = known(0);
+                let asset_accounts = vec![known(6), known(12), known(18), known(24)];
+                let deposit_amounts = vec![
+                    (((seed >> 30) & 0x3f) as u64) * 100_000,
+                    (((seed >> 36) & 0x3f) as u64) * 100_000,
+                ];
+                let result = self
+                    .pool
+                    .create(
+                        ctx,
+                        &target_pool_token_account,
+                        &actor.key,
+                        &asset_accounts,
+                        deposit_amounts,
+                        &self.serum_market.as_ref().unwrap().market_key.pubkey(),
+                        700_000,
+                        15,
+                        0,
+                        TradeLimits::default(),
+                    )
+                    .await;
+                self.check(ctx, turn, Some(i), "create", result, |_, _| {
+                    Err("an existing pool was created again".to_owned())
+                })
+                .await
+            }
+            1 => {
+                let target_pool_token_account = known(0);
+                let amount = (((seed >> 6) & 0x3f) as u64) * 100_000;
+                let asset_accounts = vec![known(12), known(18), known(24), known(30)];
+                let result = self
+                    .pool
+                    .deposit(
+                        ctx,
+                        amount,
+                        &target_pool_token_account,
+                        &actor.key,
+                        &asset_accounts,
+                    )
+                    .await;
+                self.check(ctx, turn, Some(i), "deposit", result, |model, now| {
+                    model.deposit(
+                        now,
+                        &pool_asset_keys,
+                        &target_pool_token_account,
+                        &asset_accounts,
+                        amount,
+                    )
+                })
+                .await
+            }
+            2 => {
+                let order = Order {
+                    open_orders_account: known(0),
+                };
+                let side = match (seed >> 6) & 1 {
+                    0 => Side::Ask,
+                    1 => Side::Bid,
+                    _ => unreachable!(),
+                };
+                let result = self
+                    .pool
+                    .create_new_order(
+                        ctx,
+                        self.serum_market.as_ref().unwrap(),
+                        ((seed >> 7) & 0x3f) as u64 % (self.pool.mints.len() as u64),
+                        ((seed >> 13) & 0x3f) as u64 % (self.pool.mints.len() as u64),
+                        &order,
+                        side,
+                        NonZeroU64::new((((seed >> 19) & 0x3f) << 4) as u64 + 1).unwrap(),
+                        NonZeroU16::new((((seed >> 25) & 0x3f) << 4) as u16 + 1).unwrap(),
+                    )
+                    .await;
+                // Orders are not part of the model, which follows the pool once one is placed
+                if result.is_ok() {
+                    self.resync(ctx).await;
+                    Ok(())
+                } else {
+                    self.check(ctx, turn, Some(i), "create order", result, |_, _| Ok(()))
+                        .await
+                }
+            }
+            3 => {
+                let order = Order {
+                    open_orders_account: known(0),
+                };
+                let result = self
+                    .pool
+                    .settle(
+                        ctx,
+                        self.serum_market.as_ref().unwrap(),
+                        ((seed >> 6) & 0x3f) as u64 % (self.pool.mints.len() as u64),
+                        ((seed >> 12) & 0x3f) as u64 % (self.pool.mints.len() as u64),
+                        &order,
+                    )
+                    .await;
+                if result.is_ok() {
+                    self.resync(ctx).await;
+                    Ok(())
+                } else {
+                    self.check(ctx, turn, Some(i), "settle", result, |_, _| Ok(()))
+                        .await
+                }
+            }
+            4 => {
+                // let order = Order {
+                //     open_orders_account: *get_element_from_seed(&self.known_accounts, (seed & 0x3f) as u8)
+                // };
+                // let result = self.pool.cancel_order(
+                //     ctx,
+                //     self.serum_market.as_ref().unwrap(),
+                //     &order
+                // ).await;
+                // result_err_filter(result)?;
+                Ok(())
+            }
+            5 => {
+                let source_pool_token_account = known(0);
+                let asset_accounts = vec![known(6), known(12), known(18), known(24)];
+                let amount = (((seed >> 30) & 0x3f) << 4) as u64;
+                let result = self
+                    .pool
+                    .redeem(
+                        ctx,
+                        amount,
+                        &actor.key,
+                        &source_pool_token_account,
+                        &asset_accounts,
+                    )
+                    .await;
+                self.check(ctx, turn, Some(i), "redeem", result, |model, now| {
+                    model.redeem(
+                        now,
+                        &pool_asset_keys,
+                        &source_pool_token_account,
+                        &asset_accounts,
+                        amount,
+                    )
+                })
+                .await
+            }
+            6 => {
+                let result = self.pool.collect_fees(ctx).await;
+                self.check(ctx, turn, Some(i), "collect fees", result, |model, now| {
+                    model.collect_fees(now)
+                })
+                .await
+            }
+            7 => {
+                let target_pool_token_account = known(0);
+                let result = self.pool.claim_fees(ctx, &target_pool_token_account).await;
+                self.check(ctx, turn, Some(i), "claim fees", result, |model, now| {
+                    model.claim_fees(now, &target_pool_token_account)
+                })
+                .await
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+    }
+}
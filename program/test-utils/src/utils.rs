@@ -3,19 +3,18 @@ use std::{convert::TryInto, num::NonZeroU8, str::FromStr};
 #[cfg(feature = "fuzz")]
 use arbitrary::Unstructured;
 
-#[cfg(not(feature = "fuzz"))]
 use bonfida_bot::{
-    state::{unpack_assets, PoolHeader},
+    error::BonfidaBotError,
+    state::PoolState,
 };
 
-#[cfg(feature = "fuzz")]
-use crate::state::{unpack_assets, PoolHeader};
-
-use solana_program::{instruction::{Instruction, InstructionError}, program_error::ProgramError, program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction};
-use solana_program_test::{BanksClient, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext, find_file, read_file};
-use solana_sdk::{account::Account, signature::{Keypair, Signer}, transaction::{Transaction, TransactionError}, transport::TransportError};
+use solana_program::{clock::Clock, instruction::{Instruction, InstructionError}, program_error::ProgramError, program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_instruction, sysvar};
+use solana_program_test::{BanksClient, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext, processor};
+use solana_sdk::{account::{Account, from_account}, message::Message, signature::{Keypair, Signer}, transaction::{Transaction, TransactionError}, transport::TransportError};
 use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
-use spl_token::{instruction::initialize_account, state::Mint};
+use spl_token::{instruction::{initialize_account, initialize_mint}, state::Mint};
+
+use super::mock_dex;
 
 const SRM_MINT_KEY: &str = "SRMuApVNdxXokk5GT7XD5cUUgXMBCoAz2LHeuAoKWRt";
 const FIDA_MINT_KEY: &str = "EchesyfXePKdLtoiZSL8pBe8Myagyy8ZRqsACNCFGnvp";
@@ -39,6 +38,15 @@ impl Context {
     }
 
     pub async fn init() -> Context {
+        Self::init_with_accounts(|_, _, _| {}).await
+    }
+
+    /// Same as `init`, with `add_accounts` adding accounts to the cluster before it starts. It is
+    /// given the program id and the FIDA, SRM, pc and coin mints.
+    pub async fn init_with_accounts<F>(add_accounts: F) -> Context
+    where
+        F: FnOnce(&mut ProgramTest, &Pubkey, &[MintInfo]),
+    {
         let bonfidabot_program_id = Pubkey::new_unique();
         let serum_program_id = Pubkey::new_unique();
 
@@ -49,16 +57,11 @@ impl Context {
         );
 
 
-        // Load The Serum Dex program
-        program_test.add_account(
+        // Load the mock of the Serum Dex program
+        program_test.add_program(
+            "mock_dex",
             serum_program_id,
-            Account {
-                lamports: u32::MAX.into(),
-                data: read_file(find_file("serum_dex.so").unwrap()),
-                owner: solana_program::bpf_loader::id(),
-                executable: true,
-                ..Account::default()
-            },
+            processor!(mock_dex::process_instruction),
         );
         let payer = Keypair::new();
         program_test.add_account(
@@ -74,6 +77,11 @@ impl Context {
         let srm_mint = mint_bootstrap(Some(SRM_MINT_KEY), 6, &mut program_test, &mint_authority.pubkey());
         let pc_mint = mint_bootstrap(None, 6, &mut program_test, &mint_authority.pubkey());
         let coin_mint = mint_bootstrap(None, 6, &mut program_test, &mint_authority.pubkey());
+        add_accounts(
+            &mut program_test,
+            &bonfidabot_program_id,
+            &[fida_mint, srm_mint, pc_mint, coin_mint],
+        );
 
         let mut test_state = program_test.start_with_context().await;
         test_state.payer = payer;
@@ -98,6 +106,54 @@ impl Context {
             self.coin_mint
         ]
     }
+
+    /// Creates a new mint with `mint_authority` as its authority.
+    pub async fn create_mint(&self, decimals: u8) -> Result<MintInfo, TransportError> {
+        let mint = Keypair::new();
+        let instructions = vec![
+            system_instruction::create_account(
+                &self.test_state.payer.pubkey(),
+                &mint.pubkey(),
+                Rent::default().minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &self.mint_authority.pubkey(),
+                None,
+                decimals,
+            )
+            .unwrap(),
+        ];
+        wrap_process_transaction(self, instructions, vec![&mint]).await?;
+        let data = self.test_state.banks_client
+            .to_owned()
+            .get_account(mint.pubkey())
+            .await?
+            .unwrap()
+            .data;
+        Ok((mint.pubkey(), Mint::unpack(&data).unwrap()))
+    }
+
+    pub async fn clock(&self) -> Clock {
+        let clock_account = self.test_state.banks_client
+            .to_owned()
+            .get_account(sysvar::clock::id())
+            .await
+            .unwrap()
+            .unwrap();
+        from_account::<Clock>(&clock_account).unwrap()
+    }
+
+    /// Warps the bank `slots` slots ahead. The clock timestamp moves forward by the default slot
+    /// duration for each slot.
+    pub async fn advance_clock(&mut self, slots: u64) {
+        let slot = self.clock().await.slot;
+        self.test_state.warp_to_slot(slot + slots).unwrap();
+        self.refresh_blockhash().await;
+    }
 }
 
 
@@ -185,17 +241,15 @@ pub async fn print_pool_data(
     pool_key: &Pubkey,
     banks_client: &BanksClient,
 ) -> Result<(), ProgramError> {
-    let data = banks_client
+    let mut data = banks_client
         .to_owned()
         .get_account(*pool_key)
         .await
         .unwrap()
         .unwrap()
         .data;
-    let pool_header = PoolHeader::unpack(&data[..PoolHeader::LEN]).unwrap();
-    let pool_asset_offset = PoolHeader::LEN + 32 * (pool_header.number_of_markets as usize);
-    let pool_assets = unpack_assets(&data[pool_asset_offset..])?;
-    for asset in pool_assets {
+    let pool_state = PoolState::new(&mut data)?;
+    for (_, asset) in pool_state.assets() {
         print!("{:?}", asset);
         let pool_asset_key = get_associated_token_address(&pool_key, &asset.mint_address);
         let asset_data = banks_client
@@ -237,6 +291,41 @@ pub async fn wrap_process_transaction(
         .await
 }
 
+/// Serialized size of a transaction with the given instructions, to compare with
+/// `PACKET_DATA_SIZE`.
+pub fn transaction_size(ctx: &Context, instructions: &[Instruction], number_of_signers: usize) -> usize {
+    let message = Message::new(instructions, Some(&ctx.test_state.payer.pubkey()));
+    // The signatures are prefixed with their compact encoded count, a single byte here
+    message.serialize().len() + 1 + 64 * number_of_signers
+}
+
+/// Simulates a transaction to measure the compute units it consumes, then processes it.
+pub async fn process_transaction_with_compute_units(
+    ctx: &Context,
+    instructions: Vec<Instruction>,
+    mut signers: Vec<&Keypair>,
+) -> Result<u64, TransportError> {
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&ctx.test_state.payer.pubkey()));
+    signers.push(&ctx.test_state.payer);
+    transaction.partial_sign(&signers, ctx.test_state.last_blockhash);
+    let simulation = ctx.test_state.banks_client
+        .to_owned()
+        .simulate_transaction(transaction.clone())
+        .await?;
+    if let Some(Err(e)) = simulation.result {
+        return Err(e.into());
+    }
+    let units_consumed = simulation
+        .simulation_details
+        .map(|d| d.units_consumed)
+        .unwrap_or(0);
+    ctx.test_state.banks_client
+        .to_owned()
+        .process_transaction(transaction)
+        .await?;
+    Ok(units_consumed)
+}
+
 pub fn add_token_account(
     program_test: &mut ProgramTest,
     account_address: Pubkey,
@@ -308,6 +397,17 @@ pub fn arbitraryNonZeroU8(u: &mut Unstructured<'_>) -> arbitrary::Result<NonZero
     Ok(NonZeroU8::new(u.arbitrary()?).unwrap_or(NonZeroU8::new(1).unwrap()))
 }
 
+/// Returns the program error a transaction failed with, if it is a bonfida-bot error.
+pub fn bonfida_bot_error(result: Result<(), TransportError>) -> Option<BonfidaBotError> {
+    match result {
+        Err(TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => BonfidaBotError::decode(code),
+        _ => None,
+    }
+}
+
 pub fn result_err_filter(e: Result<(), TransportError>) -> Result<(), TransportError>{
     if let Err(TransportError::TransactionError(te)) = &e {
         match te {
@@ -319,12 +419,15 @@ pub fn result_err_filter(e: Result<(), TransportError>) -> Result<(), TransportE
                     | InstructionError::InsufficientFunds
                     | InstructionError::AccountAlreadyInitialized
                     | InstructionError::InvalidSeeds
-                    | InstructionError::Custom(2)
-                    | InstructionError::Custom(3)
+                    | InstructionError::MissingRequiredSignature
                     | InstructionError::Custom(0x10000e7) // Serum invalid openorder account owner error
                     | InstructionError::Custom(0x1000683) // Serum invalid pc payer account
-                    | InstructionError::Custom(0x1000684) // Serum invalid coin payer account
-                    | InstructionError::Custom(4) => {Ok(())},
+                    | InstructionError::Custom(0x1000684) => {Ok(())}, // Serum invalid coin payer account
+                    // Any program error other than an invalid instruction or an overflow is expected
+                    InstructionError::Custom(code) if !matches!(
+                        BonfidaBotError::decode(*code),
+                        None | Some(BonfidaBotError::InvalidInstruction) | Some(BonfidaBotError::Overflow)
+                    ) => {Ok(())},
                     _ => {
                         print!("{:?}", ie);
                         e
@@ -1,4 +0,0 @@
-pub mod market;
-pub mod utils;
-pub mod pool;
-pub mod simulation;
\ No newline at end of file
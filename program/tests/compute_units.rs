@@ -0,0 +1,435 @@
+#![cfg(feature = "test-bpf")]
+//! Compute units consumed by each pool instruction across asset and market counts.
+//!
+//! Measurements are compared to the baseline in `tests/compute_units.json` and the test fails when
+//! an instruction consumes more than `THRESHOLD_PERCENT` above it. Missing entries are recorded,
+//! run with `UPDATE_COMPUTE_BASELINE=1` to record all of them again. The orders instructions are
+//! not measured as long as orders cannot be created.
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use bonfida_bot::{
+    instruction::{
+        claim_fees, collect_fees, create, deposit, init, post_bond, process_deposit_queue,
+        process_redeem_queue, redeem, request_bond_withdrawal, request_deposit, request_redeem,
+    },
+    state::MIN_BOND_AMOUNT,
+    utils::{find_bond_key, find_deposit_request_key, find_redeem_request_key},
+};
+use bonfida_bot_test_utils::{
+    builder::token_balance,
+    pool::TestPool,
+    utils::{
+        process_transaction_with_compute_units, transaction_size, wrap_process_transaction, Context,
+    },
+};
+use solana_program::{
+    instruction::Instruction, pubkey::Pubkey, system_instruction, system_program, sysvar,
+};
+use solana_sdk::{
+    packet::PACKET_DATA_SIZE,
+    signature::{Keypair, Signer},
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::instruction::mint_to;
+
+const MAX_NUMBER_OF_ASSETS: usize = 16;
+const MARKET_COUNTS: &[u16] = &[1, 8, 32];
+const THRESHOLD_PERCENT: u64 = 5;
+const MAX_COMPUTE_UNITS: u64 = 200_000;
+
+#[derive(Debug)]
+enum Outcome {
+    Units(u64),
+    TooLarge(usize),
+    Failed(String),
+}
+
+fn baseline_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compute_units.json")
+}
+
+async fn measure(ctx: &Context, instructions: Vec<Instruction>, signers: Vec<&Keypair>) -> Outcome {
+    let size = transaction_size(ctx, &instructions, signers.len() + 1);
+    if size > PACKET_DATA_SIZE {
+        return Outcome::TooLarge(size);
+    }
+    match process_transaction_with_compute_units(ctx, instructions, signers).await {
+        Ok(units) => Outcome::Units(units),
+        Err(e) => Outcome::Failed(format!("{:?}", e)),
+    }
+}
+
+/// Measures every instruction on a new pool, stopping at the first one which cannot be executed.
+async fn measure_pool(
+    number_of_assets: usize,
+    number_of_markets: u16,
+) -> Vec<(&'static str, Outcome)> {
+    let mut ctx = Context::init().await;
+    let mut mints = ctx.get_mints();
+    mints.truncate(number_of_assets);
+    while mints.len() < number_of_assets {
+        mints.push(ctx.create_mint(6).await.unwrap());
+    }
+    let mut pool = TestPool::new(&ctx);
+    for mint_info in &mints {
+        pool.add_mint(None, mint_info);
+    }
+    let pool_asset_keys = pool
+        .mints
+        .iter()
+        .map(|m| m.pool_asset_key)
+        .collect::<Vec<_>>();
+    let program_id = ctx.bonfidabot_program_id;
+    let mut outcomes = vec![];
+
+    let init_instruction = init(
+        &spl_token::id(),
+        &system_program::id(),
+        &sysvar::rent::id(),
+        &program_id,
+        &pool.mint_key,
+        &ctx.test_state.payer.pubkey(),
+        &pool.key,
+        pool.seeds,
+        number_of_assets as u32,
+        number_of_markets,
+    )
+    .unwrap();
+    outcomes.push(("init", measure(&ctx, vec![init_instruction], vec![]).await));
+    if !matches!(outcomes.last(), Some((_, Outcome::Units(_)))) {
+        return outcomes;
+    }
+    for m in &pool.mints {
+        let instruction =
+            create_associated_token_account(&ctx.test_state.payer.pubkey(), &pool.key, &m.key);
+        wrap_process_transaction(&ctx, vec![instruction], vec![])
+            .await
+            .unwrap();
+    }
+    let signal_provider_pool_token_key = pool
+        .get_pt_account(&ctx, &pool.signal_provider.pubkey())
+        .await;
+
+    let creator = pool.fund_actor(&ctx, Keypair::new()).await;
+    let creator_pool_token_key = creator.pool_token_account.unwrap();
+    let create_instruction = create(
+        &spl_token::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.mint_key,
+        &pool.key,
+        pool.seeds,
+        &pool_asset_keys,
+        &creator_pool_token_key,
+        &creator.key.pubkey(),
+        &creator.asset_accounts,
+        &ctx.serum_program_id,
+        &pool.signal_provider.pubkey(),
+        1311,
+        100,
+        0,
+        vec![1_000_000; number_of_assets],
+        (0..number_of_markets)
+            .map(|_| Pubkey::new_unique())
+            .collect(),
+    )
+    .unwrap();
+    outcomes.push((
+        "create",
+        measure(&ctx, vec![create_instruction], vec![&creator.key]).await,
+    ));
+    if !matches!(outcomes.last(), Some((_, Outcome::Units(_)))) {
+        return outcomes;
+    }
+
+    let depositor = pool.fund_actor(&ctx, Keypair::new()).await;
+    let depositor_pool_token_key = depositor.pool_token_account.unwrap();
+    let deposit_instruction = deposit(
+        &spl_token::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.mint_key,
+        &pool.key,
+        &pool_asset_keys,
+        &depositor_pool_token_key,
+        &depositor.key.pubkey(),
+        &depositor.asset_accounts,
+        pool.seeds,
+        500_000,
+    )
+    .unwrap();
+    outcomes.push((
+        "deposit",
+        measure(&ctx, vec![deposit_instruction], vec![&depositor.key]).await,
+    ));
+
+    // Let enough management fees accrue for a pool token to be collected
+    ctx.advance_clock(10_000).await;
+    let collect_fees_instruction = collect_fees(
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.key,
+        &pool.mint_key,
+        pool.seeds,
+    )
+    .unwrap();
+    outcomes.push((
+        "collect_fees",
+        measure(&ctx, vec![collect_fees_instruction], vec![]).await,
+    ));
+
+    let claim_fees_instruction = claim_fees(
+        &spl_token::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.key,
+        &pool.mint_key,
+        &signal_provider_pool_token_key,
+        pool.seeds,
+    )
+    .unwrap();
+    outcomes.push((
+        "claim_fees",
+        measure(&ctx, vec![claim_fees_instruction], vec![]).await,
+    ));
+
+    let (redeem_request_key, _) = find_redeem_request_key(&program_id, &pool.seeds, 0);
+    let request_redeem_instruction = request_redeem(
+        &spl_token::id(),
+        &system_program::id(),
+        &sysvar::rent::id(),
+        &program_id,
+        &pool.mint_key,
+        &pool.key,
+        &redeem_request_key,
+        &depositor.key.pubkey(),
+        &depositor_pool_token_key,
+        pool.seeds,
+        100_000,
+    )
+    .unwrap();
+    outcomes.push((
+        "request_redeem",
+        measure(&ctx, vec![request_redeem_instruction], vec![&depositor.key]).await,
+    ));
+
+    let process_redeem_queue_instruction = process_redeem_queue(
+        &spl_token::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.mint_key,
+        &pool.key,
+        &pool.mints.iter().map(|m| m.key).collect(),
+        &vec![(redeem_request_key, depositor.key.pubkey())],
+        pool.seeds,
+    )
+    .unwrap();
+    outcomes.push((
+        "process_redeem_queue",
+        measure(&ctx, vec![process_redeem_queue_instruction], vec![]).await,
+    ));
+
+    let (deposit_request_key, _) = find_deposit_request_key(&program_id, &pool.seeds, 0);
+    let expiry_timestamp = ctx.clock().await.unix_timestamp as u64 + 3600;
+    let request_deposit_instruction = request_deposit(
+        &spl_token::id(),
+        &system_program::id(),
+        &sysvar::rent::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.mint_key,
+        &pool.key,
+        &deposit_request_key,
+        &depositor.key.pubkey(),
+        &pool.mints.iter().map(|m| m.key).collect(),
+        &depositor.asset_accounts,
+        pool.seeds,
+        1_000,
+        expiry_timestamp,
+        vec![1 << 20; number_of_assets],
+    )
+    .unwrap();
+    outcomes.push((
+        "request_deposit",
+        measure(
+            &ctx,
+            vec![request_deposit_instruction],
+            vec![&depositor.key],
+        )
+        .await,
+    ));
+
+    let process_deposit_queue_instruction = process_deposit_queue(
+        &spl_token::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.mint_key,
+        &pool.key,
+        &pool.mints.iter().map(|m| m.key).collect(),
+        &vec![(
+            deposit_request_key,
+            depositor.key.pubkey(),
+            pool.mints.iter().map(|m| m.key).collect(),
+        )],
+        pool.seeds,
+    )
+    .unwrap();
+    outcomes.push((
+        "process_deposit_queue",
+        measure(&ctx, vec![process_deposit_queue_instruction], vec![]).await,
+    ));
+
+    let (bond_key, _) = find_bond_key(&program_id, &pool.seeds);
+    let signal_provider_fida_key =
+        get_associated_token_address(&pool.signal_provider.pubkey(), &ctx.fida_mint.0);
+    let instructions = vec![
+        create_associated_token_account(
+            &ctx.test_state.payer.pubkey(),
+            &pool.signal_provider.pubkey(),
+            &ctx.fida_mint.0,
+        ),
+        mint_to(
+            &spl_token::id(),
+            &ctx.fida_mint.0,
+            &signal_provider_fida_key,
+            &ctx.mint_authority.pubkey(),
+            &[],
+            MIN_BOND_AMOUNT,
+        )
+        .unwrap(),
+        system_instruction::transfer(
+            &ctx.test_state.payer.pubkey(),
+            &pool.signal_provider.pubkey(),
+            1 << 30,
+        ),
+    ];
+    wrap_process_transaction(&ctx, instructions, vec![&ctx.mint_authority])
+        .await
+        .unwrap();
+    let post_bond_instruction = post_bond(
+        &spl_token::id(),
+        &system_program::id(),
+        &sysvar::rent::id(),
+        &program_id,
+        &pool.key,
+        &ctx.fida_mint.0,
+        &bond_key,
+        &pool.signal_provider.pubkey(),
+        &signal_provider_fida_key,
+        pool.seeds,
+        MIN_BOND_AMOUNT,
+    )
+    .unwrap();
+    outcomes.push((
+        "post_bond",
+        measure(
+            &ctx,
+            vec![post_bond_instruction],
+            vec![&pool.signal_provider],
+        )
+        .await,
+    ));
+
+    let request_bond_withdrawal_instruction = request_bond_withdrawal(
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.key,
+        &pool.signal_provider.pubkey(),
+        pool.seeds,
+    )
+    .unwrap();
+    outcomes.push((
+        "request_bond_withdrawal",
+        measure(
+            &ctx,
+            vec![request_bond_withdrawal_instruction],
+            vec![&pool.signal_provider],
+        )
+        .await,
+    ));
+
+    let redeem_instruction = redeem(
+        &spl_token::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &pool.mint_key,
+        &pool.key,
+        &pool_asset_keys,
+        &creator.key.pubkey(),
+        &creator_pool_token_key,
+        &creator.asset_accounts,
+        pool.seeds,
+        token_balance(&ctx, &creator_pool_token_key).await,
+    )
+    .unwrap();
+    outcomes.push((
+        "redeem",
+        measure(&ctx, vec![redeem_instruction], vec![&creator.key]).await,
+    ));
+    outcomes
+}
+
+#[tokio::test]
+async fn test_compute_units() {
+    let update = std::env::var("UPDATE_COMPUTE_BASELINE").is_ok();
+    let mut baseline: BTreeMap<String, u64> = fs::read_to_string(baseline_path())
+        .map(|data| serde_json::from_str(&data).unwrap())
+        .unwrap_or_default();
+    let mut regressions = vec![];
+    // Largest asset count executed within the compute budget, per instruction
+    let mut max_asset_counts: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for &number_of_markets in MARKET_COUNTS {
+        for number_of_assets in 1..=MAX_NUMBER_OF_ASSETS {
+            for (instruction, outcome) in measure_pool(number_of_assets, number_of_markets).await {
+                let key = format!(
+                    "{}/assets={}/markets={}",
+                    instruction, number_of_assets, number_of_markets
+                );
+                println!("{} : {:?}", key, outcome);
+                let units = match outcome {
+                    Outcome::Units(units) => units,
+                    _ => {
+                        if baseline.contains_key(&key) && !update {
+                            regressions.push(format!("{} no longer executes : {:?}", key, outcome));
+                        }
+                        continue;
+                    }
+                };
+                if units <= MAX_COMPUTE_UNITS && number_of_markets == MARKET_COUNTS[0] {
+                    let max = max_asset_counts.entry(instruction).or_insert(0);
+                    *max = (*max).max(number_of_assets);
+                }
+                match baseline.get(&key) {
+                    Some(&expected) if !update => {
+                        if units * 100 > expected * (100 + THRESHOLD_PERCENT) {
+                            regressions.push(format!(
+                                "{} consumes {} compute units, {} in the baseline",
+                                key, units, expected
+                            ));
+                        } else if units * 100 < expected * (100 - THRESHOLD_PERCENT) {
+                            println!(
+                                "{} improved from {} to {} compute units, consider updating the baseline",
+                                key, expected, units
+                            );
+                        }
+                    }
+                    _ => {
+                        baseline.insert(key, units);
+                    }
+                }
+            }
+        }
+    }
+
+    println!("Maximum asset count executed in a single transaction :");
+    for (instruction, max) in &max_asset_counts {
+        println!("    {} : {}", instruction, max);
+    }
+    fs::write(
+        baseline_path(),
+        serde_json::to_string_pretty(&baseline).unwrap() + "\n",
+    )
+    .unwrap();
+    assert!(regressions.is_empty(), "{}", regressions.join("\n"));
+}
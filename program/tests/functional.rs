@@ -1,29 +1,44 @@
 #![cfg(feature = "test-bpf")]
-use serum_dex::{instruction::SelfTradeBehavior, matching::Side};
-use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use serum_dex::{
+    instruction::SelfTradeBehavior,
+    matching::{OrderType as SerumOrderType, Side},
+};
+use solana_program::{program_pack::Pack, pubkey::Pubkey, system_instruction, sysvar};
+use solana_program_test::ProgramTest;
 
-use solana_sdk::{signature::Keypair, signature::Signer};
+use solana_sdk::{account::Account, signature::Keypair, signature::Signer};
+use spl_associated_token_account::get_associated_token_address;
 
-use spl_token;
+use spl_token::{self, instruction::mint_to, state::Mint};
 use std::{
     convert::TryInto,
-    num::{NonZeroU16, NonZeroU64},
+    num::{NonZeroU16, NonZeroU64, NonZeroU8},
     str::FromStr,
 };
 
-mod common;
-
-use common::{
+use bonfida_bot::{
+    error::BonfidaBotError,
+    instruction::{process_deposit_queue, redeem, remove_trader, with_multisig_signers, OrderType},
+    state::{
+        DepositRequest, PoolAsset, PoolHeader, PoolState, PoolStatus, TradeLimits, Trader,
+        BOND_NOTICE_PERIOD, BONFIDA_BNB, BONFIDA_FEE, LEGACY_HEADER_LEN,
+        MAX_PENDING_ORDER_DURATION, MIN_BOND_AMOUNT, PUBKEY_LENGTH, TRADING_DAY_DURATION,
+    },
+    utils::{find_deposit_request_key, find_trader_key},
+};
+use bonfida_bot_test_utils::{
+    builder::{token_balance, PoolBuilder},
     simulation::Actor,
     utils::{
-        clone_keypair,
-        print_pool_data, Context, OpenOrderView,
+        add_token_account, bonfida_bot_error, clone_keypair,
+        create_and_get_associated_token_address, print_pool_data, wrap_process_transaction,
+        Context, OpenOrderView,
     },
 };
 
-use common::pool::TestPool;
+use bonfida_bot_test_utils::pool::TestPool;
 
-use common::market::SerumMarket;
+use bonfida_bot_test_utils::market::SerumMarket;
 
 const SRM_MINT_KEY: &str = "SRMuApVNdxXokk5GT7XD5cUUgXMBCoAz2LHeuAoKWRt";
 
@@ -97,8 +112,10 @@ async fn test_bonfida_bot() {
         &source_actor.asset_accounts,
         deposit_amounts,
         &serum_market.market_key.pubkey(),
-        604800,
-        100
+        1311,
+        100,
+        0,
+        TradeLimits::default(),
     )
     .await
     .unwrap();
@@ -235,3 +252,1878 @@ async fn test_bonfida_bot() {
         .await
         .unwrap();
 }
+
+#[tokio::test]
+async fn test_pool_builder() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new()
+        .mints(5)
+        .fee_ratio(0)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    assert_eq!(env.pool.mints.len(), 5);
+    assert!(env.creator.pool_token_balance > 0);
+
+    let investor = env.pool.fund_actor(&ctx, Keypair::new()).await;
+    let investor_pool_token_account = investor.pool_token_account.unwrap();
+    env.pool
+        .deposit(
+            &ctx,
+            env.creator.pool_token_balance,
+            &investor_pool_token_account,
+            &investor.key,
+            &investor.asset_accounts,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        token_balance(&ctx, &investor_pool_token_account).await,
+        env.creator.pool_token_balance
+    );
+
+    let timestamp = ctx.clock().await.unix_timestamp;
+    ctx.advance_clock(10_000).await;
+    assert!(ctx.clock().await.unix_timestamp > timestamp);
+
+    env.pool
+        .redeem(
+            &ctx,
+            env.creator.pool_token_balance,
+            &investor.key,
+            &investor_pool_token_account,
+            &investor.asset_accounts,
+        )
+        .await
+        .unwrap();
+    assert_eq!(token_balance(&ctx, &investor_pool_token_account).await, 0);
+}
+
+#[tokio::test]
+async fn test_pool_builder_orders() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new().mints(2).build(&mut ctx).await.unwrap();
+    let coin_key = env.pool.mints[1].pool_asset_key;
+    let pc_key = env.pool.mints[0].pool_asset_key;
+    let price = NonZeroU64::new(100).unwrap();
+
+    let maker = env
+        .market
+        .place_maker_order(
+            &ctx,
+            Side::Ask,
+            price,
+            NonZeroU64::new(1000).unwrap(),
+            &ctx.mint_authority,
+        )
+        .await
+        .unwrap();
+    // A sixteenth of the pool price currency buys 625 lots
+    let max_qty = NonZeroU16::new(1 << 12).unwrap();
+    let order = env
+        .place_order(&mut ctx, Side::Bid, price, max_qty)
+        .await
+        .unwrap();
+    assert_eq!(token_balance(&ctx, &pc_key).await, 1_000_000 - 62_500);
+    env.consume_events(&ctx, &order, &maker).await;
+    let openorder_view =
+        OpenOrderView::get(order.open_orders_account, &ctx.test_state.banks_client)
+            .await
+            .unwrap();
+    assert_eq!(openorder_view.native_coin_free, 625_000);
+    assert_eq!(openorder_view.native_pc_total, 0);
+
+    env.settle(&ctx, &order).await.unwrap();
+    assert_eq!(token_balance(&ctx, &coin_key).await, 1_000_000 + 625_000);
+    assert_eq!(env.pool.header(&ctx).await.status, PoolStatus::Unlocked);
+}
+
+#[tokio::test]
+async fn test_claim_fees() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new().build(&mut ctx).await.unwrap();
+    let fee_recipients = [
+        env.pool
+            .get_pt_account(&ctx, &env.pool.signal_provider.pubkey())
+            .await,
+        get_associated_token_address(&Pubkey::from_str(BONFIDA_FEE).unwrap(), &env.pool.mint_key),
+        get_associated_token_address(&Pubkey::from_str(BONFIDA_BNB).unwrap(), &env.pool.mint_key),
+    ];
+
+    let investor = env.pool.fund_actor(&ctx, Keypair::new()).await;
+    env.pool
+        .deposit(
+            &ctx,
+            1_000_003,
+            investor.pool_token_account.as_ref().unwrap(),
+            &investor.key,
+            &investor.asset_accounts,
+        )
+        .await
+        .unwrap();
+    // The fees are only accounted for in the pool until they are claimed
+    for recipient in &fee_recipients {
+        assert_eq!(token_balance(&ctx, recipient).await, 0);
+    }
+    // Only the fee recipients can claim
+    assert!(env
+        .pool
+        .claim_fees(&ctx, investor.pool_token_account.as_ref().unwrap())
+        .await
+        .is_err());
+
+    ctx.advance_clock(10_000).await;
+    for recipient in &fee_recipients {
+        env.pool.claim_fees(&ctx, recipient).await.unwrap();
+    }
+    let mut pool_data = ctx
+        .test_state
+        .banks_client
+        .get_account(env.pool.key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    let header = PoolState::new(&mut pool_data).unwrap().header().unwrap();
+    let mut claimed = [0; 3];
+    for (i, recipient) in fee_recipients.iter().enumerate() {
+        claimed[i] = token_balance(&ctx, recipient).await;
+    }
+    assert_eq!(header.claimed_fees, claimed);
+    // Nothing is lost to the rounding of the split
+    assert_eq!(claimed.iter().sum::<u64>(), header.accrued_fees);
+    assert!(claimed[0] >= claimed[1] && claimed[1] >= claimed[2] && claimed[2] > 0);
+    assert!(env.pool.claim_fees(&ctx, &fee_recipients[2]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_redeem_queue() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new()
+        .mints(2)
+        .fee_ratio(0)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let investor = env.pool.fund_actor(&ctx, Keypair::new()).await;
+    let investor_pool_token_account = investor.pool_token_account.unwrap();
+    env.pool
+        .deposit(
+            &ctx,
+            2_000_000,
+            &investor_pool_token_account,
+            &investor.key,
+            &investor.asset_accounts,
+        )
+        .await
+        .unwrap();
+
+    let mut requests = vec![];
+    for amount in &[500_000, 1_500_000] {
+        let request = env
+            .pool
+            .request_redeem(&ctx, *amount, &investor.key, &investor_pool_token_account)
+            .await
+            .unwrap();
+        requests.push((request, investor.key.pubkey()));
+    }
+    // The pool tokens are escrowed until the requests are paid out
+    assert_eq!(token_balance(&ctx, &investor_pool_token_account).await, 0);
+    assert_eq!(
+        token_balance(
+            &ctx,
+            &get_associated_token_address(&requests[1].0, &env.pool.mint_key)
+        )
+        .await,
+        1_500_000
+    );
+
+    let mut asset_balances = vec![];
+    for account in &investor.asset_accounts {
+        asset_balances.push(token_balance(&ctx, account).await);
+    }
+    // The requests are paid out in the order of the queue
+    assert!(env
+        .pool
+        .process_redeem_queue(&ctx, &requests[1..].to_vec())
+        .await
+        .is_err());
+    env.pool
+        .process_redeem_queue(&ctx, &requests)
+        .await
+        .unwrap();
+    for (account, before) in investor.asset_accounts.iter().zip(asset_balances) {
+        assert!(token_balance(&ctx, account).await > before);
+    }
+    // The request accounts are closed
+    for (request, _) in &requests {
+        assert!(ctx
+            .test_state
+            .banks_client
+            .get_account(*request)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    assert!(env
+        .pool
+        .process_redeem_queue(&ctx, &requests[..1].to_vec())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_redeem_unsettled_funds() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new()
+        .mints(2)
+        .fee_ratio(0)
+        .annual_fee_ratio(0)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let creator_pool_token_account = env.creator.pool_token_account.unwrap();
+    let price = NonZeroU64::new(100).unwrap();
+    let max_qty = NonZeroU16::new(1 << 12).unwrap();
+
+    // The pool buys 625 lots, which are left unsettled in its OpenOrders account
+    let maker = env
+        .market
+        .place_maker_order(
+            &ctx,
+            Side::Ask,
+            price,
+            NonZeroU64::new(1000).unwrap(),
+            &ctx.mint_authority,
+        )
+        .await
+        .unwrap();
+    let order = env
+        .place_order(&mut ctx, Side::Bid, price, max_qty)
+        .await
+        .unwrap();
+    env.consume_events(&ctx, &order, &maker).await;
+    let open_orders = [(env.market.market_key.pubkey(), order.open_orders_account)];
+
+    let amount = env.creator.pool_token_balance / 10;
+    let result = env
+        .pool
+        .redeem(
+            &ctx,
+            amount,
+            &env.creator.key,
+            &creator_pool_token_account,
+            &env.creator.asset_accounts,
+        )
+        .await;
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::PendingOrders)
+    );
+
+    // The unsettled funds are part of the payout
+    let balances_before = [
+        token_balance(&ctx, &env.creator.asset_accounts[0]).await,
+        token_balance(&ctx, &env.creator.asset_accounts[1]).await,
+    ];
+    env.pool
+        .redeem_with_open_orders(
+            &ctx,
+            amount,
+            &env.creator.key,
+            &creator_pool_token_account,
+            &env.creator.asset_accounts,
+            &open_orders,
+        )
+        .await
+        .unwrap();
+    for (i, pool_value) in [1_000_000 - 62_500, 1_000_000 + 625_000].iter().enumerate() {
+        let expected =
+            (amount as u128 * pool_value / env.creator.pool_token_balance as u128) as u64;
+        assert_eq!(
+            token_balance(&ctx, &env.creator.asset_accounts[i]).await,
+            balances_before[i] + expected
+        );
+    }
+
+    // Orders left on the book keep their funds out of reach
+    let resting_order = env
+        .place_order_with(
+            &mut ctx,
+            Side::Bid,
+            NonZeroU64::new(90).unwrap(),
+            max_qty,
+            SerumOrderType::Limit,
+            SelfTradeBehavior::DecrementTake,
+            None,
+        )
+        .await
+        .unwrap();
+    let result = env
+        .pool
+        .redeem_with_open_orders(
+            &ctx,
+            amount,
+            &env.creator.key,
+            &creator_pool_token_account,
+            &env.creator.asset_accounts,
+            &[
+                open_orders[0],
+                (
+                    env.market.market_key.pubkey(),
+                    resting_order.open_orders_account,
+                ),
+            ],
+        )
+        .await;
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::PendingOrders)
+    );
+}
+
+/// Returns a seed from which both the pool and the pool mint addresses can be derived.
+fn find_pool_seed(program_id: &Pubkey) -> [u8; 32] {
+    (0u8..)
+        .map(|i| [i; 32])
+        .find(|seed| {
+            Pubkey::create_program_address(&[seed], program_id).is_ok()
+                && Pubkey::create_program_address(&[seed, &[1]], program_id).is_ok()
+        })
+        .unwrap()
+}
+
+/// Adds the pool account with the given data, along with the pool mint, the pool asset accounts
+/// which hold `amount` of each of `mints` and the accounts of `owner`, who holds the `amount`
+/// pooltokens of the supply.
+fn add_pool_accounts(
+    program_test: &mut ProgramTest,
+    program_id: &Pubkey,
+    pool_seed: &[u8; 32],
+    data: Vec<u8>,
+    mints: &[Pubkey],
+    owner: &Pubkey,
+    amount: u64,
+) {
+    let pool_key = Pubkey::create_program_address(&[pool_seed], program_id).unwrap();
+    let mint_key = Pubkey::create_program_address(&[pool_seed, &[1]], program_id).unwrap();
+    program_test.add_account(
+        pool_key,
+        Account {
+            lamports: u32::MAX.into(),
+            data,
+            owner: *program_id,
+            ..Account::default()
+        },
+    );
+
+    let mut mint_data = [0u8; Mint::LEN];
+    Mint {
+        mint_authority: Some(pool_key).into(),
+        supply: amount,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: None.into(),
+    }
+    .pack_into_slice(&mut mint_data);
+    program_test.add_account(
+        mint_key,
+        Account {
+            lamports: u32::MAX.into(),
+            data: mint_data.into(),
+            owner: spl_token::id(),
+            ..Account::default()
+        },
+    );
+    add_token_account(
+        program_test,
+        get_associated_token_address(owner, &mint_key),
+        *owner,
+        mint_key,
+        amount,
+    );
+    for mint in mints {
+        add_token_account(
+            program_test,
+            get_associated_token_address(&pool_key, mint),
+            pool_key,
+            *mint,
+            amount,
+        );
+        add_token_account(
+            program_test,
+            get_associated_token_address(owner, mint),
+            *owner,
+            *mint,
+            0,
+        );
+    }
+}
+
+/// Adds an unlocked pool laid out by the first version of the program, which holds `amount` of
+/// each asset and whose `amount` pooltokens are held by `owner`. Returns the pool seed.
+fn add_legacy_pool(
+    program_test: &mut ProgramTest,
+    program_id: &Pubkey,
+    mints: &[Pubkey],
+    owner: &Pubkey,
+    amount: u64,
+) -> [u8; 32] {
+    let pool_seed = find_pool_seed(program_id);
+    let mut data = vec![0u8; LEGACY_HEADER_LEN + PUBKEY_LENGTH + PoolAsset::LEN * mints.len()];
+    data[32..64].copy_from_slice(&pool_seed);
+    data[64..96].copy_from_slice(&owner.to_bytes());
+    data[96] = 0x3f;
+    data[97..99].copy_from_slice(&1u16.to_le_bytes());
+    // Fee ratio and fee collection period of the first version
+    data[99..101].copy_from_slice(&15u16.to_le_bytes());
+    data[109..117].copy_from_slice(&604_800u64.to_le_bytes());
+    data[LEGACY_HEADER_LEN..LEGACY_HEADER_LEN + PUBKEY_LENGTH]
+        .copy_from_slice(&Pubkey::new_unique().to_bytes());
+    for (i, mint) in mints.iter().enumerate() {
+        let offset = LEGACY_HEADER_LEN + PUBKEY_LENGTH + i * PoolAsset::LEN;
+        data[offset..offset + PUBKEY_LENGTH].copy_from_slice(&mint.to_bytes());
+    }
+    add_pool_accounts(
+        program_test,
+        program_id,
+        &pool_seed,
+        data,
+        mints,
+        owner,
+        amount,
+    );
+    pool_seed
+}
+
+#[tokio::test]
+async fn test_redeem_legacy_pool() {
+    let owner = Keypair::new();
+    let mut pool_seed = [0u8; 32];
+    let mut mints = vec![];
+    let ctx = Context::init_with_accounts(|program_test, program_id, mint_infos| {
+        mints = mint_infos[..2].iter().map(|(key, _)| *key).collect();
+        pool_seed = add_legacy_pool(program_test, program_id, &mints, &owner.pubkey(), 1_000_000);
+    })
+    .await;
+    let program_id = ctx.bonfidabot_program_id;
+    let pool_key = Pubkey::create_program_address(&[&pool_seed], &program_id).unwrap();
+    let mint_key = Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+    let pool_asset_keys: Vec<Pubkey> = mints
+        .iter()
+        .map(|m| get_associated_token_address(&pool_key, m))
+        .collect();
+    let owner_asset_keys: Vec<Pubkey> = mints
+        .iter()
+        .map(|m| get_associated_token_address(&owner.pubkey(), m))
+        .collect();
+    let redeem_instruction = |amount| {
+        redeem(
+            &spl_token::id(),
+            &sysvar::clock::id(),
+            &program_id,
+            &mint_key,
+            &pool_key,
+            &pool_asset_keys,
+            &owner.pubkey(),
+            &get_associated_token_address(&owner.pubkey(), &mint_key),
+            &owner_asset_keys,
+            pool_seed,
+            amount,
+        )
+        .unwrap()
+    };
+
+    // The fees of the first version are not taken, the redeem pays out the pool share
+    wrap_process_transaction(&ctx, vec![redeem_instruction(250_000)], vec![&owner])
+        .await
+        .unwrap();
+    for key in &owner_asset_keys {
+        assert_eq!(token_balance(&ctx, key).await, 250_000);
+    }
+
+    // Redeeming the remaining pooltokens resets the pool and keeps the rest of the old header
+    wrap_process_transaction(&ctx, vec![redeem_instruction(750_000)], vec![&owner])
+        .await
+        .unwrap();
+    for key in &owner_asset_keys {
+        assert_eq!(token_balance(&ctx, key).await, 1_000_000);
+    }
+    let mut pool_data = ctx
+        .test_state
+        .banks_client
+        .to_owned()
+        .get_account(pool_key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    let pool_state = PoolState::new(&mut pool_data).unwrap();
+    assert!(pool_state.is_legacy());
+    assert!(pool_state.header().is_err());
+    assert_eq!(&pool_data[109..117], &604_800u64.to_le_bytes());
+}
+
+#[tokio::test]
+async fn test_deposit_queue() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new()
+        .mints(2)
+        .annual_fee_ratio(0)
+        .fee_ratio(0)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let investor = env.pool.fund_actor(&ctx, Keypair::new()).await;
+    let investor_pool_token_account = investor.pool_token_account.unwrap();
+    let supply = spl_token::state::Mint::unpack(
+        &ctx.test_state
+            .banks_client
+            .get_account(env.pool.mint_key)
+            .await
+            .unwrap()
+            .unwrap()
+            .data,
+    )
+    .unwrap()
+    .supply;
+    // The basket buying half of the supply, rounded up in favor of the pool
+    let mut basket = vec![];
+    let mut asset_balances = vec![];
+    for (mint, account) in env.pool.mints.iter().zip(&investor.asset_accounts) {
+        let pool_amount = token_balance(&ctx, &mint.pool_asset_key).await as u128;
+        basket.push(
+            ((pool_amount * (supply / 2) as u128 + supply as u128 - 1) / supply as u128) as u64,
+        );
+        asset_balances.push(token_balance(&ctx, account).await);
+    }
+
+    let timestamp = ctx.clock().await.unix_timestamp as u64;
+    assert!(env
+        .pool
+        .request_deposit(
+            &ctx,
+            supply / 2,
+            timestamp,
+            &investor.key,
+            &investor.asset_accounts,
+            basket.clone(),
+        )
+        .await
+        .is_err());
+
+    // The first basket has some slack which is refunded, the second one is short of a token
+    let mut requests = vec![];
+    for asset_amounts in &[
+        vec![basket[0] + 10, basket[1] + 10],
+        vec![basket[0] - 1, basket[1] + 10],
+    ] {
+        let request = env
+            .pool
+            .request_deposit(
+                &ctx,
+                supply / 2,
+                timestamp + 3600,
+                &investor.key,
+                &investor.asset_accounts,
+                asset_amounts.clone(),
+            )
+            .await
+            .unwrap();
+        requests.push((request, investor.key.pubkey()));
+    }
+    assert_eq!(
+        token_balance(
+            &ctx,
+            &get_associated_token_address(&requests[0].0, &env.pool.mints[0].key)
+        )
+        .await,
+        basket[0] + 10
+    );
+
+    // The requests are processed in the order of the queue
+    assert!(env
+        .pool
+        .process_deposit_queue(&ctx, &requests[1..].to_vec())
+        .await
+        .is_err());
+    env.pool
+        .process_deposit_queue(&ctx, &requests)
+        .await
+        .unwrap();
+    assert_eq!(
+        token_balance(&ctx, &investor_pool_token_account).await,
+        supply / 2
+    );
+    for i in 0..basket.len() {
+        assert_eq!(
+            token_balance(&ctx, &investor.asset_accounts[i]).await,
+            asset_balances[i] - basket[i]
+        );
+    }
+    // The request and escrow accounts are closed
+    for (request, _) in &requests {
+        let mut keys = vec![*request];
+        keys.extend(
+            env.pool
+                .mints
+                .iter()
+                .map(|m| get_associated_token_address(request, &m.key)),
+        );
+        for key in keys {
+            assert!(ctx
+                .test_state
+                .banks_client
+                .get_account(key)
+                .await
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    // Expired requests are refunded
+    let timestamp = ctx.clock().await.unix_timestamp as u64;
+    let request = env
+        .pool
+        .request_deposit(
+            &ctx,
+            supply / 2,
+            timestamp + 1,
+            &investor.key,
+            &investor.asset_accounts,
+            basket.clone(),
+        )
+        .await
+        .unwrap();
+    ctx.advance_clock(10_000).await;
+    env.pool
+        .process_deposit_queue(&ctx, &vec![(request, investor.key.pubkey())])
+        .await
+        .unwrap();
+    assert_eq!(
+        token_balance(&ctx, &investor_pool_token_account).await,
+        supply / 2
+    );
+    for i in 0..basket.len() {
+        assert_eq!(
+            token_balance(&ctx, &investor.asset_accounts[i]).await,
+            asset_balances[i] - basket[i]
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_deposit_queue_dropped_asset() {
+    // The pool dropped its second asset after the request escrowed a basket of both assets
+    let owner = Keypair::new();
+    let amount = 1_000_000;
+    let mut pool_seed = [0u8; 32];
+    let mut mints = vec![];
+    let ctx = Context::init_with_accounts(|program_test, program_id, mint_infos| {
+        mints = mint_infos[..2].iter().map(|(key, _)| *key).collect();
+        pool_seed = find_pool_seed(program_id);
+        let header = PoolHeader {
+            seed: pool_seed,
+            signal_provider: owner.pubkey(),
+            status: PoolStatus::Unlocked,
+            number_of_markets: 1,
+            deposit_queue_tail: 1,
+            ..PoolHeader::default()
+        };
+        let mut data = vec![0u8; PoolHeader::LEN + PUBKEY_LENGTH + PoolAsset::LEN];
+        PoolState::new(&mut data)
+            .unwrap()
+            .init(
+                &header,
+                &[Pubkey::new_unique()],
+                &[PoolAsset {
+                    mint_address: mints[0],
+                }],
+            )
+            .unwrap();
+        add_pool_accounts(
+            program_test,
+            program_id,
+            &pool_seed,
+            data,
+            &mints[..1],
+            &owner.pubkey(),
+            amount,
+        );
+        add_token_account(
+            program_test,
+            get_associated_token_address(&owner.pubkey(), &mints[1]),
+            owner.pubkey(),
+            mints[1],
+            0,
+        );
+
+        let (request_key, nonce) = find_deposit_request_key(program_id, &pool_seed, 0);
+        let mut request_data = vec![0u8; DepositRequest::size(2)];
+        DepositRequest {
+            owner: owner.pubkey(),
+            pool_token_amount: amount / 2,
+            expiry_timestamp: u64::MAX,
+            nonce,
+            escrowed_mints: mints.clone(),
+        }
+        .pack_into_slice(&mut request_data);
+        program_test.add_account(
+            request_key,
+            Account {
+                lamports: u32::MAX.into(),
+                data: request_data,
+                owner: *program_id,
+                ..Account::default()
+            },
+        );
+        for mint in &mints {
+            add_token_account(
+                program_test,
+                get_associated_token_address(&request_key, mint),
+                request_key,
+                *mint,
+                amount / 2 + 10,
+            );
+        }
+    })
+    .await;
+    let program_id = ctx.bonfidabot_program_id;
+    let pool_key = Pubkey::create_program_address(&[&pool_seed], &program_id).unwrap();
+    let mint_key = Pubkey::create_program_address(&[&pool_seed, &[1]], &program_id).unwrap();
+    let (request_key, _) = find_deposit_request_key(&program_id, &pool_seed, 0);
+
+    let instruction = process_deposit_queue(
+        &spl_token::id(),
+        &sysvar::clock::id(),
+        &program_id,
+        &mint_key,
+        &pool_key,
+        &mints[..1].to_vec(),
+        &vec![(request_key, owner.pubkey(), mints.clone())],
+        pool_seed,
+    )
+    .unwrap();
+    wrap_process_transaction(&ctx, vec![instruction], vec![])
+        .await
+        .unwrap();
+
+    // The basket of the remaining asset is deposited and the dropped asset is refunded in full
+    assert_eq!(
+        token_balance(
+            &ctx,
+            &get_associated_token_address(&owner.pubkey(), &mint_key)
+        )
+        .await,
+        amount + amount / 2
+    );
+    assert_eq!(
+        token_balance(&ctx, &get_associated_token_address(&pool_key, &mints[0])).await,
+        amount + amount / 2
+    );
+    assert_eq!(
+        token_balance(
+            &ctx,
+            &get_associated_token_address(&owner.pubkey(), &mints[0])
+        )
+        .await,
+        10
+    );
+    assert_eq!(
+        token_balance(
+            &ctx,
+            &get_associated_token_address(&owner.pubkey(), &mints[1])
+        )
+        .await,
+        amount / 2 + 10
+    );
+    // The request and both escrow accounts are closed
+    let mut keys = vec![request_key];
+    keys.extend(
+        mints
+            .iter()
+            .map(|m| get_associated_token_address(&request_key, m)),
+    );
+    for key in keys {
+        assert!(ctx
+            .test_state
+            .banks_client
+            .to_owned()
+            .get_account(key)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    let mut pool_data = ctx
+        .test_state
+        .banks_client
+        .to_owned()
+        .get_account(pool_key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    assert_eq!(
+        PoolState::new(&mut pool_data)
+            .unwrap()
+            .header()
+            .unwrap()
+            .deposit_queue_head,
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_bond() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new().mints(2).build(&mut ctx).await.unwrap();
+    let signal_provider = env.pool.signal_provider.pubkey();
+
+    // Fund the signal provider with FIDA and lamports for the escrow account
+    let (create_instruction, sp_fida_key) = create_and_get_associated_token_address(
+        &ctx.test_state.payer.pubkey(),
+        &signal_provider,
+        &ctx.fida_mint.0,
+    );
+    let instructions = vec![
+        create_instruction,
+        mint_to(
+            &spl_token::id(),
+            &ctx.fida_mint.0,
+            &sp_fida_key,
+            &ctx.mint_authority.pubkey(),
+            &[],
+            2 * MIN_BOND_AMOUNT,
+        )
+        .unwrap(),
+        system_instruction::transfer(&ctx.test_state.payer.pubkey(), &signal_provider, 1 << 30),
+    ];
+    wrap_process_transaction(&ctx, instructions, vec![&ctx.mint_authority])
+        .await
+        .unwrap();
+
+    // The bond has to reach the minimum
+    assert!(env
+        .pool
+        .post_bond(&ctx, MIN_BOND_AMOUNT - 1, &sp_fida_key)
+        .await
+        .is_err());
+    env.pool
+        .post_bond(&ctx, MIN_BOND_AMOUNT, &sp_fida_key)
+        .await
+        .unwrap();
+    assert_eq!(
+        token_balance(&ctx, &env.pool.bond_escrow_key()).await,
+        MIN_BOND_AMOUNT
+    );
+    assert_eq!(env.pool.header(&ctx).await.bond_amount, MIN_BOND_AMOUNT);
+
+    // Nothing to slash without pending orders
+    assert!(env.pool.slash_bond(&ctx).await.is_err());
+
+    // The bond is locked until the notice period is over
+    assert!(env.pool.withdraw_bond(&ctx, &sp_fida_key).await.is_err());
+    env.pool.request_bond_withdrawal(&ctx).await.unwrap();
+    assert!(env.pool.withdraw_bond(&ctx, &sp_fida_key).await.is_err());
+    // Slots last 400ms by default
+    ctx.advance_clock(BOND_NOTICE_PERIOD * 5 / 2 + 10_000).await;
+    env.pool.withdraw_bond(&ctx, &sp_fida_key).await.unwrap();
+    assert_eq!(token_balance(&ctx, &sp_fida_key).await, 2 * MIN_BOND_AMOUNT);
+    assert_eq!(env.pool.header(&ctx).await.bond_amount, 0);
+    assert!(ctx
+        .test_state
+        .banks_client
+        .get_account(env.pool.bond_escrow_key())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(env.pool.request_bond_withdrawal(&ctx).await.is_err());
+}
+
+#[tokio::test]
+async fn test_slash_bond() {
+    let mut ctx = Context::init().await;
+    // The bond of a pool without a FIDA asset is slashed into the slot reserved when posting it
+    let env = PoolBuilder::new()
+        .mints(2)
+        .without_fida()
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let signal_provider = env.pool.signal_provider.pubkey();
+    let pool_fida_key = get_associated_token_address(&env.pool.key, &ctx.fida_mint.0);
+
+    let (create_instruction, sp_fida_key) = create_and_get_associated_token_address(
+        &ctx.test_state.payer.pubkey(),
+        &signal_provider,
+        &ctx.fida_mint.0,
+    );
+    let instructions = vec![
+        create_instruction,
+        mint_to(
+            &spl_token::id(),
+            &ctx.fida_mint.0,
+            &sp_fida_key,
+            &ctx.mint_authority.pubkey(),
+            &[],
+            MIN_BOND_AMOUNT,
+        )
+        .unwrap(),
+        system_instruction::transfer(&ctx.test_state.payer.pubkey(), &signal_provider, 1 << 30),
+    ];
+    wrap_process_transaction(&ctx, instructions, vec![&ctx.mint_authority])
+        .await
+        .unwrap();
+    env.pool
+        .post_bond(&ctx, MIN_BOND_AMOUNT, &sp_fida_key)
+        .await
+        .unwrap();
+    let mut pool_data = ctx
+        .test_state
+        .banks_client
+        .to_owned()
+        .get_account(env.pool.key)
+        .await
+        .unwrap()
+        .unwrap()
+        .data;
+    assert!(PoolState::new(&mut pool_data)
+        .unwrap()
+        .find_asset(&ctx.fida_mint.0)
+        .is_some());
+    assert_eq!(token_balance(&ctx, &pool_fida_key).await, 0);
+
+    // A resting limit order does not start the timer
+    let limit_price = NonZeroU64::new(100).unwrap();
+    let max_qty = NonZeroU16::new(1 << 10).unwrap();
+    env.place_order_with(
+        &mut ctx,
+        Side::Bid,
+        limit_price,
+        max_qty,
+        SerumOrderType::Limit,
+        SelfTradeBehavior::DecrementTake,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.pool.header(&ctx).await.pending_order_timestamp, 0);
+    ctx.advance_clock(MAX_PENDING_ORDER_DURATION * 5 / 2 + 10_000)
+        .await;
+    assert_eq!(
+        bonfida_bot_error(env.pool.slash_bond(&ctx).await),
+        Some(BonfidaBotError::NotSlashable)
+    );
+
+    // An immediate or cancel order does
+    env.place_order(&mut ctx, Side::Bid, limit_price, max_qty)
+        .await
+        .unwrap();
+    let header = env.pool.header(&ctx).await;
+    assert_ne!(header.pending_order_timestamp, 0);
+    assert_eq!(
+        header.status,
+        PoolStatus::PendingOrder(NonZeroU8::new(2).unwrap())
+    );
+    // The same slashing transaction would be rejected as a duplicate
+    ctx.refresh_blockhash().await;
+    assert_eq!(
+        bonfida_bot_error(env.pool.slash_bond(&ctx).await),
+        Some(BonfidaBotError::NotSlashable)
+    );
+
+    // Slots last 400ms by default
+    ctx.advance_clock(MAX_PENDING_ORDER_DURATION * 5 / 2 + 10_000)
+        .await;
+    env.pool.slash_bond(&ctx).await.unwrap();
+    assert_eq!(env.pool.header(&ctx).await.bond_amount, 0);
+    assert_eq!(token_balance(&ctx, &pool_fida_key).await, MIN_BOND_AMOUNT);
+    assert!(ctx
+        .test_state
+        .banks_client
+        .get_account(env.pool.bond_escrow_key())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_signal_provider_stake() {
+    let mut ctx = Context::init().await;
+    // The signal provider has to hold a quarter of the pool tokens
+    let env = PoolBuilder::new()
+        .mints(2)
+        .annual_fee_ratio(0)
+        .fee_ratio(0)
+        .min_signal_provider_ratio(1 << 14)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let signal_provider = env
+        .pool
+        .fund_actor(&ctx, clone_keypair(&env.pool.signal_provider))
+        .await;
+    let signal_provider_pool_token_account = signal_provider.pool_token_account.unwrap();
+    assert_eq!(
+        env.pool.header(&ctx).await.min_signal_provider_ratio,
+        1 << 14
+    );
+
+    // The signal provider ends up with a third of the pool
+    env.pool
+        .deposit(
+            &ctx,
+            500_000,
+            &signal_provider_pool_token_account,
+            &signal_provider.key,
+            &signal_provider.asset_accounts,
+        )
+        .await
+        .unwrap();
+
+    // Redeems cannot take the signal provider below a quarter of the pool
+    assert!(env
+        .pool
+        .redeem(
+            &ctx,
+            200_000,
+            &signal_provider.key,
+            &signal_provider_pool_token_account,
+            &signal_provider.asset_accounts,
+        )
+        .await
+        .is_err());
+    env.pool
+        .redeem(
+            &ctx,
+            100_000,
+            &signal_provider.key,
+            &signal_provider_pool_token_account,
+            &signal_provider.asset_accounts,
+        )
+        .await
+        .unwrap();
+    assert!(env
+        .pool
+        .request_redeem(
+            &ctx,
+            100_000,
+            &signal_provider.key,
+            &signal_provider_pool_token_account,
+        )
+        .await
+        .is_err());
+    assert_eq!(
+        token_balance(&ctx, &signal_provider_pool_token_account).await,
+        400_000
+    );
+
+    // The other holders are not restricted, and the signal provider can leave an empty pool
+    env.pool
+        .redeem(
+            &ctx,
+            env.creator.pool_token_balance,
+            &env.creator.key,
+            env.creator.pool_token_account.as_ref().unwrap(),
+            &env.creator.asset_accounts,
+        )
+        .await
+        .unwrap();
+    env.pool
+        .redeem(
+            &ctx,
+            400_000,
+            &signal_provider.key,
+            &signal_provider_pool_token_account,
+            &signal_provider.asset_accounts,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        token_balance(&ctx, &signal_provider_pool_token_account).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_traders() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new().mints(2).build(&mut ctx).await.unwrap();
+    let signal_provider = clone_keypair(&env.pool.signal_provider);
+    let trader = Keypair::new();
+    let instructions = vec![
+        system_instruction::transfer(
+            &ctx.test_state.payer.pubkey(),
+            &signal_provider.pubkey(),
+            1 << 30,
+        ),
+        system_instruction::transfer(&ctx.test_state.payer.pubkey(), &trader.pubkey(), 1 << 30),
+    ];
+    wrap_process_transaction(&ctx, instructions, vec![])
+        .await
+        .unwrap();
+
+    // Only the signal provider manages the traders, and it cannot be one
+    assert!(env
+        .pool
+        .add_trader(&ctx, &trader, &trader.pubkey(), 0)
+        .await
+        .is_err());
+    assert!(env
+        .pool
+        .add_trader(&ctx, &signal_provider, &signal_provider.pubkey(), 0)
+        .await
+        .is_err());
+    let trader_account_key = env
+        .pool
+        .add_trader(&ctx, &signal_provider, &trader.pubkey(), 6554)
+        .await
+        .unwrap();
+    let trader_account = Trader::unpack(
+        &ctx.test_state
+            .banks_client
+            .get_account(trader_account_key)
+            .await
+            .unwrap()
+            .unwrap()
+            .data,
+    )
+    .unwrap();
+    assert_eq!(trader_account.trader, trader.pubkey());
+    assert_eq!(trader_account.max_ratio_of_pool_assets_to_trade, 6554);
+    assert_eq!(env.pool.header(&ctx).await.number_of_traders, 1);
+
+    // Adding the trader again updates its limits
+    env.pool
+        .add_trader(&ctx, &signal_provider, &trader.pubkey(), 0)
+        .await
+        .unwrap();
+    let trader_account = Trader::unpack(
+        &ctx.test_state
+            .banks_client
+            .get_account(trader_account_key)
+            .await
+            .unwrap()
+            .unwrap()
+            .data,
+    )
+    .unwrap();
+    assert_eq!(trader_account.max_ratio_of_pool_assets_to_trade, 0);
+    assert_eq!(env.pool.header(&ctx).await.number_of_traders, 1);
+
+    // The trader cannot revoke itself, the signal provider gets the rent back
+    assert!(env
+        .pool
+        .remove_trader(&ctx, &trader, &trader.pubkey())
+        .await
+        .is_err());
+    let rent = ctx
+        .test_state
+        .banks_client
+        .get_account(trader_account_key)
+        .await
+        .unwrap()
+        .unwrap()
+        .lamports;
+    let balance = ctx
+        .test_state
+        .banks_client
+        .get_balance(signal_provider.pubkey())
+        .await
+        .unwrap();
+    env.pool
+        .remove_trader(&ctx, &signal_provider, &trader.pubkey())
+        .await
+        .unwrap();
+    assert!(ctx
+        .test_state
+        .banks_client
+        .get_account(trader_account_key)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        ctx.test_state
+            .banks_client
+            .get_balance(signal_provider.pubkey())
+            .await
+            .unwrap(),
+        balance + rent
+    );
+    assert_eq!(env.pool.header(&ctx).await.number_of_traders, 0);
+}
+
+#[tokio::test]
+async fn test_multisig() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new().mints(2).build(&mut ctx).await.unwrap();
+    let signal_provider = clone_keypair(&env.pool.signal_provider);
+    let signers = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let signer_keys: Vec<Pubkey> = signers.iter().map(|s| s.pubkey()).collect();
+    let trader = Pubkey::new_unique();
+    let instructions = vec![system_instruction::transfer(
+        &ctx.test_state.payer.pubkey(),
+        &signal_provider.pubkey(),
+        1 << 30,
+    )];
+    wrap_process_transaction(&ctx, instructions, vec![])
+        .await
+        .unwrap();
+    env.pool
+        .add_trader(&ctx, &signal_provider, &trader, 0)
+        .await
+        .unwrap();
+
+    // Invalid configurations are rejected
+    assert!(env
+        .pool
+        .set_multisig(&ctx, &[], 3, signer_keys[..2].to_vec())
+        .await
+        .is_err());
+    env.pool
+        .set_multisig(&ctx, &[], 2, signer_keys.clone())
+        .await
+        .unwrap();
+    let header = env.pool.header(&ctx).await;
+    assert_eq!(header.multisig_threshold, 2);
+    assert_eq!(header.multisig_signers[..3], signer_keys[..]);
+
+    // The signal provider key alone, or a single signer, cannot act anymore
+    assert!(env
+        .pool
+        .remove_trader(&ctx, &signal_provider, &trader)
+        .await
+        .is_err());
+    assert!(env
+        .pool
+        .set_multisig(&ctx, &[&signers[0]], 0, vec![])
+        .await
+        .is_err());
+    let (trader_account_key, _) = find_trader_key(
+        &ctx.bonfidabot_program_id,
+        &env.pool.seeds,
+        &signal_provider.pubkey(),
+        &trader,
+    );
+    let instruction = with_multisig_signers(
+        remove_trader(
+            &ctx.bonfidabot_program_id,
+            &env.pool.key,
+            &trader_account_key,
+            &signal_provider.pubkey(),
+            env.pool.seeds,
+            &trader,
+        )
+        .unwrap(),
+        &signal_provider.pubkey(),
+        &[&signer_keys[0], &signer_keys[2]],
+    );
+    wrap_process_transaction(&ctx, vec![instruction], vec![&signers[0], &signers[2]])
+        .await
+        .unwrap();
+    assert!(ctx
+        .test_state
+        .banks_client
+        .get_account(trader_account_key)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Two signers restore the single signal provider
+    env.pool
+        .set_multisig(&ctx, &[&signers[1], &signers[2]], 0, vec![])
+        .await
+        .unwrap();
+    assert_eq!(env.pool.header(&ctx).await.multisig_threshold, 0);
+    env.pool
+        .set_multisig(&ctx, &[], 1, signer_keys[..1].to_vec())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_trade_limits() {
+    let mut ctx = Context::init().await;
+    let trade_limits = TradeLimits {
+        max_order_ratio: 1 << 13,
+        max_daily_turnover: 1 << 16,
+        max_daily_orders: 10,
+        max_price_deviation: 6554,
+        allowed_order_types: 1 << OrderType::ImmediateOrCancel as u8,
+        allowed_self_trade_behaviors: 0,
+    };
+    let env = PoolBuilder::new()
+        .mints(2)
+        .trade_limits(trade_limits)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+
+    // The limits are set on creation and no trading day has started yet
+    let header = env.pool.header(&ctx).await;
+    assert_eq!(header.trade_limits, trade_limits);
+    assert_eq!(header.trading_day_start, 0);
+    assert_eq!(header.daily_turnover, 0);
+    assert_eq!(header.daily_orders, 0);
+}
+
+#[tokio::test]
+async fn test_one_order_per_open_orders() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new().mints(2).build(&mut ctx).await.unwrap();
+    let limit_price = NonZeroU64::new(100).unwrap();
+    let max_qty = NonZeroU16::new(1 << 10).unwrap();
+
+    let order = env
+        .place_order(&mut ctx, Side::Bid, limit_price, max_qty)
+        .await
+        .unwrap();
+    // The unmatched funds of the order stay in its OpenOrders account until it is settled
+    let result = env
+        .pool
+        .create_new_order(
+            &mut ctx,
+            &env.market,
+            0,
+            1,
+            &order,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 9).unwrap(),
+        )
+        .await;
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::OpenOrdersInUse)
+    );
+
+    env.market
+        .crank(&ctx, vec![&order.open_orders_account])
+        .await;
+    env.settle(&ctx, &order).await.unwrap();
+    assert_eq!(env.pool.header(&ctx).await.status, PoolStatus::Unlocked);
+    // The same order was rejected with the current blockhash
+    ctx.refresh_blockhash().await;
+    env.pool
+        .create_new_order(
+            &mut ctx,
+            &env.market,
+            0,
+            1,
+            &order,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 9).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        env.pool.header(&ctx).await.status,
+        PoolStatus::PendingOrder(NonZeroU8::new(1).unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_price_band() {
+    let mut ctx = Context::init().await;
+    // Orders are priced within 10% of the market book
+    let env = PoolBuilder::new()
+        .mints(2)
+        .trade_limits(TradeLimits {
+            max_price_deviation: 6554,
+            ..TradeLimits::default()
+        })
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let max_qty = NonZeroU16::new(1 << 10).unwrap();
+
+    // There is no reference price on an empty book
+    let result = env
+        .place_order(&mut ctx, Side::Bid, NonZeroU64::new(100).unwrap(), max_qty)
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::PriceOutOfBand)
+    );
+
+    env.market
+        .place_maker_order(
+            &ctx,
+            Side::Ask,
+            NonZeroU64::new(100).unwrap(),
+            NonZeroU64::new(1000).unwrap(),
+            &ctx.mint_authority,
+        )
+        .await
+        .unwrap();
+
+    // Buying above the best ask, or selling below it without bids, is bounded by the band
+    for (side, price) in [(Side::Bid, 111), (Side::Ask, 89)].iter() {
+        let result = env
+            .place_order(&mut ctx, *side, NonZeroU64::new(*price).unwrap(), max_qty)
+            .await
+            .map(|_| ());
+        assert_eq!(
+            bonfida_bot_error(result),
+            Some(BonfidaBotError::PriceOutOfBand)
+        );
+    }
+    env.place_order(&mut ctx, Side::Bid, NonZeroU64::new(110).unwrap(), max_qty)
+        .await
+        .unwrap();
+    env.place_order(&mut ctx, Side::Ask, NonZeroU64::new(90).unwrap(), max_qty)
+        .await
+        .unwrap();
+    assert_eq!(
+        env.pool.header(&ctx).await.status,
+        PoolStatus::PendingOrder(NonZeroU8::new(2).unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_order_policy() {
+    let mut ctx = Context::init().await;
+    // Immediate or cancel orders only, decrementing the take on self trades
+    let env = PoolBuilder::new()
+        .mints(2)
+        .trade_limits(TradeLimits {
+            allowed_order_types: 1 << OrderType::ImmediateOrCancel as u8,
+            allowed_self_trade_behaviors: 1
+                << bonfida_bot::instruction::SelfTradeBehavior::DecrementTake as u8,
+            ..TradeLimits::default()
+        })
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let limit_price = NonZeroU64::new(100).unwrap();
+    let max_qty = NonZeroU16::new(1 << 10).unwrap();
+
+    let result = env
+        .place_order_with(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            max_qty,
+            SerumOrderType::PostOnly,
+            SelfTradeBehavior::DecrementTake,
+            None,
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::OrderTypeNotAllowed)
+    );
+    let result = env
+        .place_order_with(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            max_qty,
+            SerumOrderType::ImmediateOrCancel,
+            SelfTradeBehavior::AbortTransaction,
+            None,
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::SelfTradeBehaviorNotAllowed)
+    );
+    env.place_order_with(
+        &mut ctx,
+        Side::Bid,
+        limit_price,
+        max_qty,
+        SerumOrderType::ImmediateOrCancel,
+        SelfTradeBehavior::DecrementTake,
+        None,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_daily_limits() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new()
+        .mints(2)
+        .trade_limits(TradeLimits {
+            max_order_ratio: 1 << 12,
+            max_daily_turnover: 5 << 11,
+            max_daily_orders: 2,
+            ..TradeLimits::default()
+        })
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let limit_price = NonZeroU64::new(100).unwrap();
+
+    let result = env
+        .place_order(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new((1 << 12) + 1).unwrap(),
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::OrderTooLarge)
+    );
+    for _ in 0..2 {
+        env.place_order(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 12).unwrap(),
+        )
+        .await
+        .unwrap();
+    }
+    let header = env.pool.header(&ctx).await;
+    assert_eq!(header.daily_turnover, 2 << 12);
+    assert_eq!(header.daily_orders, 2);
+
+    // The turnover is checked before the number of orders
+    let result = env
+        .place_order(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 12).unwrap(),
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::DailyTurnoverExceeded)
+    );
+    let result = env
+        .place_order(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 11).unwrap(),
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::TooManyDailyOrders)
+    );
+
+    // A new trading day starts with the first order once the current one has ended
+    // Slots last 400ms by default
+    ctx.advance_clock(TRADING_DAY_DURATION * 5 / 2 + 10_000)
+        .await;
+    env.place_order(
+        &mut ctx,
+        Side::Bid,
+        limit_price,
+        NonZeroU16::new(1 << 12).unwrap(),
+    )
+    .await
+    .unwrap();
+    let new_header = env.pool.header(&ctx).await;
+    assert!(new_header.trading_day_start >= header.trading_day_start + TRADING_DAY_DURATION);
+    assert_eq!(new_header.daily_turnover, 1 << 12);
+    assert_eq!(new_header.daily_orders, 1);
+}
+
+#[tokio::test]
+async fn test_trader_orders() {
+    let mut ctx = Context::init().await;
+    let env = PoolBuilder::new().mints(2).build(&mut ctx).await.unwrap();
+    let signal_provider = clone_keypair(&env.pool.signal_provider);
+    let trader = Keypair::new();
+    let instructions = vec![system_instruction::transfer(
+        &ctx.test_state.payer.pubkey(),
+        &signal_provider.pubkey(),
+        1 << 30,
+    )];
+    wrap_process_transaction(&ctx, instructions, vec![])
+        .await
+        .unwrap();
+    env.pool
+        .add_trader(&ctx, &signal_provider, &trader.pubkey(), 1 << 12)
+        .await
+        .unwrap();
+    let limit_price = NonZeroU64::new(100).unwrap();
+
+    // Traders place orders within their own limit, other keys and the signal provider cannot
+    env.place_order_with(
+        &mut ctx,
+        Side::Bid,
+        limit_price,
+        NonZeroU16::new(1 << 12).unwrap(),
+        SerumOrderType::ImmediateOrCancel,
+        SelfTradeBehavior::DecrementTake,
+        Some(&trader),
+    )
+    .await
+    .unwrap();
+    let result = env
+        .place_order_with(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new((1 << 12) + 1).unwrap(),
+            SerumOrderType::ImmediateOrCancel,
+            SelfTradeBehavior::DecrementTake,
+            Some(&trader),
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::TradeLimitExceeded)
+    );
+    let result = env
+        .place_order_with(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 12).unwrap(),
+            SerumOrderType::ImmediateOrCancel,
+            SelfTradeBehavior::DecrementTake,
+            Some(&Keypair::new()),
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::InvalidTrader)
+    );
+    let result = env
+        .place_order(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 12).unwrap(),
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::TradingDelegated)
+    );
+
+    // Revoked traders cannot place orders anymore, and the signal provider trades again
+    env.pool
+        .remove_trader(&ctx, &signal_provider, &trader.pubkey())
+        .await
+        .unwrap();
+    let result = env
+        .place_order_with(
+            &mut ctx,
+            Side::Bid,
+            limit_price,
+            NonZeroU16::new(1 << 12).unwrap(),
+            SerumOrderType::ImmediateOrCancel,
+            SelfTradeBehavior::DecrementTake,
+            Some(&trader),
+        )
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::InvalidTrader)
+    );
+    env.place_order(
+        &mut ctx,
+        Side::Bid,
+        limit_price,
+        NonZeroU16::new(1 << 12).unwrap(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_traders_of_recreated_pool() {
+    let mut ctx = Context::init().await;
+    let mut env = PoolBuilder::new()
+        .mints(2)
+        .annual_fee_ratio(0)
+        .fee_ratio(0)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let signal_provider = clone_keypair(&env.pool.signal_provider);
+    let trader = Keypair::new();
+    let instructions = vec![system_instruction::transfer(
+        &ctx.test_state.payer.pubkey(),
+        &signal_provider.pubkey(),
+        1 << 30,
+    )];
+    wrap_process_transaction(&ctx, instructions, vec![])
+        .await
+        .unwrap();
+    let trader_account_key = env
+        .pool
+        .add_trader(&ctx, &signal_provider, &trader.pubkey(), 0)
+        .await
+        .unwrap();
+
+    // The pool is emptied, which resets it and keeps the trader account
+    env.pool
+        .redeem(
+            &ctx,
+            env.creator.pool_token_balance,
+            &env.creator.key,
+            env.creator.pool_token_account.as_ref().unwrap(),
+            &env.creator.asset_accounts,
+        )
+        .await
+        .unwrap();
+    assert!(ctx
+        .test_state
+        .banks_client
+        .get_account(trader_account_key)
+        .await
+        .unwrap()
+        .is_some());
+
+    // Another signal provider cannot create the pool again until the trader is removed
+    env.pool.signal_provider = Keypair::new();
+    let result = env
+        .pool
+        .create(
+            &ctx,
+            env.creator.pool_token_account.as_ref().unwrap(),
+            &env.creator.key,
+            &env.creator.asset_accounts,
+            vec![1_000_000; 2],
+            &env.market.market_key.pubkey(),
+            0,
+            0,
+            0,
+            TradeLimits::default(),
+        )
+        .await;
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::PoolAlreadyExists)
+    );
+
+    // The same signal provider gets its trader back, and still leaves the trading to it
+    env.pool.signal_provider = signal_provider;
+    ctx.refresh_blockhash().await;
+    env.pool
+        .create(
+            &ctx,
+            env.creator.pool_token_account.as_ref().unwrap(),
+            &env.creator.key,
+            &env.creator.asset_accounts,
+            vec![1_000_000; 2],
+            &env.market.market_key.pubkey(),
+            0,
+            0,
+            0,
+            TradeLimits::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(env.pool.header(&ctx).await.number_of_traders, 1);
+    let limit_price = NonZeroU64::new(100).unwrap();
+    let max_qty = NonZeroU16::new(1 << 12).unwrap();
+    let result = env
+        .place_order(&mut ctx, Side::Bid, limit_price, max_qty)
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::TradingDelegated)
+    );
+    env.place_order_with(
+        &mut ctx,
+        Side::Bid,
+        limit_price,
+        max_qty,
+        SerumOrderType::ImmediateOrCancel,
+        SelfTradeBehavior::DecrementTake,
+        Some(&trader),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_signal_provider_stake_orders() {
+    let mut ctx = Context::init().await;
+    // The signal provider has to hold a quarter of the pool tokens to place orders
+    let env = PoolBuilder::new()
+        .mints(2)
+        .annual_fee_ratio(0)
+        .fee_ratio(0)
+        .min_signal_provider_ratio(1 << 14)
+        .build(&mut ctx)
+        .await
+        .unwrap();
+    let signal_provider = env
+        .pool
+        .fund_actor(&ctx, clone_keypair(&env.pool.signal_provider))
+        .await;
+    let signal_provider_pool_token_account = signal_provider.pool_token_account.unwrap();
+    let investor = env.pool.fund_actor(&ctx, Keypair::new()).await;
+    let limit_price = NonZeroU64::new(100).unwrap();
+    let max_qty = NonZeroU16::new(1 << 12).unwrap();
+
+    let result = env
+        .place_order(&mut ctx, Side::Bid, limit_price, max_qty)
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::SignalProviderStakeTooLow)
+    );
+
+    // A third of the pool is diluted to a fifth by a new investor
+    env.pool
+        .deposit(
+            &ctx,
+            500_000,
+            &signal_provider_pool_token_account,
+            &signal_provider.key,
+            &signal_provider.asset_accounts,
+        )
+        .await
+        .unwrap();
+    env.pool
+        .deposit(
+            &ctx,
+            1_000_000,
+            investor.pool_token_account.as_ref().unwrap(),
+            &investor.key,
+            &investor.asset_accounts,
+        )
+        .await
+        .unwrap();
+    let result = env
+        .place_order(&mut ctx, Side::Bid, limit_price, max_qty)
+        .await
+        .map(|_| ());
+    assert_eq!(
+        bonfida_bot_error(result),
+        Some(BonfidaBotError::SignalProviderStakeTooLow)
+    );
+
+    // Back above a quarter of the pool
+    env.pool
+        .deposit(
+            &ctx,
+            300_000,
+            &signal_provider_pool_token_account,
+            &signal_provider.key,
+            &signal_provider.asset_accounts,
+        )
+        .await
+        .unwrap();
+    env.place_order(&mut ctx, Side::Bid, limit_price, max_qty)
+        .await
+        .unwrap();
+}
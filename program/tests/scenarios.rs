@@ -0,0 +1,23 @@
+#![cfg(feature = "test-bpf")]
+//! Replays the simulation executions recorded in `tests/scenarios`.
+use std::{fs, path::PathBuf};
+
+use bonfida_bot_test_utils::simulation::Execution;
+
+#[tokio::test]
+async fn test_recorded_scenarios() {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+    let mut paths = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |e| e == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        println!("Replaying {}", path.display());
+        let execution = Execution::load(&path).unwrap();
+        if let Err(divergence) = execution.replay().await {
+            panic!("{} : {}", path.display(), divergence);
+        }
+    }
+}